@@ -30,17 +30,38 @@ use cloud_openapi::{
         RegisterRevisionCommand, ResourceLabel, RevisionItemPage, TokenInfo,
     },
 };
-use reqwest::header;
-use serde::{Deserialize, Serialize};
+use reqwest::{header, Method, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::models::{
+    AccountQuotas, AppLimits, AppLink, AppMetadata, AppMetrics, CreateAppLink, CreateKeyValueStore,
+    CreateLogDrain, CreateWebhook, DatabaseMetadata, DeleteKeyValuePair, ErrorPage, FederatedToken,
+    KeyValueKey, KeyValueStore, KeyValueStoreStats, LogDrain, OidcTokenExchange, QueryResult,
+    Region, ServerVersion, SetKeyValuePair, SetVariablePair, SqlQuery, Template,
+    TouchKeyValuePairs, TrafficSplit, Webhook,
+};
+use crate::response_cache;
+use crate::timing::timed;
 use crate::CloudClientInterface;
 
 const JSON_MIME_TYPE: &str = "application/json";
 
+/// Names the responses cached for a login without putting its token in the
+/// names of cache files.
+fn cache_scope(conn_info: &ConnectionConfig) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    conn_info.url.trim_end_matches('/').hash(&mut hasher);
+    conn_info.token.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
 pub struct Client {
     configuration: Configuration,
+    /// Keeps the responses cached for this login apart from other logins'
+    cache_scope: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -50,12 +71,26 @@ pub struct ConnectionConfig {
     pub url: String,
 }
 
+/// The User-Agent sent with every request: the plugin and Spin versions and
+/// the platform, so that problems can be traced to the clients causing them.
+pub fn user_agent() -> String {
+    format!(
+        "{}/{} spin/{} ({}; {})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::var("SPIN_VERSION").unwrap_or_else(|_| "0".to_string()),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
 impl Client {
     pub fn new(conn_info: ConnectionConfig) -> Self {
         let mut headers = header::HeaderMap::new();
         headers.insert(header::ACCEPT, JSON_MIME_TYPE.parse().unwrap());
         headers.insert(header::CONTENT_TYPE, JSON_MIME_TYPE.parse().unwrap());
 
+        let cache_scope = cache_scope(&conn_info);
         let base_path = match conn_info.url.strip_suffix('/') {
             Some(s) => s.to_owned(),
             None => conn_info.url,
@@ -63,12 +98,7 @@ impl Client {
 
         let configuration = Configuration {
             base_path,
-            user_agent: Some(format!(
-                "{}/{} spin/{}",
-                env!("CARGO_PKG_NAME"),
-                env!("CARGO_PKG_VERSION"),
-                std::env::var("SPIN_VERSION").unwrap_or_else(|_| "0".to_string())
-            )),
+            user_agent: Some(user_agent()),
             client: reqwest::Client::builder()
                 .danger_accept_invalid_certs(conn_info.insecure)
                 .default_headers(headers)
@@ -83,104 +113,173 @@ impl Client {
             }),
         };
 
-        Self { configuration }
+        Self {
+            configuration,
+            cache_scope,
+        }
+    }
+
+    // Lookups cached before a change may no longer be true after it.
+    fn forget_cached_responses(&self) {
+        response_cache::invalidate(&self.cache_scope);
+    }
+
+    // Builds an authenticated request for endpoints which are not yet covered by
+    // the OpenAPI specification.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut builder = self
+            .configuration
+            .client
+            .request(method, format!("{}/{}", self.configuration.base_path, path));
+        if let Some(user_agent) = &self.configuration.user_agent {
+            builder = builder.header(header::USER_AGENT, user_agent);
+        }
+        if let Some(api_key) = &self.configuration.api_key {
+            builder = builder.bearer_auth(&api_key.key);
+        }
+        builder
     }
 }
 
 #[async_trait]
 impl CloudClientInterface for Client {
     async fn create_device_code(&self, client_id: Uuid) -> Result<DeviceCodeItem> {
-        api_device_codes_post(
-            &self.configuration,
-            CreateDeviceCodeCommand { client_id },
-            None,
-        )
+        timed("create_device_code", async move {
+            api_device_codes_post(
+                &self.configuration,
+                CreateDeviceCodeCommand { client_id },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
         .await
-        .map_err(format_response_error)
     }
 
     async fn login(&self, token: String) -> Result<TokenInfo> {
-        // When the new OpenAPI specification is released, manually crafting
-        // the request should no longer be necessary.
-        let response = self
-            .configuration
-            .client
-            .post(format!("{}/api/auth-tokens", self.configuration.base_path))
-            .body(
-                serde_json::json!(
-                    {
-                        "provider": "DeviceFlow",
-                        "clientId": "583e63e9-461f-4fbe-a246-23e0fb1cad10",
-                        "providerCode": token,
-                    }
+        timed("login", async move {
+            // When the new OpenAPI specification is released, manually crafting
+            // the request should no longer be necessary.
+            let response = self
+                .configuration
+                .client
+                .post(format!("{}/api/auth-tokens", self.configuration.base_path))
+                .body(
+                    serde_json::json!(
+                        {
+                            "provider": "DeviceFlow",
+                            "clientId": "583e63e9-461f-4fbe-a246-23e0fb1cad10",
+                            "providerCode": token,
+                        }
+                    )
+                    .to_string(),
                 )
-                .to_string(),
-            )
-            .send()
-            .await?;
+                .send()
+                .await?;
 
-        serde_json::from_reader(response.bytes().await?.as_ref())
-            .context("Failed to parse response")
+            serde_json::from_reader(response.bytes().await?.as_ref())
+                .context("Failed to parse response")
+        })
+        .await
     }
 
     async fn refresh_token(&self, token: String, refresh_token: String) -> Result<TokenInfo> {
-        api_auth_tokens_refresh_post(
-            &self.configuration,
-            RefreshTokenCommand {
-                token,
-                refresh_token,
-            },
-            None,
-        )
+        timed("refresh_token", async move {
+            api_auth_tokens_refresh_post(
+                &self.configuration,
+                RefreshTokenCommand {
+                    token,
+                    refresh_token,
+                },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
         .await
-        .map_err(format_response_error)
     }
 
-    async fn add_app(&self, name: &str, storage_id: &str) -> Result<Uuid> {
-        api_apps_post(
-            &self.configuration,
-            CreateAppCommand {
-                name: name.to_string(),
-                storage_id: storage_id.to_string(),
-                create_default_database: None,
-            },
-            None,
-        )
+    async fn exchange_oidc_token(&self, exchange: OidcTokenExchange) -> Result<FederatedToken> {
+        timed("exchange_oidc_token", async move {
+            let response = self
+                .request(Method::POST, "api/auth-tokens/oidc")
+                .json(&exchange)
+                .send()
+                .await?;
+            parse_response(response).await
+        })
         .await
-        .map_err(format_response_error)
     }
 
-    async fn remove_app(&self, id: String) -> Result<()> {
-        api_apps_id_delete(&self.configuration, &id, None)
+    async fn add_app(&self, name: &str, storage_id: &str) -> Result<Uuid> {
+        let result = timed("add_app", async move {
+            api_apps_post(
+                &self.configuration,
+                CreateAppCommand {
+                    name: name.to_string(),
+                    storage_id: storage_id.to_string(),
+                    create_default_database: None,
+                },
+                None,
+            )
             .await
             .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
+    }
+
+    async fn remove_app(&self, id: String) -> Result<()> {
+        let result = timed("remove_app", async move {
+            api_apps_id_delete(&self.configuration, &id, None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
     }
 
     async fn get_app(&self, id: String) -> Result<AppItem> {
-        api_apps_id_get(&self.configuration, &id, None)
-            .await
-            .map_err(format_response_error)
+        timed("get_app", async move {
+            api_apps_id_get(&self.configuration, &id, None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await
     }
 
     async fn list_apps(&self, page_size: i32, page_index: Option<i32>) -> Result<AppItemPage> {
-        api_apps_get(
-            &self.configuration,
-            None,
-            page_index,
-            Some(page_size),
-            None,
-            None,
-            None,
-            None,
+        let key = format!("apps-{page_size}-{}", page_index.unwrap_or_default());
+        response_cache::cached(
+            &self.cache_scope,
+            &key,
+            timed("list_apps", async move {
+                api_apps_get(
+                    &self.configuration,
+                    None,
+                    page_index,
+                    Some(page_size),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .map_err(format_response_error)
+            }),
         )
         .await
-        .map_err(format_response_error)
     }
 
     async fn app_logs(&self, id: String) -> Result<GetAppLogsVm> {
-        api_apps_id_logs_get(&self.configuration, &id, None, None, None)
-            .await
-            .map_err(format_response_error)
+        timed("app_logs", async move {
+            api_apps_id_logs_get(&self.configuration, &id, None, None, None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await
     }
 
     async fn app_logs_raw(
@@ -189,9 +288,12 @@ impl CloudClientInterface for Client {
         max_lines: Option<i32>,
         since: Option<String>,
     ) -> Result<GetAppRawLogsVm> {
-        api_apps_id_logs_raw_get(&self.configuration, &id, max_lines, since.as_deref(), None)
-            .await
-            .map_err(format_response_error)
+        timed("app_logs_raw", async move {
+            api_apps_id_logs_raw_get(&self.configuration, &id, max_lines, since.as_deref(), None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await
     }
 
     async fn add_revision(
@@ -199,37 +301,64 @@ impl CloudClientInterface for Client {
         app_storage_id: String,
         revision_number: String,
     ) -> anyhow::Result<()> {
-        api_revisions_post(
-            &self.configuration,
-            RegisterRevisionCommand {
-                app_storage_id,
-                revision_number,
-            },
-            None,
-        )
-        .await
-        .map_err(format_response_error)
+        let result = timed("add_revision", async move {
+            api_revisions_post(
+                &self.configuration,
+                RegisterRevisionCommand {
+                    app_storage_id,
+                    revision_number,
+                },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
     }
 
     async fn list_revisions(&self) -> anyhow::Result<RevisionItemPage> {
-        api_revisions_get(&self.configuration, None, None, None, None)
-            .await
-            .map_err(format_response_error)
+        timed("list_revisions", async move {
+            api_revisions_get(&self.configuration, None, None, None, None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await
     }
 
     async fn list_revisions_next(
         &self,
         previous: &RevisionItemPage,
     ) -> anyhow::Result<RevisionItemPage> {
-        api_revisions_get(
-            &self.configuration,
-            Some(previous.page_index + 1),
-            Some(previous.page_size),
-            None,
-            None,
-        )
+        timed("list_revisions_next", async move {
+            api_revisions_get(
+                &self.configuration,
+                Some(previous.page_index + 1),
+                Some(previous.page_size),
+                None,
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
         .await
-        .map_err(format_response_error)
+    }
+
+    async fn delete_revision(&self, app_id: Uuid, revision_id: Uuid) -> anyhow::Result<()> {
+        let result = timed("delete_revision", async move {
+            let response = self
+                .request(
+                    Method::DELETE,
+                    &format!("api/apps/{app_id}/revisions/{revision_id}"),
+                )
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await;
+        self.forget_cached_responses();
+        result
     }
 
     async fn add_key_value_pair(
@@ -239,18 +368,118 @@ impl CloudClientInterface for Client {
         key: String,
         value: String,
     ) -> anyhow::Result<()> {
-        api_key_value_pairs_post(
-            &self.configuration,
-            CreateKeyValuePairCommand {
-                app_id,
-                store_name,
-                key,
-                value,
-            },
-            None,
-        )
+        timed("add_key_value_pair", async move {
+            api_key_value_pairs_post(
+                &self.configuration,
+                CreateKeyValuePairCommand {
+                    app_id,
+                    store_name,
+                    key,
+                    value,
+                },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
+        .await
+    }
+
+    async fn set_key_value_pair(&self, pair: SetKeyValuePair) -> anyhow::Result<()> {
+        timed("set_key_value_pair", async move {
+            let response = self
+                .request(Method::POST, "api/key-value-pairs")
+                .json(&pair)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn delete_key_value_pair(&self, pair: DeleteKeyValuePair) -> anyhow::Result<()> {
+        timed("delete_key_value_pair", async move {
+            let response = self
+                .request(Method::DELETE, "api/key-value-pairs")
+                .json(&pair)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn list_key_value_keys(
+        &self,
+        app_id: Uuid,
+        store_name: &str,
+    ) -> anyhow::Result<Vec<KeyValueKey>> {
+        timed("list_key_value_keys", async move {
+            let response = self
+                .request(Method::GET, "api/key-value-pairs/keys")
+                .query(&[
+                    ("appId", app_id.to_string().as_str()),
+                    ("storeName", store_name),
+                ])
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn touch_key_value_pairs(&self, touch: TouchKeyValuePairs) -> anyhow::Result<()> {
+        timed("touch_key_value_pairs", async move {
+            let response = self
+                .request(Method::POST, "api/key-value-pairs/touch")
+                .json(&touch)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn create_key_value_store(&self, store: CreateKeyValueStore) -> anyhow::Result<()> {
+        timed("create_key_value_store", async move {
+            let response = self
+                .request(Method::POST, "api/key-value-stores")
+                .json(&store)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn list_key_value_stores(&self) -> anyhow::Result<Vec<KeyValueStore>> {
+        timed("list_key_value_stores", async move {
+            let response = self
+                .request(Method::GET, "api/key-value-stores")
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn get_key_value_store_stats(
+        &self,
+        store: &str,
+        window_seconds: u64,
+    ) -> anyhow::Result<KeyValueStoreStats> {
+        timed("get_key_value_store_stats", async move {
+            let response = self
+                .request(Method::GET, "api/key-value-stores/stats")
+                .query(&[
+                    ("name", store),
+                    ("windowSeconds", &window_seconds.to_string()),
+                ])
+                .send()
+                .await?;
+            parse_response(response).await
+        })
         .await
-        .map_err(format_response_error)
     }
 
     async fn add_variable_pair(
@@ -259,90 +488,189 @@ impl CloudClientInterface for Client {
         variable: String,
         value: String,
     ) -> anyhow::Result<()> {
-        api_variable_pairs_post(
-            &self.configuration,
-            CreateVariablePairCommand {
-                app_id,
-                variable,
-                value,
-            },
-            None,
-        )
+        timed("add_variable_pair", async move {
+            api_variable_pairs_post(
+                &self.configuration,
+                CreateVariablePairCommand {
+                    app_id,
+                    variable,
+                    value,
+                },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
+        .await
+    }
+
+    async fn set_variable_pair(&self, pair: SetVariablePair) -> anyhow::Result<()> {
+        timed("set_variable_pair", async move {
+            let response = self
+                .request(Method::POST, "api/variable-pairs")
+                .json(&pair)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn reveal_variable(&self, app_id: Uuid, variable: &str) -> anyhow::Result<String> {
+        timed("reveal_variable", async move {
+            let response = self
+                .request(Method::GET, "api/variable-pairs/reveal")
+                .query(&[
+                    ("appId", app_id.to_string().as_str()),
+                    ("variable", variable),
+                ])
+                .send()
+                .await?;
+            let revealed: RevealedVariable = parse_response(response).await?;
+            Ok(revealed.value)
+        })
         .await
-        .map_err(format_response_error)
     }
 
     async fn delete_variable_pair(&self, app_id: Uuid, variable: String) -> anyhow::Result<()> {
-        api_variable_pairs_delete(
-            &self.configuration,
-            DeleteVariablePairCommand { app_id, variable },
-            None,
-        )
+        timed("delete_variable_pair", async move {
+            api_variable_pairs_delete(
+                &self.configuration,
+                DeleteVariablePairCommand { app_id, variable },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
         .await
-        .map_err(format_response_error)
     }
 
     async fn get_variable_pairs(&self, app_id: Uuid) -> anyhow::Result<Vec<String>> {
-        let list = api_variable_pairs_get(&self.configuration, GetVariablesQuery { app_id }, None)
-            .await
-            .map_err(format_response_error)?;
-        Ok(list.vars)
+        timed("get_variable_pairs", async move {
+            let list =
+                api_variable_pairs_get(&self.configuration, GetVariablesQuery { app_id }, None)
+                    .await
+                    .map_err(format_response_error)?;
+            Ok(list.vars)
+        })
+        .await
     }
 
     async fn create_database(
         &self,
         name: String,
         resource_label: Option<ResourceLabel>,
+        region: Option<String>,
     ) -> anyhow::Result<()> {
-        let (app_id, label) = match resource_label {
-            Some(rl) => (Some(Some(rl.app_id)), Some(Some(rl.label))),
-            None => (None, None),
-        };
-        api_sql_databases_create_post(
-            &self.configuration,
-            CreateSqlDatabaseCommand {
-                name,
-                app_id,
-                label,
-            },
-            None,
+        let result = timed("create_database", async move {
+            // The OpenAPI specification does not know about regions yet, so
+            // requests for a specific region are built by hand.
+            if let Some(region) = region {
+                let response = self
+                    .request(Method::POST, "api/sql-databases/create")
+                    .json(&CreateSqlDatabaseInRegionCommand {
+                        name,
+                        app_id: resource_label.as_ref().map(|rl| rl.app_id),
+                        label: resource_label.map(|rl| rl.label),
+                        region,
+                    })
+                    .send()
+                    .await?;
+                return check_response(response).await;
+            }
+            let (app_id, label) = match resource_label {
+                Some(rl) => (Some(Some(rl.app_id)), Some(Some(rl.label))),
+                None => (None, None),
+            };
+            api_sql_databases_create_post(
+                &self.configuration,
+                CreateSqlDatabaseCommand {
+                    name,
+                    app_id,
+                    label,
+                },
+                None,
+            )
+            .await
+            .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
+    }
+
+    async fn list_regions(&self) -> anyhow::Result<Vec<Region>> {
+        let key = "regions".to_owned();
+        response_cache::cached(
+            &self.cache_scope,
+            &key,
+            timed("list_regions", async move {
+                let response = self.request(Method::GET, "api/regions").send().await?;
+                parse_response(response).await
+            }),
         )
         .await
-        .map_err(format_response_error)
+    }
+
+    async fn list_templates(&self) -> anyhow::Result<Vec<Template>> {
+        timed("list_templates", async move {
+            let response = self.request(Method::GET, "api/templates").send().await?;
+            parse_response(response).await
+        })
+        .await
     }
 
     async fn execute_sql(&self, database: String, statement: String) -> anyhow::Result<()> {
-        api_sql_databases_execute_post(
-            &self.configuration,
-            ExecuteSqlStatementCommand {
-                database,
-                statement,
-                default: false,
-            },
-            None,
-        )
+        timed("execute_sql", async move {
+            api_sql_databases_execute_post(
+                &self.configuration,
+                ExecuteSqlStatementCommand {
+                    database,
+                    statement,
+                    default: false,
+                },
+                None,
+            )
+            .await
+            .map_err(format_response_error)?;
+            Ok(())
+        })
         .await
-        .map_err(format_response_error)?;
-        Ok(())
     }
 
     async fn delete_database(&self, name: String) -> anyhow::Result<()> {
-        api_sql_databases_delete(&self.configuration, DeleteSqlDatabaseCommand { name }, None)
-            .await
-            .map_err(format_response_error)
+        let result = timed("delete_database", async move {
+            api_sql_databases_delete(&self.configuration, DeleteSqlDatabaseCommand { name }, None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
     }
 
     async fn get_databases(&self, app_id: Option<Uuid>) -> anyhow::Result<Vec<Database>> {
-        let list = api_sql_databases_get(
-            &self.configuration,
-            GetSqlDatabasesQuery {
-                app_id: Some(app_id),
-            },
-            None,
+        let key = match app_id {
+            Some(app_id) => format!("databases-{app_id}"),
+            None => "databases".to_owned(),
+        };
+        response_cache::cached(
+            &self.cache_scope,
+            &key,
+            timed("get_databases", async move {
+                let list = api_sql_databases_get(
+                    &self.configuration,
+                    GetSqlDatabasesQuery {
+                        app_id: Some(app_id),
+                    },
+                    None,
+                )
+                .await
+                .map_err(format_response_error)?;
+                Ok(list.databases)
+            }),
         )
         .await
-        .map_err(format_response_error)?;
-        Ok(list.databases)
     }
 
     async fn create_database_link(
@@ -350,9 +678,19 @@ impl CloudClientInterface for Client {
         database: &str,
         resource_label: ResourceLabel,
     ) -> anyhow::Result<()> {
-        api_sql_databases_database_links_post(&self.configuration, database, resource_label, None)
+        let result = timed("create_database_link", async move {
+            api_sql_databases_database_links_post(
+                &self.configuration,
+                database,
+                resource_label,
+                None,
+            )
             .await
             .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
     }
 
     async fn remove_database_link(
@@ -360,15 +698,315 @@ impl CloudClientInterface for Client {
         database: &str,
         resource_label: ResourceLabel,
     ) -> anyhow::Result<()> {
-        api_sql_databases_database_links_delete(&self.configuration, database, resource_label, None)
+        let result = timed("remove_database_link", async move {
+            api_sql_databases_database_links_delete(
+                &self.configuration,
+                database,
+                resource_label,
+                None,
+            )
             .await
             .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
     }
 
     async fn rename_database(&self, database: String, new_name: String) -> anyhow::Result<()> {
-        api_sql_databases_database_rename_patch(&self.configuration, &database, &new_name, None)
-            .await
-            .map_err(format_response_error)
+        let result = timed("rename_database", async move {
+            api_sql_databases_database_rename_patch(&self.configuration, &database, &new_name, None)
+                .await
+                .map_err(format_response_error)
+        })
+        .await;
+        self.forget_cached_responses();
+        result
+    }
+
+    async fn get_database_metadata(&self) -> anyhow::Result<Vec<DatabaseMetadata>> {
+        timed("get_database_metadata", async move {
+            let response = self
+                .request(Method::GET, "api/sql-databases/metadata")
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn query_sql(&self, query: SqlQuery) -> anyhow::Result<QueryResult> {
+        timed("query_sql", async move {
+            let response = self
+                .request(Method::POST, "api/sql-databases/query")
+                .json(&query)
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn cancel_sql_statements(&self, database: String) -> anyhow::Result<()> {
+        timed("cancel_sql_statements", async move {
+            let response = self
+                .request(Method::POST, "api/sql-databases/cancel")
+                .json(&serde_json::json!({ "database": database }))
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn get_apps_metadata(&self) -> anyhow::Result<Vec<AppMetadata>> {
+        timed("get_apps_metadata", async move {
+            let response = self
+                .request(Method::GET, "api/apps/metadata")
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn get_app_limits(&self, app_id: Uuid) -> anyhow::Result<AppLimits> {
+        timed("get_app_limits", async move {
+            let response = self
+                .request(Method::GET, &format!("api/apps/{app_id}/limits"))
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn set_app_limits(&self, app_id: Uuid, limits: AppLimits) -> anyhow::Result<()> {
+        timed("set_app_limits", async move {
+            let response = self
+                .request(Method::PATCH, &format!("api/apps/{app_id}/limits"))
+                .json(&limits)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn get_app_metrics(
+        &self,
+        app_id: Uuid,
+        window_seconds: u64,
+    ) -> anyhow::Result<AppMetrics> {
+        timed("get_app_metrics", async move {
+            let response = self
+                .request(Method::GET, &format!("api/apps/{app_id}/metrics"))
+                .query(&[("windowSeconds", window_seconds.to_string())])
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn get_server_version(&self) -> anyhow::Result<ServerVersion> {
+        timed("get_server_version", async move {
+            let response = self.request(Method::GET, "api/version").send().await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn get_app_traffic(&self, app_id: Uuid) -> anyhow::Result<TrafficSplit> {
+        timed("get_app_traffic", async move {
+            let response = self
+                .request(Method::GET, &format!("api/apps/{app_id}/traffic"))
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn set_app_traffic(&self, app_id: Uuid, split: TrafficSplit) -> anyhow::Result<()> {
+        timed("set_app_traffic", async move {
+            let response = self
+                .request(Method::PUT, &format!("api/apps/{app_id}/traffic"))
+                .json(&split)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn set_error_page(
+        &self,
+        app_id: Uuid,
+        kind: &str,
+        page: ErrorPage,
+    ) -> anyhow::Result<()> {
+        timed("set_error_page", async move {
+            let response = self
+                .request(
+                    Method::PUT,
+                    &format!("api/apps/{app_id}/error-pages/{kind}"),
+                )
+                .json(&page)
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn remove_error_page(&self, app_id: Uuid, kind: &str) -> anyhow::Result<()> {
+        timed("remove_error_page", async move {
+            let response = self
+                .request(
+                    Method::DELETE,
+                    &format!("api/apps/{app_id}/error-pages/{kind}"),
+                )
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn list_webhooks(&self, app_id: Uuid) -> anyhow::Result<Vec<Webhook>> {
+        timed("list_webhooks", async move {
+            let response = self
+                .request(Method::GET, &format!("api/apps/{app_id}/webhooks"))
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn add_webhook(&self, app_id: Uuid, webhook: CreateWebhook) -> anyhow::Result<Webhook> {
+        timed("add_webhook", async move {
+            let response = self
+                .request(Method::POST, &format!("api/apps/{app_id}/webhooks"))
+                .json(&webhook)
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn remove_webhook(&self, app_id: Uuid, webhook_id: Uuid) -> anyhow::Result<()> {
+        timed("remove_webhook", async move {
+            let response = self
+                .request(
+                    Method::DELETE,
+                    &format!("api/apps/{app_id}/webhooks/{webhook_id}"),
+                )
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn list_log_drains(&self, app_id: Uuid) -> anyhow::Result<Vec<LogDrain>> {
+        timed("list_log_drains", async move {
+            let response = self
+                .request(Method::GET, &format!("api/apps/{app_id}/log-drains"))
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn add_log_drain(&self, app_id: Uuid, drain: CreateLogDrain) -> anyhow::Result<LogDrain> {
+        timed("add_log_drain", async move {
+            let response = self
+                .request(Method::POST, &format!("api/apps/{app_id}/log-drains"))
+                .json(&drain)
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn remove_log_drain(&self, app_id: Uuid, drain_id: Uuid) -> anyhow::Result<()> {
+        timed("remove_log_drain", async move {
+            let response = self
+                .request(
+                    Method::DELETE,
+                    &format!("api/apps/{app_id}/log-drains/{drain_id}"),
+                )
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn list_app_links(&self, app_id: Uuid) -> anyhow::Result<Vec<AppLink>> {
+        timed("list_app_links", async move {
+            let response = self
+                .request(Method::GET, &format!("api/apps/{app_id}/app-links"))
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn create_app_link(&self, app_id: Uuid, link: CreateAppLink) -> anyhow::Result<AppLink> {
+        timed("create_app_link", async move {
+            let response = self
+                .request(Method::POST, &format!("api/apps/{app_id}/app-links"))
+                .json(&link)
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn remove_app_link(&self, app_id: Uuid, link_id: Uuid) -> anyhow::Result<()> {
+        timed("remove_app_link", async move {
+            let response = self
+                .request(
+                    Method::DELETE,
+                    &format!("api/apps/{app_id}/app-links/{link_id}"),
+                )
+                .send()
+                .await?;
+            check_response(response).await
+        })
+        .await
+    }
+
+    async fn get_account_quotas(&self) -> anyhow::Result<AccountQuotas> {
+        timed("get_account_quotas", async move {
+            let response = self
+                .request(Method::GET, "api/accounts/quotas")
+                .send()
+                .await?;
+            parse_response(response).await
+        })
+        .await
+    }
+
+    async fn is_app_name_available(&self, name: &str) -> anyhow::Result<bool> {
+        timed("is_app_name_available", async move {
+            let response = self
+                .request(Method::GET, "api/apps/name-availability")
+                .query(&[("name", name)])
+                .send()
+                .await?;
+            let availability: NameAvailability = parse_response(response).await?;
+            Ok(availability.available)
+        })
+        .await
     }
 }
 
@@ -385,16 +1023,7 @@ struct CloudProblemDetails {
 
 fn format_response_error<T>(e: Error<T>) -> anyhow::Error {
     match e {
-        Error::ResponseError(r) => {
-            // Validation failures are distinguished by the presence of `errors` so try that first
-            if let Ok(m) = serde_json::from_str::<ValidationExceptionMessage>(&r.content) {
-                anyhow::anyhow!("{} {:?}", m.title, m.errors)
-            } else if let Ok(d) = serde_json::from_str::<CloudProblemDetails>(&r.content) {
-                anyhow::anyhow!("{}", d.detail)
-            } else {
-                anyhow::anyhow!("response status code: {}", r.status)
-            }
-        }
+        Error::ResponseError(r) => format_error_content(r.status, &r.content),
         Error::Serde(err) => {
             anyhow::anyhow!(format!("could not parse JSON object: {}", err))
         }
@@ -402,6 +1031,73 @@ fn format_response_error<T>(e: Error<T>) -> anyhow::Error {
     }
 }
 
+fn format_error_content(status: reqwest::StatusCode, content: &str) -> anyhow::Error {
+    // Validation failures are distinguished by the presence of `errors` so try that first
+    let message = if let Ok(m) = serde_json::from_str::<ValidationExceptionMessage>(content) {
+        format!("{} {:?}", m.title, m.errors)
+    } else if let Ok(d) = serde_json::from_str::<CloudProblemDetails>(content) {
+        d.detail
+    } else {
+        format!("response status code: {}", status)
+    };
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        Unauthorized(message).into()
+    } else {
+        anyhow::anyhow!(message)
+    }
+}
+
+/// The error when Cloud does not accept the login a request was made with,
+/// so that callers can tell an expired login from other failures.
+#[derive(Debug)]
+pub struct Unauthorized(pub String);
+
+impl std::fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+async fn check_response(response: Response) -> Result<()> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let content = response.text().await.unwrap_or_default();
+    Err(format_error_content(status, &content))
+}
+
+async fn parse_response<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+    let content = response.text().await?;
+    if !status.is_success() {
+        return Err(format_error_content(status, &content));
+    }
+    serde_json::from_str(&content).context("Failed to parse response")
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct RevealedVariable {
+    value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct NameAvailability {
+    available: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct CreateSqlDatabaseInRegionCommand {
+    name: String,
+    #[serde(rename = "appId", skip_serializing_if = "Option::is_none")]
+    app_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    region: String,
+}
+
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 struct PatchChannelCommand {
     #[serde(rename = "channelId", skip_serializing_if = "Option::is_none")]
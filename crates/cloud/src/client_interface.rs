@@ -8,6 +8,14 @@ use cloud_openapi::models::{
 use std::string::String;
 use uuid::Uuid;
 
+use crate::models::{
+    AccountQuotas, AppLimits, AppLink, AppMetadata, AppMetrics, CreateAppLink, CreateKeyValueStore,
+    CreateLogDrain, CreateWebhook, DatabaseMetadata, DeleteKeyValuePair, ErrorPage, FederatedToken,
+    KeyValueKey, KeyValueStore, KeyValueStoreStats, LogDrain, OidcTokenExchange, QueryResult,
+    Region, ServerVersion, SetKeyValuePair, SetVariablePair, SqlQuery, Template,
+    TouchKeyValuePairs, TrafficSplit, Webhook,
+};
+
 #[cfg_attr(feature = "mocks", mockall::automock)]
 #[async_trait]
 pub trait CloudClientInterface: Send + Sync {
@@ -17,6 +25,8 @@ pub trait CloudClientInterface: Send + Sync {
 
     async fn refresh_token(&self, token: String, refresh_token: String) -> Result<TokenInfo>;
 
+    async fn exchange_oidc_token(&self, exchange: OidcTokenExchange) -> Result<FederatedToken>;
+
     async fn add_app(&self, name: &str, storage_id: &str) -> Result<Uuid>;
 
     async fn remove_app(&self, id: String) -> Result<()>;
@@ -47,6 +57,8 @@ pub trait CloudClientInterface: Send + Sync {
         previous: &RevisionItemPage,
     ) -> anyhow::Result<RevisionItemPage>;
 
+    async fn delete_revision(&self, app_id: Uuid, revision_id: Uuid) -> anyhow::Result<()>;
+
     async fn add_key_value_pair(
         &self,
         app_id: Uuid,
@@ -55,6 +67,28 @@ pub trait CloudClientInterface: Send + Sync {
         value: String,
     ) -> anyhow::Result<()>;
 
+    async fn set_key_value_pair(&self, pair: SetKeyValuePair) -> anyhow::Result<()>;
+
+    async fn delete_key_value_pair(&self, pair: DeleteKeyValuePair) -> anyhow::Result<()>;
+
+    async fn list_key_value_keys(
+        &self,
+        app_id: Uuid,
+        store_name: &str,
+    ) -> anyhow::Result<Vec<KeyValueKey>>;
+
+    async fn touch_key_value_pairs(&self, touch: TouchKeyValuePairs) -> anyhow::Result<()>;
+
+    async fn create_key_value_store(&self, store: CreateKeyValueStore) -> anyhow::Result<()>;
+
+    async fn list_key_value_stores(&self) -> anyhow::Result<Vec<KeyValueStore>>;
+
+    async fn get_key_value_store_stats(
+        &self,
+        store: &str,
+        window_seconds: u64,
+    ) -> anyhow::Result<KeyValueStoreStats>;
+
     async fn add_variable_pair(
         &self,
         app_id: Uuid,
@@ -62,6 +96,10 @@ pub trait CloudClientInterface: Send + Sync {
         value: String,
     ) -> anyhow::Result<()>;
 
+    async fn set_variable_pair(&self, pair: SetVariablePair) -> anyhow::Result<()>;
+
+    async fn reveal_variable(&self, app_id: Uuid, variable: &str) -> anyhow::Result<String>;
+
     async fn delete_variable_pair(&self, app_id: Uuid, variable: String) -> anyhow::Result<()>;
 
     async fn get_variable_pairs(&self, app_id: Uuid) -> anyhow::Result<Vec<String>>;
@@ -70,8 +108,13 @@ pub trait CloudClientInterface: Send + Sync {
         &self,
         name: String,
         resource_label: Option<ResourceLabel>,
+        region: Option<String>,
     ) -> anyhow::Result<()>;
 
+    async fn list_regions(&self) -> anyhow::Result<Vec<Region>>;
+
+    async fn list_templates(&self) -> anyhow::Result<Vec<Template>>;
+
     async fn execute_sql(&self, database: String, statement: String) -> anyhow::Result<()>;
 
     async fn delete_database(&self, name: String) -> anyhow::Result<()>;
@@ -91,4 +134,56 @@ pub trait CloudClientInterface: Send + Sync {
     ) -> anyhow::Result<()>;
 
     async fn rename_database(&self, database: String, new_name: String) -> anyhow::Result<()>;
+
+    async fn get_database_metadata(&self) -> anyhow::Result<Vec<DatabaseMetadata>>;
+
+    async fn query_sql(&self, query: SqlQuery) -> anyhow::Result<QueryResult>;
+
+    async fn cancel_sql_statements(&self, database: String) -> anyhow::Result<()>;
+
+    async fn get_apps_metadata(&self) -> anyhow::Result<Vec<AppMetadata>>;
+
+    async fn get_app_limits(&self, app_id: Uuid) -> anyhow::Result<AppLimits>;
+
+    async fn set_app_limits(&self, app_id: Uuid, limits: AppLimits) -> anyhow::Result<()>;
+
+    async fn get_app_metrics(
+        &self,
+        app_id: Uuid,
+        window_seconds: u64,
+    ) -> anyhow::Result<AppMetrics>;
+
+    async fn get_server_version(&self) -> anyhow::Result<ServerVersion>;
+
+    async fn get_app_traffic(&self, app_id: Uuid) -> anyhow::Result<TrafficSplit>;
+
+    async fn set_app_traffic(&self, app_id: Uuid, split: TrafficSplit) -> anyhow::Result<()>;
+
+    async fn set_error_page(&self, app_id: Uuid, kind: &str, page: ErrorPage)
+        -> anyhow::Result<()>;
+
+    async fn remove_error_page(&self, app_id: Uuid, kind: &str) -> anyhow::Result<()>;
+
+    async fn list_webhooks(&self, app_id: Uuid) -> anyhow::Result<Vec<Webhook>>;
+
+    async fn add_webhook(&self, app_id: Uuid, webhook: CreateWebhook) -> anyhow::Result<Webhook>;
+
+    async fn remove_webhook(&self, app_id: Uuid, webhook_id: Uuid) -> anyhow::Result<()>;
+
+    async fn list_log_drains(&self, app_id: Uuid) -> anyhow::Result<Vec<LogDrain>>;
+
+    async fn add_log_drain(&self, app_id: Uuid, drain: CreateLogDrain) -> anyhow::Result<LogDrain>;
+
+    async fn remove_log_drain(&self, app_id: Uuid, drain_id: Uuid) -> anyhow::Result<()>;
+
+    async fn list_app_links(&self, app_id: Uuid) -> anyhow::Result<Vec<AppLink>>;
+
+    async fn create_app_link(&self, app_id: Uuid, link: CreateAppLink) -> anyhow::Result<AppLink>;
+
+    async fn remove_app_link(&self, app_id: Uuid, link_id: Uuid) -> anyhow::Result<()>;
+
+    async fn get_account_quotas(&self) -> anyhow::Result<AccountQuotas>;
+
+    /// Whether no app, in this account or any other, uses `name`.
+    async fn is_app_name_available(&self, name: &str) -> anyhow::Result<bool>;
 }
@@ -1,6 +1,9 @@
 pub mod client;
 mod client_interface;
 mod cloud_client_extensions;
+pub mod models;
+pub mod response_cache;
+pub mod timing;
 
 pub use client_interface::CloudClientInterface;
 #[cfg(feature = "mocks")]
@@ -0,0 +1,350 @@
+//! Models for Cloud API endpoints which are not yet part of the OpenAPI specification.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A CI job's OIDC identity token, to exchange for a Cloud token. With an
+/// app, the Cloud token can only deploy and manage that app.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OidcTokenExchange {
+    #[serde(rename = "idToken")]
+    pub id_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+}
+
+/// A short-lived token given in exchange for an OIDC identity token. It
+/// cannot be refreshed; the job exchanges a new identity token instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FederatedToken {
+    pub token: String,
+    /// RFC 3339
+    pub expiration: String,
+}
+
+/// Per-app runtime limits. A limit of `None` means the platform default applies.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AppLimits {
+    #[serde(rename = "maxConcurrency", skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<u32>,
+    #[serde(rename = "memoryMb", skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u32>,
+    #[serde(rename = "timeoutSecs", skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u32>,
+}
+
+/// The version of the platform, and what it knows about plugin versions
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ServerVersion {
+    pub version: String,
+    /// The oldest plugin version the platform still supports
+    #[serde(rename = "minimumPluginVersion", default)]
+    pub minimum_plugin_version: Option<String>,
+    #[serde(rename = "knownIssues", default)]
+    pub known_issues: Vec<KnownIssue>,
+}
+
+/// A problem affecting some plugin versions when used with the platform
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KnownIssue {
+    /// The affected versions, as a semver requirement such as `<0.6`
+    #[serde(rename = "pluginVersions")]
+    pub plugin_versions: String,
+    pub description: String,
+}
+
+/// How an app's requests are shared between its revisions. The weights are
+/// percentages and add up to 100.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct TrafficSplit {
+    #[serde(default)]
+    pub revisions: Vec<RevisionWeight>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RevisionWeight {
+    #[serde(rename = "revisionNumber")]
+    pub revision_number: String,
+    pub weight: u8,
+}
+
+/// How much an app was used over a window of time ending now. Latencies are
+/// `None` when the app served no requests in the window.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AppMetrics {
+    #[serde(rename = "windowSeconds")]
+    pub window_seconds: u64,
+    #[serde(default)]
+    pub requests: u64,
+    /// Requests answered with a 5xx status
+    #[serde(default)]
+    pub errors: u64,
+    #[serde(rename = "latencyP50Ms", default)]
+    pub latency_p50_ms: Option<f64>,
+    #[serde(rename = "latencyP95Ms", default)]
+    pub latency_p95_ms: Option<f64>,
+}
+
+/// Lifecycle details of an app. Timestamps are RFC 3339, and `None` where the
+/// platform did not record them.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AppMetadata {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: Option<String>,
+    #[serde(rename = "lastDeployedAt", default)]
+    pub last_deployed_at: Option<String>,
+}
+
+/// Ownership and usage details of a SQLite database. Fields are `None` where
+/// the platform does not record them, e.g. for databases created before it did.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct DatabaseMetadata {
+    pub name: String,
+    #[serde(rename = "createdBy", default)]
+    pub created_by: Option<String>,
+    #[serde(rename = "createdByCurrentUser", default)]
+    pub created_by_current_user: bool,
+    /// RFC 3339 timestamp
+    #[serde(rename = "createdAt", default)]
+    pub created_at: Option<String>,
+    #[serde(rename = "sizeBytes", default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub region: Option<String>,
+    /// RFC 3339 timestamp of the last query or statement run against the
+    /// database
+    #[serde(rename = "lastAccessedAt", default)]
+    pub last_accessed_at: Option<String>,
+}
+
+/// A region in which databases and key value stores can be created.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    #[serde(rename = "displayName", default)]
+    pub display_name: Option<String>,
+    /// Whether resources are created here when no region is given
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// An app template in the Cloud catalog. Templates are Spin templates kept
+/// in git repositories.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Template {
+    /// The Spin template ID, such as `http-rust`
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// The git repository the template is installed from
+    pub repository: String,
+    /// The branch to install from, or the repository's default if `None`
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A read-only SQL statement to run against a database.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SqlQuery {
+    pub database: String,
+    pub statement: String,
+}
+
+/// The rows returned by a SQL query. Each row has one value per column, in
+/// the same order as `columns`.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// A custom page which the platform serves in place of an app's own response,
+/// for example while the app is failing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErrorPage {
+    pub content: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+}
+
+/// A key value pair to write to an app's store, optionally expiring after `ttl_seconds`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SetKeyValuePair {
+    #[serde(rename = "appId")]
+    pub app_id: Uuid,
+    #[serde(rename = "storeName")]
+    pub store_name: String,
+    pub key: String,
+    pub value: String,
+    #[serde(rename = "ttlSeconds", skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+}
+
+/// A key to remove from an app's store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteKeyValuePair {
+    #[serde(rename = "appId")]
+    pub app_id: Uuid,
+    #[serde(rename = "storeName")]
+    pub store_name: String,
+    pub key: String,
+}
+
+/// A key in an app's key value store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyValueKey {
+    pub key: String,
+    /// RFC 3339 timestamp, or `None` if the key does not expire
+    #[serde(rename = "expiresAt", default)]
+    pub expires_at: Option<String>,
+}
+
+/// A key value store which is not tied to a single app.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyValueStore {
+    pub name: String,
+    #[serde(default)]
+    pub region: Option<String>,
+}
+
+/// How much a key value store holds, and how much it was used over a window
+/// of time ending now.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyValueStoreStats {
+    pub name: String,
+    #[serde(rename = "keyCount")]
+    pub key_count: u64,
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: u64,
+    #[serde(rename = "windowSeconds")]
+    pub window_seconds: u64,
+    #[serde(default)]
+    pub reads: u64,
+    #[serde(default)]
+    pub writes: u64,
+    #[serde(default)]
+    pub deletes: u64,
+}
+
+/// The details needed to create a key value store. Without a region, the
+/// store is created in the platform's default region.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateKeyValueStore {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+/// Extends the lifetime of keys, so that they expire `ttl_seconds` from now.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TouchKeyValuePairs {
+    #[serde(rename = "appId")]
+    pub app_id: Uuid,
+    #[serde(rename = "storeName")]
+    pub store_name: String,
+    pub keys: Vec<String>,
+    #[serde(rename = "ttlSeconds")]
+    pub ttl_seconds: u64,
+}
+
+/// A variable to set on an app. Secret values are never shown in listings
+/// and can only be read back by revealing them explicitly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SetVariablePair {
+    #[serde(rename = "appId")]
+    pub app_id: Uuid,
+    pub variable: String,
+    pub value: String,
+    pub secret: bool,
+}
+
+/// A platform-side webhook which is notified of lifecycle events for an app.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+}
+
+/// The details needed to register a new webhook for an app.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub events: Vec<String>,
+}
+
+/// An external endpoint to which the platform continuously forwards an app's logs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogDrain {
+    pub id: Uuid,
+    /// `syslog` or `http`
+    #[serde(rename = "type")]
+    pub drain_type: String,
+    pub url: String,
+}
+
+/// The details needed to add a log drain to an app.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateLogDrain {
+    #[serde(rename = "type")]
+    pub drain_type: String,
+    pub url: String,
+}
+
+/// A link through which one app calls another over the platform's internal
+/// network, addressing it by `label` rather than by its public URL.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppLink {
+    pub id: Uuid,
+    pub label: String,
+    #[serde(rename = "targetAppId")]
+    pub target_app_id: Uuid,
+    #[serde(rename = "targetAppName")]
+    pub target_app_name: String,
+}
+
+/// The details needed to let an app call another by a label.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateAppLink {
+    pub label: String,
+    #[serde(rename = "targetAppId")]
+    pub target_app_id: Uuid,
+}
+
+/// How much of one kind of resource an account uses, and how much its plan
+/// allows. A `limit` of `None` means there is no limit.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Quota {
+    pub used: u32,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+impl Quota {
+    /// How many more can be created, or `None` if there is no limit.
+    pub fn remaining(&self) -> Option<u32> {
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
+}
+
+/// The account's use of resources which are limited by its plan.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountQuotas {
+    /// The name of the account's plan, where the platform reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    pub apps: Quota,
+    pub databases: Quota,
+    /// The most each app's limits can be raised to on this plan
+    #[serde(
+        rename = "maxAppLimits",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_app_limits: Option<AppLimits>,
+}
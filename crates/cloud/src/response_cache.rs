@@ -0,0 +1,199 @@
+//! Keeps the responses to lookups which rarely change, such as the list of
+//! databases, so that a command which needs one several times asks the
+//! platform only once. Responses are kept in memory for the life of the
+//! process and, if [`persist_in`] is called, in files so that commands run
+//! in quick succession share them. Either way they expire after a short
+//! time, and any change made through the client forgets every response for
+//! that login.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// How long a response is used for before it is requested again
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+static CONFIG: Mutex<Config> = Mutex::new(Config {
+    enabled: true,
+    ttl: DEFAULT_TTL,
+    dir: None,
+});
+
+static ENTRIES: Mutex<Option<HashMap<String, Entry>>> = Mutex::new(None);
+
+struct Config {
+    enabled: bool,
+    ttl: Duration,
+    dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Seconds since the Unix epoch
+    stored_at: u64,
+    value: Value,
+}
+
+/// Stops responses being cached, so that every lookup goes to the platform.
+pub fn disable() {
+    config().enabled = false;
+}
+
+/// Also keeps responses in files in `dir`, so that later commands can use
+/// them until they expire.
+pub fn persist_in(dir: PathBuf) {
+    config().dir = Some(dir);
+}
+
+/// Returns the cached response for `key` if there is one, and otherwise
+/// fetches it, caching it if the fetch succeeds. `scope` keeps the responses
+/// seen by different logins apart.
+pub(crate) async fn cached<T, F>(scope: &str, key: &str, fetch: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T>>,
+{
+    if let Some(value) = get(scope, key) {
+        return Ok(value);
+    }
+    let value = fetch.await?;
+    put(scope, key, &value);
+    Ok(value)
+}
+
+/// Forgets every response cached for `scope`.
+pub(crate) fn invalidate(scope: &str) {
+    let prefix = format!("{scope}/");
+    if let Some(entries) = entries().as_mut() {
+        entries.retain(|key, _| !key.starts_with(&prefix));
+    }
+    if let Some(dir) = &config().dir {
+        _ = std::fs::remove_dir_all(dir.join(scope));
+    }
+}
+
+fn get<T: DeserializeOwned>(scope: &str, key: &str) -> Option<T> {
+    let (enabled, ttl, dir) = {
+        let config = config();
+        (config.enabled, config.ttl, config.dir.clone())
+    };
+    if !enabled {
+        return None;
+    }
+    let memory_key = format!("{scope}/{key}");
+    let in_memory = entries()
+        .as_ref()
+        .and_then(|entries| entries.get(&memory_key).cloned());
+    let entry = match in_memory {
+        Some(entry) => entry,
+        None => {
+            let text = std::fs::read_to_string(dir?.join(scope).join(file_name(key))).ok()?;
+            serde_json::from_str(&text).ok()?
+        }
+    };
+    if now().saturating_sub(entry.stored_at) >= ttl.as_secs() {
+        return None;
+    }
+    serde_json::from_value(entry.value).ok()
+}
+
+fn put<T: Serialize>(scope: &str, key: &str, value: &T) {
+    let (enabled, dir) = {
+        let config = config();
+        (config.enabled, config.dir.clone())
+    };
+    if !enabled {
+        return;
+    }
+    let Ok(value) = serde_json::to_value(value) else {
+        return;
+    };
+    let entry = Entry {
+        stored_at: now(),
+        value,
+    };
+    if let Some(dir) = dir {
+        // A cache which cannot be written is only slower.
+        let dir = dir.join(scope);
+        if std::fs::create_dir_all(&dir).is_ok() {
+            if let Ok(text) = serde_json::to_string(&entry) {
+                _ = std::fs::write(dir.join(file_name(key)), text);
+            }
+        }
+    }
+    entries()
+        .get_or_insert_with(HashMap::new)
+        .insert(format!("{scope}/{key}"), entry);
+}
+
+fn file_name(key: &str) -> String {
+    let safe = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{safe}.json")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn config() -> std::sync::MutexGuard<'static, Config> {
+    CONFIG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn entries() -> std::sync::MutexGuard<'static, Option<HashMap<String, Entry>>> {
+    ENTRIES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn responses_are_reused_until_invalidated() -> Result<()> {
+        let scope = "test-scope";
+        let first: Vec<String> =
+            cached(scope, "databases", async { Ok(vec!["db1".to_owned()]) }).await?;
+        let second: Vec<String> = cached(scope, "databases", async {
+            Ok(vec!["db1".to_owned(), "db2".to_owned()])
+        })
+        .await?;
+        assert_eq!(first, second);
+
+        invalidate(scope);
+        let third: Vec<String> = cached(scope, "databases", async {
+            Ok(vec!["db1".to_owned(), "db2".to_owned()])
+        })
+        .await?;
+        assert_eq!(third.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_are_not_cached() -> Result<()> {
+        let scope = "failing-scope";
+        let failed: Result<Vec<String>> =
+            cached(scope, "apps", async { Err(anyhow::anyhow!("unavailable")) }).await;
+        assert!(failed.is_err());
+        let apps: Vec<String> =
+            cached(scope, "apps", async { Ok(vec!["todo".to_owned()]) }).await?;
+        assert_eq!(apps, vec!["todo"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+//! Reports how long each call to the Cloud API takes, for callers which
+//! want to show where time goes.
+
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Receives the name of each API call, such as `list_apps`, and how long it took.
+pub type Recorder = fn(&'static str, Duration);
+
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+/// Sets the function which is told about every API call made from now on.
+/// Only the first call has any effect.
+pub fn set_recorder(recorder: Recorder) {
+    let _ = RECORDER.set(recorder);
+}
+
+pub(crate) async fn timed<T>(call: &'static str, request: impl Future<Output = T>) -> T {
+    let Some(recorder) = RECORDER.get() else {
+        return request.await;
+    };
+    let start = Instant::now();
+    let result = request.await;
+    recorder(call, start.elapsed());
+    result
+}
@@ -0,0 +1,265 @@
+//! Answers to interactive prompts, recorded with `--record-answers` and
+//! replayed with `--answers`.
+//!
+//! Commands prompt through [`confirm`], [`select`] and [`input`] rather than
+//! using dialoguer directly. While recording, every answer is written to the
+//! answers file as soon as it is given, so the file is complete even if the
+//! command fails part way. While replaying, prompts are answered from the
+//! file, in order, without asking; a prompt whose text differs from the one
+//! recorded fails rather than risk answering a different question.
+//!
+//! The interactive `sqlite shell` is a session rather than a prompt, and is
+//! not recorded.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// One answered prompt
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Answer {
+    pub prompt: String,
+    /// `None` if the prompt was cancelled
+    pub answer: Option<AnswerValue>,
+}
+
+/// A confirmation, or the text typed or chosen
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnswerValue {
+    Bool(bool),
+    Text(String),
+}
+
+enum Session {
+    Recording { path: PathBuf, answers: Vec<Answer> },
+    Replaying(Replay),
+}
+
+/// Recorded answers still to be given, in order
+#[derive(Debug)]
+struct Replay {
+    answers: VecDeque<Answer>,
+    given: usize,
+}
+
+impl Replay {
+    fn next(&mut self, prompt: &str) -> Result<Option<AnswerValue>> {
+        let Some(answer) = self.answers.pop_front() else {
+            bail!(
+                "The answers file has no answer for prompt {prompt:?}: it only records {} answer(s)",
+                self.given
+            );
+        };
+        self.given += 1;
+        if answer.prompt != prompt {
+            bail!(
+                "Answer {} in the answers file is for prompt {:?}, but the command asked {prompt:?}",
+                self.given,
+                answer.prompt
+            );
+        }
+        Ok(answer.answer)
+    }
+}
+
+/// Records every answer given from now on to `path`.
+pub fn record_to(path: &Path) {
+    set_session(Session::Recording {
+        path: path.to_owned(),
+        answers: vec![],
+    });
+}
+
+/// Answers prompts from now on from the answers recorded in `path`.
+pub fn replay_from(path: &Path) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read answers file {}", path.display()))?;
+    let answers: Vec<Answer> = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid answers file {}", path.display()))?;
+    set_session(Session::Replaying(Replay {
+        answers: answers.into(),
+        given: 0,
+    }));
+    Ok(())
+}
+
+/// Whether prompts are answered from a file, in which case the command
+/// may prompt even when it could not otherwise ask the user.
+pub fn replaying() -> bool {
+    matches!(*session(), Some(Session::Replaying(_)))
+}
+
+/// Asks a yes or no question. Cancelling counts as no.
+pub fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    let answer = answered(prompt, || {
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt(prompt)
+            .default(default)
+            .interact_opt()?
+            .unwrap_or_default();
+        Ok(Some(AnswerValue::Bool(confirmed)))
+    })?;
+    match answer {
+        Some(AnswerValue::Bool(confirmed)) => Ok(confirmed),
+        None => Ok(false),
+        Some(AnswerValue::Text(text)) => {
+            bail!("Expected a yes or no answer to prompt {prompt:?}, but the answers file has {text:?}")
+        }
+    }
+}
+
+/// Asks the user to choose one of `items`, returning its index, or `None`
+/// if they cancelled. Choices are recorded by their text.
+pub fn select<T: AsRef<str>>(prompt: &str, items: &[T], default: usize) -> Result<Option<usize>> {
+    let items = items.iter().map(|i| i.as_ref()).collect::<Vec<_>>();
+    let answer = answered(prompt, || {
+        let index = dialoguer::Select::new()
+            .with_prompt(prompt)
+            .items(&items)
+            .default(default)
+            .interact_opt()?;
+        Ok(index.map(|i| AnswerValue::Text(items[i].to_owned())))
+    })?;
+    match answer {
+        None => Ok(None),
+        Some(AnswerValue::Text(choice)) => items
+            .iter()
+            .position(|item| *item == choice)
+            .map(Some)
+            .with_context(|| {
+                format!("The recorded choice {choice:?} is not one of the options for prompt {prompt:?}")
+            }),
+        Some(AnswerValue::Bool(_)) => {
+            bail!("Expected a choice for prompt {prompt:?}, but the answers file has a yes or no answer")
+        }
+    }
+}
+
+/// Asks the user to type a line of text.
+pub fn input(prompt: &str, default: Option<String>) -> Result<String> {
+    input_validated(prompt, default, |_| Ok(()))
+}
+
+/// Asks the user to type a line of text which `validate` accepts. Replayed
+/// answers are validated too.
+pub fn input_validated(
+    prompt: &str,
+    default: Option<String>,
+    validate: impl Fn(&str) -> Result<(), String>,
+) -> Result<String> {
+    let answer = answered(prompt, || {
+        let mut input = dialoguer::Input::<String>::new();
+        input
+            .with_prompt(prompt)
+            .validate_with(|text: &String| validate(text));
+        if let Some(default) = default.clone() {
+            input.default(default);
+        }
+        Ok(Some(AnswerValue::Text(input.interact_text()?)))
+    })?;
+    match answer {
+        Some(AnswerValue::Text(text)) => {
+            validate(&text)
+                .map_err(|e| anyhow::anyhow!("Invalid recorded answer {text:?}: {e}"))?;
+            Ok(text)
+        }
+        _ => bail!("Expected text for prompt {prompt:?}, but the answers file has none"),
+    }
+}
+
+/// Takes the answer from the answers file when replaying, and otherwise
+/// asks, recording the answer if recording.
+fn answered(
+    prompt: &str,
+    ask: impl FnOnce() -> Result<Option<AnswerValue>>,
+) -> Result<Option<AnswerValue>> {
+    if let Some(Session::Replaying(replay)) = session().as_mut() {
+        return replay.next(prompt);
+    }
+    let answer = ask()?;
+    if let Some(Session::Recording { path, answers }) = session().as_mut() {
+        answers.push(Answer {
+            prompt: prompt.to_owned(),
+            answer: answer.clone(),
+        });
+        std::fs::write(&*path, serde_json::to_string_pretty(answers)?)
+            .with_context(|| format!("Could not write answers file {}", path.display()))?;
+    }
+    Ok(answer)
+}
+
+fn session() -> std::sync::MutexGuard<'static, Option<Session>> {
+    // A panic while holding the lock leaves the session usable.
+    SESSION.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_session(new: Session) {
+    *session() = Some(new);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn replay(answers: &[(&str, Option<AnswerValue>)]) -> Replay {
+        Replay {
+            answers: answers
+                .iter()
+                .map(|(prompt, answer)| Answer {
+                    prompt: prompt.to_string(),
+                    answer: answer.clone(),
+                })
+                .collect(),
+            given: 0,
+        }
+    }
+
+    #[test]
+    fn answers_are_replayed_in_order() -> Result<()> {
+        let mut replay = replay(&[
+            ("Continue anyway?", Some(AnswerValue::Bool(true))),
+            ("Which database?", Some(AnswerValue::Text("todo-db".into()))),
+        ]);
+        assert_eq!(
+            replay.next("Continue anyway?")?,
+            Some(AnswerValue::Bool(true))
+        );
+        assert_eq!(
+            replay.next("Which database?")?,
+            Some(AnswerValue::Text("todo-db".into()))
+        );
+        assert_eq!(
+            replay.next("Delete it?").unwrap_err().to_string(),
+            r#"The answers file has no answer for prompt "Delete it?": it only records 2 answer(s)"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn a_different_prompt_is_not_answered() {
+        let mut replay = replay(&[("Continue anyway?", Some(AnswerValue::Bool(true)))]);
+        assert_eq!(
+            replay.next("Delete 3 app(s)?").unwrap_err().to_string(),
+            r#"Answer 1 in the answers file is for prompt "Continue anyway?", but the command asked "Delete 3 app(s)?""#
+        );
+    }
+
+    #[test]
+    fn answers_file_format() -> Result<()> {
+        let answers: Vec<Answer> = serde_json::from_value(serde_json::json!([
+            { "prompt": "Continue anyway?", "answer": true },
+            { "prompt": "App name", "answer": "todo" },
+            { "prompt": "Which label?", "answer": null },
+        ]))?;
+        assert_eq!(answers[0].answer, Some(AnswerValue::Bool(true)));
+        assert_eq!(answers[1].answer, Some(AnswerValue::Text("todo".into())));
+        assert_eq!(answers[2].answer, None);
+        Ok(())
+    }
+}
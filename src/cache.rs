@@ -0,0 +1,164 @@
+//! Files the plugin keeps between runs to avoid downloading and uploading
+//! the same content again. Everything here can be deleted at any time; it is
+//! fetched again when next needed.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+
+/// A directory of cached files, and what it holds.
+pub(crate) struct Cache {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub dir: PathBuf,
+}
+
+/// The number and total size of a set of cached files.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Usage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+fn cache_root() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .context("Cannot find cache directory")?
+        .join("fermyon")
+        .join("cloud-plugin"))
+}
+
+/// The registry client's cache of component Wasm, static files and
+/// manifests. The plugin keeps its own rather than sharing Spin's, so that
+/// clearing it does not affect `spin up`.
+pub(crate) fn registry_cache_dir() -> Result<PathBuf> {
+    Ok(cache_root()?.join("registry"))
+}
+
+/// Responses to Cloud API lookups, kept between commands when the
+/// `api-cache` setting is on.
+fn api_cache_dir() -> Result<PathBuf> {
+    Ok(cache_root()?.join("api"))
+}
+
+/// Sets up caching of API lookups: within this process unless `no_cache`,
+/// and between commands too if the user has turned that on.
+pub fn configure_api_cache(no_cache: bool) {
+    if no_cache {
+        cloud::response_cache::disable();
+    } else if crate::opts::EnvSettings::from_env().api_cache {
+        if let Ok(dir) = api_cache_dir() {
+            cloud::response_cache::persist_in(dir);
+        }
+    }
+}
+
+/// Every cache the plugin maintains.
+pub(crate) fn caches() -> Result<Vec<Cache>> {
+    Ok(vec![
+        Cache {
+            name: "registry",
+            description: "Components and files pulled from or pushed to registries",
+            dir: registry_cache_dir()?,
+        },
+        Cache {
+            name: "api",
+            description: "Recent responses to lookups such as the database list",
+            dir: api_cache_dir()?,
+        },
+    ])
+}
+
+impl Cache {
+    pub fn usage(&self) -> Usage {
+        files_in(&self.dir).fold(Usage::default(), |usage, (_, metadata)| Usage {
+            files: usage.files + 1,
+            bytes: usage.bytes + metadata.len(),
+        })
+    }
+
+    /// Deletes the cached files which were last modified longer ago than
+    /// `older_than`, or all of them if it is `None`. Returns what was
+    /// deleted, or would be with `dry_run`.
+    pub fn prune(&self, older_than: Option<Duration>, dry_run: bool) -> Result<Usage> {
+        let cutoff = older_than.and_then(|age| SystemTime::now().checked_sub(age));
+        let mut removed = Usage::default();
+        for (path, metadata) in files_in(&self.dir) {
+            let expired = match cutoff {
+                None => true,
+                Some(cutoff) => metadata.modified().is_ok_and(|m| m < cutoff),
+            };
+            if !expired {
+                continue;
+            }
+            if !dry_run {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Could not delete {}", path.display()))?;
+            }
+            removed.files += 1;
+            removed.bytes += metadata.len();
+        }
+        if !dry_run {
+            remove_empty_dirs(&self.dir);
+        }
+        Ok(removed)
+    }
+}
+
+fn files_in(dir: &Path) -> impl Iterator<Item = (PathBuf, std::fs::Metadata)> {
+    walkdir::WalkDir::new(dir).into_iter().filter_map(|entry| {
+        let entry = entry.ok()?;
+        let metadata = entry.metadata().ok()?;
+        metadata.is_file().then(|| (entry.into_path(), metadata))
+    })
+}
+
+// Removes directories left empty by pruning, keeping `dir` itself.
+fn remove_empty_dirs(dir: &Path) {
+    for entry in walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+    {
+        // Fails harmlessly if the directory still has files in it.
+        let _ = std::fs::remove_dir(entry.path());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prunes_only_old_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = Cache {
+            name: "test",
+            description: "",
+            dir: dir.path().to_owned(),
+        };
+        std::fs::create_dir_all(dir.path().join("wasm/sha256"))?;
+        std::fs::write(dir.path().join("wasm/sha256/abc"), [0; 100])?;
+        std::fs::write(dir.path().join("manifest.json"), [0; 20])?;
+        assert_eq!(
+            cache.usage(),
+            Usage {
+                files: 2,
+                bytes: 120
+            }
+        );
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(cache.prune(Some(day), false)?, Usage::default());
+        assert_eq!(cache.prune(None, true)?.files, 2);
+        assert_eq!(cache.usage().files, 2);
+
+        assert_eq!(cache.prune(None, false)?.bytes, 120);
+        assert_eq!(cache.usage(), Usage::default());
+        assert!(!dir.path().join("wasm").exists());
+        assert!(dir.path().exists());
+        Ok(())
+    }
+}
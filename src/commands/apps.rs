@@ -1,8 +1,42 @@
-use crate::commands::{client_and_app_id, create_cloud_client, CommonArgs};
-use anyhow::{Context, Result};
-use clap::Parser;
-use cloud::{CloudClientInterface, DEFAULT_APPLIST_PAGE_SIZE};
-use cloud_openapi::models::{AppItem, AppItemPage, ValidationStatus};
+use crate::answers;
+use crate::cache::registry_cache_dir;
+use crate::commands::deploy::{app_routes, build_app_base_url, login_connection};
+use crate::commands::logs::parse_duration;
+use crate::commands::usage::print_plan;
+use crate::commands::{client_and_app_id, confirm_environment, create_cloud_client, CommonArgs};
+use crate::ops::apps::{
+    app_id, delete_app, delete_revision, list_app_revisions, list_apps, revision_page,
+    revisions_to_prune,
+};
+use crate::ops::deploy::registry_reference;
+use crate::ops::link::{list_links, LabelledLink};
+use crate::ops::resolve::not_found;
+use crate::ops::sqlite::{app_database_links, list_databases};
+use crate::ops::variables::get_variables;
+use crate::opts::{EnvSettings, CLOUD_APP_ENV, CLOUD_FORMAT_ENV};
+use crate::progress::{Progress, ProgressFormat};
+use crate::table::new_table;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{ArgGroup, Parser, ValueEnum};
+use cloud::{
+    client::{Client as CloudClient, ConnectionConfig},
+    models::{AppLimits, AppMetadata, ErrorPage, RevisionWeight, TrafficSplit},
+    CloudClientInterface,
+};
+use cloud_openapi::models::{AppItem, ValidationStatus};
+use oci_distribution::{token_cache, Reference, RegistryOperation};
+use serde::Serialize;
+use spin_locked_app::locked::LockedApp;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+mod env_template;
+mod metrics;
+
+use env_template::{env_file, runtime_config, ENV_FILE, RUNTIME_CONFIG_FILE};
 
 #[derive(Parser, Debug)]
 #[clap(about = "Manage applications deployed to Fermyon Cloud")]
@@ -13,6 +47,30 @@ pub enum AppsCommand {
     Delete(DeleteCommand),
     /// Get details about a deployed app in Fermyon Cloud
     Info(InfoCommand),
+    /// Manage the runtime limits of an app deployed in Fermyon Cloud
+    #[clap(subcommand)]
+    Limits(LimitsCommand),
+    /// Download the deployed artifact of an app for inspection or local use
+    Pull(PullCommand),
+    /// Show the HTTP routes of the deployed app and the URLs they are served at
+    Routes(RoutesCommand),
+    /// List the revisions of an app, newest first
+    Revisions(RevisionsCommand),
+    /// Delete old revisions of an app, reclaiming their storage
+    PruneRevisions(PruneRevisionsCommand),
+    /// Manage the page served when an app is failing or down for maintenance
+    #[clap(subcommand)]
+    ErrorPage(ErrorPageCommand),
+    /// Share an app's requests between its revisions, for gradual rollouts
+    #[clap(subcommand)]
+    Traffic(TrafficCommand),
+    /// Write a runtime config and env file for running the app locally with
+    /// `spin up`, standing in for its Cloud databases and variables
+    EnvTemplate(EnvTemplateCommand),
+    /// Show how many requests apps served, and how quickly, over a recent
+    /// window. With --format prometheus or --listen, the metrics can be
+    /// scraped by Prometheus.
+    Metrics(MetricsCommand),
 }
 
 #[derive(Parser, Debug)]
@@ -22,17 +80,289 @@ pub struct ListCommand {
 }
 
 #[derive(Parser, Debug)]
+#[clap(group(ArgGroup::new("selection").required(true).args(&["apps", "pattern"])))]
 pub struct DeleteCommand {
+    /// Names of Spin apps to delete
+    pub apps: Vec<String>,
+    /// Delete every app whose name matches this glob pattern, such as 'pr-*'.
+    /// You are asked to confirm unless --yes is given.
+    #[clap(long = "match", value_parser = glob::Pattern::new, conflicts_with = "apps")]
+    pub pattern: Option<glob::Pattern>,
+    /// Only delete matching apps which have not been deployed for this long
+    /// ("12h", "14d"). Apps with no recorded deployment time are kept.
+    #[clap(long = "older-than", value_parser = parse_duration, requires = "pattern", conflicts_with = "apps")]
+    pub older_than: Option<Duration>,
+    /// List the apps which would be deleted, without deleting them
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+    /// Skip the prompts to confirm deleting apps. Without it, deleting a
+    /// named app requires typing its name.
+    #[clap(short = 'y', long = "yes", takes_value = false)]
+    pub yes: bool,
+    /// How to report progress. With json, progress events are written to
+    /// stderr as newline-delimited JSON.
+    #[clap(value_enum, long = "progress", default_value = "text")]
+    pub progress: ProgressFormat,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct InfoCommand {
     /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
     pub app: String,
     #[clap(flatten)]
     common: CommonArgs,
 }
 
 #[derive(Parser, Debug)]
-pub struct InfoCommand {
+pub struct PullCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// The revision to download. If omitted, the most recently deployed
+    /// revision is downloaded.
+    #[clap(long = "revision")]
+    pub revision: Option<String>,
+    /// The directory to download the app into, which must be empty or not
+    /// yet exist. If omitted, a directory named after the app is used.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct RoutesCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct EnvTemplateCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// The directory to write runtime-config.toml and .env into
+    #[clap(short = 'o', long = "output-dir", default_value = ".")]
+    pub output_dir: PathBuf,
+    /// Overwrite the files if they already exist
+    #[clap(long = "force", takes_value = false)]
+    pub force: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct MetricsCommand {
+    /// Names of Spin apps. If omitted, every app is shown.
+    pub apps: Vec<String>,
+    /// How far back to count requests, such as "5m" or "1h"
+    #[clap(long = "window", value_parser = parse_duration, default_value = "5m")]
+    pub window: Duration,
+    /// Format of the metrics
+    #[clap(value_enum, long = "format", default_value = "table")]
+    pub format: MetricsFormat,
+    /// Serve the metrics for Prometheus at /metrics on this address, such
+    /// as ":9100", instead of printing them. They are fetched afresh for
+    /// each scrape.
+    #[clap(long = "listen", value_parser = metrics::parse_listen_address, conflicts_with = "format")]
+    pub listen: Option<std::net::SocketAddr>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum MetricsFormat {
+    Table,
+    Json,
+    Prometheus,
+}
+
+#[derive(Parser, Debug)]
+pub struct RevisionsCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Most revisions to list
+    #[clap(long = "limit")]
+    pub limit: Option<usize>,
+    /// Only list revisions deployed before this revision. With --limit,
+    /// this pages through older history.
+    #[clap(long = "before")]
+    pub before: Option<String>,
+    /// Format of list
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    pub format: ListFormat,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ListFormat {
+    Table,
+    Json,
+}
+
+/// A page of revisions, for JSON output. Passing `next_before` as
+/// `--before` fetches the next page, and it is `None` on the last page.
+#[derive(Serialize)]
+struct RevisionPageJson<'a> {
+    app: &'a str,
+    revisions: Vec<RevisionJson<'a>>,
+    next_before: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct RevisionJson<'a> {
+    id: Uuid,
+    revision_number: &'a str,
+    /// Whether a channel of the app is serving this revision
+    active: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct PruneRevisionsCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Number of the most recently deployed revisions to keep. The active
+    /// revision is always kept in addition to these.
+    #[clap(long = "keep", default_value = "10")]
+    pub keep: usize,
+    /// List the revisions which would be deleted, without deleting them
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+    /// Skip the prompt to confirm deleting revisions
+    #[clap(short = 'y', long = "yes", takes_value = false)]
+    pub yes: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub enum ErrorPageCommand {
+    /// Upload a custom error page for an app
+    Set(SetErrorPageCommand),
+    /// Remove a custom error page, restoring the platform default
+    Unset(UnsetErrorPageCommand),
+}
+
+/// The situations in which the platform serves an error page instead of the app
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ErrorPageKind {
+    /// The app failed to handle the request (a 5xx response)
+    ServerError,
+    /// The app is unavailable, for example during a deployment
+    Maintenance,
+}
+
+impl ErrorPageKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ServerError => "server-error",
+            Self::Maintenance => "maintenance",
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct SetErrorPageCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// The HTML or plain text file to serve
+    #[clap(short = 'f', long = "file")]
+    pub file: PathBuf,
+    /// When the page is served
+    #[clap(value_enum, long = "kind", default_value = "server-error")]
+    pub kind: ErrorPageKind,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct UnsetErrorPageCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Which page to remove
+    #[clap(value_enum, long = "kind", default_value = "server-error")]
+    pub kind: ErrorPageKind,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub enum TrafficCommand {
+    /// Show how an app's requests are shared between its revisions
+    Show(ShowTrafficCommand),
+    /// Set how an app's requests are shared between its revisions
+    Set(SetTrafficCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct ShowTrafficCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Format of output
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    pub format: ListFormat,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetTrafficCommand {
     /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
     pub app: String,
+    /// The percentage of requests a revision serves, such as 42=90. Can be
+    /// used multiple times, and the percentages must add up to 100.
+    /// Revisions left out, or given 0, serve no requests.
+    #[clap(long = "revision", value_name = "REVISION=PERCENT", value_parser = parse_revision_weight, required = true)]
+    pub revisions: Vec<RevisionWeight>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub enum LimitsCommand {
+    /// Show the current runtime limits of an app
+    Get(GetLimitsCommand),
+    /// Change the runtime limits of an app
+    Set(SetLimitsCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct GetLimitsCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+#[clap(group(ArgGroup::new("limits").required(true).multiple(true)))]
+pub struct SetLimitsCommand {
+    /// Name of Spin app
+    #[clap(env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Maximum number of requests the app may handle concurrently
+    #[clap(long = "max-concurrency", group = "limits")]
+    pub max_concurrency: Option<u32>,
+    /// Maximum memory, in megabytes, available to each instance of the app
+    #[clap(long = "memory", group = "limits")]
+    pub memory_mb: Option<u32>,
+    /// Maximum time, in seconds, the app may take to handle a request
+    #[clap(long = "timeout", group = "limits")]
+    pub timeout_secs: Option<u32>,
     #[clap(flatten)]
     common: CommonArgs,
 }
@@ -43,40 +373,414 @@ impl AppsCommand {
             AppsCommand::List(cmd) => cmd.run().await,
             AppsCommand::Delete(cmd) => cmd.run().await,
             AppsCommand::Info(cmd) => cmd.run().await,
+            AppsCommand::Limits(cmd) => cmd.run().await,
+            AppsCommand::Pull(cmd) => cmd.run().await,
+            AppsCommand::Routes(cmd) => cmd.run().await,
+            AppsCommand::Revisions(cmd) => cmd.run().await,
+            AppsCommand::PruneRevisions(cmd) => cmd.run().await,
+            AppsCommand::ErrorPage(cmd) => cmd.run().await,
+            AppsCommand::Traffic(cmd) => cmd.run().await,
+            AppsCommand::EnvTemplate(cmd) => cmd.run().await,
+            AppsCommand::Metrics(cmd) => cmd.run().await,
         }
     }
 }
 
+impl MetricsCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        if let Some(address) = self.listen {
+            return metrics::serve(&client, address, &self.apps, self.window).await;
+        }
+        let samples = metrics::collect(&client, &self.apps, self.window).await?;
+        match self.format {
+            MetricsFormat::Table => metrics::print_table(&samples),
+            MetricsFormat::Json => println!("{}", metrics::to_json(&samples)?),
+            MetricsFormat::Prometheus => print!("{}", metrics::to_prometheus(&samples)),
+        }
+        if let Some(failed) = samples.iter().find(|s| s.metrics.is_none()) {
+            bail!("Problem fetching metrics of app {}", failed.app);
+        }
+        Ok(())
+    }
+}
+
+impl EnvTemplateCommand {
+    pub async fn run(self) -> Result<()> {
+        let config_path = self.output_dir.join(RUNTIME_CONFIG_FILE);
+        let env_path = self.output_dir.join(ENV_FILE);
+        if !self.force {
+            if let Some(existing) = [&config_path, &env_path].into_iter().find(|p| p.exists()) {
+                bail!(
+                    "{} already exists. Use --force to overwrite it.",
+                    existing.display()
+                );
+            }
+        }
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let links = app_database_links(&list_databases(&client).await?, &self.app);
+        let variables = get_variables(&client, app_id).await?;
+
+        std::fs::create_dir_all(&self.output_dir)
+            .with_context(|| format!("Could not create directory {}", self.output_dir.display()))?;
+        for (path, contents) in [
+            (&config_path, runtime_config(&self.app, &links)),
+            (&env_path, env_file(&self.app, &variables)),
+        ] {
+            std::fs::write(path, contents)
+                .with_context(|| format!("Could not write {}", path.display()))?;
+            println!("Wrote {}", path.display());
+        }
+        println!(
+            "Fill in the variable values in {}, then run the app locally with:\n  set -a; . {}; set +a\n  spin up --runtime-config-file {}",
+            env_path.display(),
+            env_path.display(),
+            config_path.display()
+        );
+        Ok(())
+    }
+}
+
 impl ListCommand {
     pub async fn run(self) -> Result<()> {
         let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
-        let mut app_list_page = client.list_apps(DEFAULT_APPLIST_PAGE_SIZE, None).await?;
-        if app_list_page.total_items <= 0 {
+        let apps = list_apps(&client).await?;
+        if apps.is_empty() {
             eprintln!("No applications found");
         } else {
-            print_app_list(&app_list_page);
-            let mut page_index = 1;
-            while !app_list_page.is_last_page {
-                app_list_page = client
-                    .list_apps(DEFAULT_APPLIST_PAGE_SIZE, Some(page_index))
-                    .await?;
-                print_app_list(&app_list_page);
-                page_index += 1;
-            }
+            print_app_list(&apps);
         }
         Ok(())
     }
 }
 
 impl DeleteCommand {
+    pub async fn run(self) -> Result<()> {
+        if !self.dry_run {
+            confirm_environment(self.common.deployment_env_id.as_deref())?;
+        }
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let mut targets = self.targets(&client).await?;
+        if targets.is_empty() {
+            println!("No apps to delete");
+            return Ok(());
+        }
+        if self.dry_run {
+            println!("Would delete {} app(s):", targets.len());
+            for (name, _) in &targets {
+                println!("  {name}");
+            }
+            return Ok(());
+        }
+        if self.pattern.is_some() && !self.yes && !confirm_bulk_delete(&targets)? {
+            println!("No apps deleted");
+            return Ok(());
+        }
+        if self.pattern.is_none() && !self.yes {
+            if !EnvSettings::from_env().interactive() {
+                bail!(
+                    "Use --yes to delete {} without confirmation",
+                    targets
+                        .iter()
+                        .map(|(name, _)| format!("app \"{name}\""))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            let mut confirmed = vec![];
+            for (name, id) in targets {
+                let impact = deletion_impact(&client, id).await?;
+                if confirm_app_delete(&name, &impact)? {
+                    confirmed.push((name, id));
+                }
+            }
+            if confirmed.is_empty() {
+                println!("No apps deleted");
+                return Ok(());
+            }
+            targets = confirmed;
+        }
+
+        if let ([(name, id)], None) = (targets.as_slice(), &self.pattern) {
+            delete_app(&client, name, *id).await?;
+            println!("Deleted app \"{name}\" successfully.");
+            return Ok(());
+        }
+        let results = delete_apps(&client, &targets, Progress::new(self.progress)).await;
+        let mut table = new_table();
+        table.set_header(vec!["App", "Result"]);
+        table.add_rows(
+            targets
+                .iter()
+                .zip(&results)
+                .map(|((name, _), result)| match result {
+                    Ok(()) => [name.clone(), "deleted".to_owned()],
+                    Err(e) => [name.clone(), format!("failed: {e:#}")],
+                }),
+        );
+        println!("{table}");
+        let failed = results.iter().filter(|r| r.is_err()).count();
+        if failed > 0 {
+            bail!("{failed} of {} apps could not be deleted", results.len());
+        }
+        Ok(())
+    }
+
+    /// The names and IDs of the apps to delete. Every app named explicitly
+    /// must exist.
+    async fn targets(&self, client: &impl CloudClientInterface) -> Result<Vec<(String, Uuid)>> {
+        let apps = list_apps(client).await?;
+        let Some(pattern) = &self.pattern else {
+            return self
+                .apps
+                .iter()
+                .map(|name| {
+                    apps.iter()
+                        .find(|a| &a.name == name)
+                        .map(|a| (a.name.clone(), a.id))
+                        .ok_or_else(|| not_found("app", name, apps.iter().map(|a| a.name.as_str())))
+                })
+                .collect();
+        };
+
+        let mut matched = apps
+            .into_iter()
+            .filter(|a| pattern.matches(&a.name))
+            .map(|a| (a.name, a.id))
+            .collect::<Vec<_>>();
+        if let Some(older_than) = self.older_than {
+            let metadata = client
+                .get_apps_metadata()
+                .await
+                .context("Problem fetching app deployment times")?;
+            let cutoff = Utc::now() - chrono::Duration::from_std(older_than)?;
+            matched.retain(|(_, id)| {
+                last_deployed(metadata.iter().find(|m| &m.id == id)).is_some_and(|t| t < cutoff)
+            });
+        }
+        Ok(matched)
+    }
+}
+
+// When an app was last deployed, falling back to when it was created.
+fn last_deployed(metadata: Option<&AppMetadata>) -> Option<DateTime<Utc>> {
+    let metadata = metadata?;
+    let time = metadata
+        .last_deployed_at
+        .as_deref()
+        .or(metadata.created_at.as_deref())?;
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// What is lost along with an app when it is deleted
+struct DeletionImpact {
+    links: Vec<LabelledLink>,
+    variables: usize,
+}
+
+async fn deletion_impact(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+) -> Result<DeletionImpact> {
+    Ok(DeletionImpact {
+        links: list_links(client, app_id).await?,
+        variables: get_variables(client, app_id).await?.len(),
+    })
+}
+
+fn describe_deletion(app: &str, impact: &DeletionImpact) -> String {
+    let mut description = format!(
+        "Deleting app \"{app}\" permanently removes its deployed revisions, its URL and its {} variable(s).\n",
+        impact.variables
+    );
+    if !impact.links.is_empty() {
+        description.push_str(
+            "Its links to these resources are removed, but the resources themselves are kept:\n",
+        );
+        for link in &impact.links {
+            description.push_str(&format!(
+                "  {} {} (label \"{}\")\n",
+                link.kind, link.resource, link.label
+            ));
+        }
+    }
+    description
+}
+
+// Like deleting a database, deleting a named app requires typing its name.
+fn confirm_app_delete(app: &str, impact: &DeletionImpact) -> Result<bool> {
+    print!("{}", describe_deletion(app, impact));
+    let answer = answers::input(
+        &format!("The action is irreversible. Please type \"{app}\" for confirmation"),
+        None,
+    )?;
+    if answer != app {
+        println!("Invalid confirmation. Will not delete app \"{app}\".");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn confirm_bulk_delete(targets: &[(String, Uuid)]) -> Result<bool> {
+    if !EnvSettings::from_env().interactive() {
+        bail!(
+            "Use --yes to delete {} app(s) without confirmation",
+            targets.len()
+        );
+    }
+    println!("The following apps will be deleted:");
+    for (name, _) in targets {
+        println!("  {name}");
+    }
+    answers::confirm(
+        &format!("Delete {} app(s)? This cannot be undone.", targets.len()),
+        false,
+    )
+}
+
+/// Deletes each app in turn, carrying on past failures. Results are in the
+/// same order as the apps.
+async fn delete_apps(
+    client: &impl CloudClientInterface,
+    apps: &[(String, Uuid)],
+    progress: Progress,
+) -> Vec<Result<()>> {
+    let mut results = Vec::with_capacity(apps.len());
+    for (index, (name, id)) in apps.iter().enumerate() {
+        progress.item("deleting", name, index, apps.len());
+        results.push(delete_app(client, name, *id).await);
+    }
+    progress.phase("done", 100);
+    results
+}
+
+impl RevisionsCommand {
     pub async fn run(self) -> Result<()> {
         let (client, app_id) =
             client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
-        client
-            .remove_app(app_id.to_string())
+        let app = client
+            .get_app(app_id.to_string())
+            .await
+            .with_context(|| format!("Error: could not get details about {}", &self.app))?;
+        let active = app
+            .channels
+            .iter()
+            .filter_map(|c| c.active_revision_number.clone())
+            .collect::<Vec<_>>();
+        let revisions = list_app_revisions(&client, app_id).await?;
+        let page = revision_page(revisions, self.before.as_deref(), self.limit)?;
+        let oldest = page
+            .revisions
+            .last()
+            .filter(|_| page.more)
+            .map(|r| r.revision_number.as_str());
+
+        match self.format {
+            ListFormat::Json => {
+                let json = RevisionPageJson {
+                    app: &self.app,
+                    revisions: page
+                        .revisions
+                        .iter()
+                        .map(|r| RevisionJson {
+                            id: r.id,
+                            revision_number: &r.revision_number,
+                            active: active.contains(&r.revision_number),
+                        })
+                        .collect(),
+                    next_before: oldest,
+                };
+                println!("{}", serde_json::to_string_pretty(&json)?);
+            }
+            ListFormat::Table if page.revisions.is_empty() => {
+                eprintln!("No revisions found for app \"{}\"", &self.app);
+            }
+            ListFormat::Table => {
+                let mut table = new_table();
+                table.set_header(vec!["Revision", "Active", "ID"]);
+                table.add_rows(page.revisions.iter().map(|r| {
+                    [
+                        r.revision_number.clone(),
+                        if active.contains(&r.revision_number) {
+                            "yes".to_owned()
+                        } else {
+                            String::new()
+                        },
+                        r.id.to_string(),
+                    ]
+                }));
+                println!("{table}");
+                if let Some(oldest) = oldest {
+                    eprintln!("Older revisions exist. List them with --before {oldest}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PruneRevisionsCommand {
+    pub async fn run(self) -> Result<()> {
+        if !self.dry_run {
+            confirm_environment(self.common.deployment_env_id.as_deref())?;
+        }
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let app = client
+            .get_app(app_id.to_string())
             .await
-            .with_context(|| format!("Problem deleting app named {}", &self.app))?;
-        println!("Deleted app \"{}\" successfully.", &self.app);
+            .with_context(|| format!("Error: could not get details about {}", &self.app))?;
+        let active = app
+            .channels
+            .iter()
+            .filter_map(|c| c.active_revision_number.clone())
+            .collect::<Vec<_>>();
+        let revisions = list_app_revisions(&client, app_id).await?;
+        let prune = revisions_to_prune(revisions, self.keep, &active);
+        if prune.is_empty() {
+            println!(
+                r#"App "{}" has no more than {} old revision(s) to keep"#,
+                self.app, self.keep
+            );
+            return Ok(());
+        }
+
+        let numbers = prune
+            .iter()
+            .map(|r| r.revision_number.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.dry_run {
+            println!("Would delete {} revision(s): {numbers}", prune.len());
+            return Ok(());
+        }
+        if !self.yes {
+            if !EnvSettings::from_env().interactive() {
+                bail!(
+                    "Use --yes to delete {} revision(s) without confirmation",
+                    prune.len()
+                );
+            }
+            let confirmed = answers::confirm(
+                &format!(
+                    r#"Delete revision(s) {numbers} of app "{}"? This cannot be undone."#,
+                    self.app
+                ),
+                false,
+            )?;
+            if !confirmed {
+                println!("No revisions deleted");
+                return Ok(());
+            }
+        }
+
+        for revision in &prune {
+            delete_revision(&client, app_id, revision).await?;
+            println!("Deleted revision {}", revision.revision_number);
+        }
         Ok(())
     }
 }
@@ -98,11 +802,475 @@ impl InfoCommand {
         if let Some(domain) = in_progress_domain {
             println!("Validation for {} is in progress", domain);
         };
+        // Not all Cloud instances report limits or quotas, so don't fail the whole command over them.
+        let quotas = client.get_account_quotas().await.ok();
+        if let Ok(limits) = client.get_app_limits(app_id).await {
+            println!("Limits:");
+            print_limits(
+                &limits,
+                quotas.as_ref().and_then(|q| q.max_app_limits.as_ref()),
+                "  ",
+            );
+        }
+        if let Some(quotas) = &quotas {
+            println!("Account:");
+            print_plan(quotas, "  ");
+        }
+
+        Ok(())
+    }
+}
+
+impl LimitsCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Get(cmd) => cmd.run().await,
+            Self::Set(cmd) => cmd.run().await,
+        }
+    }
+}
 
+impl GetLimitsCommand {
+    pub async fn run(self) -> Result<()> {
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let limits = client
+            .get_app_limits(app_id)
+            .await
+            .with_context(|| format!("Problem fetching limits for app {}", &self.app))?;
+        print_limits(&limits, None, "");
+        Ok(())
+    }
+}
+
+impl SetLimitsCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let limits = AppLimits {
+            max_concurrency: self.max_concurrency,
+            memory_mb: self.memory_mb,
+            timeout_secs: self.timeout_secs,
+        };
+        client
+            .set_app_limits(app_id, limits)
+            .await
+            .with_context(|| format!("Problem updating limits for app {}", &self.app))?;
+        println!("Updated limits for app \"{}\".", &self.app);
+        let limits = client.get_app_limits(app_id).await?;
+        print_limits(&limits, None, "");
         Ok(())
     }
 }
 
+impl PullCommand {
+    pub async fn run(self) -> Result<()> {
+        let login_connection = login_connection(self.common.deployment_env_id.as_deref()).await?;
+        let connection_config = ConnectionConfig {
+            url: login_connection.url.to_string(),
+            insecure: login_connection.danger_accept_invalid_certs,
+            token: login_connection.token,
+        };
+        let client = CloudClient::new(connection_config.clone());
+        let app_id = app_id(&client, &self.app).await?;
+
+        let revisions = list_app_revisions(&client, app_id).await?;
+        let revision = match &self.revision {
+            Some(wanted) => revisions
+                .iter()
+                .find(|r| &r.revision_number == wanted)
+                .with_context(|| format!(r#"App "{}" has no revision "{wanted}""#, self.app))?,
+            None => revisions
+                .last()
+                .with_context(|| format!(r#"App "{}" has no deployed revisions"#, self.app))?,
+        };
+
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&self.app));
+        ensure_empty_dir(&output)?;
+
+        let reference =
+            registry_reference(&connection_config.url, &self.app, &revision.revision_number)?;
+        println!(
+            "Downloading {} revision {} to {}...",
+            self.app,
+            revision.revision_number,
+            output.display()
+        );
+        let locked_app = pull_app(&reference, &output, &connection_config)
+            .await
+            .with_context(|| format!("Problem downloading app {}", self.app))?;
+        println!(
+            "Downloaded {} component(s). The locked app manifest is at {}",
+            locked_app.components.len(),
+            output.join(LOCKED_APP_FILE).display()
+        );
+        Ok(())
+    }
+}
+
+impl RoutesCommand {
+    pub async fn run(self) -> Result<()> {
+        let login_connection = login_connection(self.common.deployment_env_id.as_deref()).await?;
+        let connection_config = ConnectionConfig {
+            url: login_connection.url.to_string(),
+            insecure: login_connection.danger_accept_invalid_certs,
+            token: login_connection.token,
+        };
+        let client = CloudClient::new(connection_config.clone());
+        let app_id = app_id(&client, &self.app).await?;
+        let app = client
+            .get_app(app_id.to_string())
+            .await
+            .with_context(|| format!("Error: could not get details about {}", &self.app))?;
+
+        let domain = domains_current_and_in_progress(&app)
+            .0
+            .with_context(|| format!(r#"App "{}" has no domain yet"#, self.app))?;
+        let app_base_url = build_app_base_url(domain, &login_connection.url)?;
+
+        let dir = tempfile::tempdir()?;
+        let (revision, locked_app) =
+            load_active_revision(&app, &self.app, dir.path(), &connection_config).await?;
+
+        let routes = app_routes(&locked_app, &app_base_url);
+        if routes.is_empty() {
+            eprintln!(
+                r#"Revision {revision} of app "{}" has no HTTP routes"#,
+                self.app
+            );
+            return Ok(());
+        }
+        println!("Routes of revision {revision}:");
+        let mut table = new_table();
+        table.set_header(vec!["Component", "Route", "URL"]);
+        table.add_rows(routes.into_iter().map(|r| [r.component, r.route, r.url]));
+        println!("{table}");
+        Ok(())
+    }
+}
+
+impl ErrorPageCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Set(cmd) => cmd.run().await,
+            Self::Unset(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl SetErrorPageCommand {
+    pub async fn run(self) -> Result<()> {
+        let page = load_error_page(&self.file)?;
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        client
+            .set_error_page(app_id, self.kind.as_str(), page)
+            .await
+            .with_context(|| format!("Problem setting error page for app {}", &self.app))?;
+        println!(
+            "Set {} page for app \"{}\" from {}",
+            self.kind.as_str(),
+            &self.app,
+            self.file.display()
+        );
+        Ok(())
+    }
+}
+
+impl UnsetErrorPageCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        client
+            .remove_error_page(app_id, self.kind.as_str())
+            .await
+            .with_context(|| format!("Problem removing error page for app {}", &self.app))?;
+        println!(
+            "Removed {} page for app \"{}\". The platform default will be served.",
+            self.kind.as_str(),
+            &self.app
+        );
+        Ok(())
+    }
+}
+
+impl TrafficCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Show(cmd) => cmd.run().await,
+            Self::Set(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl ShowTrafficCommand {
+    pub async fn run(self) -> Result<()> {
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let split = client
+            .get_app_traffic(app_id)
+            .await
+            .with_context(|| format!("Problem fetching traffic split for app {}", &self.app))?;
+        match self.format {
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&split)?),
+            ListFormat::Table => print_traffic(&split),
+        }
+        Ok(())
+    }
+}
+
+impl SetTrafficCommand {
+    pub async fn run(self) -> Result<()> {
+        let split = traffic_split(self.revisions)?;
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let revisions = list_app_revisions(&client, app_id).await?;
+        for weight in &split.revisions {
+            if !revisions
+                .iter()
+                .any(|r| r.revision_number == weight.revision_number)
+            {
+                return Err(not_found(
+                    "revision",
+                    &weight.revision_number,
+                    revisions.iter().map(|r| r.revision_number.as_str()),
+                ));
+            }
+        }
+        client
+            .set_app_traffic(app_id, split)
+            .await
+            .with_context(|| format!("Problem setting traffic split for app {}", &self.app))?;
+        println!("Updated traffic split for app \"{}\".", &self.app);
+        print_traffic(&client.get_app_traffic(app_id).await?);
+        Ok(())
+    }
+}
+
+fn parse_revision_weight(s: &str) -> Result<RevisionWeight, String> {
+    let (revision, percent) = s
+        .split_once('=')
+        .filter(|(r, _)| !r.trim().is_empty())
+        .ok_or_else(|| format!("'{s}' is not of the form REVISION=PERCENT, such as 42=90"))?;
+    let weight = percent
+        .trim()
+        .parse::<u8>()
+        .ok()
+        .filter(|w| *w <= 100)
+        .ok_or_else(|| format!("'{percent}' is not a percentage from 0 to 100"))?;
+    Ok(RevisionWeight {
+        revision_number: revision.trim().to_owned(),
+        weight,
+    })
+}
+
+/// Checks that each revision is given once and that the percentages add up
+/// to 100, leaving out revisions which are to serve nothing.
+fn traffic_split(revisions: Vec<RevisionWeight>) -> Result<TrafficSplit> {
+    for (i, weight) in revisions.iter().enumerate() {
+        if revisions[..i]
+            .iter()
+            .any(|w| w.revision_number == weight.revision_number)
+        {
+            bail!(
+                "Revision {} is given more than once",
+                weight.revision_number
+            );
+        }
+    }
+    let total = revisions.iter().map(|w| u32::from(w.weight)).sum::<u32>();
+    if total != 100 {
+        bail!("The percentages add up to {total}, not 100");
+    }
+    Ok(TrafficSplit {
+        revisions: revisions.into_iter().filter(|w| w.weight > 0).collect(),
+    })
+}
+
+fn print_traffic(split: &TrafficSplit) {
+    let mut table = new_table();
+    table.set_header(vec!["Revision", "Requests"]);
+    table.add_rows(
+        split
+            .revisions
+            .iter()
+            .map(|w| [w.revision_number.clone(), format!("{}%", w.weight)]),
+    );
+    println!("{table}");
+}
+
+// Error pages are served in place of the app, so anything they reference must
+// be inlined. This leaves room for inline styles without encouraging bundles.
+const MAX_ERROR_PAGE_BYTES: u64 = 64 * 1024;
+
+fn load_error_page(path: &Path) -> Result<ErrorPage> {
+    let content_type = match path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("html" | "htm") => "text/html",
+        Some("txt") => "text/plain",
+        _ => bail!(
+            "Error page {} must be an HTML (.html) or plain text (.txt) file",
+            path.display()
+        ),
+    };
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Could not read error page {}", path.display()))?
+        .len();
+    if size > MAX_ERROR_PAGE_BYTES {
+        bail!(
+            "Error page {} is {size} bytes, but the limit is {MAX_ERROR_PAGE_BYTES} bytes",
+            path.display()
+        );
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Error page {} is not valid UTF-8 text", path.display()))?;
+    if content.trim().is_empty() {
+        bail!("Error page {} is empty", path.display());
+    }
+    Ok(ErrorPage {
+        content,
+        content_type: content_type.to_owned(),
+    })
+}
+
+const LOCKED_APP_FILE: &str = "spin.lock";
+
+fn ensure_empty_dir(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        if dir.read_dir()?.next().is_some() {
+            bail!("Output directory {} is not empty", dir.display());
+        }
+    } else {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create directory {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+// Loads the app from the Cloud registry. Component Wasm and files are kept in
+// the OCI cache and `working_dir`.
+async fn load_deployed_app(
+    reference: &str,
+    working_dir: &Path,
+    connection_config: &ConnectionConfig,
+) -> Result<LockedApp> {
+    let oci_ref = Reference::try_from(reference)
+        .with_context(|| format!("Could not parse reference '{reference}'"))?;
+    let mut oci_client =
+        spin_oci::Client::new(connection_config.insecure, Some(registry_cache_dir()?))
+            .await
+            .context("cannot create registry client")?;
+    oci_client.insert_token(
+        &oci_ref,
+        RegistryOperation::Pull,
+        token_cache::RegistryTokenType::Bearer(token_cache::RegistryToken::Token {
+            token: connection_config.token.clone(),
+        }),
+    );
+    spin_oci::OciLoader::new(working_dir)
+        .load_app(&mut oci_client, reference)
+        .await
+}
+
+/// Loads the active revision of an app from the Cloud registry, returning
+/// its revision number with it. Component Wasm and files are kept in the OCI
+/// cache and `working_dir`.
+pub(crate) async fn load_active_revision(
+    app: &AppItem,
+    name: &str,
+    working_dir: &Path,
+    connection_config: &ConnectionConfig,
+) -> Result<(String, LockedApp)> {
+    let revision = app
+        .channels
+        .first()
+        .and_then(|c| c.active_revision_number.clone())
+        .with_context(|| format!(r#"App "{name}" has no active revision"#))?;
+    let reference = registry_reference(&connection_config.url, name, &revision)?;
+    let locked_app = load_deployed_app(&reference, working_dir, connection_config)
+        .await
+        .with_context(|| format!("Problem loading revision {revision} of app {name}"))?;
+    Ok((revision, locked_app))
+}
+
+// Loads the app from the Cloud registry into `output`, then copies the component
+// Wasm out of the OCI cache so that the directory is self-contained.
+async fn pull_app(
+    reference: &str,
+    output: &Path,
+    connection_config: &ConnectionConfig,
+) -> Result<LockedApp> {
+    let output = output.canonicalize()?;
+    let mut locked_app = load_deployed_app(reference, &output, connection_config).await?;
+
+    for component in &mut locked_app.components {
+        let source = component
+            .source
+            .content
+            .source
+            .as_deref()
+            .with_context(|| format!("Component {} has no source", component.id))?;
+        let cached = Url::parse(source)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .with_context(|| {
+                format!(
+                    "Component {} has an unexpected source {source}",
+                    component.id
+                )
+            })?;
+        let local = output.join(format!("{}.wasm", component.id));
+        std::fs::copy(&cached, &local)
+            .with_context(|| format!("Could not copy Wasm for component {}", component.id))?;
+        let local_url = Url::from_file_path(&local)
+            .map_err(|_| anyhow!("Could not convert {} to a URL", local.display()))?;
+        component.source.content.source = Some(local_url.to_string());
+    }
+
+    std::fs::write(
+        output.join(LOCKED_APP_FILE),
+        serde_json::to_vec_pretty(&locked_app)?,
+    )?;
+    Ok(locked_app)
+}
+
+/// Prints an app's limits, alongside the most the plan allows where known.
+pub(crate) fn print_limits(limits: &AppLimits, max: Option<&AppLimits>, indent: &str) {
+    let show = |value: Option<u32>, max: Option<u32>, unit: &str| {
+        let value = match value {
+            Some(v) => format!("{v}{unit}"),
+            None => "platform default".to_owned(),
+        };
+        match max {
+            Some(m) => format!("{value} (plan allows up to {m}{unit})"),
+            None => value,
+        }
+    };
+    let max = max.cloned().unwrap_or_default();
+    println!(
+        "{indent}Max concurrency: {}",
+        show(limits.max_concurrency, max.max_concurrency, "")
+    );
+    println!(
+        "{indent}Memory: {}",
+        show(limits.memory_mb, max.memory_mb, " MB")
+    );
+    println!(
+        "{indent}Timeout: {}",
+        show(limits.timeout_secs, max.timeout_secs, "s")
+    );
+}
+
 fn domains_current_and_in_progress(app: &AppItem) -> (Option<&String>, Option<&String>) {
     let auto_domain = app.channels[0].domain.as_ref();
     match &app.domain {
@@ -125,8 +1293,141 @@ fn print_if_present(prefix: &str, value: Option<&String>) {
     }
 }
 
-fn print_app_list(page: &AppItemPage) {
-    for app in &page.items {
+fn print_app_list(apps: &[AppItem]) {
+    for app in apps {
         println!("{}", app.name);
     }
 }
+
+#[cfg(test)]
+mod apps_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    #[test]
+    fn deletion_lists_what_is_lost() {
+        let impact = DeletionImpact {
+            links: vec![LabelledLink {
+                kind: "sqlite",
+                label: "default".to_owned(),
+                resource: "shop-db".to_owned(),
+            }],
+            variables: 3,
+        };
+        assert_eq!(
+            describe_deletion("shop", &impact),
+            "Deleting app \"shop\" permanently removes its deployed revisions, its URL and its 3 variable(s).
+Its links to these resources are removed, but the resources themselves are kept:
+  sqlite shop-db (label \"default\")
+"
+        );
+    }
+
+    #[test]
+    fn age_is_measured_from_last_deployment() {
+        let metadata = |created: Option<&str>, deployed: Option<&str>| AppMetadata {
+            id: Uuid::new_v4(),
+            name: "pr-1".to_owned(),
+            created_at: created.map(str::to_owned),
+            last_deployed_at: deployed.map(str::to_owned),
+        };
+        let time = |t: &str| Some(DateTime::parse_from_rfc3339(t).unwrap().with_timezone(&Utc));
+
+        assert_eq!(
+            last_deployed(Some(&metadata(
+                Some("2024-01-01T00:00:00Z"),
+                Some("2024-03-01T00:00:00Z")
+            ))),
+            time("2024-03-01T00:00:00Z")
+        );
+        assert_eq!(
+            last_deployed(Some(&metadata(Some("2024-01-01T00:00:00Z"), None))),
+            time("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(last_deployed(Some(&metadata(None, None))), None);
+        assert_eq!(last_deployed(None), None);
+    }
+
+    #[tokio::test]
+    async fn bulk_delete_carries_on_past_failures() {
+        let apps = vec![
+            ("pr-1".to_owned(), Uuid::new_v4()),
+            ("pr-2".to_owned(), Uuid::new_v4()),
+            ("pr-3".to_owned(), Uuid::new_v4()),
+        ];
+        let failing = apps[1].1.to_string();
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_remove_app().times(3).returning(move |id| {
+            if id == failing {
+                Err(anyhow!("app is locked"))
+            } else {
+                Ok(())
+            }
+        });
+
+        let results = delete_apps(&mock, &apps, Progress::new(ProgressFormat::Text)).await;
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn traffic_percentages_must_add_up_to_100() -> Result<()> {
+        let weights = |args: &[&str]| {
+            args.iter()
+                .map(|a| parse_revision_weight(a))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        assert!(parse_revision_weight("42").is_err());
+        assert!(parse_revision_weight("42=101").is_err());
+        assert!(parse_revision_weight("=10").is_err());
+
+        let split = traffic_split(weights(&["42=90", "43=10", "41=0"]).unwrap())?;
+        assert_eq!(
+            split.revisions,
+            vec![
+                RevisionWeight {
+                    revision_number: "42".to_owned(),
+                    weight: 90
+                },
+                RevisionWeight {
+                    revision_number: "43".to_owned(),
+                    weight: 10
+                },
+            ]
+        );
+        assert!(traffic_split(weights(&["42=90", "43=20"]).unwrap()).is_err());
+        assert!(traffic_split(weights(&["42=50", "42=50"]).unwrap()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn error_page_content_type_follows_extension() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let html = dir.path().join("500.html");
+        std::fs::write(&html, "<h1>Back soon</h1>")?;
+        assert_eq!(load_error_page(&html)?.content_type, "text/html");
+
+        let text = dir.path().join("down.txt");
+        std::fs::write(&text, "Back soon")?;
+        assert_eq!(load_error_page(&text)?.content_type, "text/plain");
+
+        let image = dir.path().join("down.png");
+        std::fs::write(&image, "not really a png")?;
+        assert!(load_error_page(&image).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn error_page_must_be_small_and_non_empty() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let large = dir.path().join("large.html");
+        std::fs::write(&large, "x".repeat(MAX_ERROR_PAGE_BYTES as usize + 1))?;
+        assert!(load_error_page(&large).is_err());
+
+        let empty = dir.path().join("empty.html");
+        std::fs::write(&empty, "  \n")?;
+        assert!(load_error_page(&empty).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use crate::ops::link::Link;
+use crate::ops::variables::Variable;
+
+/// The runtime config file written for `spin up`
+pub(super) const RUNTIME_CONFIG_FILE: &str = "runtime-config.toml";
+/// The file of variable values written for `spin up`
+pub(super) const ENV_FILE: &str = ".env";
+
+/// A runtime config giving each database the app is linked to in Cloud a
+/// local SQLite file under the same label.
+pub(super) fn runtime_config(app: &str, links: &[Link]) -> String {
+    let mut config = format!(
+        "# Local equivalents of the Fermyon Cloud resources used by app \"{app}\".\n\
+         # The \"default\" key value store needs no configuration to run locally.\n"
+    );
+    if links.is_empty() {
+        config.push_str("# The app is not linked to any databases.\n");
+    }
+    for link in links {
+        let label = &link.resource_label.label;
+        // Writing to a String cannot fail.
+        let _ = write!(
+            config,
+            "\n# Linked to database \"{}\" in Fermyon Cloud\n\
+             [sqlite_database.{}]\n\
+             type = \"spin\"\n\
+             path = \".spin/sqlite_{}.db\"\n",
+            link.resource,
+            toml_key(label),
+            file_safe(label),
+        );
+    }
+    config
+}
+
+/// An env file with a line for each of the app's variables, in the form
+/// Spin reads variables from the environment. Values in Cloud cannot be
+/// listed, so they are left for the developer to fill in.
+pub(super) fn env_file(app: &str, variables: &[Variable]) -> String {
+    let mut env =
+        format!("# Variables of app \"{app}\" in Fermyon Cloud. Fill in values to use locally.\n");
+    if variables.is_empty() {
+        env.push_str("# The app has no variables.\n");
+    }
+    for variable in variables {
+        if variable.secret {
+            env.push_str("# Secret in Cloud; use a development value here.\n");
+        }
+        let _ = writeln!(env, "SPIN_VARIABLE_{}=", variable.key.to_ascii_uppercase());
+    }
+    env
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_owned()
+    } else {
+        toml::Value::String(key.to_owned()).to_string()
+    }
+}
+
+fn file_safe(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud_openapi::models::ResourceLabel;
+    use uuid::Uuid;
+
+    fn link(label: &str, database: &str) -> Link {
+        Link::new(
+            ResourceLabel {
+                app_id: Uuid::new_v4(),
+                label: label.to_owned(),
+                app_name: Some("todo".to_owned()),
+            },
+            database.to_owned(),
+        )
+    }
+
+    #[test]
+    fn runtime_config_has_a_local_file_per_linked_database() {
+        let config = runtime_config(
+            "todo",
+            &[link("default", "todo-db"), link("audit log", "audit")],
+        );
+        let parsed: toml::Value = toml::from_str(&config).unwrap();
+        assert_eq!(
+            parsed["sqlite_database"]["default"]["path"].as_str(),
+            Some(".spin/sqlite_default.db")
+        );
+        assert_eq!(
+            parsed["sqlite_database"]["audit log"]["path"].as_str(),
+            Some(".spin/sqlite_audit_log.db")
+        );
+        assert!(config.contains("# Linked to database \"todo-db\" in Fermyon Cloud"));
+    }
+
+    #[test]
+    fn env_file_lists_variables_for_spin() {
+        let variables = vec![
+            Variable {
+                key: "api_url".to_owned(),
+                secret: false,
+            },
+            Variable {
+                key: "api_token".to_owned(),
+                secret: true,
+            },
+        ];
+        assert_eq!(
+            env_file("todo", &variables),
+            "# Variables of app \"todo\" in Fermyon Cloud. Fill in values to use locally.\n\
+             SPIN_VARIABLE_API_URL=\n\
+             # Secret in Cloud; use a development value here.\n\
+             SPIN_VARIABLE_API_TOKEN=\n"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use cloud::models::AppMetrics;
+use cloud::CloudClientInterface;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::ops::apps::list_apps;
+use crate::ops::resolve::not_found;
+use crate::table::new_table;
+
+/// The content type of the Prometheus text exposition format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// How long a scraper has to send its request before the connection is
+/// dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The metrics of one app, or `None` if they could not be fetched
+#[derive(Debug)]
+pub(super) struct AppSample {
+    pub app: String,
+    pub metrics: Option<AppMetrics>,
+}
+
+#[derive(Serialize)]
+struct AppMetricsJson<'a> {
+    app: &'a str,
+    #[serde(flatten)]
+    metrics: &'a AppMetrics,
+}
+
+/// Fetches the metrics of the named apps, or of every app if none are
+/// named. Apps are listed afresh each time, so that an exporter picks up
+/// apps deployed after it started. An app whose metrics cannot be fetched
+/// is still sampled, with no metrics.
+pub(super) async fn collect(
+    client: &impl CloudClientInterface,
+    names: &[String],
+    window: Duration,
+) -> Result<Vec<AppSample>> {
+    let apps = list_apps(client).await.context("Problem listing apps")?;
+    if let Some(missing) = names.iter().find(|n| !apps.iter().any(|a| &a.name == *n)) {
+        return Err(not_found(
+            "app",
+            missing,
+            apps.iter().map(|a| a.name.as_str()),
+        ));
+    }
+    let mut samples = vec![];
+    for app in apps
+        .iter()
+        .filter(|a| names.is_empty() || names.contains(&a.name))
+    {
+        let metrics = client.get_app_metrics(app.id, window.as_secs()).await.ok();
+        samples.push(AppSample {
+            app: app.name.clone(),
+            metrics,
+        });
+    }
+    samples.sort_by(|a, b| a.app.cmp(&b.app));
+    Ok(samples)
+}
+
+pub(super) fn print_table(samples: &[AppSample]) {
+    let mut table = new_table();
+    table.set_header(vec!["App", "Requests", "Errors", "p50", "p95"]);
+    table.add_rows(samples.iter().map(|s| match &s.metrics {
+        Some(m) => [
+            s.app.clone(),
+            m.requests.to_string(),
+            m.errors.to_string(),
+            format_latency(m.latency_p50_ms),
+            format_latency(m.latency_p95_ms),
+        ],
+        None => [
+            s.app.clone(),
+            "unavailable".to_owned(),
+            "-".to_owned(),
+            "-".to_owned(),
+            "-".to_owned(),
+        ],
+    }));
+    println!("{table}");
+}
+
+fn format_latency(ms: Option<f64>) -> String {
+    ms.map(|ms| format!("{ms:.1}ms"))
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+pub(super) fn to_json(samples: &[AppSample]) -> Result<String> {
+    let json = samples
+        .iter()
+        .filter_map(|s| {
+            Some(AppMetricsJson {
+                app: &s.app,
+                metrics: s.metrics.as_ref()?,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&json)?)
+}
+
+/// Renders the samples in the Prometheus text exposition format. The counts
+/// cover a sliding window rather than growing for the life of the app, so
+/// they are exposed as gauges labelled with the window, not as counters.
+pub(super) fn to_prometheus(samples: &[AppSample]) -> String {
+    let mut text = String::new();
+    family(
+        &mut text,
+        "spin_cloud_app_up",
+        "Whether the metrics of the app could be fetched",
+    );
+    for sample in samples {
+        sample_line(
+            &mut text,
+            "spin_cloud_app_up",
+            &[("app", &sample.app)],
+            if sample.metrics.is_some() { 1.0 } else { 0.0 },
+        );
+    }
+
+    let fetched = samples
+        .iter()
+        .filter_map(|s| Some((s.app.as_str(), s.metrics.as_ref()?)))
+        .collect::<Vec<_>>();
+    family(
+        &mut text,
+        "spin_cloud_app_requests",
+        "Requests served by the app in the window",
+    );
+    for (app, m) in &fetched {
+        let window = m.window_seconds.to_string();
+        let labels = [("app", *app), ("window_seconds", window.as_str())];
+        sample_line(
+            &mut text,
+            "spin_cloud_app_requests",
+            &labels,
+            m.requests as f64,
+        );
+    }
+    family(
+        &mut text,
+        "spin_cloud_app_errors",
+        "Requests the app answered with a 5xx status in the window",
+    );
+    for (app, m) in &fetched {
+        let window = m.window_seconds.to_string();
+        let labels = [("app", *app), ("window_seconds", window.as_str())];
+        sample_line(&mut text, "spin_cloud_app_errors", &labels, m.errors as f64);
+    }
+    family(
+        &mut text,
+        "spin_cloud_app_request_duration_seconds",
+        "Quantiles of the time the app took to answer requests in the window",
+    );
+    for (app, m) in &fetched {
+        let window = m.window_seconds.to_string();
+        for (quantile, ms) in [("0.5", m.latency_p50_ms), ("0.95", m.latency_p95_ms)] {
+            if let Some(ms) = ms {
+                let labels = [
+                    ("app", *app),
+                    ("window_seconds", window.as_str()),
+                    ("quantile", quantile),
+                ];
+                sample_line(
+                    &mut text,
+                    "spin_cloud_app_request_duration_seconds",
+                    &labels,
+                    ms / 1000.0,
+                );
+            }
+        }
+    }
+    text
+}
+
+fn family(text: &mut String, name: &str, help: &str) {
+    // Writing to a String cannot fail.
+    let _ = writeln!(text, "# HELP {name} {help}\n# TYPE {name} gauge");
+}
+
+fn sample_line(text: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let labels = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape_label(v)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let _ = writeln!(text, "{name}{{{labels}}} {value}");
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Parses `--listen`. An address without a host, such as `:9100`, listens
+/// on every interface.
+pub(super) fn parse_listen_address(s: &str) -> Result<SocketAddr, String> {
+    let address = match s.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{port}"),
+        None => s.to_owned(),
+    };
+    address.parse().map_err(|_| {
+        format!("'{s}' is not a valid address to listen on, such as ':9100' or '127.0.0.1:9100'")
+    })
+}
+
+/// Serves the metrics of the apps at `/metrics` until the process is
+/// stopped, fetching them afresh for each scrape. Scrapes are answered one
+/// at a time, which is all a Prometheus server needs.
+pub(super) async fn serve(
+    client: &impl CloudClientInterface,
+    address: SocketAddr,
+    names: &[String],
+    window: Duration,
+) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Could not listen on {address}"))?;
+    eprintln!("Serving app metrics at http://{address}/metrics");
+    loop {
+        let (stream, _) = listener.accept().await?;
+        if let Err(e) = answer(stream, client, names, window).await {
+            eprintln!("Problem answering a scrape: {e:#}");
+        }
+    }
+}
+
+async fn answer(
+    mut stream: TcpStream,
+    client: &impl CloudClientInterface,
+    names: &[String],
+    window: Duration,
+) -> Result<()> {
+    let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .context("Timed out waiting for the request")??;
+    let (status, body) = match request_target(&request) {
+        Some(("GET", "/metrics")) => match collect(client, names, window).await {
+            Ok(samples) => ("200 OK", to_prometheus(&samples)),
+            Err(e) => ("503 Service Unavailable", format!("{e:#}\n")),
+        },
+        Some((_, "/metrics")) => ("405 Method Not Allowed", "Use GET\n".to_owned()),
+        _ => (
+            "404 Not Found",
+            "Metrics are served at /metrics\n".to_owned(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {PROMETHEUS_CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Reads up to the blank line ending the request's headers. Scrapes have no
+/// body, so nothing after it is needed.
+async fn read_request_head(stream: &mut TcpStream) -> Result<String> {
+    const MAX_HEAD: usize = 16 * 1024;
+    let mut head = vec![];
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+        if head.len() > MAX_HEAD {
+            bail!("Request headers are too large");
+        }
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// The method and path of a request, without any query string
+fn request_target(request: &str) -> Option<(&str, &str)> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    let method = parts.next()?;
+    let target = parts.next()?;
+    let path = target.split('?').next().unwrap_or(target);
+    Some((method, path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn metrics_render_as_prometheus_gauges() {
+        let samples = vec![
+            AppSample {
+                app: "todo".to_owned(),
+                metrics: Some(AppMetrics {
+                    window_seconds: 300,
+                    requests: 120,
+                    errors: 3,
+                    latency_p50_ms: Some(12.5),
+                    latency_p95_ms: None,
+                }),
+            },
+            AppSample {
+                app: "say \"hi\"".to_owned(),
+                metrics: None,
+            },
+        ];
+        assert_eq!(
+            to_prometheus(&samples),
+            "# HELP spin_cloud_app_up Whether the metrics of the app could be fetched\n\
+             # TYPE spin_cloud_app_up gauge\n\
+             spin_cloud_app_up{app=\"todo\"} 1\n\
+             spin_cloud_app_up{app=\"say \\\"hi\\\"\"} 0\n\
+             # HELP spin_cloud_app_requests Requests served by the app in the window\n\
+             # TYPE spin_cloud_app_requests gauge\n\
+             spin_cloud_app_requests{app=\"todo\",window_seconds=\"300\"} 120\n\
+             # HELP spin_cloud_app_errors Requests the app answered with a 5xx status in the window\n\
+             # TYPE spin_cloud_app_errors gauge\n\
+             spin_cloud_app_errors{app=\"todo\",window_seconds=\"300\"} 3\n\
+             # HELP spin_cloud_app_request_duration_seconds Quantiles of the time the app took to answer requests in the window\n\
+             # TYPE spin_cloud_app_request_duration_seconds gauge\n\
+             spin_cloud_app_request_duration_seconds{app=\"todo\",window_seconds=\"300\",quantile=\"0.5\"} 0.0125\n"
+        );
+    }
+
+    #[test]
+    fn listen_addresses_and_requests_are_parsed() {
+        assert_eq!(
+            parse_listen_address(":9100"),
+            Ok("0.0.0.0:9100".parse().unwrap())
+        );
+        assert_eq!(
+            parse_listen_address("127.0.0.1:8080"),
+            Ok("127.0.0.1:8080".parse().unwrap())
+        );
+        assert!(parse_listen_address("9100").is_err());
+        assert_eq!(
+            request_target("GET /metrics?format=text HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some(("GET", "/metrics"))
+        );
+        assert_eq!(request_target(""), None);
+    }
+}
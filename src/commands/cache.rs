@@ -0,0 +1,77 @@
+use anyhow::Result;
+use clap::Parser;
+use std::time::Duration;
+
+use crate::cache::{caches, Usage};
+use crate::commands::logs::parse_duration;
+use crate::commands::sqlite::format_size;
+use crate::table::new_table;
+
+/// Inspect and clear the files the plugin caches between runs
+#[derive(Parser, Debug)]
+pub enum CacheCommand {
+    /// Show where each cache is and how much space it uses
+    Info(InfoCommand),
+    /// Delete cached files. They are fetched again when next needed.
+    Clear(ClearCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct InfoCommand {}
+
+#[derive(Parser, Debug)]
+pub struct ClearCommand {
+    /// Only delete files which have not changed for this long ("12h", "14d")
+    #[clap(long = "older-than", value_parser = parse_duration)]
+    pub older_than: Option<Duration>,
+    /// Report what would be deleted, without deleting it
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+}
+
+impl CacheCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Info(cmd) => cmd.run(),
+            Self::Clear(cmd) => cmd.run(),
+        }
+    }
+}
+
+impl InfoCommand {
+    fn run(self) -> Result<()> {
+        let mut table = new_table();
+        table.set_header(vec!["Cache", "Files", "Size", "Location", "Contents"]);
+        for cache in caches()? {
+            let Usage { files, bytes } = cache.usage();
+            table.add_row(vec![
+                cache.name.to_owned(),
+                files.to_string(),
+                format_size(bytes),
+                cache.dir.display().to_string(),
+                cache.description.to_owned(),
+            ]);
+        }
+        println!("{table}");
+        Ok(())
+    }
+}
+
+impl ClearCommand {
+    fn run(self) -> Result<()> {
+        let verb = if self.dry_run {
+            "Would delete"
+        } else {
+            "Deleted"
+        };
+        for cache in caches()? {
+            let Usage { files, bytes } = cache.prune(self.older_than, self.dry_run)?;
+            println!(
+                "{verb} {files} file(s), {}, from the {} cache",
+                format_size(bytes),
+                cache.name
+            );
+        }
+        Ok(())
+    }
+}
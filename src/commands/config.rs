@@ -0,0 +1,81 @@
+use anyhow::Result;
+use clap::Parser;
+
+use crate::plugin_config::{PluginConfig, SETTINGS};
+use crate::table::new_table;
+
+/// View and change the plugin's saved settings
+#[derive(Parser, Debug)]
+pub enum ConfigCommand {
+    /// Print the saved value of a setting
+    Get(GetCommand),
+    /// Save a value for a setting
+    Set(SetCommand),
+    /// Remove the saved value of a setting, restoring its default
+    Unset(UnsetCommand),
+    /// List every setting and its saved value
+    List(ListCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct GetCommand {
+    /// Name of the setting
+    pub key: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetCommand {
+    /// Name of the setting
+    pub key: String,
+    /// Value to save
+    pub value: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct UnsetCommand {
+    /// Name of the setting
+    pub key: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListCommand {}
+
+impl ConfigCommand {
+    pub async fn run(self) -> Result<()> {
+        let mut config = PluginConfig::load()?;
+        match self {
+            Self::Get(cmd) => {
+                if let Some(value) = config.get(&cmd.key)? {
+                    println!("{value}");
+                }
+            }
+            Self::Set(cmd) => {
+                let value = config.set(&cmd.key, &cmd.value)?.to_owned();
+                config.save()?;
+                println!("Set {} to {value}", cmd.key);
+            }
+            Self::Unset(cmd) => {
+                if config.unset(&cmd.key)? {
+                    config.save()?;
+                    println!("Removed the saved value of {}", cmd.key);
+                } else {
+                    println!("{} has no saved value", cmd.key);
+                }
+            }
+            Self::List(_) => {
+                let mut table = new_table();
+                table.set_header(vec!["Setting", "Value", "Description"]);
+                for setting in SETTINGS {
+                    table.add_row(vec![
+                        setting.key,
+                        config.get(setting.key)?.unwrap_or_default(),
+                        setting.description,
+                    ]);
+                }
+                println!("{table}");
+                println!("Saved in {}", PluginConfig::path()?.display());
+            }
+        }
+        Ok(())
+    }
+}
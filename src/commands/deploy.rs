@@ -5,7 +5,7 @@ use cloud::{
     client::{Client as CloudClient, ConnectionConfig},
     CloudClientExt, CloudClientInterface,
 };
-use oci_distribution::{token_cache, Reference, RegistryOperation};
+use cloud_openapi::models::AppItem;
 use spin_common::arg_parser::parse_kv;
 use spin_http::{app_info::AppInfo, routes::RoutePattern};
 use spin_locked_app::locked;
@@ -13,33 +13,71 @@ use tokio::fs;
 use tracing::instrument;
 
 use std::{
-    collections::HashSet,
-    io::{self, Write},
+    collections::{BTreeMap, HashSet},
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
 };
 use url::Url;
 
 use crate::{
-    commands::{
-        variables::{get_variables, set_variables},
-        DEFAULT_CLOUD_URL,
-    },
+    commands::{confirm_project_environment, DEFAULT_CLOUD_URL},
     spin,
 };
 
 use crate::{
-    commands::login::{LoginCommand, LoginConnection},
+    answers,
+    cache::registry_cache_dir,
+    commands::login::{
+        cached_oidc_login_connection, identity_available, setup_hint, LoginCommand, LoginConnection,
+    },
+    config_migrations::CONFIG_VERSION,
+    ops::deploy::{push_app, registry_reference, release, Release, SPIN_DEFAULT_KV_STORE},
+    ops::regions::check_region,
+    ops::sqlite::{app_database_links, list_databases},
+    ops::variables::get_variables,
     opts::*,
+    progress::{Progress, ProgressFormat},
+    project_config::{ProjectConfig, PROJECT_CONFIG_FILE},
 };
 
+mod build_info;
 mod database;
+mod lockfile;
+mod packaging;
+mod preflight;
+mod reproducible;
+mod routing;
 
 use database::{
-    create_and_link_databases_for_existing_app, create_databases_for_new_app, link_databases,
+    create_and_link_databases_for_existing_app, create_databases_for_new_app,
+    ensure_resources_provisioned,
 };
+use lockfile::{Lockfile, LOCKFILE};
+use preflight::{preflight, ManifestSummary};
+use reproducible::ArtifactDigests;
+use routing::{override_route_prefix, parse_route_prefix};
 
 const DEVELOPER_CLOUD_FAQ: &str = "https://developer.fermyon.com/cloud/faq";
-const SPIN_DEFAULT_KV_STORE: &str = "default";
+
+/// The exit status when `deploy --wait-timeout` deploys the app but it does
+/// not become ready in time
+pub const DEPLOYED_NOT_READY_EXIT_CODE: i32 = 3;
+
+/// The error when `deploy --wait-timeout` gives up waiting for a deployed
+/// app. The plugin exits with [`DEPLOYED_NOT_READY_EXIT_CODE`] for it.
+#[derive(Debug)]
+pub struct DeployedNotReady;
+
+impl std::fmt::Display for DeployedNotReady {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Application deployed, but it did not become ready within the wait timeout"
+        )
+    }
+}
+
+impl std::error::Error for DeployedNotReady {}
 
 /// Package and upload an application to the Fermyon Cloud.
 #[derive(Parser, Debug)]
@@ -47,7 +85,10 @@ const SPIN_DEFAULT_KV_STORE: &str = "default";
 pub struct DeployCommand {
     /// The application to deploy. This may be a manifest (spin.toml) file, a
     /// directory containing a spin.toml file, or a remote registry reference.
-    /// If omitted, it defaults to "spin.toml".
+    /// If omitted, it defaults to "spin.toml". Paths in the manifest, the
+    /// project's .spinignore and spin-cloud.toml are all found relative to
+    /// the manifest, so an app in a subdirectory can be deployed without
+    /// changing into it.
     #[clap(
         name = APPLICATION_OPT,
         short = 'f',
@@ -89,6 +130,12 @@ pub struct DeployCommand {
     #[clap(long = "readiness-timeout", default_value = "60")]
     pub readiness_timeout_secs: u16,
 
+    /// Like --readiness-timeout, but if the application is deployed and
+    /// does not become ready in time, exit with status 3 rather than 0, so
+    /// that pipelines can tell slow or failed startup apart from a failed upload.
+    #[clap(long = "wait-timeout", conflicts_with = "readiness-timeout-secs")]
+    pub wait_timeout_secs: Option<u16>,
+
     /// Deploy to the Fermyon instance saved under the specified name.
     /// If omitted, Spin deploys to the default unnamed instance.
     #[clap(
@@ -111,6 +158,16 @@ pub struct DeployCommand {
     #[clap(long = "variable", parse(try_from_str = parse_kv))]
     pub variables: Vec<(String, String)>,
 
+    /// Do not check that the app's required variables have values in the
+    /// cloud. Use this if variables are managed separately from deployment;
+    /// the app may fail at runtime if any are missing.
+    #[clap(
+        long = "skip-variables",
+        takes_value = false,
+        conflicts_with = "variables"
+    )]
+    pub skip_variables: bool,
+
     /// Specifies how application labels (such as SQLite databases) should
     /// be linked if they are not already linked. This is intended for
     /// non-interactive environments such as release pipelines; therefore,
@@ -120,19 +177,152 @@ pub struct DeployCommand {
     /// do not exist will be created.
     #[clap(long = "link")]
     pub links: Vec<String>,
+
+    /// Never create databases or key value stores. The app may only be
+    /// linked to existing resources which are pre-approved in the project's
+    /// spin-cloud.toml; if anything else is needed, the deployment fails with
+    /// a list of what must be provisioned first.
+    #[clap(
+        long = "no-resource-provisioning",
+        takes_value = false,
+        conflicts_with = "links"
+    )]
+    pub no_resource_provisioning: bool,
+
+    /// Region in which to create any databases the app needs. Run `spin
+    /// cloud regions` to see the choices. If omitted, the platform's
+    /// default region is used. Existing databases are not moved.
+    #[clap(long = "region", conflicts_with = "no-resource-provisioning")]
+    pub region: Option<String>,
+
+    /// Do not check, create or link the databases the app's labels refer
+    /// to. Use this if links are managed separately from deployment, for
+    /// example with `spin cloud link`; labels which are not linked are left
+    /// unlinked.
+    #[clap(
+        long = "skip-link-checks",
+        takes_value = false,
+        conflicts_with_all = &["links", "no-resource-provisioning", "region"]
+    )]
+    pub skip_link_checks: bool,
+
+    /// Fail instead of warning if the files being uploaded include likely
+    /// secrets (such as private keys or .env files) or very large files.
+    /// Intended files can be allowed in the project's spin-cloud.toml.
+    #[clap(long = "strict-packaging", takes_value = false)]
+    pub strict_packaging: bool,
+
+    /// List the files which were left out of the upload because they match
+    /// the project's .spinignore. As in a .gitignore, patterns are matched
+    /// against where files are in the project.
+    #[clap(long = "show-ignored", takes_value = false)]
+    pub show_ignored: bool,
+
+    /// Do not record the project's git commit, branch and uncommitted
+    /// changes with the revision.
+    #[clap(long = "no-build-info", takes_value = false)]
+    pub no_build_info: bool,
+
+    /// How to report progress. With json, progress events are written to
+    /// stderr as newline-delimited JSON.
+    #[clap(value_enum, long = "progress", default_value = "text")]
+    pub progress: ProgressFormat,
+
+    /// The name to deploy the app as, instead of the name in its manifest
+    #[clap(long = "name", value_parser = parse_app_name)]
+    pub name: Option<String>,
+
+    /// Serve the app's HTTP routes under this path instead of the base in
+    /// its manifest, such as /staging. The override is recorded with the
+    /// revision, so the same app can be deployed with different routing in
+    /// different environments.
+    #[clap(long = "route-prefix", value_parser = parse_route_prefix)]
+    pub route_prefix: Option<String>,
+
+    /// Package the app a second time and fail, listing the files which
+    /// differ, unless both packagings produce the same artifacts.
+    #[clap(long = "verify-reproducible", takes_value = false)]
+    pub verify_reproducible: bool,
+
+    /// After a successful deployment, record the app, the databases linked
+    /// to its labels and the digests of its artifacts in spin-cloud.lock,
+    /// next to the manifest. Whenever that file exists, deployments report
+    /// how they differ from it.
+    #[clap(long = "lockfile", takes_value = false)]
+    pub lockfile: bool,
+
+    /// Fail, before uploading anything, unless the deployment would match
+    /// spin-cloud.lock exactly: the same app, channel, linked databases,
+    /// key value stores and artifacts.
+    #[clap(long = "frozen", takes_value = false)]
+    pub frozen: bool,
 }
 
 impl DeployCommand {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(mut self) -> Result<()> {
+        confirm_project_environment(self.deployment_env_id.as_deref(), &self.project_dir())?;
+        let login_connection = login_connection(self.deployment_env_id.as_deref()).await?;
+        self.preflight(&login_connection)
+            .await
+            .map_err(|e| anyhow!("{:?}\n\nLearn more at {}", e, DEVELOPER_CLOUD_FAQ))?;
+
         if self.build {
             self.run_spin_build().await?;
         }
 
-        let login_connection = login_connection(self.deployment_env_id.as_deref()).await?;
-
-        self.deploy_cloud(login_connection)
+        let fail_if_not_ready = self.wait_timeout_secs.is_some();
+        let readiness = self
+            .deploy_cloud(login_connection)
             .await
-            .map_err(|e| anyhow!("{:?}\n\nLearn more at {}", e, DEVELOPER_CLOUD_FAQ))
+            .map_err(|e| anyhow!("{:?}\n\nLearn more at {}", e, DEVELOPER_CLOUD_FAQ))?;
+        if fail_if_not_ready && readiness == Readiness::NotReady {
+            return Err(DeployedNotReady.into());
+        }
+        Ok(())
+    }
+
+    // Building and uploading can take minutes, so problems the platform
+    // would reject the deployment for are looked for first.
+    async fn preflight(&mut self, login_connection: &LoginConnection) -> Result<()> {
+        let client = CloudClient::new(ConnectionConfig {
+            url: login_connection.url.to_string(),
+            insecure: login_connection.danger_accept_invalid_certs,
+            token: login_connection.token.clone(),
+        });
+        let mut summary = match self.resolve_app_source() {
+            AppSource::File(manifest) => {
+                let mut summary = ManifestSummary::from_file(&manifest)?;
+                if self.skip_link_checks {
+                    summary.sqlite_labels.clear();
+                }
+                summary.name = sanitize_app_name(self.name.as_ref().unwrap_or(&summary.name));
+                Some(summary)
+            }
+            _ => None,
+        };
+        let mut approved_databases = ProjectConfig::load_from_dir(&self.project_dir())?
+            .resources
+            .sqlite;
+        for link in &self.links {
+            let LinkageSpec::SqliteLabel {
+                label,
+                name: database::DatabaseRef::Named(database),
+            } = parse_one_linkage_spec(link)?;
+            approved_databases.insert(label, database);
+        }
+        let original_name = summary.as_ref().map(|s| s.name.clone());
+        preflight(
+            &client,
+            summary.as_mut(),
+            &approved_databases,
+            EnvSettings::from_env().interactive(),
+        )
+        .await?;
+        let name = summary.map(|s| s.name);
+        if name != original_name {
+            self.name = name;
+        }
+        Ok(())
     }
 
     fn resolve_app_source(&self) -> AppSource {
@@ -172,7 +362,7 @@ impl DeployCommand {
         }
     }
 
-    async fn deploy_cloud(self, login_connection: LoginConnection) -> Result<()> {
+    async fn deploy_cloud(self, login_connection: LoginConnection) -> Result<Readiness> {
         let connection_config = ConnectionConfig {
             url: login_connection.url.to_string(),
             insecure: login_connection.danger_accept_invalid_certs,
@@ -180,119 +370,167 @@ impl DeployCommand {
         };
 
         let client = CloudClient::new(connection_config.clone());
-        let interact = self.interaction_strategy()?;
+        let project_config = ProjectConfig::load_from_dir(&self.project_dir())?;
+        let interact = self.interaction_strategy(&project_config)?;
+        let progress = Progress::new(self.progress);
 
         let dir = tempfile::tempdir()?;
 
-        let application = self.load_cloud_app(dir.path()).await?;
+        progress.phase("loading", 0);
+
+        let mut application = self.load_cloud_app(dir.path()).await?;
 
         validate_cloud_app(&application)?;
-        self.validate_deployment_environment(&application, &client)
+        let ignored = self.apply_spinignore(&mut application, dir.path())?;
+        self.report_ignored(&ignored);
+        self.record_build_info(&mut application).await?;
+        if self.verify_reproducible {
+            self.verify_reproducible(&application).await?;
+        }
+        if !self.check_packaged_files(&application, &project_config)? {
+            return Ok(Readiness::Unchecked);
+        }
+        if self.no_resource_provisioning {
+            ensure_resources_provisioned(
+                &client,
+                &sanitize_app_name(application.name()?),
+                &application.sqlite_databases(),
+                &application.key_value_stores(),
+                &project_config.resources,
+            )
             .await?;
+        }
+        if let Some(region) = &self.region {
+            check_region(&client, region).await?;
+        }
+        if !self.skip_variables {
+            self.validate_deployment_environment(&application, &client)
+                .await?;
+        }
 
-        let digest = self
-            .push_oci(application.clone(), connection_config.clone())
-            .await?;
+        let locked = Lockfile::load_from_dir(&self.project_dir())?;
+        if self.frozen && locked.is_none() {
+            bail!("--frozen needs {LOCKFILE}. Deploy with --lockfile to create it");
+        }
+        if let Some(locked) = &locked {
+            let planned = resolve_lock(&client, &application, None).await?;
+            check_drift(locked, &planned, self.frozen)?;
+        }
+        let project_dir = self.project_dir();
+        let write_lockfile = self.lockfile;
 
         let name = sanitize_app_name(application.name()?);
-        let storage_id = format!("oci://{}", name);
         let version = sanitize_app_version(application.version()?);
 
+        progress.transfer("uploading", 20, dir_size(dir.path()));
+        let reference = registry_reference(&connection_config.url, &name, &version)?;
+        println!("Uploading {name} version {version} to Fermyon Cloud...");
+        let digest = push_app(&connection_config, application.0.clone(), &reference).await?;
+
         println!("Deploying...");
+        progress.phase("deploying", 60);
 
-        // Create or update app
-        let app_id = match client.get_app_id(&name).await? {
+        let app_id = client.get_app_id(&name).await?;
+        let labels = self.sqlite_labels_to_link(&application);
+        let databases_to_link = match app_id {
             Some(app_id) => {
-                let labels = application.sqlite_databases();
                 if !labels.is_empty()
                     && create_and_link_databases_for_existing_app(
                         &client,
                         &name,
                         app_id,
                         labels,
+                        self.region.as_deref(),
                         interact.as_ref(),
                     )
                     .await?
                     .is_none()
                 {
                     // User canceled terminal interaction
-                    return Ok(());
-                }
-                client
-                    .add_revision(storage_id.clone(), version.clone())
-                    .await?;
-
-                for kv in self.key_values {
-                    client
-                        .add_key_value_pair(app_id, SPIN_DEFAULT_KV_STORE.to_string(), kv.0, kv.1)
-                        .await
-                        .context("Problem creating key/value")?;
-                }
-
-                set_variables(&client, app_id, &self.variables).await?;
-
-                app_id
-            }
-            None => {
-                let labels = application.sqlite_databases();
-                let databases_to_link =
-                    match create_databases_for_new_app(&client, &name, labels, interact.as_ref())
-                        .await?
-                    {
-                        Some(dbs) => dbs,
-                        None => return Ok(()), // User canceled terminal interaction
-                    };
-
-                let app_id = client
-                    .add_app(&name, &storage_id)
-                    .await
-                    .context("Unable to create app")?;
-
-                // Now that the app has been created, we can link databases to it.
-                link_databases(&client, &name, app_id, databases_to_link).await?;
-
-                client
-                    .add_revision(storage_id.clone(), version.clone())
-                    .await?;
-
-                for kv in self.key_values {
-                    client
-                        .add_key_value_pair(app_id, SPIN_DEFAULT_KV_STORE.to_string(), kv.0, kv.1)
-                        .await
-                        .context("Problem creating key/value")?;
+                    return Ok(Readiness::Unchecked);
                 }
-
-                set_variables(&client, app_id, &self.variables).await?;
-
-                app_id
+                vec![]
             }
+            None => match create_databases_for_new_app(
+                &client,
+                &name,
+                labels,
+                self.region.as_deref(),
+                interact.as_ref(),
+            )
+            .await?
+            {
+                Some(dbs) => dbs,
+                None => return Ok(Readiness::Unchecked), // User canceled terminal interaction
+            },
         };
 
-        let app = client
-            .get_app(app_id.to_string())
-            .await
-            .context("Problem getting app by id")?;
+        let app = release(
+            &client,
+            app_id,
+            &Release {
+                name: name.clone(),
+                version,
+                key_values: self.key_values,
+                variables: self.variables,
+            },
+            databases_to_link,
+        )
+        .await?;
+
+        if write_lockfile {
+            resolve_lock(&client, &application, Some(&app))
+                .await?
+                .save_to_dir(&project_dir)?;
+            println!("Recorded the deployment in {LOCKFILE}");
+        }
 
         let app_base_url = build_app_base_url(&app.subdomain, &login_connection.url)?;
         let (http_base, http_routes) = application.http_routes();
         if !http_routes.is_empty() {
-            wait_for_ready(
+            progress.phase("waiting", 80);
+            let readiness = wait_for_ready(
                 &app_base_url,
                 &digest.unwrap_or_default(),
-                self.readiness_timeout_secs,
+                self.wait_timeout_secs
+                    .unwrap_or(self.readiness_timeout_secs),
                 Destination::Cloud(connection_config.clone().url),
             )
             .await;
+            progress.phase("done", 100);
             let base = http_base.unwrap_or_else(|| "/".to_owned());
             print_available_routes(&name, &app_base_url, &base, &http_routes);
+            Ok(readiness)
         } else {
+            progress.phase("done", 100);
             println!("Application is running at {}", app.subdomain);
+            Ok(Readiness::Unchecked)
         }
+    }
 
-        Ok(())
+    fn sqlite_labels_to_link(&self, application: &DeployableApp) -> HashSet<String> {
+        if self.skip_link_checks {
+            HashSet::new()
+        } else {
+            application.sqlite_databases()
+        }
     }
 
-    fn interaction_strategy(&self) -> anyhow::Result<Box<dyn database::InteractionStrategy>> {
+    fn interaction_strategy(
+        &self,
+        project_config: &ProjectConfig,
+    ) -> anyhow::Result<Box<dyn database::InteractionStrategy>> {
+        // Without prompts, labels can only be resolved from the project config.
+        let scripted_from_config = self.no_resource_provisioning
+            || (self.links.is_empty() && !EnvSettings::from_env().interactive());
+        if scripted_from_config {
+            let mut script = database::Scripted::default();
+            for (label, database) in &project_config.resources.sqlite {
+                script.set_label_action(label, database::DatabaseRef::Named(database.clone()))?;
+            }
+            return Ok(Box::new(script));
+        }
+
         if self.links.is_empty() {
             return Ok(Box::new(database::Interactive));
         }
@@ -301,6 +539,18 @@ impl DeployCommand {
         Ok(Box::new(script))
     }
 
+    // The project config sits next to the manifest, or in the current
+    // directory for apps which are not loaded from a local file. Like the
+    // manifest's own relative paths, the project's files are found relative
+    // to the manifest, so `--from` deploys an app in a subdirectory just as
+    // running from that directory would.
+    fn project_dir(&self) -> PathBuf {
+        match self.resolve_app_source() {
+            AppSource::File(manifest) => manifest_dir(&manifest),
+            _ => PathBuf::from("."),
+        }
+    }
+
     async fn load_cloud_app(&self, working_dir: &Path) -> Result<DeployableApp, anyhow::Error> {
         let app_source = self.resolve_app_source();
 
@@ -313,7 +563,7 @@ impl DeployCommand {
                 .await?
             }
             AppSource::OciRegistry(reference) => {
-                let mut oci_client = spin_oci::Client::new(false, None)
+                let mut oci_client = spin_oci::Client::new(false, Some(registry_cache_dir()?))
                     .await
                     .context("cannot create registry client")?;
 
@@ -342,12 +592,109 @@ impl DeployCommand {
             );
         }
 
-        let locked_app = ensure_http_base_set(locked_app);
+        let mut locked_app = ensure_http_base_set(locked_app);
+        if let Some(name) = &self.name {
+            locked_app
+                .metadata
+                .insert("name".to_owned(), sanitize_app_name(name).into());
+        }
+        if let Some(prefix) = &self.route_prefix {
+            override_route_prefix(&mut locked_app.metadata, prefix);
+        }
         let locked_app = ensure_plugin_version_set(locked_app);
 
         Ok(DeployableApp(locked_app))
     }
 
+    async fn record_build_info(&self, app: &mut DeployableApp) -> Result<()> {
+        // Apps from a registry were built elsewhere, so the local checkout
+        // says nothing about them.
+        if self.no_build_info || !matches!(self.resolve_app_source(), AppSource::File(_)) {
+            return Ok(());
+        }
+        if let Some(info) = build_info::BuildInfo::capture(&self.project_dir()).await {
+            println!("Recording build info: {info}");
+            info.annotate(&mut app.0)?;
+        }
+        Ok(())
+    }
+
+    /// Leaves the files matched by the project's .spinignore out of the app,
+    /// returning their paths.
+    fn apply_spinignore(&self, app: &mut DeployableApp, working_dir: &Path) -> Result<Vec<String>> {
+        // A .spinignore applies to the files of a local project, not to apps
+        // pulled from a registry.
+        let AppSource::File(manifest) = self.resolve_app_source() else {
+            return Ok(vec![]);
+        };
+        let Some(ignore) = packaging::load_spinignore(&self.project_dir())? else {
+            return Ok(vec![]);
+        };
+        let placements = packaging::manifest_placements(&manifest)?;
+        packaging::apply_spinignore(&mut app.0, &ignore, &placements, working_dir)
+    }
+
+    fn report_ignored(&self, ignored: &[String]) {
+        if ignored.is_empty() {
+            return;
+        }
+        if self.show_ignored {
+            println!(
+                "Skipping {} file(s) matched by {}:",
+                ignored.len(),
+                packaging::SPINIGNORE_FILE
+            );
+            for path in ignored {
+                println!("  {path}");
+            }
+        } else {
+            println!(
+                "Skipping {} file(s) matched by {} (use --show-ignored to list them)",
+                ignored.len(),
+                packaging::SPINIGNORE_FILE
+            );
+        }
+    }
+
+    async fn verify_reproducible(&self, app: &DeployableApp) -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut repackaged = self.load_cloud_app(dir.path()).await?;
+        self.apply_spinignore(&mut repackaged, dir.path())?;
+        let digests = ArtifactDigests::of(&app.0)?;
+        digests.ensure_same(&ArtifactDigests::of(&repackaged.0)?)?;
+        println!("Packaging is reproducible ({})", digests.digest());
+        Ok(())
+    }
+
+    // Returns false if the user chose not to continue.
+    fn check_packaged_files(
+        &self,
+        app: &DeployableApp,
+        project_config: &ProjectConfig,
+    ) -> Result<bool> {
+        let mounts = packaging::file_mounts(&app.0)?;
+        let findings = packaging::scan(&mounts, &project_config.packaging)?;
+        if findings.is_empty() {
+            return Ok(true);
+        }
+
+        eprintln!("The following files will be uploaded but look like secrets or are very large:");
+        for finding in &findings {
+            eprintln!("  - {finding}");
+        }
+        eprintln!(
+            "If they are intended, add them to the [packaging] allow list in {PROJECT_CONFIG_FILE}."
+        );
+        if self.strict_packaging {
+            bail!("Packaging check failed for {} file(s)", findings.len());
+        }
+        let can_prompt = std::io::stdin().is_terminal() || answers::replaying();
+        if !can_prompt || !EnvSettings::from_env().interactive() {
+            return Ok(true);
+        }
+        answers::confirm("Upload these files anyway?", false)
+    }
+
     async fn validate_deployment_environment(
         &self,
         app: &DeployableApp,
@@ -413,53 +760,21 @@ impl DeployCommand {
         Err(anyhow!("The application requires values for the following variable(s) which have not been set: {list_text}. Use the --variable flag to provide values."))
     }
 
-    async fn push_oci(
-        &self,
-        application: DeployableApp,
-        connection_config: ConnectionConfig,
-    ) -> Result<Option<String>> {
-        let mut client = spin_oci::Client::new(connection_config.insecure, None).await?;
-
-        let cloud_url =
-            Url::parse(connection_config.url.as_str()).context("Unable to parse cloud URL")?;
-        let cloud_host = cloud_url
-            .host_str()
-            .context("Unable to derive host from cloud URL")?;
-        let cloud_registry_host = format!("registry.{cloud_host}");
-
-        let reference = format!(
-            "{}/{}:{}",
-            cloud_registry_host,
-            &sanitize_app_name(application.name()?),
-            &sanitize_app_version(application.version()?)
-        );
-
-        let oci_ref = Reference::try_from(reference.as_ref())
-            .context(format!("Could not parse reference '{reference}'"))?;
-
-        client.insert_token(
-            &oci_ref,
-            RegistryOperation::Push,
-            token_cache::RegistryTokenType::Bearer(token_cache::RegistryToken::Token {
-                token: connection_config.token,
-            }),
-        );
-
-        println!(
-            "Uploading {} version {} to Fermyon Cloud...",
-            &oci_ref.repository(),
-            &oci_ref.tag().unwrap_or(application.version()?)
-        );
-        let digest = client.push_locked(application.0, reference).await?;
-
-        Ok(digest)
-    }
-
     async fn run_spin_build(&self) -> Result<()> {
         self.resolve_app_source().build().await
     }
 }
 
+// The total size of the files under `dir`, which holds the files to upload.
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
 // Spin now allows HTTP apps to omit the base path, but Cloud
 // doesn't yet like this. This works around that by defaulting
 // base if not set. (We don't check trigger type because by the
@@ -477,11 +792,15 @@ fn ensure_http_base_set(mut locked_app: locked::LockedApp) -> locked::LockedApp
     locked_app
 }
 
+/// The locked app metadata recording which version of the plugin deployed it
+const PLUGIN_VERSION_METADATA_KEY: &str = "cloud_plugin_version";
+
 // Insert cloud plugin version into locked app metadata
 fn ensure_plugin_version_set(mut locked_app: locked::LockedApp) -> locked::LockedApp {
-    locked_app
-        .metadata
-        .insert("cloud_plugin_version".to_owned(), crate::VERSION.into());
+    locked_app.metadata.insert(
+        PLUGIN_VERSION_METADATA_KEY.to_owned(),
+        crate::VERSION.into(),
+    );
     locked_app
 }
 
@@ -493,6 +812,76 @@ enum AppSource {
     Unresolvable(String),
 }
 
+/// What deploying `application` resolves to. `app` is the app once it has
+/// been deployed; before then, the existing app of the same name is used,
+/// if there is one.
+async fn resolve_lock(
+    client: &impl CloudClientInterface,
+    application: &DeployableApp,
+    app: Option<&AppItem>,
+) -> Result<Lockfile> {
+    let name = sanitize_app_name(application.name()?);
+    let existing = match app {
+        Some(_) => None,
+        None => match client.get_app_id(&name).await? {
+            Some(id) => Some(
+                client
+                    .get_app(id.to_string())
+                    .await
+                    .context("Problem getting app by id")?,
+            ),
+            None => None,
+        },
+    };
+    let app = app.or(existing.as_ref());
+    let labels = application.sqlite_databases();
+    let sqlite = if labels.is_empty() {
+        BTreeMap::new()
+    } else {
+        app_database_links(&list_databases(client).await?, &name)
+            .into_iter()
+            .filter(|l| labels.contains(&l.resource_label.label))
+            .map(|l| (l.resource_label.label, l.resource))
+            .collect()
+    };
+    Ok(Lockfile::new(
+        &name,
+        app.map(|a| a.id.to_string()),
+        app.and_then(|a| a.channels.first()).map(|c| c.name.clone()),
+        &ArtifactDigests::of(&application.0)?,
+        sqlite,
+        application.key_value_stores().into_iter().collect(),
+    ))
+}
+
+/// Reports how a deployment differs from the lockfile. With `frozen`, any
+/// difference fails the deployment.
+fn check_drift(locked: &Lockfile, planned: &Lockfile, frozen: bool) -> Result<()> {
+    let drift = locked.drift(planned);
+    if drift.is_empty() {
+        return Ok(());
+    }
+    eprintln!("This deployment differs from {LOCKFILE}:");
+    for line in drift.render(false) {
+        eprintln!("  {line}");
+    }
+    if frozen {
+        bail!(
+            "--frozen refuses changes which are not in {LOCKFILE}. Deploy with --lockfile to record them"
+        );
+    }
+    Ok(())
+}
+
+/// The directory containing a manifest. A bare file name such as
+/// `spin.toml` has an empty parent, which is the current directory.
+fn manifest_dir(manifest: &Path) -> PathBuf {
+    match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+        _ => PathBuf::from("."),
+    }
+}
+
 impl AppSource {
     fn unresolvable(message: impl Into<String>) -> Self {
         Self::Unresolvable(message.into())
@@ -537,6 +926,12 @@ fn check_safe_app_name(name: &str) -> Result<()> {
     }
 }
 
+/// Checks an app name given on the command line.
+pub(crate) fn parse_app_name(name: &str) -> Result<String> {
+    check_safe_app_name(name)?;
+    Ok(name.to_owned())
+}
+
 // Sanitize app name to conform to Docker repo name conventions
 // From https://docs.docker.com/engine/reference/commandline/tag/#extended-description:
 // The path consists of slash-separated components. Each component may contain lowercase letters, digits and separators.
@@ -580,7 +975,7 @@ fn validate_cloud_app(app: &DeployableApp) -> Result<()> {
 }
 
 #[derive(Clone)]
-struct DeployableApp(locked::LockedApp);
+pub(crate) struct DeployableApp(pub(crate) locked::LockedApp);
 
 struct DeployableComponent(locked::LockedComponent);
 
@@ -611,13 +1006,29 @@ impl DeployableApp {
             .collect()
     }
 
-    fn sqlite_databases(&self) -> HashSet<String> {
+    pub(crate) fn sqlite_databases(&self) -> HashSet<String> {
         self.components()
             .iter()
             .flat_map(|c| c.sqlite_databases())
             .collect()
     }
 
+    /// The components which declare `label` among their SQLite databases.
+    pub(crate) fn components_using_database(&self, label: &str) -> Vec<String> {
+        self.components()
+            .iter()
+            .filter(|c| c.sqlite_databases().iter().any(|l| l == label))
+            .map(|c| c.id().to_owned())
+            .collect()
+    }
+
+    fn key_value_stores(&self) -> HashSet<String> {
+        self.components()
+            .iter()
+            .flat_map(|c| c.key_value_stores())
+            .collect()
+    }
+
     fn http_routes(&self) -> (Option<String>, Vec<HttpRoute>) {
         let base = self
             .0
@@ -700,7 +1111,7 @@ impl DeployableComponent {
     }
 }
 
-fn build_app_base_url(app_domain: &str, cloud_url: &Url) -> Result<Url> {
+pub(crate) fn build_app_base_url(app_domain: &str, cloud_url: &Url) -> Result<Url> {
     // HACK: We assume that the scheme (https vs http) of apps will match that of Cloud...
     let scheme = cloud_url.scheme();
     Url::parse(&format!("{scheme}://{app_domain}/")).with_context(|| {
@@ -709,19 +1120,29 @@ fn build_app_base_url(app_domain: &str, cloud_url: &Url) -> Result<Url> {
 }
 
 const READINESS_POLL_INTERVAL_SECS: u64 = 2;
+const READINESS_HEARTBEAT_INTERVAL_SECS: u64 = 10;
 
 enum Destination {
     Cloud(String),
 }
 
+/// Whether a deployed application was seen to become ready.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Readiness {
+    Ready,
+    NotReady,
+    /// Readiness was not waited for, or could not be checked.
+    Unchecked,
+}
+
 async fn wait_for_ready(
     app_base_url: &Url,
     app_version: &str,
     readiness_timeout_secs: u16,
     destination: Destination,
-) {
+) -> Readiness {
     if readiness_timeout_secs == 0 {
-        return;
+        return Readiness::Unchecked;
     }
 
     let app_info_url = app_base_url
@@ -734,28 +1155,41 @@ async fn wait_for_ready(
     let start = std::time::Instant::now();
     let readiness_timeout = std::time::Duration::from_secs(u64::from(readiness_timeout_secs));
     let poll_interval = tokio::time::Duration::from_secs(READINESS_POLL_INTERVAL_SECS);
+    let heartbeat_interval = std::time::Duration::from_secs(READINESS_HEARTBEAT_INTERVAL_SECS);
+    let mut last_heartbeat = start;
 
     print!("Waiting for application to become ready");
     let _ = std::io::stdout().flush();
     loop {
-        match is_ready(&app_info_url, app_version).await {
+        let probe = match is_ready(&app_info_url, app_version).await {
             Err(err) => {
                 println!("... readiness check failed: {err:?}");
-                return;
+                return Readiness::Unchecked;
             }
-            Ok(true) => {
+            Ok(probe) if probe.ready => {
                 println!("... ready");
-                return;
+                return Readiness::Ready;
             }
-            Ok(false) => {}
-        }
+            Ok(probe) => probe,
+        };
 
         print!(".");
+        // Periodically say what the check is seeing, so that slow startups
+        // in CI logs don't look like a hang.
+        if last_heartbeat.elapsed() >= heartbeat_interval {
+            last_heartbeat = std::time::Instant::now();
+            println!(
+                " ({}s elapsed, last check: {})",
+                start.elapsed().as_secs(),
+                probe.observed
+            );
+        }
         let _ = std::io::stdout().flush();
 
         if start.elapsed() >= readiness_timeout {
             println!();
             println!("Application deployed, but Spin could not establish readiness");
+            println!("Last readiness check: {}", probe.observed);
             match destination {
                 Destination::Cloud(url) => {
                     println!(
@@ -763,26 +1197,43 @@ async fn wait_for_ready(
                     );
                 }
             }
-            return;
+            return Readiness::NotReady;
         }
         tokio::time::sleep(poll_interval).await;
     }
 }
 
+/// The outcome of a single readiness check.
+struct ReadinessProbe {
+    ready: bool,
+    /// What the check saw, for reporting progress.
+    observed: String,
+}
+
+impl ReadinessProbe {
+    fn not_ready(observed: impl Into<String>) -> Self {
+        Self {
+            ready: false,
+            observed: observed.into(),
+        }
+    }
+}
+
 #[instrument(level = "debug")]
-async fn is_ready(app_info_url: &str, expected_version: &str) -> Result<bool> {
+async fn is_ready(app_info_url: &str, expected_version: &str) -> Result<ReadinessProbe> {
     // If the request fails, we assume the app isn't ready
     let resp = match reqwest::get(app_info_url).await {
         Ok(resp) => resp,
         Err(err) => {
             tracing::warn!("Readiness check failed: {err:?}");
-            return Ok(false);
+            return Ok(ReadinessProbe::not_ready("no response"));
         }
     };
     // If the response status isn't success, the app isn't ready
-    if !resp.status().is_success() {
-        tracing::debug!("App not ready: {}", resp.status());
-        return Ok(false);
+    let status = resp.status();
+    if !status.is_success() {
+        tracing::debug!("App not ready: {}", status);
+        return Ok(ReadinessProbe::not_ready(format!("HTTP {status}")));
     }
     // If the app was previously deployed then it will have an outdated
     // version, in which case the app isn't ready
@@ -790,16 +1241,26 @@ async fn is_ready(app_info_url: &str, expected_version: &str) -> Result<bool> {
         let active_version = app_info.oci_image_digest;
         if active_version.as_deref() != Some(expected_version) {
             tracing::debug!("Active version {active_version:?} != expected {expected_version:?}");
-            return Ok(false);
+            return Ok(ReadinessProbe::not_ready(format!(
+                "HTTP {status}, previous version still active"
+            )));
         }
     }
-    Ok(true)
+    Ok(ReadinessProbe {
+        ready: true,
+        observed: format!("HTTP {status}"),
+    })
 }
 
-fn print_available_routes(app_name: &str, app_base_url: &Url, base: &str, routes: &[HttpRoute]) {
+// Returns the URL prefix which routes are appended to, and the base path of
+// the app's routes.
+fn route_prefix_and_base(app_base_url: &Url, base: &str) -> (String, String) {
     // Strip any trailing slash from base URL
     let app_base_url = app_base_url.to_string();
-    let route_prefix = app_base_url.strip_suffix('/').unwrap_or(&app_base_url);
+    let route_prefix = app_base_url
+        .strip_suffix('/')
+        .unwrap_or(&app_base_url)
+        .to_owned();
 
     // Ensure base starts with a /
     let base = if !base.starts_with('/') {
@@ -807,6 +1268,40 @@ fn print_available_routes(app_name: &str, app_base_url: &Url, base: &str, routes
     } else {
         base.to_owned()
     };
+    (route_prefix, base)
+}
+
+/// An HTTP route of an app, and the external URL at which it is served
+#[derive(Debug, PartialEq)]
+pub(crate) struct AppRoute {
+    pub component: String,
+    pub route: String,
+    pub url: String,
+}
+
+/// Lists the HTTP routes of a locked app, served from `app_base_url`.
+pub(crate) fn app_routes(app: &locked::LockedApp, app_base_url: &Url) -> Vec<AppRoute> {
+    let (base, routes) = DeployableApp(app.clone()).http_routes();
+    route_urls(app_base_url, base.as_deref().unwrap_or("/"), routes)
+}
+
+fn route_urls(app_base_url: &Url, base: &str, routes: Vec<HttpRoute>) -> Vec<AppRoute> {
+    let (route_prefix, base) = route_prefix_and_base(app_base_url, base);
+    routes
+        .into_iter()
+        .map(|r| {
+            let route = RoutePattern::from(&base, &r.route_pattern).to_string();
+            AppRoute {
+                url: format!("{route_prefix}{route}"),
+                component: r.id,
+                route,
+            }
+        })
+        .collect()
+}
+
+fn print_available_routes(app_name: &str, app_base_url: &Url, base: &str, routes: &[HttpRoute]) {
+    let (route_prefix, base) = route_prefix_and_base(app_base_url, base);
 
     let app_root_url = format!("{route_prefix}{base}");
     let admin_url = format!("{}app/{app_name}", DEFAULT_CLOUD_URL); // URL already has scheme and /
@@ -844,7 +1339,38 @@ fn has_expired(login_connection: &LoginConnection) -> Result<bool> {
     }
 }
 
+// A token from the environment is used as given. It is never saved, and there
+// is no refresh token, so an expired token has to be replaced by whoever set it.
+fn env_login_connection(token: &str, url: Option<&str>) -> Result<LoginConnection> {
+    let url = url.unwrap_or(DEFAULT_CLOUD_URL);
+    Ok(LoginConnection {
+        url: Url::parse(url).with_context(|| format!("Invalid {CLOUD_URL_ENV} '{url}'"))?,
+        danger_accept_invalid_certs: false,
+        token: token.to_owned(),
+        refresh_token: None,
+        expiration: None,
+        version: CONFIG_VERSION,
+    })
+}
+
+// Pipeline logs should show which credentials a job used, so in CI this is
+// reported once per run.
+fn report_ci_auth(settings: &EnvSettings, credentials: &str) {
+    static REPORTED: std::sync::Once = std::sync::Once::new();
+    if let Some(ci) = settings.ci {
+        REPORTED
+            .call_once(|| eprintln!("Detected {}; authenticating with {credentials}", ci.name()));
+    }
+}
+
 pub async fn login_connection(deployment_env_id: Option<&str>) -> Result<LoginConnection> {
+    let _timing = crate::timing::step("Auth");
+    let settings = EnvSettings::from_env();
+    if let Some(token) = &settings.token {
+        report_ci_auth(&settings, CLOUD_TOKEN_ENV);
+        return env_login_connection(token, settings.url.as_deref());
+    }
+    let deployment_env_id = deployment_env_id.or(settings.profile.as_deref());
     let path = config_file_path(deployment_env_id)?;
 
     // log in if config.json does not exist or cannot be read
@@ -859,9 +1385,29 @@ pub async fn login_connection(deployment_env_id: Option<&str>) -> Result<LoginCo
                     std::process::exit(1);
                 }
                 None => {
+                    if let Some(ci) = settings.ci {
+                        if identity_available() {
+                            report_ci_auth(&settings, "the job's OIDC identity");
+                            let url = settings.url.as_deref().unwrap_or(DEFAULT_CLOUD_URL);
+                            let url = Url::parse(url)
+                                .with_context(|| format!("Invalid {CLOUD_URL_ENV} '{url}'"))?;
+                            return cached_oidc_login_connection(&url, settings.app.as_deref())
+                                .await;
+                        }
+                        bail!(
+                            "Not logged in. In {} set {CLOUD_TOKEN_ENV}, for example from a secret, or {} to log in with the job's OIDC identity",
+                            ci.name(),
+                            setup_hint(Some(ci))
+                        );
+                    }
+                    if !settings.interactive() {
+                        bail!(
+                            "Not logged in. Set {CLOUD_TOKEN_ENV}, or run `spin cloud login` first"
+                        );
+                    }
                     // log in, then read config
                     // TODO: propagate deployment id (or bail if nondefault?)
-                    LoginCommand::parse_from(vec!["login"]).run().await?;
+                    LoginCommand::parse_from(vec!["login"]).run_login().await?;
                     fs::read_to_string(path.clone()).await?
                 }
             }
@@ -872,6 +1418,10 @@ pub async fn login_connection(deployment_env_id: Option<&str>) -> Result<LoginCo
     };
 
     let mut login_connection: LoginConnection = serde_json::from_str(&data)?;
+    match deployment_env_id {
+        Some(name) => report_ci_auth(&settings, &format!("the saved login '{name}'")),
+        None => report_ci_auth(&settings, "the saved login"),
+    }
     let expired = match has_expired(&login_connection) {
         Ok(val) => val,
         Err(err) => {
@@ -935,7 +1485,10 @@ pub async fn login_connection(deployment_env_id: Option<&str>) -> Result<LoginCo
                         std::process::exit(1);
                     }
                     None => {
-                        LoginCommand::parse_from(vec!["login"]).run().await?;
+                        if !settings.interactive() {
+                            bail!("Your login has expired. Set {CLOUD_TOKEN_ENV}, or run `spin cloud login` to log in again");
+                        }
+                        LoginCommand::parse_from(vec!["login"]).run_login().await?;
                         let new_data = fs::read_to_string(path.clone()).await.context(format!(
                             "Cannot find spin config at {}",
                             path.to_string_lossy()
@@ -1056,10 +1609,24 @@ mod test {
             registry_source: None,
             build: false,
             readiness_timeout_secs: 60,
+            wait_timeout_secs: None,
             deployment_env_id: None,
             key_values: vec![],
             variables: vec![],
             links: vec![],
+            no_resource_provisioning: false,
+            region: None,
+            skip_variables: false,
+            skip_link_checks: false,
+            strict_packaging: false,
+            show_ignored: false,
+            no_build_info: false,
+            progress: ProgressFormat::Text,
+            name: None,
+            route_prefix: None,
+            verify_reproducible: false,
+            lockfile: false,
+            frozen: false,
         }
     }
 
@@ -1108,6 +1675,16 @@ mod test {
         assert_eq!("/", base);
     }
 
+    #[test]
+    fn project_files_are_found_next_to_the_manifest() {
+        let mut cmd = deploy_cmd_for_test_file("minimal_v2.toml");
+        cmd.file_source = None;
+        cmd.app_source = Some("testdata/minimal_v2.toml".to_owned());
+        assert_eq!(cmd.project_dir(), PathBuf::from("testdata"));
+
+        assert_eq!(manifest_dir(Path::new("spin.toml")), PathBuf::from("."));
+    }
+
     #[tokio::test]
     async fn plugin_version_should_be_set() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -1118,6 +1695,36 @@ mod test {
         assert_eq!(crate::VERSION, version);
     }
 
+    #[test]
+    fn route_urls_include_base_and_domain() {
+        let route = |id: &str, pattern: &str| HttpRoute {
+            id: id.to_owned(),
+            description: None,
+            route_pattern: pattern.to_owned(),
+        };
+        let base_url = Url::parse("https://shop-abc.fermyon.app/").unwrap();
+        let routes = route_urls(
+            &base_url,
+            "shop",
+            vec![route("api", "/api/..."), route("home", "/index.html")],
+        );
+        assert_eq!(
+            routes,
+            vec![
+                AppRoute {
+                    component: "api".to_owned(),
+                    route: "/shop/api/...".to_owned(),
+                    url: "https://shop-abc.fermyon.app/shop/api/...".to_owned(),
+                },
+                AppRoute {
+                    component: "home".to_owned(),
+                    route: "/shop/index.html".to_owned(),
+                    url: "https://shop-abc.fermyon.app/shop/index.html".to_owned(),
+                },
+            ]
+        );
+    }
+
     fn string_set(strs: &[&str]) -> HashSet<String> {
         strs.iter().map(|s| s.to_string()).collect()
     }
@@ -1133,18 +1740,23 @@ mod test {
         client.expect_get_databases().returning(|_| Ok(vec![]));
         client
             .expect_create_database()
-            .withf(|db, rlabel| db == "def-o-rama" && rlabel.is_none())
-            .returning(move |_, _| Ok(()));
+            .withf(|db, rlabel, region| {
+                db == "def-o-rama" && rlabel.is_none() && region.as_deref() == Some("eu-west")
+            })
+            .returning(move |_, _, _| Ok(()));
         client.expect_get_databases().returning(|_| Ok(vec![]));
         client
             .expect_create_database()
-            .withf(|db, rlabel| db == "excel" && rlabel.is_none())
-            .returning(|_, _| Ok(()));
+            .withf(|db, rlabel, region| {
+                db == "excel" && rlabel.is_none() && region.as_deref() == Some("eu-west")
+            })
+            .returning(|_, _, _| Ok(()));
 
         let databases_to_link = database::create_databases_for_new_app(
             &client,
             "test:script-new-app",
             labels,
+            Some("eu-west"),
             &linkages,
         )
         .await
@@ -1161,7 +1773,7 @@ mod test {
             .withf(|db, rlabel| db == "excel" && rlabel.label == "finance")
             .returning(|_, _| Ok(()));
 
-        database::link_databases(
+        crate::ops::deploy::link_databases(
             &client,
             "test:script-new-app",
             uuid::Uuid::new_v4(),
@@ -0,0 +1,137 @@
+use chrono::Utc;
+use serde::Serialize;
+use spin_locked_app::locked::LockedApp;
+use std::path::Path;
+
+/// The locked app metadata key under which build information is recorded
+pub(super) const BUILD_INFO_METADATA_KEY: &str = "build_info";
+
+/// Where the source of a deployed revision came from. The fields follow the
+/// names vergen uses for the plugin's own build information.
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct BuildInfo {
+    pub git_sha: String,
+    /// `None` if the working tree has a detached HEAD
+    pub git_branch: Option<String>,
+    /// Whether the working tree had uncommitted changes
+    pub git_dirty: bool,
+    /// RFC 3339 time at which the revision was deployed
+    pub build_timestamp: String,
+}
+
+impl BuildInfo {
+    /// Captures the state of the git repository containing `project_dir`, or
+    /// returns `None` if it is not in a repository or git is not installed.
+    pub async fn capture(project_dir: &Path) -> Option<Self> {
+        let dir = if project_dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            project_dir
+        };
+        let git_sha = git(dir, &["rev-parse", "HEAD"]).await?;
+        let git_branch = git(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+            .await
+            .filter(|branch| branch != "HEAD");
+        let git_dirty = git(dir, &["status", "--porcelain"])
+            .await
+            .is_some_and(|status| !status.is_empty());
+        Some(Self {
+            git_sha,
+            git_branch,
+            git_dirty,
+            build_timestamp: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Records the build information in the app's metadata, which is stored
+    /// with the revision.
+    pub fn annotate(&self, app: &mut LockedApp) -> anyhow::Result<()> {
+        app.metadata.insert(
+            BUILD_INFO_METADATA_KEY.to_owned(),
+            serde_json::to_value(self)?,
+        );
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let short_sha = self.git_sha.get(..7).unwrap_or(&self.git_sha);
+        write!(f, "{short_sha}")?;
+        if let Some(branch) = &self.git_branch {
+            write!(f, " on {branch}")?;
+        }
+        if self.git_dirty {
+            write!(f, " (with uncommitted changes)")?;
+        }
+        Ok(())
+    }
+}
+
+// Runs git in `dir`, returning its trimmed output if it succeeded.
+async fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn captures_commit_branch_and_dirty_state() {
+        let dir = tempfile::tempdir().unwrap();
+        run_git(dir.path(), &["init", "--initial-branch", "main"]);
+        std::fs::write(dir.path().join("spin.toml"), "").unwrap();
+        run_git(dir.path(), &["add", "."]);
+        run_git(dir.path(), &["commit", "-m", "initial"]);
+
+        let info = BuildInfo::capture(dir.path()).await.unwrap();
+        assert_eq!(info.git_sha.len(), 40);
+        assert_eq!(info.git_branch.as_deref(), Some("main"));
+        assert!(!info.git_dirty);
+
+        std::fs::write(dir.path().join("spin.toml"), "changed").unwrap();
+        assert!(BuildInfo::capture(dir.path()).await.unwrap().git_dirty);
+    }
+
+    #[tokio::test]
+    async fn no_build_info_outside_a_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(BuildInfo::capture(dir.path()).await, None);
+    }
+
+    #[test]
+    fn describes_revision_source() {
+        let info = BuildInfo {
+            git_sha: "0123456789abcdef".to_owned(),
+            git_branch: Some("main".to_owned()),
+            git_dirty: true,
+            build_timestamp: "2024-05-01T12:00:00+00:00".to_owned(),
+        };
+        assert_eq!(
+            info.to_string(),
+            "0123456 on main (with uncommitted changes)"
+        );
+    }
+}
@@ -7,9 +7,11 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use uuid::Uuid;
 
+use crate::answers;
+use crate::project_config::{ApprovedResources, PROJECT_CONFIG_FILE};
 use crate::random_name::RandomNameGenerator;
 
-use crate::commands::sqlite::database_has_link;
+use crate::ops::sqlite::database_has_link;
 
 /// A user's selection of a database to link to a label
 pub(super) enum DatabaseSelection {
@@ -76,12 +78,7 @@ impl InteractionStrategy for Interactive {
         let existing_opt = "Use an existing database and link app to it";
         let create_opt = "Create a new database and link the app to it";
         let opts = vec![existing_opt, create_opt];
-        let index = match dialoguer::Select::new()
-            .with_prompt(prompt)
-            .items(&opts)
-            .default(1)
-            .interact_opt()?
-        {
+        let index = match answers::select(&prompt, &opts, 1)? {
             Some(i) => i,
             None => return Ok(DatabaseSelection::Cancelled),
         };
@@ -115,12 +112,7 @@ impl Interactive {
     ) -> Result<DatabaseSelection> {
         let prompt =
             format!(r#"Which database would you like to link to {name} using the label "{label}""#);
-        let index = match dialoguer::Select::new()
-            .with_prompt(prompt)
-            .items(&database_names)
-            .default(0)
-            .interact_opt()?
-        {
+        let index = match answers::select(&prompt, &database_names, 0)? {
             Some(i) => i,
             None => return Ok(DatabaseSelection::Cancelled),
         };
@@ -143,10 +135,7 @@ impl Interactive {
     Note: This name is used when managing your database at the account level. The app "{name}" will refer to this database by the label "{label}".
     Other apps can use different labels to refer to the same database."#
         );
-        let name = dialoguer::Input::new()
-            .with_prompt(prompt)
-            .default(default_name)
-            .interact_text()?;
+        let name = answers::input(&prompt, Some(default_name))?;
         Ok(DatabaseSelection::New(name))
     }
 }
@@ -218,6 +207,7 @@ pub(super) async fn create_databases_for_new_app(
     client: &impl CloudClientInterface,
     name: &str,
     labels: HashSet<String>,
+    region: Option<&str>,
     interact: &dyn InteractionStrategy,
 ) -> anyhow::Result<Option<Vec<(String, String)>>> {
     let mut databases_to_link = Vec::new();
@@ -225,7 +215,9 @@ pub(super) async fn create_databases_for_new_app(
         let db = match get_database_selection_for_new_app(name, client, &label, interact).await? {
             DatabaseSelection::Existing(db) => db,
             DatabaseSelection::New(db) => {
-                client.create_database(db.clone(), None).await?;
+                client
+                    .create_database(db.clone(), None, region.map(str::to_owned))
+                    .await?;
                 db
             }
             // User canceled terminal interaction
@@ -243,6 +235,7 @@ pub(super) async fn create_and_link_databases_for_existing_app(
     app_name: &str,
     app_id: Uuid,
     labels: HashSet<String>,
+    region: Option<&str>,
     interact: &dyn InteractionStrategy,
 ) -> anyhow::Result<Option<()>> {
     for label in labels {
@@ -259,7 +252,9 @@ pub(super) async fn create_and_link_databases_for_existing_app(
                 // User canceled terminal interaction
                 DatabaseSelection::Cancelled => return Ok(None),
                 DatabaseSelection::New(db) => {
-                    client.create_database(db, Some(resource_label)).await?;
+                    client
+                        .create_database(db, Some(resource_label), region.map(str::to_owned))
+                        .await?;
                 }
                 DatabaseSelection::Existing(db) => {
                     client
@@ -275,27 +270,142 @@ pub(super) async fn create_and_link_databases_for_existing_app(
     Ok(Some(()))
 }
 
-pub(super) async fn link_databases(
+// Used when deploying without resource provisioning: fails, listing everything an
+// operator needs to set up, unless every label the app uses is either already linked
+// or pre-approved in the project config and refers to an existing resource.
+pub(super) async fn ensure_resources_provisioned(
     client: &impl CloudClientInterface,
     app_name: &str,
-    app_id: Uuid,
-    database_labels: Vec<(String, String)>,
+    sqlite_labels: &HashSet<String>,
+    key_value_stores: &HashSet<String>,
+    approved: &ApprovedResources,
 ) -> anyhow::Result<()> {
-    for (database, label) in database_labels {
-        let resource_label = ResourceLabel {
-            label,
-            app_id,
+    let databases = if sqlite_labels.is_empty() {
+        vec![]
+    } else {
+        client.get_databases(None).await?
+    };
+    let missing = unprovisioned_resources(
+        app_name,
+        sqlite_labels,
+        key_value_stores,
+        &databases,
+        approved,
+    );
+    if missing.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "Resource provisioning is disabled, but the app needs resources which have not been provisioned. An operator must provide:\n{}",
+        missing
+            .iter()
+            .map(|m| format!("  - {m}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+fn unprovisioned_resources(
+    app_name: &str,
+    sqlite_labels: &HashSet<String>,
+    key_value_stores: &HashSet<String>,
+    databases: &[Database],
+    approved: &ApprovedResources,
+) -> Vec<String> {
+    let mut sqlite_labels = sqlite_labels.iter().collect::<Vec<_>>();
+    sqlite_labels.sort();
+    let mut key_value_stores = key_value_stores.iter().collect::<Vec<_>>();
+    key_value_stores.sort();
+
+    let mut missing = vec![];
+    for label in sqlite_labels {
+        if databases
+            .iter()
+            .any(|d| database_has_link(d, label, Some(app_name)))
+        {
+            continue;
+        }
+        match approved.sqlite.get(label) {
+            None => missing.push(format!(
+                r#"a SQLite database for label "{label}", approved in {PROJECT_CONFIG_FILE}"#
+            )),
+            Some(db) if !databases.iter().any(|d| &d.name == db) => missing.push(format!(
+                r#"SQLite database "{db}" (approved for label "{label}" but does not exist)"#
+            )),
+            Some(_) => {}
+        }
+    }
+    for store in key_value_stores {
+        if !approved.key_value_stores.contains(store) {
+            missing.push(format!(
+                r#"key value store "{store}", approved in {PROJECT_CONFIG_FILE}"#
+            ));
+        }
+    }
+    missing
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn db(name: &str, links: Vec<ResourceLabel>) -> Database {
+        Database::new(name.to_owned(), links)
+    }
+
+    fn label_link(app_name: &str, label: &str) -> ResourceLabel {
+        ResourceLabel {
+            app_id: Uuid::new_v4(),
+            label: label.to_owned(),
             app_name: Some(app_name.to_owned()),
+        }
+    }
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn provisioned_resources_pass() {
+        let approved = ApprovedResources {
+            sqlite: [("main".to_owned(), "inventory".to_owned())].into(),
+            key_value_stores: vec!["default".to_owned()],
+        };
+        let databases = vec![
+            db("inventory", vec![]),
+            db("audit", vec![label_link("shop", "audit")]),
+        ];
+        let missing = unprovisioned_resources(
+            "shop",
+            &set(&["main", "audit"]),
+            &set(&["default"]),
+            &databases,
+            &approved,
+        );
+        assert!(missing.is_empty(), "{missing:?}");
+    }
+
+    #[test]
+    fn reports_everything_needing_provisioning() {
+        let approved = ApprovedResources {
+            sqlite: [("main".to_owned(), "inventory".to_owned())].into(),
+            key_value_stores: vec![],
         };
-        client
-            .create_database_link(&database, resource_label)
-            .await
-            .with_context(|| {
-                format!(
-                    r#"Failed to link database "{}" to app "{}""#,
-                    database, app_name
-                )
-            })?;
+        let databases = vec![db("audit", vec![label_link("other-app", "audit")])];
+        let missing = unprovisioned_resources(
+            "shop",
+            &set(&["main", "audit"]),
+            &set(&["default"]),
+            &databases,
+            &approved,
+        );
+        assert_eq!(
+            missing,
+            vec![
+                r#"a SQLite database for label "audit", approved in spin-cloud.toml"#,
+                r#"SQLite database "inventory" (approved for label "main" but does not exist)"#,
+                r#"key value store "default", approved in spin-cloud.toml"#,
+            ]
+        );
     }
-    Ok(())
 }
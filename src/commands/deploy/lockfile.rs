@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::reproducible::ArtifactDigests;
+use crate::diff::Diff;
+
+/// The file, in the project root, recording what a deployment resolved to
+pub(super) const LOCKFILE: &str = "spin-cloud.lock";
+
+const LOCKFILE_VERSION: u32 = 1;
+
+/// What a deployment of the project resolved to: the app it updated, the
+/// resources its labels are linked to and the digests of what was
+/// uploaded. Later deployments compare themselves with it to find drift.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct Lockfile {
+    pub version: u32,
+    pub app_name: String,
+    /// `None` if the app has not been created yet
+    pub app_id: Option<String>,
+    /// The channel serving the app
+    pub channel: Option<String>,
+    /// A single digest covering every artifact
+    pub artifact_digest: String,
+    /// Key value stores used by the app
+    #[serde(default)]
+    pub key_value_stores: Vec<String>,
+    /// The database linked to each SQLite label
+    #[serde(default)]
+    pub sqlite: BTreeMap<String, String>,
+    /// The digest of each artifact, named as by `--verify-reproducible`
+    #[serde(default)]
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    pub fn new(
+        app_name: &str,
+        app_id: Option<String>,
+        channel: Option<String>,
+        digests: &ArtifactDigests,
+        sqlite: BTreeMap<String, String>,
+        mut key_value_stores: Vec<String>,
+    ) -> Self {
+        key_value_stores.sort();
+        Self {
+            version: LOCKFILE_VERSION,
+            app_name: app_name.to_owned(),
+            app_id,
+            channel,
+            artifact_digest: digests.digest(),
+            key_value_stores,
+            sqlite,
+            artifacts: digests.parts().clone(),
+        }
+    }
+
+    /// Loads the lockfile in `dir`, if there is one.
+    pub fn load_from_dir(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(LOCKFILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let lockfile = toml::from_str(&text)
+            .with_context(|| format!("Invalid lockfile {}", path.display()))?;
+        Ok(Some(lockfile))
+    }
+
+    pub fn save_to_dir(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(LOCKFILE);
+        let text = format!(
+            "# Written by `spin cloud deploy --lockfile`. Commit it to detect drift between deployments.\n{}",
+            toml::to_string_pretty(self)?
+        );
+        std::fs::write(&path, text).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    /// What differs between the locked deployment and `other`, entry by
+    /// entry.
+    pub fn drift(&self, other: &Self) -> Diff {
+        Diff::between(&self.entries(), &other.entries())
+    }
+
+    fn entries(&self) -> BTreeMap<String, Option<String>> {
+        let mut entries = BTreeMap::new();
+        entries.insert("app".to_owned(), Some(self.app_name.clone()));
+        entries.insert("app id".to_owned(), self.app_id.clone());
+        entries.insert("channel".to_owned(), self.channel.clone());
+        for (label, database) in &self.sqlite {
+            entries.insert(format!("sqlite {label}"), Some(database.clone()));
+        }
+        for store in &self.key_value_stores {
+            entries.insert(format!("key value store {store}"), None);
+        }
+        for (part, digest) in &self.artifacts {
+            entries.insert(part.clone(), Some(digest.clone()));
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lockfile() -> Lockfile {
+        Lockfile {
+            version: LOCKFILE_VERSION,
+            app_name: "todo".to_owned(),
+            app_id: Some("3f2a1c5e-0000-4000-8000-000000000000".to_owned()),
+            channel: Some("production".to_owned()),
+            artifact_digest: "sha256:all".to_owned(),
+            key_value_stores: vec!["default".to_owned()],
+            sqlite: [("default".to_owned(), "todo-db".to_owned())].into(),
+            artifacts: [
+                ("manifest".to_owned(), "sha256:m".to_owned()),
+                ("component web module".to_owned(), "sha256:w".to_owned()),
+            ]
+            .into(),
+        }
+    }
+
+    #[test]
+    fn lockfile_round_trips() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(Lockfile::load_from_dir(dir.path())?, None);
+        lockfile().save_to_dir(dir.path())?;
+        assert_eq!(Lockfile::load_from_dir(dir.path())?, Some(lockfile()));
+        Ok(())
+    }
+
+    #[test]
+    fn drift_lists_changed_resources_and_artifacts() {
+        let mut planned = lockfile();
+        planned
+            .sqlite
+            .insert("default".to_owned(), "todo-db-2".to_owned());
+        planned
+            .artifacts
+            .insert("component web module".to_owned(), "sha256:w2".to_owned());
+        planned.key_value_stores.clear();
+        assert_eq!(
+            lockfile().drift(&planned).render(false),
+            vec![
+                "~ component web module     sha256:w -> sha256:w2",
+                "- key value store default",
+                "~ sqlite default           todo-db -> todo-db-2",
+            ]
+        );
+        assert!(lockfile().drift(&lockfile()).is_empty());
+    }
+}
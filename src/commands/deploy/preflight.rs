@@ -0,0 +1,341 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use cloud::{client::Unauthorized, models::AccountQuotas, CloudClientExt, CloudClientInterface};
+
+use super::check_safe_app_name;
+use crate::answers;
+use crate::random_name::RandomNameGenerator;
+
+/// How many free names are suggested when the app's name is taken
+const NAME_SUGGESTIONS: usize = 3;
+const NAME_SUGGESTION_MAX_ATTEMPTS: usize = 20;
+
+/// What the checks need to know about an app, read straight from its
+/// manifest so that they can run before the app is built.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct ManifestSummary {
+    pub name: String,
+    pub sqlite_labels: BTreeSet<String>,
+}
+
+impl ManifestSummary {
+    /// Reads the app name and database labels from a version 1 or 2 manifest.
+    pub fn from_file(manifest: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(manifest)
+            .with_context(|| format!("Could not read manifest {}", manifest.display()))?;
+        let manifest: toml::Value = toml::from_str(&text)
+            .with_context(|| format!("Could not parse manifest {}", manifest.display()))?;
+        Ok(Self::from_toml(&manifest))
+    }
+
+    fn from_toml(manifest: &toml::Value) -> Self {
+        let str_at = |value: Option<&toml::Value>| {
+            value
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_owned()
+        };
+        // Version 2 manifests keep components in a table keyed by ID, and
+        // version 1 manifests in an array.
+        let (name, components) = match manifest.get("application") {
+            Some(application) => (
+                str_at(application.get("name")),
+                manifest
+                    .get("component")
+                    .and_then(|c| c.as_table())
+                    .map(|c| c.values().collect::<Vec<_>>())
+                    .unwrap_or_default(),
+            ),
+            None => (
+                str_at(manifest.get("name")),
+                manifest
+                    .get("component")
+                    .and_then(|c| c.as_array())
+                    .map(|c| c.iter().collect::<Vec<_>>())
+                    .unwrap_or_default(),
+            ),
+        };
+        let sqlite_labels = components
+            .into_iter()
+            .filter_map(|c| c.get("sqlite_databases")?.as_array())
+            .flatten()
+            .filter_map(|label| label.as_str())
+            .map(|label| label.to_owned())
+            .collect();
+        Self {
+            name,
+            sqlite_labels,
+        }
+    }
+}
+
+/// Checks, before anything is built or uploaded, that the login is still
+/// valid and that the account can take the app and any new databases it needs.
+/// If the account's quotas cannot be read, a warning is printed and they are
+/// not checked.
+/// `app` is `None` for apps from a registry, whose manifest is not available
+/// yet; for those only the login is checked. `approved_databases` maps labels
+/// to the existing databases they will be linked to.
+///
+/// If another account already has the app's name, a free one is chosen with
+/// the user's help and `app.name` is changed to it.
+pub(super) async fn preflight(
+    client: &impl CloudClientInterface,
+    app: Option<&mut ManifestSummary>,
+    approved_databases: &BTreeMap<String, String>,
+    interactive: bool,
+) -> Result<()> {
+    // Not every platform can report quotas, so only a login it refuses stops
+    // the deployment; otherwise the quotas are left for it to enforce.
+    let quotas = match client.get_account_quotas().await {
+        Ok(quotas) => Some(quotas),
+        Err(e) if e.is::<Unauthorized>() => {
+            return Err(e.context(
+                "Your login with Fermyon Cloud was not accepted. If it has expired, run `spin cloud login` and try again",
+            ))
+        }
+        Err(e) => {
+            eprintln!("Warning: could not check the account's quotas, so they are not checked before deploying: {e:#}");
+            None
+        }
+    };
+    let Some(app) = app else {
+        return Ok(());
+    };
+
+    let (new_app, linked_labels) = match client.get_app_id(&app.name).await? {
+        Some(app_id) => {
+            let linked = client
+                .get_databases(Some(app_id))
+                .await
+                .context("could not fetch databases")?
+                .into_iter()
+                .flat_map(|d| d.links)
+                .filter(|l| l.app_id == app_id)
+                .map(|l| l.label)
+                .collect::<BTreeSet<_>>();
+            (false, linked)
+        }
+        None => {
+            if !client.is_app_name_available(&app.name).await? {
+                app.name = choose_other_name(client, &app.name, interactive).await?;
+            }
+            (true, BTreeSet::new())
+        }
+    };
+
+    let Some(quotas) = quotas else {
+        return Ok(());
+    };
+    let existing_databases = if approved_databases.is_empty() {
+        BTreeSet::new()
+    } else {
+        client
+            .get_databases(None)
+            .await
+            .context("could not fetch databases")?
+            .into_iter()
+            .map(|d| d.name)
+            .collect()
+    };
+    let new_databases = app
+        .sqlite_labels
+        .iter()
+        .filter(|label| !linked_labels.contains(*label))
+        .filter(|label| {
+            !matches!(approved_databases.get(*label),
+                Some(database) if existing_databases.contains(database))
+        })
+        .count();
+    check_quotas(&quotas, &app.name, new_app, new_databases)
+}
+
+async fn choose_other_name(
+    client: &impl CloudClientInterface,
+    name: &str,
+    interactive: bool,
+) -> Result<String> {
+    let suggestions = suggest_names(client, name).await?;
+    if !interactive {
+        bail!(
+            r#"The app name "{name}" is already used by another account. Deploy with --name to use a different name, such as {}."#,
+            suggestions.join(", ")
+        );
+    }
+    let mut items = suggestions.clone();
+    items.push("Enter a different name".to_owned());
+    let index = answers::select(
+        &format!(
+            r#"The app name "{name}" is already used by another account. Which name would you like to deploy as?"#
+        ),
+        &items,
+        0,
+    )?
+    .context("No app name chosen")?;
+    if let Some(suggestion) = suggestions.get(index) {
+        return Ok(suggestion.clone());
+    }
+    loop {
+        let name = answers::input_validated("App name", None, |n| {
+            check_safe_app_name(n).map_err(|e| e.to_string())
+        })?;
+        if client.is_app_name_available(&name).await? {
+            return Ok(name);
+        }
+        eprintln!(r#"The app name "{name}" is already in use"#);
+    }
+}
+
+/// Free names made from `name` and a random suffix, such as `myapp-floral-otter`.
+async fn suggest_names(client: &impl CloudClientInterface, name: &str) -> Result<Vec<String>> {
+    let generator = RandomNameGenerator::new();
+    let mut suggestions = Vec::with_capacity(NAME_SUGGESTIONS);
+    for _ in 0..NAME_SUGGESTION_MAX_ATTEMPTS {
+        let candidate = format!("{name}-{}", generator.generate());
+        if !suggestions.contains(&candidate) && client.is_app_name_available(&candidate).await? {
+            suggestions.push(candidate);
+            if suggestions.len() == NAME_SUGGESTIONS {
+                break;
+            }
+        }
+    }
+    if suggestions.is_empty() {
+        bail!(
+            r#"The app name "{name}" is already used by another account. Deploy with --name to use a different name."#
+        );
+    }
+    Ok(suggestions)
+}
+
+fn check_quotas(
+    quotas: &AccountQuotas,
+    app: &str,
+    new_app: bool,
+    new_databases: usize,
+) -> Result<()> {
+    if new_app && quotas.apps.remaining() == Some(0) {
+        bail!(
+            r#"Cannot create app "{app}": the account already has {} apps, the most its plan allows. Delete an app with `spin cloud apps delete` and try again, or run `spin cloud usage` to see the plan's limits."#,
+            quotas.apps.used
+        );
+    }
+    match quotas.databases.remaining() {
+        Some(remaining) if new_databases > remaining as usize => bail!(
+            r#"App "{app}" needs {new_databases} new database(s), but the account's plan allows only {remaining} more. Link the app to existing databases with `--link sqlite:label=database`, or delete unused databases. Run `spin cloud usage` to see the plan's limits."#
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud::models::Quota;
+
+    #[test]
+    fn summary_is_read_from_either_manifest_version() -> Result<()> {
+        let v2 = toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "todo"
+            [component.api]
+            source = "api.wasm"
+            sqlite_databases = ["default", "audit"]
+            [component.web]
+            source = "web.wasm"
+            "#,
+        )?;
+        let v1 = toml::from_str(
+            r#"
+            spin_manifest_version = "1"
+            name = "todo"
+            [[component]]
+            id = "api"
+            sqlite_databases = ["default"]
+            "#,
+        )?;
+
+        let summary = ManifestSummary::from_toml(&v2);
+        assert_eq!(summary.name, "todo");
+        assert_eq!(
+            summary.sqlite_labels.into_iter().collect::<Vec<_>>(),
+            vec!["audit", "default"]
+        );
+        assert_eq!(
+            ManifestSummary::from_toml(&v1),
+            ManifestSummary {
+                name: "todo".to_owned(),
+                sqlite_labels: ["default".to_owned()].into(),
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn taken_names_get_free_suggestions() -> Result<()> {
+        let mut mock = cloud::MockCloudClientInterface::new();
+        mock.expect_is_app_name_available()
+            .returning(|name| Ok(name != "todo"));
+
+        let suggestions = suggest_names(&mock, "todo").await?;
+        assert_eq!(suggestions.len(), NAME_SUGGESTIONS);
+        assert!(suggestions.iter().all(|s| s.starts_with("todo-")));
+
+        let err = choose_other_name(&mock, "todo", false)
+            .await
+            .expect_err("should not choose a name without prompting");
+        assert!(err.to_string().contains("Deploy with --name"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_a_refused_login_stops_the_quota_check() -> Result<()> {
+        let mut mock = cloud::MockCloudClientInterface::new();
+        mock.expect_get_account_quotas()
+            .times(1)
+            .returning(|| Err(anyhow::anyhow!("response status code: 404 Not Found")));
+        mock.expect_list_apps().returning(|_, _| {
+            Ok(cloud_openapi::models::AppItemPage {
+                is_last_page: true,
+                ..Default::default()
+            })
+        });
+        mock.expect_is_app_name_available().returning(|_| Ok(true));
+        let mut app = ManifestSummary {
+            name: "todo".to_owned(),
+            sqlite_labels: ["default".to_owned()].into(),
+        };
+        preflight(&mock, Some(&mut app), &BTreeMap::new(), false).await?;
+
+        let mut mock = cloud::MockCloudClientInterface::new();
+        mock.expect_get_account_quotas()
+            .returning(|| Err(Unauthorized("Unauthorized".to_owned()).into()));
+        let err = preflight(&mock, None, &BTreeMap::new(), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("run `spin cloud login`"));
+        Ok(())
+    }
+
+    #[test]
+    fn quotas_must_allow_new_resources() {
+        let quotas = AccountQuotas {
+            apps: Quota {
+                used: 5,
+                limit: Some(5),
+            },
+            databases: Quota {
+                used: 1,
+                limit: Some(3),
+            },
+            ..Default::default()
+        };
+        assert!(check_quotas(&quotas, "todo", false, 2).is_ok());
+        assert!(check_quotas(&quotas, "todo", true, 0).is_err());
+        assert!(check_quotas(&quotas, "todo", false, 3).is_err());
+        assert!(check_quotas(&AccountQuotas::default(), "todo", true, 10).is_ok());
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use spin_locked_app::locked::{ContentRef, LockedApp};
+use std::collections::BTreeMap;
+use std::path::Path;
+use url::Url;
+
+use super::build_info::BUILD_INFO_METADATA_KEY;
+use super::packaging::file_mounts;
+use super::PLUGIN_VERSION_METADATA_KEY;
+use crate::diff::Diff;
+
+/// Digests of each part of a packaged app: its locked manifest, each
+/// component's Wasm module and each mounted file. Parts are named so that
+/// a difference between two packagings says which file changed.
+#[derive(Debug, PartialEq)]
+pub(super) struct ArtifactDigests {
+    parts: BTreeMap<String, String>,
+}
+
+impl ArtifactDigests {
+    pub fn of(app: &LockedApp) -> Result<Self> {
+        let mut parts = BTreeMap::new();
+        parts.insert("manifest".to_owned(), manifest_digest(app)?);
+        for component in &app.components {
+            parts.insert(
+                format!("component {} module", component.id),
+                content_digest(&component.source.content).with_context(|| {
+                    format!("Could not read module of component {}", component.id)
+                })?,
+            );
+        }
+        for mount in file_mounts(app)? {
+            for entry in walkdir::WalkDir::new(&mount.source).sort_by_file_name() {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let relative = entry
+                    .path()
+                    .strip_prefix(&mount.source)
+                    .unwrap_or(Path::new(""));
+                let guest_path = mount.guest_path.join(relative);
+                parts.insert(
+                    format!("file {}", guest_path.display()),
+                    file_digest(entry.path())?,
+                );
+            }
+        }
+        Ok(Self { parts })
+    }
+
+    /// The digest of each part, by name
+    pub fn parts(&self) -> &BTreeMap<String, String> {
+        &self.parts
+    }
+
+    /// A single digest covering every part
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (name, digest) in &self.parts {
+            hasher.update(format!("{name} {digest}\n"));
+        }
+        format!("sha256:{:x}", hasher.finalize())
+    }
+
+    /// Fails, listing the parts which differ, unless both packagings
+    /// produced the same artifacts.
+    pub fn ensure_same(&self, other: &Self) -> Result<()> {
+        let as_diffable = |digests: &Self| {
+            digests
+                .parts
+                .iter()
+                .map(|(name, digest)| (name.clone(), Some(digest.clone())))
+                .collect()
+        };
+        let diff = Diff::between(&as_diffable(self), &as_diffable(other));
+        if diff.is_empty() {
+            return Ok(());
+        }
+        eprintln!("Packaging the app twice produced different artifacts:");
+        for line in diff.render(false) {
+            eprintln!("  {line}");
+        }
+        bail!(
+            "The app's deployment artifacts are not reproducible: {} part(s) differ",
+            diff.changes.len()
+        )
+    }
+}
+
+/// The locked manifest, leaving out what legitimately differs between two
+/// packagings: where files were staged, when the build was recorded, and
+/// which version of the plugin packaged it.
+fn manifest_digest(app: &LockedApp) -> Result<String> {
+    let mut app = app.clone();
+    app.metadata.remove(BUILD_INFO_METADATA_KEY);
+    app.metadata.remove(PLUGIN_VERSION_METADATA_KEY);
+    for component in &mut app.components {
+        component.source.content.source = None;
+        for file in &mut component.files {
+            file.content.source = None;
+        }
+    }
+    let json = serde_json::to_vec(&app)?;
+    Ok(format!("sha256:{:x}", Sha256::digest(json)))
+}
+
+fn content_digest(content: &ContentRef) -> Result<String> {
+    match (&content.source, &content.inline) {
+        (Some(source), _) => {
+            let path = Url::parse(source)
+                .ok()
+                .and_then(|url| url.to_file_path().ok())
+                .with_context(|| format!("Unexpected content source {source}"))?;
+            file_digest(&path)
+        }
+        (None, Some(inline)) => Ok(format!("sha256:{:x}", Sha256::digest(inline))),
+        (None, None) => Ok(content.digest.clone().unwrap_or_default()),
+    }
+}
+
+fn file_digest(path: &Path) -> Result<String> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    Ok(format!("sha256:{:x}", Sha256::digest(content)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn staged_app(dir: &Path, asset: &str) -> Result<LockedApp> {
+        std::fs::write(dir.join("app.wasm"), b"\0asm")?;
+        std::fs::create_dir_all(dir.join("static"))?;
+        std::fs::write(dir.join("static/index.html"), asset)?;
+        let source = |path: &Path| Url::from_file_path(path).unwrap().to_string();
+        Ok(serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 1,
+            "metadata": { "name": "web" },
+            "variables": {},
+            "triggers": [],
+            "components": [{
+                "id": "web",
+                "metadata": {},
+                "source": {
+                    "content_type": "application/wasm",
+                    "content": { "source": source(&dir.join("app.wasm")) },
+                },
+                "env": {},
+                "files": [{
+                    "content": { "source": source(&dir.join("static")) },
+                    "path": "/",
+                }],
+                "config": {},
+            }],
+        }))?)
+    }
+
+    #[test]
+    fn staging_directory_build_info_and_plugin_version_do_not_change_the_digest() -> Result<()> {
+        let (first_dir, second_dir) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        let mut first = staged_app(first_dir.path(), "<html></html>")?;
+        let mut second = staged_app(second_dir.path(), "<html></html>")?;
+        second
+            .metadata
+            .insert(BUILD_INFO_METADATA_KEY.to_owned(), "later".into());
+        first
+            .metadata
+            .insert(PLUGIN_VERSION_METADATA_KEY.to_owned(), "0.9.0".into());
+        second
+            .metadata
+            .insert(PLUGIN_VERSION_METADATA_KEY.to_owned(), "0.10.0".into());
+
+        let first = ArtifactDigests::of(&first)?;
+        let second = ArtifactDigests::of(&second)?;
+        assert_eq!(first.digest(), second.digest());
+        first.ensure_same(&second)
+    }
+
+    #[test]
+    fn changed_files_are_reported() -> Result<()> {
+        let (first_dir, second_dir) = (tempfile::tempdir()?, tempfile::tempdir()?);
+        let first = ArtifactDigests::of(&staged_app(first_dir.path(), "<html></html>")?)?;
+        let second = ArtifactDigests::of(&staged_app(second_dir.path(), "<html>2</html>")?)?;
+        assert_ne!(first.digest(), second.digest());
+        let err = first.ensure_same(&second).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The app's deployment artifacts are not reproducible: 1 part(s) differ"
+        );
+        Ok(())
+    }
+}
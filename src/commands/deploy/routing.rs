@@ -0,0 +1,70 @@
+use anyhow::{bail, Result};
+use serde_json::{json, Map, Value};
+
+/// The locked app metadata key under which routing overrides are recorded
+const ROUTING_METADATA_KEY: &str = "routing_overrides";
+
+/// Checks a `--route-prefix`, returning it without any trailing slash
+/// unless it is the root.
+pub(super) fn parse_route_prefix(prefix: &str) -> Result<String> {
+    if !prefix.starts_with('/') {
+        bail!("the route prefix must start with '/'");
+    }
+    if prefix.contains(['*', '?', '#', ' ']) || prefix.contains("/...") {
+        bail!("the route prefix must be a plain path, without wildcards or query strings");
+    }
+    match prefix.trim_end_matches('/') {
+        "" => Ok("/".to_owned()),
+        trimmed => Ok(trimmed.to_owned()),
+    }
+}
+
+/// Serves the app's HTTP routes under `prefix` in place of the base in its
+/// manifest, and records the override in the app's metadata, which is
+/// stored with the revision.
+pub(super) fn override_route_prefix(metadata: &mut Map<String, Value>, prefix: &str) {
+    let Some(trigger) = metadata
+        .entry("trigger")
+        .or_insert_with(|| Value::Object(Default::default()))
+        .as_object_mut()
+    else {
+        return;
+    };
+    let manifest_base = trigger.insert("base".to_owned(), prefix.into());
+    metadata.insert(
+        ROUTING_METADATA_KEY.to_owned(),
+        json!({
+            "route_prefix": prefix,
+            "manifest_base": manifest_base,
+        }),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn route_prefix_must_be_a_plain_path() {
+        assert_eq!(parse_route_prefix("/staging/").unwrap(), "/staging");
+        assert_eq!(parse_route_prefix("/").unwrap(), "/");
+        assert!(parse_route_prefix("staging").is_err());
+        assert!(parse_route_prefix("/staging/...").is_err());
+        assert!(parse_route_prefix("/a?b").is_err());
+    }
+
+    #[test]
+    fn override_replaces_base_and_is_recorded() {
+        let mut metadata = Map::new();
+        metadata.insert("trigger".to_owned(), json!({"type": "http", "base": "/"}));
+
+        override_route_prefix(&mut metadata, "/staging");
+
+        assert_eq!(metadata["trigger"]["base"], "/staging");
+        assert_eq!(metadata["trigger"]["type"], "http");
+        assert_eq!(
+            metadata[ROUTING_METADATA_KEY],
+            json!({"route_prefix": "/staging", "manifest_base": "/"})
+        );
+    }
+}
@@ -0,0 +1,715 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use cloud::{
+    models::{
+        CreateKeyValueStore, DeleteKeyValuePair, KeyValueKey, KeyValueStore, KeyValueStoreStats,
+        SetKeyValuePair, TouchKeyValuePairs,
+    },
+    CloudClientInterface,
+};
+use futures::{stream, StreamExt};
+use spin_common::arg_parser::parse_kv;
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::answers;
+use crate::commands::logs::parse_duration;
+use crate::commands::sqlite::format_size;
+use crate::commands::{client_and_app_id, confirm_environment, create_cloud_client, CommonArgs};
+use crate::ops::regions::check_region;
+use crate::opts::{EnvSettings, CLOUD_APP_ENV};
+use crate::table::new_table;
+
+mod import;
+
+use import::{parse_records, to_pairs, ImportFormat};
+
+/// Manage key value stores and their contents
+#[derive(Parser, Debug)]
+pub enum KeyValueCommand {
+    /// Create a key value store
+    Create(CreateCommand),
+    /// List all your key value stores
+    List(ListCommand),
+    /// Set key value pairs, optionally expiring after a time
+    Set(SetCommand),
+    /// List the keys in a store and when they expire
+    ListKeys(ListKeysCommand),
+    /// Extend the time before keys expire
+    Touch(TouchCommand),
+    /// Set a key value pair for each record in a JSON or CSV file
+    Import(ImportCommand),
+    /// Show how many keys a store holds, their size, and recent operations
+    Stats(StatsCommand),
+    /// Delete every key in a store which starts with a prefix
+    DeleteKeys(DeleteKeysCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct CreateCommand {
+    /// Name of the store to create
+    pub name: String,
+    /// Region to create the store in. Run `spin cloud regions` to see the
+    /// choices. If omitted, the platform's default region is used.
+    #[clap(long = "region")]
+    pub region: Option<String>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Include the number of keys in each store and their total size
+    #[clap(long = "details", takes_value = false)]
+    pub details: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetCommand {
+    /// Key value pair (key=value) to set. Any existing value is overwritten.
+    #[clap(parse(try_from_str = parse_kv), required = true)]
+    pub pairs: Vec<(String, String)>,
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// The store, by the label the app uses for it
+    #[clap(short = 's', long = "store", default_value = "default")]
+    pub store: String,
+    /// Number of seconds after which the keys expire. If omitted, they never expire.
+    #[clap(long = "ttl", value_parser = clap::value_parser!(u64).range(1..))]
+    pub ttl_secs: Option<u64>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListKeysCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// The store, by the label the app uses for it
+    #[clap(short = 's', long = "store", default_value = "default")]
+    pub store: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct TouchCommand {
+    /// Key to extend
+    #[clap(required = true)]
+    pub keys: Vec<String>,
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// The store, by the label the app uses for it
+    #[clap(short = 's', long = "store", default_value = "default")]
+    pub store: String,
+    /// Number of seconds from now after which the keys expire
+    #[clap(long = "ttl", value_parser = clap::value_parser!(u64).range(1..))]
+    pub ttl_secs: u64,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ImportCommand {
+    /// The store, by the label the app uses for it
+    pub store: String,
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// File of records to import
+    #[clap(short = 'f', long = "file")]
+    pub file: PathBuf,
+    /// Format of the file. If omitted, it is inferred from the file extension.
+    #[clap(value_enum, long = "format")]
+    pub format: Option<ImportFormat>,
+    /// How to build each record's key, with {field} replaced by the value
+    /// of that field or CSV column, such as 'user:{id}'
+    #[clap(long = "key-template", default_value = "{id}")]
+    pub key_template: String,
+    /// Field or CSV column to store as the value. If omitted, the whole
+    /// record is stored as a JSON object.
+    #[clap(long = "value-field")]
+    pub value_field: Option<String>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct StatsCommand {
+    /// Name of the store
+    pub store: String,
+    /// How far back to count operations, such as "1h" or "7d"
+    #[clap(long = "window", value_parser = parse_duration, default_value = "24h")]
+    pub window: Duration,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct DeleteKeysCommand {
+    /// The store, by the label the app uses for it
+    pub store: String,
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Delete the keys which start with this prefix, such as 'sess:'
+    #[clap(long = "prefix", value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    pub prefix: String,
+    /// Show how many keys match, and some of them, without deleting any
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+    /// Skips prompt to confirm deletion of the keys
+    #[clap(short = 'y', long = "yes", takes_value = false)]
+    pub yes: bool,
+    /// How many keys to delete at the same time
+    #[clap(long = "concurrency", default_value = "8", value_parser = clap::value_parser!(u16).range(1..=64))]
+    pub concurrency: u16,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl KeyValueCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Create(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(&client).await
+            }
+            Self::List(cmd) => cmd.run().await,
+            Self::Set(cmd) => cmd.run().await,
+            Self::ListKeys(cmd) => cmd.run().await,
+            Self::Touch(cmd) => cmd.run().await,
+            Self::Import(cmd) => cmd.run().await,
+            Self::Stats(cmd) => cmd.run().await,
+            Self::DeleteKeys(cmd) => {
+                if !cmd.dry_run {
+                    confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                }
+                let (client, app_id) =
+                    client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
+                cmd.run(&client, app_id).await
+            }
+        }
+    }
+}
+
+impl CreateCommand {
+    pub async fn run(self, client: &impl CloudClientInterface) -> Result<()> {
+        let stores = client
+            .list_key_value_stores()
+            .await
+            .context("Problem fetching key value stores")?;
+        if stores.iter().any(|s| s.name == self.name) {
+            bail!(r#"Key value store "{}" already exists"#, self.name);
+        }
+        if let Some(region) = &self.region {
+            check_region(client, region).await?;
+        }
+        client
+            .create_key_value_store(CreateKeyValueStore {
+                name: self.name.clone(),
+                region: self.region.clone(),
+            })
+            .await
+            .with_context(|| format!("Problem creating key value store {}", self.name))?;
+        match &self.region {
+            Some(region) => println!(
+                r#"Key value store "{}" created in region {region}"#,
+                self.name
+            ),
+            None => println!(r#"Key value store "{}" created"#, self.name),
+        }
+        Ok(())
+    }
+}
+
+impl ListCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let mut stores = client
+            .list_key_value_stores()
+            .await
+            .context("Problem listing key value stores")?;
+        if stores.is_empty() {
+            println!("No key value stores");
+            return Ok(());
+        }
+        stores.sort_by(|a, b| a.name.cmp(&b.name));
+        if !self.details {
+            print_stores(&stores);
+            return Ok(());
+        }
+        // Only sizes are listed, so no window of operations is counted.
+        let mut stats = Vec::with_capacity(stores.len());
+        for store in &stores {
+            stats.push(
+                client
+                    .get_key_value_store_stats(&store.name, 0)
+                    .await
+                    .with_context(|| format!("Problem fetching details of store {}", store.name))?,
+            );
+        }
+        print_store_details(&stores, &stats);
+        Ok(())
+    }
+}
+
+impl StatsCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let stats = client
+            .get_key_value_store_stats(&self.store, self.window.as_secs())
+            .await
+            .with_context(|| format!("Problem fetching stats of store {}", self.store))?;
+        print_stats(&stats);
+        Ok(())
+    }
+}
+
+impl SetCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        for (key, value) in self.pairs {
+            client
+                .set_key_value_pair(SetKeyValuePair {
+                    app_id,
+                    store_name: self.store.clone(),
+                    key: key.clone(),
+                    value,
+                    ttl_seconds: self.ttl_secs,
+                })
+                .await
+                .with_context(|| format!("Problem setting key {key}"))?;
+            match self.ttl_secs {
+                Some(ttl) => println!(
+                    r#"Set "{key}" in store "{}", expiring in {ttl}s"#,
+                    self.store
+                ),
+                None => println!(r#"Set "{key}" in store "{}""#, self.store),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ListKeysCommand {
+    pub async fn run(self) -> Result<()> {
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let keys = client
+            .list_key_value_keys(app_id, &self.store)
+            .await
+            .with_context(|| format!("Problem listing keys in store {}", self.store))?;
+        if keys.is_empty() {
+            eprintln!(r#"No keys in store "{}" of app "{}""#, self.store, self.app);
+        } else {
+            print_keys(&keys, Utc::now());
+        }
+        Ok(())
+    }
+}
+
+impl TouchCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let count = self.keys.len();
+        client
+            .touch_key_value_pairs(TouchKeyValuePairs {
+                app_id,
+                store_name: self.store.clone(),
+                keys: self.keys,
+                ttl_seconds: self.ttl_secs,
+            })
+            .await
+            .with_context(|| format!("Problem extending keys in store {}", self.store))?;
+        println!(
+            r#"{count} key(s) in store "{}" now expire in {}s"#,
+            self.store, self.ttl_secs
+        );
+        Ok(())
+    }
+}
+
+impl ImportCommand {
+    pub async fn run(self) -> Result<()> {
+        let format = match self.format {
+            Some(format) => format,
+            None => match self.file.extension().and_then(|e| e.to_str()) {
+                Some("json") => ImportFormat::Json,
+                Some("csv") => ImportFormat::Csv,
+                _ => bail!(
+                    "Cannot tell the format of {}. Use --format json or --format csv",
+                    self.file.display()
+                ),
+            },
+        };
+        let text = std::fs::read_to_string(&self.file)
+            .with_context(|| format!("Could not read {}", self.file.display()))?;
+        let records = parse_records(&text, format)
+            .with_context(|| format!("Could not read records from {}", self.file.display()))?;
+        let pairs = to_pairs(&records, &self.key_template, self.value_field.as_deref())?;
+        if pairs.is_empty() {
+            println!("No records to import");
+            return Ok(());
+        }
+
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let total = pairs.len();
+        let mut failed = 0;
+        for (key, value) in pairs {
+            let result = client
+                .set_key_value_pair(SetKeyValuePair {
+                    app_id,
+                    store_name: self.store.clone(),
+                    key: key.clone(),
+                    value,
+                    ttl_seconds: None,
+                })
+                .await;
+            if let Err(e) = result {
+                eprintln!("Problem setting key {key}: {e:#}");
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            bail!("{failed} of {total} keys could not be set");
+        }
+        println!(r#"Imported {total} key(s) into store "{}""#, self.store);
+        Ok(())
+    }
+}
+
+/// How many of the matching keys are listed before deleting them
+const SAMPLE_KEYS: usize = 10;
+
+impl DeleteKeysCommand {
+    pub async fn run(self, client: &impl CloudClientInterface, app_id: Uuid) -> Result<()> {
+        let keys = client
+            .list_key_value_keys(app_id, &self.store)
+            .await
+            .with_context(|| format!("Problem listing keys in store {}", self.store))?;
+        let matching = keys_with_prefix(keys, &self.prefix);
+        if matching.is_empty() {
+            println!(
+                r#"No keys in store "{}" start with "{}""#,
+                self.store, self.prefix
+            );
+            return Ok(());
+        }
+
+        println!(
+            r#"{} key(s) in store "{}" start with "{}":"#,
+            matching.len(),
+            self.store,
+            self.prefix
+        );
+        for key in matching.iter().take(SAMPLE_KEYS) {
+            println!("  {key}");
+        }
+        if matching.len() > SAMPLE_KEYS {
+            println!("  ... and {} more", matching.len() - SAMPLE_KEYS);
+        }
+        if self.dry_run {
+            println!("Dry run: no keys were deleted");
+            return Ok(());
+        }
+        if !self.yes {
+            if !EnvSettings::from_env().interactive() {
+                bail!(
+                    "Use --yes to delete {} key(s) without confirmation",
+                    matching.len()
+                );
+            }
+            let prompt = format!("Delete {} key(s)? This cannot be undone.", matching.len());
+            if !answers::confirm(&prompt, false)? {
+                println!("No keys deleted");
+                return Ok(());
+            }
+        }
+
+        let total = matching.len();
+        let store = &self.store;
+        let failures = stream::iter(matching)
+            .map(|key| async move {
+                client
+                    .delete_key_value_pair(DeleteKeyValuePair {
+                        app_id,
+                        store_name: store.clone(),
+                        key: key.clone(),
+                    })
+                    .await
+                    .map_err(|e| (key, e))
+            })
+            .buffer_unordered(self.concurrency.into())
+            .filter_map(|result| async move { result.err() })
+            .collect::<Vec<_>>()
+            .await;
+        for (key, e) in &failures {
+            eprintln!("Problem deleting key {key}: {e:#}");
+        }
+        if !failures.is_empty() {
+            bail!("{} of {total} keys could not be deleted", failures.len());
+        }
+        println!(r#"Deleted {total} key(s) from store "{}""#, self.store);
+        Ok(())
+    }
+}
+
+/// The keys starting with `prefix`, in order.
+fn keys_with_prefix(keys: Vec<KeyValueKey>, prefix: &str) -> Vec<String> {
+    let mut matching = keys
+        .into_iter()
+        .map(|k| k.key)
+        .filter(|key| key.starts_with(prefix))
+        .collect::<Vec<_>>();
+    matching.sort();
+    matching
+}
+
+fn print_stores(stores: &[KeyValueStore]) {
+    let mut table = new_table();
+    table.set_header(vec!["Store", "Region"]);
+    table.add_rows(stores.iter().map(|s| {
+        [
+            s.name.clone(),
+            s.region.clone().unwrap_or_else(|| "-".to_owned()),
+        ]
+    }));
+    println!("{table}");
+}
+
+fn print_store_details(stores: &[KeyValueStore], stats: &[KeyValueStoreStats]) {
+    let mut table = new_table();
+    table.set_header(vec!["Store", "Region", "Keys", "Size"]);
+    table.add_rows(stores.iter().zip(stats).map(|(store, stats)| {
+        [
+            store.name.clone(),
+            store.region.clone().unwrap_or_else(|| "-".to_owned()),
+            stats.key_count.to_string(),
+            format_size(stats.size_bytes),
+        ]
+    }));
+    println!("{table}");
+}
+
+fn print_stats(stats: &KeyValueStoreStats) {
+    println!("Store: {}", stats.name);
+    println!("Keys: {}", stats.key_count);
+    println!("Size: {}", format_size(stats.size_bytes));
+    println!(
+        "Operations in the last {}:",
+        format_window(Duration::from_secs(stats.window_seconds))
+    );
+    println!("  Reads: {}", stats.reads);
+    println!("  Writes: {}", stats.writes);
+    println!("  Deletes: {}", stats.deletes);
+}
+
+// Shows a window in the largest unit it is a whole number of, as it would
+// have been given to --window.
+fn format_window(window: Duration) -> String {
+    let secs = window.as_secs();
+    match secs {
+        s if s > 0 && s % (24 * 60 * 60) == 0 => format!("{}d", s / (24 * 60 * 60)),
+        s if s > 0 && s % (60 * 60) == 0 => format!("{}h", s / (60 * 60)),
+        s if s > 0 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+fn print_keys(keys: &[KeyValueKey], now: DateTime<Utc>) {
+    let mut table = new_table();
+    table.set_header(vec!["Key", "Expires"]);
+    table.add_rows(
+        keys.iter()
+            .map(|k| [k.key.clone(), format_expiry(k.expires_at.as_deref(), now)]),
+    );
+    println!("{table}");
+}
+
+fn format_expiry(expires_at: Option<&str>, now: DateTime<Utc>) -> String {
+    let Some(expires_at) = expires_at else {
+        return "never".to_owned();
+    };
+    let Ok(time) = DateTime::parse_from_rfc3339(expires_at) else {
+        return expires_at.to_owned();
+    };
+    let remaining = time.with_timezone(&Utc) - now;
+    if remaining.num_seconds() <= 0 {
+        return "expired".to_owned();
+    }
+    let (hours, minutes, seconds) = (
+        remaining.num_hours(),
+        remaining.num_minutes() % 60,
+        remaining.num_seconds() % 60,
+    );
+    let relative = match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m {seconds}s"),
+        _ => format!("{hours}h {minutes}m"),
+    };
+    format!(
+        "{} (in {relative})",
+        time.with_timezone(&Utc).format("%Y-%m-%d %H:%M:%S UTC")
+    )
+}
+
+#[cfg(test)]
+mod key_value_tests {
+    use super::*;
+    use cloud::{models::Region, MockCloudClientInterface};
+
+    fn create_command(name: &str, region: Option<&str>) -> CreateCommand {
+        CreateCommand {
+            name: name.to_owned(),
+            region: region.map(str::to_owned),
+            common: Default::default(),
+        }
+    }
+
+    #[test]
+    fn window_is_shown_in_largest_whole_unit() {
+        assert_eq!(format_window(parse_duration("24h").unwrap()), "1d");
+        assert_eq!(format_window(parse_duration("90m").unwrap()), "90m");
+        assert_eq!(format_window(parse_duration("2h").unwrap()), "2h");
+        assert_eq!(format_window(Duration::from_secs(45)), "45s");
+    }
+
+    #[tokio::test]
+    async fn store_is_created_in_requested_region() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_key_value_stores().returning(|| Ok(vec![]));
+        mock.expect_list_regions().returning(|| {
+            Ok(vec![Region {
+                name: "eu-west".to_owned(),
+                ..Default::default()
+            }])
+        });
+        mock.expect_create_key_value_store()
+            .withf(|s| s.name == "cache" && s.region.as_deref() == Some("eu-west"))
+            .returning(|_| Ok(()));
+
+        create_command("cache", Some("eu-west")).run(&mock).await
+    }
+
+    #[tokio::test]
+    async fn store_is_not_created_in_unknown_region_or_twice() {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_key_value_stores().returning(|| {
+            Ok(vec![KeyValueStore {
+                name: "cache".to_owned(),
+                region: None,
+            }])
+        });
+        mock.expect_list_regions().returning(|| {
+            Ok(vec![Region {
+                name: "eu-west".to_owned(),
+                ..Default::default()
+            }])
+        });
+
+        let err = create_command("cache", None).run(&mock).await.unwrap_err();
+        assert_eq!(err.to_string(), r#"Key value store "cache" already exists"#);
+        let err = create_command("sessions", Some("eu-wset"))
+            .run(&mock)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"No region found with name "eu-wset". Did you mean "eu-west"?"#
+        );
+    }
+
+    fn key(key: &str) -> KeyValueKey {
+        KeyValueKey {
+            key: key.to_owned(),
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn keys_with_the_prefix_are_deleted() -> Result<()> {
+        let command = DeleteKeysCommand {
+            store: "default".to_owned(),
+            app: "todo".to_owned(),
+            prefix: "sess:".to_owned(),
+            dry_run: false,
+            yes: true,
+            concurrency: 2,
+            common: Default::default(),
+        };
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_key_value_keys().returning(|_, _| {
+            Ok(vec![
+                key("sess:b"),
+                key("user:1"),
+                key("sess:a"),
+                key("session"),
+            ])
+        });
+        mock.expect_delete_key_value_pair()
+            .withf(|pair| pair.key.starts_with("sess:") && pair.store_name == "default")
+            .times(2)
+            .returning(|_| Ok(()));
+
+        command.run(&mock, Uuid::new_v4()).await
+    }
+
+    #[tokio::test]
+    async fn dry_run_deletes_nothing() -> Result<()> {
+        let command = DeleteKeysCommand {
+            store: "default".to_owned(),
+            app: "todo".to_owned(),
+            prefix: "sess:".to_owned(),
+            dry_run: true,
+            yes: false,
+            concurrency: 8,
+            common: Default::default(),
+        };
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_key_value_keys()
+            .returning(|_, _| Ok(vec![key("sess:a")]));
+        mock.expect_delete_key_value_pair().never();
+
+        command.run(&mock, Uuid::new_v4()).await
+    }
+
+    #[test]
+    fn keys_are_matched_by_prefix_in_order() {
+        assert_eq!(
+            keys_with_prefix(vec![key("sess:b"), key("user:1"), key("sess:a")], "sess:"),
+            vec!["sess:a", "sess:b"]
+        );
+    }
+
+    #[test]
+    fn expiry_is_shown_relative_to_now() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(format_expiry(None, now), "never");
+        assert_eq!(
+            format_expiry(Some("2024-05-01T13:30:00Z"), now),
+            "2024-05-01 13:30:00 UTC (in 1h 30m)"
+        );
+        assert_eq!(
+            format_expiry(Some("2024-05-01T12:00:45Z"), now),
+            "2024-05-01 12:00:45 UTC (in 45s)"
+        );
+        assert_eq!(format_expiry(Some("2024-05-01T11:00:00Z"), now), "expired");
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+use crate::csv::parse_csv;
+
+/// Formats of files whose records can be imported as key value pairs
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// An array of objects, one per record
+    Json,
+    /// A header row naming the columns, then one row per record
+    Csv,
+}
+
+/// Reads the records of an import file as JSON objects. CSV fields are
+/// always strings.
+pub(super) fn parse_records(text: &str, format: ImportFormat) -> Result<Vec<Map<String, Value>>> {
+    match format {
+        ImportFormat::Json => {
+            let value: Value = serde_json::from_str(text).context("The file is not valid JSON")?;
+            let Value::Array(records) = value else {
+                bail!("The file must contain a JSON array of objects");
+            };
+            records
+                .into_iter()
+                .enumerate()
+                .map(|(index, record)| match record {
+                    Value::Object(fields) => Ok(fields),
+                    _ => Err(anyhow!("Record {} is not a JSON object", index + 1)),
+                })
+                .collect()
+        }
+        ImportFormat::Csv => {
+            let mut rows = parse_csv(text)?.into_iter();
+            let header = rows.next().context("The file has no header row")?;
+            rows.enumerate()
+                .map(|(index, row)| {
+                    if row.len() != header.len() {
+                        bail!(
+                            "Row {} has {} fields, but the header has {}",
+                            index + 2,
+                            row.len(),
+                            header.len()
+                        );
+                    }
+                    Ok(header
+                        .iter()
+                        .cloned()
+                        .zip(row.into_iter().map(Value::String))
+                        .collect())
+                })
+                .collect()
+        }
+    }
+}
+
+/// Builds the key for a record by replacing each `{field}` in `template`
+/// with the value of that field.
+pub(super) fn render_key(template: &str, record: &Map<String, Value>) -> Result<String> {
+    let mut key = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        key.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Key template \"{template}\" has an unclosed {{"))?;
+        let name = &rest[start + 1..start + end];
+        let value = record
+            .get(name)
+            .with_context(|| format!("Record has no field \"{name}\" for the key template"))?;
+        key.push_str(&plain_text(value));
+        rest = &rest[start + end + 1..];
+    }
+    key.push_str(rest);
+    if key.is_empty() {
+        bail!("Key template \"{template}\" produced an empty key");
+    }
+    Ok(key)
+}
+
+/// The value to store for a record: one field of it, or the whole record as
+/// JSON.
+pub(super) fn record_value(record: &Map<String, Value>, field: Option<&str>) -> Result<String> {
+    match field {
+        Some(name) => record
+            .get(name)
+            .map(plain_text)
+            .with_context(|| format!("Record has no field \"{name}\"")),
+        None => Ok(serde_json::to_string(record)?),
+    }
+}
+
+// Strings are used as they are, rather than as quoted JSON.
+fn plain_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Turns records into key value pairs, failing if two records would have
+/// the same key.
+pub(super) fn to_pairs(
+    records: &[Map<String, Value>],
+    key_template: &str,
+    value_field: Option<&str>,
+) -> Result<Vec<(String, String)>> {
+    let mut seen = HashMap::new();
+    records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let number = index + 1;
+            let key = render_key(key_template, record)
+                .with_context(|| format!("Problem with record {number}"))?;
+            if let Some(first) = seen.insert(key.clone(), number) {
+                bail!("Records {first} and {number} both have the key \"{key}\"");
+            }
+            let value = record_value(record, value_field)
+                .with_context(|| format!("Problem with record {number}"))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records_become_keyed_pairs() -> Result<()> {
+        let records = parse_records(
+            r#"[{"id": 1, "kind": "user", "name": "Ada"}, {"id": 2, "kind": "user", "name": "Bo"}]"#,
+            ImportFormat::Json,
+        )?;
+        assert_eq!(
+            to_pairs(&records, "{kind}:{id}", Some("name"))?,
+            vec![
+                ("user:1".to_owned(), "Ada".to_owned()),
+                ("user:2".to_owned(), "Bo".to_owned()),
+            ]
+        );
+        assert_eq!(
+            to_pairs(&records[..1], "{id}", None)?[0].1,
+            r#"{"id":1,"kind":"user","name":"Ada"}"#
+        );
+        assert_eq!(
+            to_pairs(&records, "{kind}", None).unwrap_err().to_string(),
+            r#"Records 1 and 2 both have the key "user""#
+        );
+        assert!(to_pairs(&records, "{missing}", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn csv_rows_must_match_header() -> Result<()> {
+        let records = parse_records("id,name\n1,Ada\n", ImportFormat::Csv)?;
+        assert_eq!(records[0]["name"], Value::String("Ada".to_owned()));
+        let err = parse_records("id,name\n1\n", ImportFormat::Csv).unwrap_err();
+        assert_eq!(err.to_string(), "Row 2 has 1 fields, but the header has 2");
+        Ok(())
+    }
+}
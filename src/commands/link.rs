@@ -1,125 +1,297 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use cloud::client::{Client as CloudClient, ConnectionConfig};
 use cloud::CloudClientInterface;
-use cloud_openapi::models::{Database, ResourceLabel};
+use serde::Serialize;
 use uuid::Uuid;
 
-use crate::commands::{client_and_app_id, sqlite::find_database_link, CommonArgs};
+use crate::answers;
+use crate::commands::apps::load_active_revision;
+use crate::commands::deploy::{login_connection, DeployableApp};
+use crate::commands::{client_and_app_id, confirm_environment, create_cloud_client, CommonArgs};
+use crate::ops::apps::app_id;
+use crate::ops::link::{
+    apply_sqlite_link, find_app_link, find_sqlite_link, link_app, list_links, plan_sqlite_link,
+    unlink_app, unlink_sqlite, LabelledLink, SqliteLinkPlan,
+};
+use crate::ops::sqlite::list_databases;
+use crate::opts::{EnvSettings, CLOUD_APP_ENV, CLOUD_NON_INTERACTIVE_ENV};
+use crate::table::new_table;
+
+/// The database label linked when `link sqlite` is not given one
+const DEFAULT_LABEL: &str = "default";
 
 /// Manage how apps and resources are linked together
 #[derive(Parser, Debug)]
 pub enum LinkCommand {
     /// Link an app to a SQLite database
     Sqlite(SqliteLinkCommand),
+    /// Let an app call another app over the internal network
+    App(AppLinkCommand),
+    /// List the databases and apps an app is linked to
+    List(ListLinksCommand),
 }
 
 #[derive(Parser, Debug)]
 pub struct SqliteLinkCommand {
     #[clap(flatten)]
     common: CommonArgs,
-    /// The name by which the application will refer to the database
-    label: String,
-    #[clap(short = 'a', long = "app")]
+    /// The name by which the application will refer to the database. If
+    /// omitted, the label "default" is used, unless the app already has
+    /// more than one database label, in which case they are listed.
+    label: Option<String>,
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
     /// The app that will be using the database
     app: String,
     /// The database that the app will refer to by the label
     #[clap(short = 'd', long = "database")]
     database: String,
+    /// Format in which to report the outcome
+    #[clap(value_enum, long = "format", default_value = "plain")]
+    format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct AppLinkCommand {
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// The app that will be calling the other app
+    caller: String,
+    /// The app that will be called
+    #[clap(long = "to")]
+    callee: String,
+    /// The name by which the calling app will refer to the called app
+    #[clap(long = "label")]
+    label: String,
+    /// Format in which to report the outcome
+    #[clap(value_enum, long = "format", default_value = "plain")]
+    format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListLinksCommand {
+    #[clap(flatten)]
+    common: CommonArgs,
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    /// The app whose links to list
+    app: String,
+    /// Format in which to list the links
+    #[clap(value_enum, long = "format", default_value = "plain")]
+    format: OutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum OutputFormat {
+    Plain,
+    Json,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum LinkAction {
+    Created,
+    Replaced,
+    Unchanged,
+    Removed,
+}
+
+/// The outcome of a link or unlink, for JSON output. `resource` is what the
+/// label refers to afterwards, and `previous_resource` what it referred to before.
+#[derive(Serialize)]
+struct LinkResultJson<'a> {
+    app_id: Uuid,
+    app: &'a str,
+    label: &'a str,
+    action: LinkAction,
+    resource: Option<&'a str>,
+    previous_resource: Option<&'a str>,
+}
+
+impl LinkResultJson<'_> {
+    fn print(&self) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(self)?);
+        Ok(())
+    }
 }
 
 impl LinkCommand {
     pub async fn run(self) -> Result<()> {
         match self {
             Self::Sqlite(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
                 let (client, app_id) =
                     client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
                 cmd.link(client, app_id).await
             }
+            Self::App(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                let caller_id = app_id(&client, &cmd.caller).await?;
+                let callee_id = app_id(&client, &cmd.callee).await?;
+                cmd.link(client, caller_id, callee_id).await
+            }
+            Self::List(cmd) => {
+                let (client, app_id) =
+                    client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
+                let links = list_links(&client, app_id).await?;
+                cmd.print(&links)
+            }
+        }
+    }
+}
+
+impl AppLinkCommand {
+    async fn link(
+        self,
+        client: impl CloudClientInterface,
+        caller_id: Uuid,
+        callee_id: Uuid,
+    ) -> Result<()> {
+        link_app(&client, caller_id, callee_id, &self.label).await?;
+        match self.format {
+            OutputFormat::Plain => println!(
+                r#"App "{}" can now call app "{}" with the label "{}""#,
+                self.caller, self.callee, self.label
+            ),
+            OutputFormat::Json => LinkResultJson {
+                app_id: caller_id,
+                app: &self.caller,
+                label: &self.label,
+                action: LinkAction::Created,
+                resource: Some(&self.callee),
+                previous_resource: None,
+            }
+            .print()?,
+        }
+        Ok(())
+    }
+}
+
+impl ListLinksCommand {
+    fn print(&self, links: &[LabelledLink]) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(links)?),
+            OutputFormat::Plain if links.is_empty() => {
+                eprintln!("App \"{}\" is not linked to any resources", self.app)
+            }
+            OutputFormat::Plain => {
+                let mut table = new_table();
+                table.set_header(vec!["Label", "Kind", "Resource"]);
+                table.add_rows(
+                    links
+                        .iter()
+                        .map(|l| [l.label.as_str(), l.kind, l.resource.as_str()]),
+                );
+                println!("{table}");
+            }
         }
+        Ok(())
     }
 }
 
 impl SqliteLinkCommand {
-    async fn link(self, client: impl CloudClientInterface, app_id: Uuid) -> Result<()> {
-        let databases = client
-            .get_databases(None)
-            .await
-            .context("could not fetch databases")?;
-        let database = databases.iter().find(|d| d.name == self.database);
-        if database.is_none() {
-            anyhow::bail!(r#"Database "{}" does not exist"#, self.database)
+    /// The link made by `sqlite create --link`, reported as `link sqlite`
+    /// reports it.
+    pub(crate) fn new(app: String, label: Option<String>, database: String) -> Self {
+        Self {
+            common: Default::default(),
+            label,
+            app,
+            database,
+            format: OutputFormat::Plain,
         }
-        let databases_for_app = databases
-            .into_iter()
-            .filter(|d| d.links.iter().any(|l| l.app_id == app_id))
-            .collect::<Vec<Database>>();
-        let (this_db, other_dbs): (Vec<&Database>, Vec<&Database>) = databases_for_app
-            .iter()
-            .partition(|d| d.name == self.database);
-        let existing_link_for_database = this_db
-            .iter()
-            .find_map(|d| find_database_link(d, &self.label));
-        let existing_link_for_other_database = other_dbs
-            .iter()
-            .find_map(|d| find_database_link(d, &self.label));
+    }
+
+    pub(crate) async fn link(self, client: impl CloudClientInterface, app_id: Uuid) -> Result<()> {
+        let label = match &self.label {
+            Some(label) => label.clone(),
+            None => self.default_label(&client, app_id).await?,
+        };
+        let plan = plan_sqlite_link(&client, app_id, &label, &self.database).await?;
         let success_msg = format!(
             r#"Database "{}" is now linked to app "{}" with the label "{}""#,
-            self.database, self.app, self.label
+            self.database, self.app, label
         );
-        match (existing_link_for_database, existing_link_for_other_database) {
-            (Some(link), _) => {
-                anyhow::bail!(
-                    r#"Database "{}" is already linked to app "{}" with the label "{}""#,
-                    link.resource,
-                    link.app_name(),
+        let previous_resource = match &plan {
+            SqliteLinkPlan::Create => None,
+            SqliteLinkPlan::Replace(link) => Some(link.resource.clone()),
+        };
+        let mut result = LinkResultJson {
+            app_id,
+            app: &self.app,
+            label: &label,
+            action: LinkAction::Created,
+            resource: Some(&self.database),
+            previous_resource: previous_resource.as_deref(),
+        };
+        if let SqliteLinkPlan::Replace(link) = &plan {
+            let prompt = format!(
+                r#"App "{}"'s "{}" label is currently linked to "{}". Change to link to database "{}" instead?"#,
+                link.app_name(),
+                link.resource_label.label,
+                link.resource,
+                self.database,
+            );
+            if !EnvSettings::from_env().interactive() {
+                bail!(
+                    r#"Label "{}" of app "{}" is already linked to database "{}". It was not changed because {CLOUD_NON_INTERACTIVE_ENV} is set; unlink it first to relink it."#,
                     link.resource_label.label,
-                );
-            }
-            (_, Some(link)) => {
-                let prompt = format!(
-                    r#"App "{}"'s "{}" label is currently linked to "{}". Change to link to database "{}" instead?"#,
                     link.app_name(),
-                    link.resource_label.label,
                     link.resource,
-                    self.database,
                 );
-                if dialoguer::Confirm::new()
-                    .with_prompt(prompt)
-                    .default(false)
-                    .interact_opt()?
-                    .unwrap_or_default()
-                {
-                    // TODO: use a relink API to remove any downtime
-                    client
-                        .remove_database_link(&link.resource, link.resource_label)
-                        .await?;
-                    let resource_label = ResourceLabel {
-                        app_id,
-                        label: self.label,
-                        app_name: None,
-                    };
-                    client
-                        .create_database_link(&self.database, resource_label)
-                        .await?;
-                    println!("{success_msg}");
-                } else {
-                    println!("The link has not been updated");
-                }
             }
-            (None, None) => {
-                let resource_label = ResourceLabel {
-                    app_id,
-                    label: self.label,
-                    app_name: None,
-                };
-                client
-                    .create_database_link(&self.database, resource_label)
-                    .await?;
-                println!("{success_msg}");
+            if !answers::confirm(&prompt, false)? {
+                if let OutputFormat::Json = self.format {
+                    result.action = LinkAction::Unchanged;
+                    result.resource = previous_resource.as_deref();
+                    return result.print();
+                }
+                println!("The link has not been updated");
+                return Ok(());
             }
+            result.action = LinkAction::Replaced;
+        }
+        apply_sqlite_link(&client, app_id, &label, &self.database, plan).await?;
+        match self.format {
+            OutputFormat::Plain => println!("{success_msg}"),
+            OutputFormat::Json => result.print()?,
         }
         Ok(())
     }
+
+    /// The label to link with when none is given: "default", which is the
+    /// label most apps use. An app which already has several labels is more
+    /// likely to mean one of those, so they are listed rather than guessed at.
+    async fn default_label(
+        &self,
+        client: &impl CloudClientInterface,
+        app_id: Uuid,
+    ) -> Result<String> {
+        let labels = list_databases(client)
+            .await?
+            .iter()
+            .flat_map(|db| &db.links)
+            .filter(|l| l.app_id == app_id)
+            .map(|l| l.label.clone())
+            .collect::<BTreeSet<_>>();
+        if labels.len() > 1 {
+            bail!(
+                r#"App "{}" already has the database labels {}. Give the label to link, for example `spin cloud link sqlite {} --app {} --database {}`"#,
+                self.app,
+                labels
+                    .iter()
+                    .map(|l| format!("\"{l}\""))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                labels.first().map(String::as_str).unwrap_or(DEFAULT_LABEL),
+                self.app,
+                self.database,
+            );
+        }
+        Ok(DEFAULT_LABEL.to_owned())
+    }
 }
 
 /// Manage unlinking apps and resources
@@ -127,91 +299,310 @@ impl SqliteLinkCommand {
 pub enum UnlinkCommand {
     /// Unlink an app from a SQLite database
     Sqlite(SqliteUnlinkCommand),
+    /// Stop an app from calling another app
+    App(AppUnlinkCommand),
 }
 
 impl UnlinkCommand {
     pub async fn run(self) -> Result<()> {
         match self {
             Self::Sqlite(cmd) => cmd.unlink().await,
+            Self::App(cmd) => cmd.unlink().await,
         }
     }
 }
 
+#[derive(Parser, Debug)]
+pub struct AppUnlinkCommand {
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// The app that calls the other app
+    caller: String,
+    /// The name by which the calling app refers to the called app
+    #[clap(long = "label")]
+    label: String,
+    /// Format in which to report the outcome
+    #[clap(value_enum, long = "format", default_value = "plain")]
+    format: OutputFormat,
+    /// Report which components of the deployed app call the app by the
+    /// label, and so would start failing, without removing the link
+    #[clap(long = "dry-run", takes_value = false)]
+    dry_run: bool,
+}
+
+impl AppUnlinkCommand {
+    async fn unlink(self) -> Result<()> {
+        if self.dry_run {
+            let env = self.common.deployment_env_id.as_deref();
+            let (client, app_id, connection_config) = connect(env, &self.caller).await?;
+            let link = find_app_link(&client, app_id, &self.caller, &self.label).await?;
+            let impact = UnlinkImpact::of(
+                &client,
+                &connection_config,
+                app_id,
+                &self.caller,
+                &self.label,
+                link.target_app_name,
+                components_calling_app,
+            )
+            .await?;
+            return impact.print(self.format, "app");
+        }
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.caller).await?;
+        let callee = unlink_app(&client, app_id, &self.caller, &self.label).await?;
+        match self.format {
+            OutputFormat::Plain => {
+                println!("App '{}' can no longer call app '{callee}'", self.caller)
+            }
+            OutputFormat::Json => LinkResultJson {
+                app_id,
+                app: &self.caller,
+                label: &self.label,
+                action: LinkAction::Removed,
+                resource: None,
+                previous_resource: Some(&callee),
+            }
+            .print()?,
+        }
+        Ok(())
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct SqliteUnlinkCommand {
     #[clap(flatten)]
     common: CommonArgs,
     /// The name by which the application refers to the database
     label: String,
-    #[clap(short = 'a', long = "app")]
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
     /// The app that will be using the database
     app: String,
+    /// Format in which to report the outcome
+    #[clap(value_enum, long = "format", default_value = "plain")]
+    format: OutputFormat,
+    /// Report which components of the deployed app use the label, and so
+    /// would start failing, without removing the link
+    #[clap(long = "dry-run", takes_value = false)]
+    dry_run: bool,
 }
 
 impl SqliteUnlinkCommand {
     async fn unlink(self) -> Result<()> {
+        if self.dry_run {
+            let env = self.common.deployment_env_id.as_deref();
+            let (client, app_id, connection_config) = connect(env, &self.app).await?;
+            let (database, _) = find_sqlite_link(&client, app_id, &self.app, &self.label).await?;
+            let impact = UnlinkImpact::of(
+                &client,
+                &connection_config,
+                app_id,
+                &self.app,
+                &self.label,
+                database,
+                DeployableApp::components_using_database,
+            )
+            .await?;
+            return impact.print(self.format, "database");
+        }
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
         let (client, app_id) =
             client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
-        let (database, label) = client
-            .get_databases(Some(app_id))
-            .await
-            .context("could not fetch databases")?
-            .into_iter()
-            .find_map(|d| {
-                d.links
-                    .into_iter()
-                    .find(|l| {
-                        matches!(&l.app_name, Some(app_name) if app_name == &self.app)
-                            && l.label == self.label
-                    })
-                    .map(|l| (d.name, l))
-            })
-            .with_context(|| {
-                format!(
-                    "no database was linked to app '{}' with label '{}'",
-                    self.app, self.label
-                )
-            })?;
-
-        client.remove_database_link(&database, label).await?;
-        println!("Database '{database}' no longer linked to app {}", self.app);
+        let database = unlink_sqlite(&client, app_id, &self.app, &self.label).await?;
+        match self.format {
+            OutputFormat::Plain => {
+                println!("Database '{database}' no longer linked to app {}", self.app)
+            }
+            OutputFormat::Json => LinkResultJson {
+                app_id,
+                app: &self.app,
+                label: &self.label,
+                action: LinkAction::Removed,
+                resource: None,
+                previous_resource: Some(&database),
+            }
+            .print()?,
+        }
         Ok(())
     }
 }
 
-/// A Link structure to ease grouping a resource with it's app and label
-#[derive(Clone, PartialEq)]
-pub struct Link {
-    pub resource_label: ResourceLabel,
-    pub resource: String,
+/// What unlinking a label would break, as found by `unlink --dry-run`
+#[derive(Serialize)]
+struct UnlinkImpact {
+    app_id: Uuid,
+    app: String,
+    label: String,
+    /// What the label is linked to now
+    resource: String,
+    /// The revision the app is serving, if it has been deployed
+    revision: Option<String>,
+    /// The components of that revision which use the label
+    affected_components: Vec<String>,
 }
 
-impl Link {
-    pub fn new(resource_label: ResourceLabel, resource: String) -> Self {
-        Self {
-            resource_label,
+impl UnlinkImpact {
+    async fn of(
+        client: &impl CloudClientInterface,
+        connection_config: &ConnectionConfig,
+        app_id: Uuid,
+        app: &str,
+        label: &str,
+        resource: String,
+        components_using: fn(&DeployableApp, &str) -> Vec<String>,
+    ) -> Result<Self> {
+        let item = client
+            .get_app(app_id.to_string())
+            .await
+            .with_context(|| format!("Error: could not get details about {app}"))?;
+        let deployed = item
+            .channels
+            .first()
+            .is_some_and(|c| c.active_revision_number.is_some());
+        let (revision, affected_components) = if deployed {
+            let dir = tempfile::tempdir()?;
+            let (revision, locked_app) =
+                load_active_revision(&item, app, dir.path(), connection_config).await?;
+            (
+                Some(revision),
+                components_using(&DeployableApp(locked_app), label),
+            )
+        } else {
+            (None, vec![])
+        };
+        Ok(Self {
+            app_id,
+            app: app.to_owned(),
+            label: label.to_owned(),
             resource,
-        }
+            revision,
+            affected_components,
+        })
     }
 
-    pub fn app_name(&self) -> &str {
-        match self.resource_label.app_name.as_ref() {
-            Some(a) => a.as_str(),
-            _ => "UNKNOWN",
+    fn print(&self, format: OutputFormat, kind: &str) -> Result<()> {
+        if let OutputFormat::Json = format {
+            println!("{}", serde_json::to_string_pretty(self)?);
+            return Ok(());
         }
+        println!(
+            "Unlinking label '{}' would stop app '{}' reaching {kind} '{}'.",
+            self.label, self.app, self.resource
+        );
+        match (&self.revision, self.affected_components.as_slice()) {
+            (None, _) => println!(
+                "App '{}' has no active revision, so nothing running uses the label.",
+                self.app
+            ),
+            (Some(revision), []) => {
+                println!("No component of revision {revision} uses the label.")
+            }
+            (Some(revision), components) => println!(
+                "These components of revision {revision} use the label and would start failing: {}",
+                components.join(", ")
+            ),
+        }
+        println!("Nothing was changed (dry run).");
+        Ok(())
     }
 }
 
+/// A client for `app`, with the app's ID and the configuration needed to
+/// pull its deployed revisions.
+async fn connect(
+    deployment_env_id: Option<&str>,
+    app: &str,
+) -> Result<(CloudClient, Uuid, ConnectionConfig)> {
+    let login_connection = login_connection(deployment_env_id).await?;
+    let connection_config = ConnectionConfig {
+        url: login_connection.url.to_string(),
+        insecure: login_connection.danger_accept_invalid_certs,
+        token: login_connection.token,
+    };
+    let client = CloudClient::new(connection_config.clone());
+    let app_id = app_id(&client, app).await?;
+    Ok((client, app_id, connection_config))
+}
+
+/// The components allowed to make outbound requests to the host `label`,
+/// which is how a linked app is called.
+fn components_calling_app(app: &DeployableApp, label: &str) -> Vec<String> {
+    let host = |allowed: &str| -> Option<String> {
+        let rest = allowed.split_once("://").map_or(allowed, |(_, r)| r);
+        rest.split([':', '/']).next().map(str::to_owned)
+    };
+    app.0
+        .components
+        .iter()
+        .filter(|c| {
+            c.metadata
+                .get("allowed_outbound_hosts")
+                .and_then(|v| v.as_array())
+                .is_some_and(|hosts| {
+                    hosts
+                        .iter()
+                        .filter_map(|h| h.as_str())
+                        .any(|h| host(h).as_deref() == Some(label))
+                })
+        })
+        .map(|c| c.id.clone())
+        .collect()
+}
+
 #[cfg(test)]
 mod link_tests {
     use super::*;
+    use cloud::models::AppLink;
     use cloud::MockCloudClientInterface;
+    use cloud_openapi::models::{Database, ResourceLabel};
+
+    #[test]
+    fn unlink_impact_lists_components_using_the_label() -> Result<()> {
+        let component = |id: &str, metadata: serde_json::Value| {
+            serde_json::json!({
+                "id": id,
+                "metadata": metadata,
+                "source": {
+                    "content_type": "application/wasm",
+                    "content": { "source": "file:///app.wasm" },
+                },
+                "env": {},
+                "files": [],
+                "config": {},
+            })
+        };
+        let app = DeployableApp(serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 1,
+            "metadata": { "name": "todo" },
+            "variables": {},
+            "triggers": [],
+            "components": [
+                component("web", serde_json::json!({
+                    "databases": ["default"],
+                    "allowed_outbound_hosts": ["http://auth:80", "https://api.example.com"],
+                })),
+                component("reports", serde_json::json!({ "databases": ["default", "audit"] })),
+                component("static", serde_json::json!({})),
+            ],
+        }))?);
+        assert_eq!(
+            app.components_using_database("default"),
+            vec!["web", "reports"]
+        );
+        assert_eq!(app.components_using_database("audit"), vec!["reports"]);
+        assert_eq!(components_calling_app(&app, "auth"), vec!["web"]);
+        assert!(components_calling_app(&app, "api").is_empty());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sqlite_link_error_database_does_not_exist() -> Result<()> {
         let command = SqliteLinkCommand {
             app: "app".to_string(),
             database: "does-not-exist".to_string(),
-            label: "label".to_string(),
+            label: Some("label".to_string()),
+            format: OutputFormat::Plain,
             common: Default::default(),
         };
         let app_id = Uuid::new_v4();
@@ -226,7 +617,7 @@ mod link_tests {
         let result = command.link(mock, app_id).await;
         assert_eq!(
             result.unwrap_err().to_string(),
-            r#"Database "does-not-exist" does not exist"#
+            r#"No database found with name "does-not-exist""#
         );
         Ok(())
     }
@@ -236,7 +627,8 @@ mod link_tests {
         let command = SqliteLinkCommand {
             app: "app".to_string(),
             database: "db1".to_string(),
-            label: "label".to_string(),
+            label: Some("label".to_string()),
+            format: OutputFormat::Plain,
             common: Default::default(),
         };
         let app_id = Uuid::new_v4();
@@ -246,7 +638,7 @@ mod link_tests {
         ];
         let expected_resource_label = ResourceLabel {
             app_id,
-            label: command.label.clone(),
+            label: "label".to_string(),
             app_name: None,
         };
 
@@ -259,12 +651,74 @@ mod link_tests {
         command.link(mock, app_id).await
     }
 
+    #[tokio::test]
+    async fn test_sqlite_link_without_label_uses_default() -> Result<()> {
+        let command = SqliteLinkCommand {
+            app: "app".to_string(),
+            database: "db1".to_string(),
+            label: None,
+            format: OutputFormat::Plain,
+            common: Default::default(),
+        };
+        let app_id = Uuid::new_v4();
+        let expected_resource_label = ResourceLabel {
+            app_id,
+            label: "default".to_string(),
+            app_name: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(|_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        mock.expect_create_database_link()
+            .withf(move |db, rl| db == "db1" && rl == &expected_resource_label)
+            .returning(|_, _| Ok(()));
+
+        command.link(mock, app_id).await
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_link_without_label_lists_several_labels() {
+        let command = SqliteLinkCommand {
+            app: "app".to_string(),
+            database: "db3".to_string(),
+            label: None,
+            format: OutputFormat::Plain,
+            common: Default::default(),
+        };
+        let app_id = Uuid::new_v4();
+        let linked = |name: &str, label: &str| {
+            Database::new(
+                name.to_string(),
+                vec![ResourceLabel {
+                    app_id,
+                    label: label.to_string(),
+                    app_name: Some("app".to_string()),
+                }],
+            )
+        };
+        let dbs = vec![
+            linked("db1", "orders"),
+            linked("db2", "audit"),
+            Database::new("db3".to_string(), vec![]),
+        ];
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().return_once(move |_| Ok(dbs));
+        let err = command.link(mock, app_id).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"App "app" already has the database labels "audit", "orders". Give the label to link, for example `spin cloud link sqlite audit --app app --database db3`"#
+        );
+    }
+
     #[tokio::test]
     async fn test_sqlite_link_errors_when_link_already_exists() -> Result<()> {
         let command = SqliteLinkCommand {
             app: "app".to_string(),
             database: "db1".to_string(),
-            label: "label".to_string(),
+            label: Some("label".to_string()),
+            format: OutputFormat::Plain,
             common: Default::default(),
         };
         let app_id = Uuid::new_v4();
@@ -273,7 +727,7 @@ mod link_tests {
                 "db1".to_string(),
                 vec![ResourceLabel {
                     app_id,
-                    label: command.label.clone(),
+                    label: "label".to_string(),
                     app_name: Some("app".to_string()),
                 }],
             ),
@@ -291,6 +745,108 @@ mod link_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_link_result_json_shape() -> Result<()> {
+        let app_id = Uuid::new_v4();
+        let result = LinkResultJson {
+            app_id,
+            app: "app",
+            label: "label",
+            action: LinkAction::Replaced,
+            resource: Some("db2"),
+            previous_resource: Some("db1"),
+        };
+        assert_eq!(
+            serde_json::to_value(&result)?,
+            serde_json::json!({
+                "app_id": app_id,
+                "app": "app",
+                "label": "label",
+                "action": "replaced",
+                "resource": "db2",
+                "previous_resource": "db1",
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_app_link_rejects_label_in_use() -> Result<()> {
+        let command = AppLinkCommand {
+            caller: "web".to_string(),
+            callee: "api".to_string(),
+            label: "backend".to_string(),
+            format: OutputFormat::Plain,
+            common: Default::default(),
+        };
+        let existing = AppLink {
+            id: Uuid::new_v4(),
+            label: "backend".to_string(),
+            target_app_id: Uuid::new_v4(),
+            target_app_name: "old-api".to_string(),
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_app_links()
+            .return_once(move |_| Ok(vec![existing]));
+        let result = command.link(mock, Uuid::new_v4(), Uuid::new_v4()).await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"Label "backend" is already linked to app "old-api""#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_link_list_includes_databases_and_apps() -> Result<()> {
+        let app_id = Uuid::new_v4();
+        let dbs = vec![Database::new(
+            "db1".to_string(),
+            vec![
+                ResourceLabel {
+                    app_id,
+                    label: "store".to_string(),
+                    app_name: Some("web".to_string()),
+                },
+                ResourceLabel {
+                    app_id: Uuid::new_v4(),
+                    label: "other".to_string(),
+                    app_name: Some("other-app".to_string()),
+                },
+            ],
+        )];
+        let app_links = vec![AppLink {
+            id: Uuid::new_v4(),
+            label: "backend".to_string(),
+            target_app_id: Uuid::new_v4(),
+            target_app_name: "api".to_string(),
+        }];
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().return_once(move |_| Ok(dbs));
+        mock.expect_list_app_links()
+            .return_once(move |_| Ok(app_links));
+
+        let links = list_links(&mock, app_id).await?;
+        assert_eq!(
+            links,
+            vec![
+                LabelledLink {
+                    kind: "app",
+                    label: "backend".to_string(),
+                    resource: "api".to_string(),
+                },
+                LabelledLink {
+                    kind: "sqlite",
+                    label: "store".to_string(),
+                    resource: "db1".to_string(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
     // TODO: add test test_sqlite_link_errors_when_link_exists_with_different_database()
     // once there is a flag to avoid prompts
 }
@@ -0,0 +1,245 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use cloud::{
+    models::{CreateLogDrain, LogDrain},
+    CloudClientInterface,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::commands::{client_and_app_id, confirm_environment, CommonArgs};
+use crate::opts::CLOUD_APP_ENV;
+use crate::table::new_table;
+
+/// Manage log drains, which forward an app's logs to external endpoints
+#[derive(Parser, Debug)]
+pub enum LogDrainsCommand {
+    /// Start forwarding an app's logs to an endpoint
+    Add(AddCommand),
+    /// List the log drains of an app
+    List(ListCommand),
+    /// Stop forwarding an app's logs to an endpoint
+    Remove(RemoveCommand),
+}
+
+/// Protocols by which logs can be forwarded
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DrainType {
+    /// Syslog over TCP, with a syslog:// or syslog+tls:// URL
+    Syslog,
+    /// Batches of log lines POSTed to an http:// or https:// URL
+    Http,
+}
+
+impl DrainType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Syslog => "syslog",
+            Self::Http => "http",
+        }
+    }
+
+    fn check_url(&self, url: &Url) -> Result<()> {
+        let schemes: &[&str] = match self {
+            Self::Syslog => &["syslog", "syslog+tls"],
+            Self::Http => &["http", "https"],
+        };
+        if !schemes.contains(&url.scheme()) {
+            bail!(
+                "{} log drains need a URL starting with {}",
+                self.as_str(),
+                schemes
+                    .iter()
+                    .map(|s| format!("{s}://"))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            );
+        }
+        if url.host_str().is_none() {
+            bail!("Log drain URL {url} has no host");
+        }
+        if *self == Self::Syslog && url.port().is_none() {
+            bail!("Syslog drain URL {url} must include a port");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct AddCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Protocol used to forward logs
+    #[clap(value_enum, short = 't', long = "type")]
+    pub drain_type: DrainType,
+    /// The endpoint which will receive the logs
+    #[clap(short = 'u', long = "url")]
+    pub url: Url,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct RemoveCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// ID or URL of the log drain to remove
+    pub drain: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl LogDrainsCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Add(cmd) => cmd.run().await,
+            Self::List(cmd) => cmd.run().await,
+            Self::Remove(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl AddCommand {
+    pub async fn run(self) -> Result<()> {
+        self.drain_type.check_url(&self.url)?;
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let drain = client
+            .add_log_drain(
+                app_id,
+                CreateLogDrain {
+                    drain_type: self.drain_type.as_str().to_owned(),
+                    url: self.url.to_string(),
+                },
+            )
+            .await
+            .with_context(|| format!("Problem adding log drain for app {}", &self.app))?;
+        println!(
+            "Logs of app \"{}\" are now forwarded to {} (log drain {})",
+            &self.app, drain.url, drain.id
+        );
+        Ok(())
+    }
+}
+
+impl ListCommand {
+    pub async fn run(self) -> Result<()> {
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let drains = client
+            .list_log_drains(app_id)
+            .await
+            .with_context(|| format!("Problem listing log drains for app {}", &self.app))?;
+        if drains.is_empty() {
+            eprintln!("No log drains configured for app \"{}\"", &self.app);
+        } else {
+            print_drains(&drains);
+        }
+        Ok(())
+    }
+}
+
+impl RemoveCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let drain_id = find_drain(&client, app_id, &self.app, &self.drain).await?;
+        client
+            .remove_log_drain(app_id, drain_id)
+            .await
+            .with_context(|| format!("Problem removing log drain from app {}", &self.app))?;
+        println!("Removed log drain {drain_id} from app \"{}\"", &self.app);
+        Ok(())
+    }
+}
+
+async fn find_drain(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    app: &str,
+    drain: &str,
+) -> Result<Uuid> {
+    let drains = client.list_log_drains(app_id).await?;
+    let matches = drains
+        .iter()
+        .filter(|d| d.id.to_string() == drain || d.url == drain)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => bail!(r#"No log drain "{drain}" found for app "{app}""#),
+        [d] => Ok(d.id),
+        _ => bail!(
+            r#"More than one log drain for app "{app}" uses the URL "{drain}". Remove it by ID instead."#
+        ),
+    }
+}
+
+fn print_drains(drains: &[LogDrain]) {
+    let mut table = new_table();
+    table.set_header(vec!["ID", "Type", "URL"]);
+    table.add_rows(
+        drains
+            .iter()
+            .map(|d| [d.id.to_string(), d.drain_type.clone(), d.url.clone()]),
+    );
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod log_drains_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    #[test]
+    fn drain_urls_must_match_type() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert!(DrainType::Http
+            .check_url(&url("https://logs.example/ingest"))
+            .is_ok());
+        assert!(DrainType::Http
+            .check_url(&url("syslog://logs.example:514"))
+            .is_err());
+        assert!(DrainType::Syslog
+            .check_url(&url("syslog+tls://logs.example:6514"))
+            .is_ok());
+        assert!(DrainType::Syslog
+            .check_url(&url("syslog://logs.example"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn finds_drain_by_id_or_url() -> Result<()> {
+        let drain = LogDrain {
+            id: Uuid::new_v4(),
+            drain_type: "http".to_owned(),
+            url: "https://logs.example/".to_owned(),
+        };
+        let id = drain.id;
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_log_drains()
+            .returning(move |_| Ok(vec![drain.clone()]));
+
+        let app_id = Uuid::new_v4();
+        assert_eq!(id, find_drain(&mock, app_id, "app", &id.to_string()).await?);
+        assert_eq!(
+            id,
+            find_drain(&mock, app_id, "app", "https://logs.example/").await?
+        );
+        assert!(find_drain(&mock, app_id, "app", "https://other.example/")
+            .await
+            .is_err());
+        Ok(())
+    }
+}
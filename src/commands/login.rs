@@ -1,3 +1,4 @@
+use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -17,13 +18,19 @@ use tracing::log;
 use url::Url;
 use uuid::Uuid;
 
+use crate::config_migrations::CONFIG_VERSION;
 use crate::opts::{
     CLOUD_SERVER_URL_OPT, CLOUD_URL_ENV, DEPLOYMENT_ENV_NAME_ENV, INSECURE_OPT, SPIN_AUTH_TOKEN,
     TOKEN,
 };
 
+use super::deploy::{config_file_path, login_connection};
 use super::DEFAULT_CLOUD_URL;
 
+mod oidc;
+
+pub(crate) use oidc::{cached_oidc_login_connection, identity_available, setup_hint};
+
 // this is the client ID registered in the Cloud's backend
 const SPIN_CLIENT_ID: &str = "583e63e9-461f-4fbe-a246-23e0fb1cad10";
 
@@ -108,6 +115,19 @@ pub struct LoginCommand {
     )]
     pub deployment_env_id: Option<String>,
 
+    /// Log in from CI by exchanging the job's OIDC identity token for a
+    /// short-lived token, instead of storing a token as a secret
+    #[clap(
+        long = "oidc",
+        takes_value = false,
+        conflicts_with_all = &["status", "list", "get-device-code", "check-device-code", TOKEN]
+    )]
+    pub oidc: bool,
+
+    /// With --oidc, only allow the token to deploy and manage this app
+    #[clap(long = "app", requires = "oidc")]
+    pub app: Option<String>,
+
     /// List saved logins.
     #[clap(
         name = "list",
@@ -119,6 +139,27 @@ pub struct LoginCommand {
         conflicts_with = "check-device-code"
     )]
     pub list: bool,
+
+    #[clap(subcommand)]
+    pub action: Option<LoginAction>,
+}
+
+#[derive(Parser, Debug)]
+pub enum LoginAction {
+    /// Print the access token of a saved login, so that other tools can call
+    /// Fermyon Cloud using the same session.
+    PrintToken(PrintTokenCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct PrintTokenCommand {
+    /// The saved login whose token to print. If omitted, the default login is used.
+    #[clap(long = "profile", env = DEPLOYMENT_ENV_NAME_ENV)]
+    pub profile: Option<String>,
+
+    /// Print the token even when standard output is a terminal.
+    #[clap(long = "force", takes_value = false)]
+    pub force: bool,
 }
 
 /// Log out of Fermyon Cloud.
@@ -150,6 +191,17 @@ fn parse_url(url: &str) -> Result<url::Url> {
 
 impl LoginCommand {
     pub async fn run(&self) -> Result<()> {
+        match &self.action {
+            Some(LoginAction::PrintToken(cmd)) => cmd.run().await,
+            None => self.run_login().await,
+        }
+    }
+
+    /// Performs the login itself, ignoring any subcommand.
+    pub(crate) async fn run_login(&self) -> Result<()> {
+        if self.oidc {
+            return self.run_oidc_login().await;
+        }
         match (
             self.list,
             self.status,
@@ -234,6 +286,21 @@ impl LoginCommand {
         self.save_login_info(&login_connection)
     }
 
+    async fn run_oidc_login(&self) -> Result<()> {
+        let login_connection =
+            oidc::oidc_login_connection(&self.cloud_url, self.insecure, self.app.as_deref())
+                .await?;
+        self.save_login_info(&login_connection)?;
+        let expiration = login_connection.expiration.as_deref().unwrap_or("unknown");
+        match &self.app {
+            Some(app) => println!(
+                "Logged in with the job's OIDC identity, for app \"{app}\" only, until {expiration}"
+            ),
+            None => println!("Logged in with the job's OIDC identity until {expiration}"),
+        }
+        Ok(())
+    }
+
     async fn login_using_token(&self) -> Result<LoginConnection> {
         // check that the user passed in a token
         let token = match self.token.clone() {
@@ -270,6 +337,7 @@ impl LoginCommand {
             token,
             refresh_token: None,
             expiration: None,
+            version: CONFIG_VERSION,
         }
     }
 
@@ -280,6 +348,7 @@ impl LoginCommand {
             token: token_info.token,
             refresh_token: Some(token_info.refresh_token),
             expiration: Some(token_info.expiration),
+            version: CONFIG_VERSION,
         }
     }
 
@@ -326,6 +395,29 @@ impl LoginCommand {
     }
 }
 
+impl PrintTokenCommand {
+    pub async fn run(&self) -> Result<()> {
+        if std::io::stdout().is_terminal() && !self.force {
+            bail!("Refusing to print an access token to the terminal. Redirect the output to the tool which needs it, or use --force.");
+        }
+        // Don't fall into an interactive login: scripts need to fail fast instead.
+        let path = config_file_path(self.profile.as_deref())?;
+        if !path.is_file() {
+            match &self.profile {
+                Some(profile) => {
+                    bail!("No login saved as '{profile}'. Run `spin cloud login` first.")
+                }
+                None => bail!("Not logged in. Run `spin cloud login` first."),
+            }
+        }
+        // This refreshes the token if it has expired, so the printed token is usable.
+        let login_connection = login_connection(self.profile.as_deref()).await?;
+        eprintln!("Warning: this token grants access to your Fermyon Cloud account. Do not share it or write it to logs.");
+        println!("{}", login_connection.token);
+        Ok(())
+    }
+}
+
 impl LogoutCommand {
     pub async fn run(&self) -> Result<()> {
         let path = self.config_file_path()?;
@@ -350,7 +442,7 @@ impl LogoutCommand {
     }
 }
 
-fn config_root_dir() -> Result<PathBuf, anyhow::Error> {
+pub(crate) fn config_root_dir() -> Result<PathBuf, anyhow::Error> {
     let root = dirs::config_dir()
         .context("Cannot find configuration directory")?
         .join("fermyon");
@@ -418,6 +510,9 @@ pub struct LoginConnection {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub expiration: Option<String>,
+    /// The schema version the login was saved with.
+    #[serde(default)]
+    pub version: u32,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -0,0 +1,220 @@
+//! Logging in from CI with the job's OIDC identity token. The identity token
+//! is exchanged for a short-lived Cloud token, so that pipelines need no
+//! long-lived token stored as a secret.
+
+use anyhow::{bail, Context, Result};
+use cloud::{
+    client::{Client, ConnectionConfig},
+    models::OidcTokenExchange,
+    CloudClientInterface,
+};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use super::LoginConnection;
+use crate::config_migrations::CONFIG_VERSION;
+use crate::opts::{CiProvider, EnvSettings, CLOUD_OIDC_TOKEN_ENV};
+
+/// The audience Fermyon Cloud expects identity tokens to be issued for
+const OIDC_AUDIENCE: &str = "fermyon-cloud";
+
+/// Where a job's identity token comes from
+#[derive(Debug, PartialEq)]
+enum IdentitySource {
+    /// Given in `CLOUD_OIDC_TOKEN`, as GitLab's `id_tokens` provide it
+    Env(String),
+    /// Requested from the GitHub Actions token service
+    GitHub {
+        request_url: String,
+        request_token: String,
+    },
+    /// Requested from the Buildkite agent
+    Buildkite,
+}
+
+impl IdentitySource {
+    fn find(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let value = |name: &str| lookup(name).filter(|v| !v.trim().is_empty());
+        if let Some(token) = value(CLOUD_OIDC_TOKEN_ENV) {
+            return Some(Self::Env(token));
+        }
+        // Only set when the workflow grants the job `id-token: write`
+        if let (Some(request_url), Some(request_token)) = (
+            value("ACTIONS_ID_TOKEN_REQUEST_URL"),
+            value("ACTIONS_ID_TOKEN_REQUEST_TOKEN"),
+        ) {
+            return Some(Self::GitHub {
+                request_url,
+                request_token,
+            });
+        }
+        if value("BUILDKITE_AGENT_ACCESS_TOKEN").is_some() {
+            return Some(Self::Buildkite);
+        }
+        None
+    }
+
+    fn from_env() -> Option<Self> {
+        Self::find(|name| std::env::var(name).ok())
+    }
+
+    async fn identity_token(&self) -> Result<String> {
+        match self {
+            Self::Env(token) => Ok(token.trim().to_owned()),
+            Self::GitHub {
+                request_url,
+                request_token,
+            } => {
+                #[derive(Deserialize)]
+                struct IdentityTokenResponse {
+                    value: String,
+                }
+                let response = reqwest::Client::new()
+                    .get(request_url)
+                    .query(&[("audience", OIDC_AUDIENCE)])
+                    .bearer_auth(request_token)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(response.json::<IdentityTokenResponse>().await?.value)
+            }
+            Self::Buildkite => {
+                let output = tokio::process::Command::new("buildkite-agent")
+                    .args(["oidc", "request-token", "--audience", OIDC_AUDIENCE])
+                    .output()
+                    .await
+                    .context("Could not run buildkite-agent")?;
+                if !output.status.success() {
+                    bail!(
+                        "buildkite-agent could not issue a token: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+                Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+            }
+        }
+    }
+}
+
+/// Whether the job can get an identity token to log in with.
+pub(crate) fn identity_available() -> bool {
+    IdentitySource::from_env().is_some()
+}
+
+/// What a job in `ci` needs to be able to log in with an identity token.
+pub(crate) fn setup_hint(ci: Option<CiProvider>) -> String {
+    match ci {
+        Some(CiProvider::GitHubActions) => {
+            "grant the job `permissions: id-token: write`".to_owned()
+        }
+        Some(CiProvider::GitLab) => format!(
+            "add an `id_tokens` entry named {CLOUD_OIDC_TOKEN_ENV} with `aud: {OIDC_AUDIENCE}` to the job"
+        ),
+        Some(CiProvider::Buildkite) => "run the job on a Buildkite agent".to_owned(),
+        None => format!("set {CLOUD_OIDC_TOKEN_ENV} to an identity token issued for the audience {OIDC_AUDIENCE}"),
+    }
+}
+
+/// Exchanges the job's identity token for a Cloud token, limited to `app`
+/// if one is given.
+pub(crate) async fn oidc_login_connection(
+    url: &Url,
+    insecure: bool,
+    app: Option<&str>,
+) -> Result<LoginConnection> {
+    let source = IdentitySource::from_env().with_context(|| {
+        format!(
+            "No OIDC identity token is available to this job. To provide one, {}",
+            setup_hint(EnvSettings::from_env().ci)
+        )
+    })?;
+    let id_token = source
+        .identity_token()
+        .await
+        .context("Could not get the job's OIDC identity token")?;
+    let client = Client::new(ConnectionConfig {
+        url: url.to_string(),
+        insecure,
+        token: Default::default(),
+    });
+    let federated = client
+        .exchange_oidc_token(OidcTokenExchange {
+            id_token,
+            app: app.map(str::to_owned),
+        })
+        .await
+        .context("Fermyon Cloud did not accept the job's OIDC identity token. Check that the repository is trusted in your account's OIDC settings")?;
+    Ok(LoginConnection {
+        url: url.clone(),
+        danger_accept_invalid_certs: insecure,
+        token: federated.token,
+        refresh_token: None,
+        expiration: Some(federated.expiration),
+        version: CONFIG_VERSION,
+    })
+}
+
+/// Like [`oidc_login_connection`], exchanging the identity token only once
+/// however many times a command needs to connect.
+pub(crate) async fn cached_oidc_login_connection(
+    url: &Url,
+    app: Option<&str>,
+) -> Result<LoginConnection> {
+    static CONNECTION: OnceCell<LoginConnection> = OnceCell::const_new();
+    CONNECTION
+        .get_or_try_init(|| oidc_login_connection(url, false, app))
+        .await
+        .cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn source(vars: &[(&str, &str)]) -> Option<IdentitySource> {
+        let vars: HashMap<_, _> = vars.iter().cloned().collect();
+        IdentitySource::find(|name| vars.get(name).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn identity_tokens_come_from_the_ci_system() {
+        assert_eq!(
+            source(&[
+                (CLOUD_OIDC_TOKEN_ENV, "eyJ.gitlab"),
+                (
+                    "ACTIONS_ID_TOKEN_REQUEST_URL",
+                    "https://token.actions.example/?api-version=2.0"
+                ),
+                ("ACTIONS_ID_TOKEN_REQUEST_TOKEN", "req"),
+            ]),
+            Some(IdentitySource::Env("eyJ.gitlab".to_owned()))
+        );
+        assert_eq!(
+            source(&[
+                (
+                    "ACTIONS_ID_TOKEN_REQUEST_URL",
+                    "https://token.actions.example/?api-version=2.0"
+                ),
+                ("ACTIONS_ID_TOKEN_REQUEST_TOKEN", "req"),
+            ]),
+            Some(IdentitySource::GitHub {
+                request_url: "https://token.actions.example/?api-version=2.0".to_owned(),
+                request_token: "req".to_owned(),
+            })
+        );
+        // Without `id-token: write` GitHub only sets the URL.
+        assert_eq!(
+            source(&[(
+                "ACTIONS_ID_TOKEN_REQUEST_URL",
+                "https://token.actions.example/"
+            )]),
+            None
+        );
+        assert_eq!(
+            source(&[("BUILDKITE_AGENT_ACCESS_TOKEN", "agent")]),
+            Some(IdentitySource::Buildkite)
+        );
+    }
+}
@@ -1,17 +1,29 @@
 use std::ops::Sub;
+use std::path::PathBuf;
 use std::time::Duration;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, Result};
 use chrono::Utc;
-use cloud::{CloudClientExt, CloudClientInterface};
+use cloud::CloudClientInterface;
 use cloud_openapi::models::Entry;
 use std::option::Option;
 
 use crate::commands::create_cloud_client;
+use crate::ops::apps::app_id;
 use crate::opts::*;
 use clap::Parser;
 use uuid::Uuid;
 
+mod collapse;
+mod reconnect;
+mod split;
+mod stats;
+
+use collapse::RepeatCollapser;
+use reconnect::{fetch_missed, reconnection_mark, BACKFILL_MAX_LINES};
+use split::ComponentFiles;
+use stats::{LogStats, UNKNOWN_COMPONENT};
+
 /// fetch logs for an app from Fermyon Cloud
 #[derive(Parser, Debug)]
 pub struct LogsCommand {
@@ -26,12 +38,47 @@ pub struct LogsCommand {
     pub deployment_env_id: Option<String>,
 
     /// App name
+    #[clap(env = CLOUD_APP_ENV)]
     pub app: String,
 
     /// Follow logs output
     #[clap(name = "follow", long = "follow")]
     pub follow: bool,
 
+    /// Instead of printing log lines, summarize every line in the `--since`
+    /// window: counts per level and component, the most repeated messages,
+    /// and the error rate over time.
+    #[clap(name = "stats", long = "stats", conflicts_with = "follow")]
+    pub stats: bool,
+
+    /// Write each component's log lines to its own file in `--output-dir`,
+    /// named after the component, instead of printing them
+    #[clap(
+        name = "split-by-component",
+        long = "split-by-component",
+        requires = "output-dir",
+        conflicts_with = "stats"
+    )]
+    pub split_by_component: bool,
+
+    /// Directory for the files written by `--split-by-component`
+    #[clap(
+        name = "output-dir",
+        long = "output-dir",
+        requires = "split-by-component"
+    )]
+    pub output_dir: Option<PathBuf>,
+
+    /// Fold consecutive identical messages into a single `message (xN)`
+    /// line. Runs are counted within each fetch, so while following, a
+    /// message which keeps repeating is summarized once per refresh.
+    #[clap(
+        name = "collapse-repeats",
+        long = "collapse-repeats",
+        conflicts_with = "stats"
+    )]
+    pub collapse_repeats: bool,
+
     /// Number of lines to show from the end of the logs
     #[clap(name = "tail", long = "tail", default_value = "10")]
     pub max_lines: i32,
@@ -63,11 +110,31 @@ impl LogsCommand {
     }
 
     async fn logs(self, client: &impl CloudClientInterface) -> Result<()> {
-        let app_id = client
-            .get_app_id(&self.app)
-            .await
-            .with_context(|| format!("failed to find app with name {:?}", &self.app))?
-            .with_context(|| format!("app with name {:?} not found", &self.app))?;
+        let app_id = app_id(client, &self.app).await?;
+
+        if self.stats {
+            let since = Utc::now().sub(self.since).to_rfc3339();
+            let entries = fetch_entries(client, app_id, None, since).await?;
+            LogStats::from_entries(&entries).print();
+            return Ok(());
+        }
+
+        let sink = match &self.output_dir {
+            Some(dir) if self.split_by_component => {
+                if self.follow {
+                    eprintln!(
+                        "Writing logs for each component to {}. Press Ctrl+C to stop.",
+                        dir.display()
+                    );
+                }
+                LogSink::Components(ComponentFiles::create(dir)?)
+            }
+            _ => LogSink::Stdout,
+        };
+        let mut output = LogOutput {
+            sink,
+            repeats: self.collapse_repeats.then(RepeatCollapser::default),
+        };
 
         fetch_logs_and_print_loop(
             client,
@@ -77,13 +144,83 @@ impl LogsCommand {
             self.max_lines,
             self.since,
             self.show_timestamp,
+            &mut output,
         )
         .await?;
 
+        if let LogSink::Components(files) = &output.sink {
+            files.print_summary();
+        }
+        Ok(())
+    }
+}
+
+/// Where log lines are written
+enum LogSink {
+    Stdout,
+    Components(ComponentFiles),
+}
+
+impl LogSink {
+    fn write_line(&mut self, component: &str, line: &str) -> Result<()> {
+        match self {
+            Self::Stdout => println!("{line}"),
+            Self::Components(files) => files.write_line(component, line)?,
+        }
         Ok(())
     }
 }
 
+struct LogOutput {
+    sink: LogSink,
+    /// Set if runs of repeated messages are folded into one line
+    repeats: Option<RepeatCollapser>,
+}
+
+impl LogOutput {
+    fn write_line(&mut self, component: &str, message: &str, line: &str) -> Result<()> {
+        let Some(repeats) = &mut self.repeats else {
+            return self.sink.write_line(component, line);
+        };
+        if let Some(ended) = repeats.push(component, message, line) {
+            self.sink.write_line(&ended.component, &ended.line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a note, such as a reconnection, where it will be seen alongside
+    /// the log lines.
+    fn mark(&mut self, note: &str) -> Result<()> {
+        self.write_pending_repeats()?;
+        match &mut self.sink {
+            LogSink::Stdout => println!("{note}"),
+            LogSink::Components(files) => {
+                eprintln!("{note}");
+                files.write_to_all(note)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes out any run of repeats still being counted, so that lines are
+    /// not held back between fetches.
+    fn flush(&mut self) -> Result<()> {
+        self.write_pending_repeats()?;
+        match &mut self.sink {
+            LogSink::Stdout => Ok(()),
+            LogSink::Components(files) => files.flush(),
+        }
+    }
+
+    fn write_pending_repeats(&mut self) -> Result<()> {
+        if let Some(ended) = self.repeats.as_mut().and_then(RepeatCollapser::finish) {
+            self.sink.write_line(&ended.component, &ended.line)?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn fetch_logs_and_print_loop(
     client: &impl CloudClientInterface,
     app_id: Uuid,
@@ -92,11 +229,18 @@ async fn fetch_logs_and_print_loop(
     max_lines: i32,
     since: Duration,
     show_timestamp: bool,
+    output: &mut LogOutput,
 ) -> Result<()> {
     let mut curr_since = Utc::now().sub(since).to_rfc3339();
-    curr_since =
-        fetch_logs_and_print_once(client, app_id, Some(max_lines), curr_since, show_timestamp)
-            .await?;
+    curr_since = fetch_logs_and_print_once(
+        client,
+        app_id,
+        Some(max_lines),
+        curr_since,
+        show_timestamp,
+        output,
+    )
+    .await?;
 
     if !follow {
         return Ok(());
@@ -104,8 +248,31 @@ async fn fetch_logs_and_print_loop(
 
     loop {
         tokio::time::sleep(interval).await;
-        curr_since =
-            fetch_logs_and_print_once(client, app_id, None, curr_since, show_timestamp).await?;
+        let fetched = fetch_logs_and_print_once(
+            client,
+            app_id,
+            None,
+            curr_since.clone(),
+            show_timestamp,
+            output,
+        )
+        .await;
+        curr_since = match fetched {
+            Ok(since) => since,
+            Err(e) => {
+                // Fill in what was logged while the connection was down,
+                // rather than silently skipping ahead.
+                let entries = fetch_missed(client, app_id, &curr_since, interval, e).await;
+                output.mark(&reconnection_mark(
+                    &entries,
+                    &curr_since,
+                    BACKFILL_MAX_LINES,
+                ))?;
+                print_logs(&entries, show_timestamp, output)?
+                    .map(str::to_owned)
+                    .unwrap_or(curr_since)
+            }
+        };
     }
 }
 
@@ -115,17 +282,15 @@ async fn fetch_logs_and_print_once(
     max_lines: Option<i32>,
     since: String,
     show_timestamp: bool,
+    output: &mut LogOutput,
 ) -> Result<String> {
-    let entries = client
-        .app_logs_raw(app_id.to_string(), max_lines, Some(since.to_string()))
-        .await?
-        .entries;
+    let entries = fetch_entries(client, app_id, max_lines, since.clone()).await?;
 
     if entries.is_empty() {
         return Ok(since.to_owned());
     }
 
-    let updated_since = print_logs(&entries, show_timestamp);
+    let updated_since = print_logs(&entries, show_timestamp, output)?;
     if let Some(u) = updated_since {
         return Ok(u.to_owned());
     }
@@ -133,33 +298,66 @@ async fn fetch_logs_and_print_once(
     Ok(since)
 }
 
-fn print_logs(entries: &[Entry], show_timestamp: bool) -> Option<&str> {
-    let mut since = None;
-    for entry in entries.iter().rev() {
-        let Some(log_lines) = entry.log_lines.as_ref() else {
-            continue;
-        };
+/// Fetches the log entries written after `since`, at most the last
+/// `max_lines` lines of them if given.
+pub(crate) async fn fetch_entries(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    max_lines: Option<i32>,
+    since: String,
+) -> Result<Vec<Entry>> {
+    Ok(client
+        .app_logs_raw(app_id.to_string(), max_lines, Some(since))
+        .await?
+        .entries)
+}
 
-        for log_entry in log_lines {
-            let Some(log) = log_entry.line.as_ref() else {
-                continue;
-            };
+/// A timestamped line of an app's logs
+pub(crate) struct LogLine<'a> {
+    pub component: &'a str,
+    pub time: &'a str,
+    pub line: &'a str,
+}
 
-            if let Some(time) = &log_entry.time {
-                if show_timestamp {
-                    println!("[{time}] {log}");
-                } else {
-                    println!("{log}");
-                }
-                since = Some(time.as_str());
-            }
+/// The timestamped lines of log entries, oldest first.
+pub(crate) fn log_lines(entries: &[Entry]) -> impl Iterator<Item = LogLine<'_>> {
+    entries.iter().rev().flat_map(|entry| {
+        let component = entry.source.as_deref().unwrap_or(UNKNOWN_COMPONENT);
+        entry.log_lines.iter().flatten().filter_map(move |l| {
+            Some(LogLine {
+                component,
+                time: l.time.as_deref()?,
+                line: l.line.as_deref()?,
+            })
+        })
+    })
+}
+
+fn print_logs<'a>(
+    entries: &'a [Entry],
+    show_timestamp: bool,
+    output: &mut LogOutput,
+) -> Result<Option<&'a str>> {
+    let mut since = None;
+    for LogLine {
+        component,
+        time,
+        line,
+    } in log_lines(entries)
+    {
+        if show_timestamp {
+            output.write_line(component, line, &format!("[{time}] {line}"))?;
+        } else {
+            output.write_line(component, line, line)?;
         }
+        since = Some(time);
     }
+    output.flush()?;
 
-    since
+    Ok(since)
 }
 
-fn parse_duration(arg: &str) -> anyhow::Result<std::time::Duration> {
+pub(crate) fn parse_duration(arg: &str) -> anyhow::Result<std::time::Duration> {
     let duration = if let Some(parg) = arg.strip_suffix('s') {
         let value = parg.parse()?;
         std::time::Duration::from_secs(value)
@@ -173,13 +371,13 @@ fn parse_duration(arg: &str) -> anyhow::Result<std::time::Duration> {
         let value: u64 = parg.parse()?;
         std::time::Duration::from_secs(value * 24 * 60 * 60)
     } else {
-        bail!(r#"since must be a number followed by an allowed unit ("300s", "5m", "4h" or "1d")"#);
+        bail!(r#"must be a number followed by an allowed unit ("300s", "5m", "4h" or "1d")"#);
     };
 
     Ok(duration)
 }
 
-fn parse_interval(arg: &str) -> anyhow::Result<std::time::Duration> {
+pub(crate) fn parse_interval(arg: &str) -> anyhow::Result<std::time::Duration> {
     let value = arg.parse()?;
     if value < 2 {
         bail!("interval cannot be less than 2 seconds")
@@ -0,0 +1,90 @@
+/// Folds runs of identical log messages into a single line. Messages are
+/// compared without their timestamps, so a component logging the same
+/// error over and over shows up once, as `message (xN)`, stamped with the
+/// time of the first occurrence.
+#[derive(Default)]
+pub(super) struct RepeatCollapser {
+    run: Option<Run>,
+}
+
+struct Run {
+    component: String,
+    message: String,
+    first_line: String,
+    count: usize,
+}
+
+/// A line ready to be written, standing for a whole run of repeats
+#[derive(Debug, PartialEq)]
+pub(super) struct CollapsedLine {
+    pub component: String,
+    pub line: String,
+}
+
+impl RepeatCollapser {
+    /// Adds a line, returning the previous run if this line ends it.
+    pub fn push(&mut self, component: &str, message: &str, line: &str) -> Option<CollapsedLine> {
+        if let Some(run) = &mut self.run {
+            if run.component == component && run.message == message {
+                run.count += 1;
+                return None;
+            }
+        }
+        let ended = self.finish();
+        self.run = Some(Run {
+            component: component.to_owned(),
+            message: message.to_owned(),
+            first_line: line.to_owned(),
+            count: 1,
+        });
+        ended
+    }
+
+    /// Ends the current run, if any.
+    pub fn finish(&mut self) -> Option<CollapsedLine> {
+        let run = self.run.take()?;
+        let line = match run.count {
+            1 => run.first_line,
+            n => format!("{} (x{n})", run.first_line),
+        };
+        Some(CollapsedLine {
+            component: run.component,
+            line,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn consecutive_repeats_are_folded() {
+        let mut collapser = RepeatCollapser::default();
+        let mut lines = vec![];
+        for (time, component, message) in [
+            ("10:00:00", "api", "connection refused"),
+            ("10:00:01", "api", "connection refused"),
+            ("10:00:02", "api", "connection refused"),
+            ("10:00:03", "web", "connection refused"),
+            ("10:00:04", "api", "retrying"),
+        ] {
+            let line = format!("[{time}] {message}");
+            lines.extend(collapser.push(component, message, &line));
+        }
+        lines.extend(collapser.finish());
+
+        assert_eq!(
+            lines
+                .iter()
+                .map(|l| format!("{}: {}", l.component, l.line))
+                .collect::<Vec<_>>(),
+            vec![
+                "api: [10:00:00] connection refused (x3)",
+                "web: [10:00:03] connection refused",
+                "api: [10:00:04] retrying",
+            ]
+        );
+        assert_eq!(collapser.finish(), None);
+    }
+}
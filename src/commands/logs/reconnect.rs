@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use cloud::CloudClientInterface;
+use cloud_openapi::models::Entry;
+use uuid::Uuid;
+
+/// The most lines fetched to fill in what was logged while disconnected.
+/// Anything logged before the oldest of those is reported as a gap.
+pub(super) const BACKFILL_MAX_LINES: i32 = 5000;
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Retries fetching the logs written after `since` until it succeeds, backing
+/// off between attempts. Following logs is usually what someone is relying on
+/// during an incident, so this never gives up on its own.
+pub(super) async fn fetch_missed(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    since: &str,
+    interval: Duration,
+    error: anyhow::Error,
+) -> Vec<Entry> {
+    eprintln!("Lost connection while following logs: {error:#}");
+    let mut delay = interval;
+    loop {
+        eprintln!("Reconnecting in {}s...", delay.as_secs());
+        tokio::time::sleep(delay).await;
+        match client
+            .app_logs_raw(
+                app_id.to_string(),
+                Some(BACKFILL_MAX_LINES),
+                Some(since.to_owned()),
+            )
+            .await
+        {
+            Ok(logs) => return logs.entries,
+            Err(e) => {
+                eprintln!("Could not reconnect: {e:#}");
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+/// The number of timestamped lines in `entries`, and the time of the oldest.
+pub(super) fn line_count_and_oldest(entries: &[Entry]) -> (usize, Option<&str>) {
+    entries
+        .iter()
+        .flat_map(|e| e.log_lines.iter().flatten())
+        .filter_map(|l| l.time.as_deref())
+        .fold((0, None), |(count, oldest), time| {
+            let oldest = match oldest {
+                Some(o) if o <= time => o,
+                _ => time,
+            };
+            (count + 1, Some(oldest))
+        })
+}
+
+/// Describes what was recovered after reconnecting, for marking the output.
+/// If the backfill was cut off at the line limit, lines between `since` and
+/// the oldest line returned are lost, and the mark says so.
+pub(super) fn reconnection_mark(entries: &[Entry], since: &str, limit: i32) -> String {
+    match line_count_and_oldest(entries) {
+        (count, Some(oldest)) if count >= limit as usize => format!(
+            "--- Reconnected. GAP: logs from {since} to {oldest} could not be recovered ---"
+        ),
+        (0, _) => "--- Reconnected. No logs were missed while disconnected ---".to_owned(),
+        (count, _) => {
+            format!("--- Reconnected. {count} lines logged while disconnected follow ---")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud_openapi::models::LogLine;
+
+    fn entry(times: &[&str]) -> Entry {
+        Entry {
+            source: Some("api".to_owned()),
+            log_lines: Some(
+                times
+                    .iter()
+                    .map(|time| LogLine {
+                        time: Some(time.to_string()),
+                        line: Some("line".to_owned()),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn gap_is_marked_when_backfill_hits_the_limit() {
+        let since = "2023-11-01T12:00:00Z";
+        let entries = vec![
+            entry(&["2023-11-01T12:05:00Z", "2023-11-01T12:06:00Z"]),
+            entry(&["2023-11-01T12:04:00Z"]),
+        ];
+        assert_eq!(
+            line_count_and_oldest(&entries),
+            (3, Some("2023-11-01T12:04:00Z"))
+        );
+        assert_eq!(
+            reconnection_mark(&entries, since, 3),
+            "--- Reconnected. GAP: logs from 2023-11-01T12:00:00Z to 2023-11-01T12:04:00Z could not be recovered ---"
+        );
+        assert_eq!(
+            reconnection_mark(&entries, since, 10),
+            "--- Reconnected. 3 lines logged while disconnected follow ---"
+        );
+        assert_eq!(
+            reconnection_mark(&[], since, 10),
+            "--- Reconnected. No logs were missed while disconnected ---"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Log files for each component of an app, kept open while logs are
+/// followed. Each file is truncated the first time a component is seen, so
+/// that running the command again does not append duplicate lines.
+pub(super) struct ComponentFiles {
+    dir: PathBuf,
+    files: BTreeMap<String, (BufWriter<File>, usize)>,
+}
+
+impl ComponentFiles {
+    pub(super) fn create(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create log directory {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_owned(),
+            files: BTreeMap::new(),
+        })
+    }
+
+    pub(super) fn write_line(&mut self, component: &str, line: &str) -> Result<()> {
+        if !self.files.contains_key(component) {
+            let path = self.path_for(component);
+            let file = File::create(&path)
+                .with_context(|| format!("Could not create log file {}", path.display()))?;
+            self.files
+                .insert(component.to_owned(), (BufWriter::new(file), 0));
+        }
+        let (file, count) = self.files.get_mut(component).unwrap();
+        writeln!(file, "{line}")?;
+        *count += 1;
+        Ok(())
+    }
+
+    /// Writes `line` to the file of every component seen so far.
+    pub(super) fn write_to_all(&mut self, line: &str) -> Result<()> {
+        for (file, _) in self.files.values_mut() {
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn flush(&mut self) -> Result<()> {
+        for (file, _) in self.files.values_mut() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Prints how many lines went to each component's file.
+    pub(super) fn print_summary(&self) {
+        if self.files.is_empty() {
+            println!("No log lines to write");
+        }
+        for (component, (_, count)) in &self.files {
+            println!(
+                "Wrote {count} lines for component \"{component}\" to {}",
+                self.path_for(component).display()
+            );
+        }
+    }
+
+    // Component IDs are normally safe file names already, but anything else
+    // is replaced rather than risk writing outside the directory.
+    fn path_for(&self, component: &str) -> PathBuf {
+        let name = component
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        self.dir.join(format!("{name}.log"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lines_are_written_to_a_file_per_component() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let logs = dir.path().join("logs");
+        std::fs::create_dir(&logs)?;
+        std::fs::write(logs.join("api.log"), "from an earlier run\n")?;
+
+        let mut files = ComponentFiles::create(&logs)?;
+        files.write_line("api", "first")?;
+        files.write_line("web", "page")?;
+        files.write_line("api", "second")?;
+        files.write_line("../escape", "nope")?;
+        files.flush()?;
+
+        assert_eq!(
+            std::fs::read_to_string(logs.join("api.log"))?,
+            "first\nsecond\n"
+        );
+        assert_eq!(std::fs::read_to_string(logs.join("web.log"))?, "page\n");
+        assert!(logs.join("___escape.log").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, FixedOffset};
+use cloud_openapi::models::Entry;
+
+use crate::table::new_table;
+
+const TOP_MESSAGES: usize = 5;
+const TIME_BUCKETS: i32 = 10;
+pub(super) const UNKNOWN_COMPONENT: &str = "UNKNOWN";
+
+/// The severity of a log line, inferred from its text
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+    Unknown,
+}
+
+impl Level {
+    // Spin components log however they like, so this looks for the conventional
+    // level markers near the start of the line rather than relying on any format.
+    pub(super) fn infer(line: &str) -> Self {
+        let head = line
+            .chars()
+            .take(48)
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let words = head
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|w| !w.is_empty());
+        for word in words {
+            match word {
+                "ERROR" | "ERR" | "FATAL" | "PANIC" | "PANICKED" => return Self::Error,
+                "WARN" | "WARNING" => return Self::Warn,
+                "INFO" => return Self::Info,
+                "DEBUG" => return Self::Debug,
+                "TRACE" => return Self::Trace,
+                _ => {}
+            }
+        }
+        Self::Unknown
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+struct StatsLine<'a> {
+    component: &'a str,
+    time: Option<DateTime<FixedOffset>>,
+    level: Level,
+    message: &'a str,
+}
+
+/// A summary of a window of log lines
+#[derive(Debug, Default)]
+pub(super) struct LogStats {
+    pub(super) total: usize,
+    pub(super) by_level: BTreeMap<Level, usize>,
+    pub(super) by_component: BTreeMap<String, usize>,
+    pub(super) top_messages: Vec<(String, usize)>,
+    pub(super) buckets: Vec<TimeBucket>,
+}
+
+#[derive(Debug)]
+pub(super) struct TimeBucket {
+    pub(super) start: DateTime<FixedOffset>,
+    pub(super) total: usize,
+    pub(super) errors: usize,
+}
+
+impl LogStats {
+    pub(super) fn from_entries(entries: &[Entry]) -> Self {
+        let lines = entries
+            .iter()
+            .flat_map(|entry| {
+                let component = entry.source.as_deref().unwrap_or(UNKNOWN_COMPONENT);
+                entry
+                    .log_lines
+                    .iter()
+                    .flatten()
+                    .filter_map(move |log_line| {
+                        let message = log_line.line.as_deref()?;
+                        let time = log_line
+                            .time
+                            .as_deref()
+                            .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+                        Some(StatsLine {
+                            component,
+                            time,
+                            level: Level::infer(message),
+                            message,
+                        })
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut stats = Self {
+            total: lines.len(),
+            ..Default::default()
+        };
+
+        let mut messages: HashMap<&str, usize> = HashMap::new();
+        for line in &lines {
+            *stats.by_level.entry(line.level).or_default() += 1;
+            *stats
+                .by_component
+                .entry(line.component.to_owned())
+                .or_default() += 1;
+            *messages.entry(line.message.trim()).or_default() += 1;
+        }
+
+        let mut top_messages = messages
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(m, count)| (m.to_owned(), count))
+            .collect::<Vec<_>>();
+        top_messages.sort_by(|(m1, c1), (m2, c2)| c2.cmp(c1).then_with(|| m1.cmp(m2)));
+        top_messages.truncate(TOP_MESSAGES);
+        stats.top_messages = top_messages;
+
+        stats.buckets = time_buckets(&lines);
+        stats
+    }
+
+    pub(super) fn print(&self) {
+        if self.total == 0 {
+            println!("No log lines in the requested window");
+            return;
+        }
+        println!("{} log lines", self.total);
+
+        let mut table = new_table();
+        table.set_header(vec!["Level", "Lines"]);
+        table.add_rows(
+            self.by_level
+                .iter()
+                .map(|(level, count)| [level.as_str().to_owned(), count.to_string()]),
+        );
+        println!("{table}");
+
+        let mut table = new_table();
+        table.set_header(vec!["Component", "Lines"]);
+        table.add_rows(
+            self.by_component
+                .iter()
+                .map(|(component, count)| [component.clone(), count.to_string()]),
+        );
+        println!("{table}");
+
+        if !self.top_messages.is_empty() {
+            let mut table = new_table();
+            table.set_header(vec!["Count", "Repeated message"]);
+            table.add_rows(
+                self.top_messages
+                    .iter()
+                    .map(|(message, count)| [count.to_string(), message.clone()]),
+            );
+            println!("{table}");
+        }
+
+        if !self.buckets.is_empty() {
+            let mut table = new_table();
+            table.set_header(vec!["From", "Lines", "Errors", "Error rate"]);
+            table.add_rows(self.buckets.iter().map(|b| {
+                let rate = if b.total == 0 {
+                    0.0
+                } else {
+                    100.0 * b.errors as f64 / b.total as f64
+                };
+                [
+                    b.start.to_rfc3339(),
+                    b.total.to_string(),
+                    b.errors.to_string(),
+                    format!("{rate:.1}%"),
+                ]
+            }));
+            println!("{table}");
+        }
+    }
+}
+
+// Splits the span between the earliest and latest timestamped lines into
+// equal buckets and counts lines and errors within each.
+fn time_buckets(lines: &[StatsLine]) -> Vec<TimeBucket> {
+    let times = lines.iter().filter_map(|l| l.time);
+    let (Some(first), Some(last)) = (times.clone().min(), times.max()) else {
+        return vec![];
+    };
+    let span = last - first;
+    let width = std::cmp::max(span / TIME_BUCKETS, Duration::seconds(1));
+    let count = std::cmp::min(
+        (span.num_milliseconds() / width.num_milliseconds()) as usize + 1,
+        TIME_BUCKETS as usize,
+    );
+
+    let mut buckets = (0..count)
+        .map(|i| TimeBucket {
+            start: first + width * i as i32,
+            total: 0,
+            errors: 0,
+        })
+        .collect::<Vec<_>>();
+    for line in lines {
+        let Some(time) = line.time else {
+            continue;
+        };
+        let index = ((time - first).num_milliseconds() / width.num_milliseconds()) as usize;
+        let bucket = &mut buckets[std::cmp::min(index, count - 1)];
+        bucket.total += 1;
+        if line.level == Level::Error {
+            bucket.errors += 1;
+        }
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud_openapi::models::LogLine;
+
+    fn entry(source: &str, lines: &[(&str, &str)]) -> Entry {
+        Entry {
+            source: Some(source.to_owned()),
+            log_lines: Some(
+                lines
+                    .iter()
+                    .map(|(time, line)| LogLine {
+                        time: Some(time.to_string()),
+                        line: Some(line.to_string()),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn level_is_inferred_from_common_markers() {
+        assert_eq!(Level::Error, Level::infer("ERROR: something broke"));
+        assert_eq!(Level::Error, Level::infer("[error] something broke"));
+        assert_eq!(
+            Level::Warn,
+            Level::infer("2023-11-01T12:00:00Z WARN low disk")
+        );
+        assert_eq!(Level::Info, Level::infer("info: handled request"));
+        assert_eq!(Level::Unknown, Level::infer("handled request"));
+        assert_eq!(Level::Unknown, Level::infer("terrorist-free zone"));
+    }
+
+    #[test]
+    fn stats_count_levels_components_and_repeats() {
+        let entries = vec![
+            entry(
+                "api",
+                &[
+                    ("2023-11-01T12:00:00Z", "ERROR db timeout"),
+                    ("2023-11-01T12:00:30Z", "ERROR db timeout"),
+                    ("2023-11-01T12:01:00Z", "INFO ok"),
+                ],
+            ),
+            entry("web", &[("2023-11-01T12:02:00Z", "served page")]),
+        ];
+        let stats = LogStats::from_entries(&entries);
+
+        assert_eq!(4, stats.total);
+        assert_eq!(Some(&2), stats.by_level.get(&Level::Error));
+        assert_eq!(Some(&1), stats.by_level.get(&Level::Info));
+        assert_eq!(Some(&1), stats.by_level.get(&Level::Unknown));
+        assert_eq!(Some(&3), stats.by_component.get("api"));
+        assert_eq!(Some(&1), stats.by_component.get("web"));
+        assert_eq!(vec![("ERROR db timeout".to_owned(), 2)], stats.top_messages);
+
+        let bucketed: usize = stats.buckets.iter().map(|b| b.total).sum();
+        let errors: usize = stats.buckets.iter().map(|b| b.errors).sum();
+        assert_eq!(4, bucketed);
+        assert_eq!(2, errors);
+        assert_eq!(1, stats.buckets[0].errors);
+    }
+}
@@ -1,18 +1,33 @@
 pub mod apps;
+pub mod cache;
+pub mod config;
 pub mod deploy;
+pub mod key_value;
 pub mod link;
+pub mod log_drains;
 pub mod login;
 pub mod logs;
+pub mod regions;
+pub mod run;
+pub mod serve_api;
 pub mod sqlite;
+pub mod templates;
+pub mod usage;
 pub mod variables;
+pub mod version;
+pub mod webhooks;
 
-use crate::{commands::deploy::login_connection, opts::DEPLOYMENT_ENV_NAME_ENV};
-use anyhow::{Context, Result};
-use clap::Args;
-use cloud::{
-    client::{Client as CloudClient, ConnectionConfig},
-    CloudClientExt,
+use crate::{
+    answers,
+    commands::deploy::login_connection,
+    ops::apps::app_id,
+    opts::{EnvSettings, CLOUD_PROFILE_ENV, DEPLOYMENT_ENV_NAME_ENV},
+    project_config::{ProjectConfig, PROJECT_CONFIG_FILE},
 };
+use anyhow::{bail, Result};
+use clap::Args;
+use cloud::client::{Client as CloudClient, ConnectionConfig};
+use std::path::Path;
 use uuid::Uuid;
 
 const DEFAULT_CLOUD_URL: &str = "https://cloud.fermyon.com/";
@@ -32,14 +47,58 @@ async fn client_and_app_id(
     app: &str,
 ) -> Result<(CloudClient, Uuid)> {
     let client = create_cloud_client(deployment_env_id).await?;
-    let app_id = client
-        .get_app_id(app)
-        .await
-        .with_context(|| format!("Error finding app_id for app '{}'", app))?
-        .with_context(|| format!("Could not find app '{}'", app))?;
+    let app_id = app_id(&client, app).await?;
     Ok((client, app_id))
 }
 
+/// Checks that the saved login in use is the one the project config in the
+/// current directory expects, asking before carrying on if it is not.
+/// Commands call this before changing anything in Cloud.
+pub(crate) fn confirm_environment(deployment_env_id: Option<&str>) -> Result<()> {
+    confirm_project_environment(deployment_env_id, Path::new("."))
+}
+
+/// Like [`confirm_environment`], for the project in `project_dir`.
+pub(crate) fn confirm_project_environment(
+    deployment_env_id: Option<&str>,
+    project_dir: &Path,
+) -> Result<()> {
+    let settings = EnvSettings::from_env();
+    // A token from the environment is not tied to any saved login.
+    if settings.token.is_some() {
+        return Ok(());
+    }
+    let config = ProjectConfig::load_from_dir(project_dir)?;
+    let active = deployment_env_id.or(settings.profile.as_deref());
+    let Some(warning) = environment_mismatch(config.environment.as_deref(), active) else {
+        return Ok(());
+    };
+    let expected = config.environment.unwrap_or_default();
+    if !settings.interactive() {
+        bail!("{warning}. Use --environment-name {expected} or set {CLOUD_PROFILE_ENV}={expected}");
+    }
+    eprintln!("Warning: {warning}.");
+    let proceed = answers::confirm("Continue anyway?", false)?;
+    if !proceed {
+        bail!("Cancelled because of the environment mismatch");
+    }
+    Ok(())
+}
+
+fn environment_mismatch(expected: Option<&str>, active: Option<&str>) -> Option<String> {
+    let expected = expected?;
+    if active == Some(expected) {
+        return None;
+    }
+    let active = match active {
+        Some(name) => format!(r#"the "{name}" environment"#),
+        None => "the default environment".to_owned(),
+    };
+    Some(format!(
+        r#"{PROJECT_CONFIG_FILE} expects the "{expected}" environment, but {active} is being used"#
+    ))
+}
+
 #[derive(Debug, Default, Args)]
 struct CommonArgs {
     /// Deploy to the Fermyon instance saved under the specified name.
@@ -52,3 +111,22 @@ struct CommonArgs {
     )]
     pub deployment_env_id: Option<String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn environment_must_match_project_config() {
+        assert_eq!(environment_mismatch(None, Some("prod")), None);
+        assert_eq!(environment_mismatch(Some("staging"), Some("staging")), None);
+        assert_eq!(
+            environment_mismatch(Some("staging"), Some("prod")).unwrap(),
+            r#"spin-cloud.toml expects the "staging" environment, but the "prod" environment is being used"#
+        );
+        assert_eq!(
+            environment_mismatch(Some("staging"), None).unwrap(),
+            r#"spin-cloud.toml expects the "staging" environment, but the default environment is being used"#
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use clap::Parser;
+use cloud::models::Region;
+
+use crate::commands::{create_cloud_client, CommonArgs};
+use crate::ops::regions::list_regions;
+use crate::table::new_table;
+
+/// List the regions in which databases and key value stores can be created
+#[derive(Parser, Debug)]
+pub struct RegionsCommand {
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl RegionsCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let mut regions = list_regions(&client).await?;
+        if regions.is_empty() {
+            println!("No regions available");
+            return Ok(());
+        }
+        regions.sort_by(|a, b| a.name.cmp(&b.name));
+        print_regions(&regions);
+        Ok(())
+    }
+}
+
+fn print_regions(regions: &[Region]) {
+    let mut table = new_table();
+    table.set_header(vec!["Region", "Description", "Default"]);
+    table.add_rows(regions.iter().map(|r| {
+        [
+            r.name.clone(),
+            r.display_name.clone().unwrap_or_default(),
+            if r.default { "yes" } else { "" }.to_owned(),
+        ]
+    }));
+    println!("{table}");
+}
@@ -0,0 +1,277 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use cloud::CloudClientInterface;
+
+use crate::commands::deploy::DeployCommand;
+use crate::commands::{confirm_environment, create_cloud_client, CommonArgs};
+use crate::ops::apps::app_id;
+use crate::ops::link::{apply_sqlite_link, plan_sqlite_link, SqliteLinkPlan};
+use crate::ops::sqlite::{create_database, execute, list_databases, ExecuteTarget};
+use crate::ops::variables::set_variables;
+use crate::table::new_table;
+
+mod script;
+
+use script::{Script, Step};
+
+/// Run a sequence of operations from a YAML, TOML or JSON script file
+#[derive(Parser, Debug)]
+pub struct RunCommand {
+    /// The script to run
+    pub script: PathBuf,
+    /// Carry on with the remaining steps after one fails instead of stopping
+    #[clap(long = "continue-on-error", takes_value = false)]
+    pub continue_on_error: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+/// What happened when a step was run
+enum StepOutcome {
+    Succeeded(String),
+    Failed(anyhow::Error),
+    /// Not attempted, because an earlier step failed
+    Skipped,
+}
+
+impl RunCommand {
+    pub async fn run(self) -> Result<()> {
+        let script = Script::from_file(&self.script)?;
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let outcomes = run_steps(
+            &client,
+            &script.steps,
+            self.common.deployment_env_id.as_deref(),
+            self.continue_on_error,
+        )
+        .await;
+
+        let mut table = new_table();
+        table.set_header(vec!["#", "Step", "Result"]);
+        let mut failures = 0;
+        for (index, (step, outcome)) in script.steps.iter().zip(&outcomes).enumerate() {
+            let result = match outcome {
+                StepOutcome::Succeeded(summary) => summary.clone(),
+                StepOutcome::Failed(e) => {
+                    failures += 1;
+                    format!("failed: {e:#}")
+                }
+                StepOutcome::Skipped => "skipped".to_owned(),
+            };
+            table.add_row(vec![(index + 1).to_string(), step.to_string(), result]);
+        }
+        println!("{table}");
+        if failures > 0 {
+            bail!("{failures} of {} steps failed", outcomes.len());
+        }
+        println!("All {} steps succeeded", outcomes.len());
+        Ok(())
+    }
+}
+
+async fn run_steps(
+    client: &impl CloudClientInterface,
+    steps: &[Step],
+    deployment_env_id: Option<&str>,
+    continue_on_error: bool,
+) -> Vec<StepOutcome> {
+    let mut outcomes = Vec::with_capacity(steps.len());
+    let mut failed = false;
+    for step in steps {
+        let outcome = if failed && !continue_on_error {
+            StepOutcome::Skipped
+        } else {
+            eprintln!("Running step: {step}");
+            match run_step(client, step, deployment_env_id).await {
+                Ok(summary) => StepOutcome::Succeeded(summary),
+                Err(e) => {
+                    failed = true;
+                    StepOutcome::Failed(e)
+                }
+            }
+        };
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+/// Runs one step, returning a summary of what it did.
+async fn run_step(
+    client: &impl CloudClientInterface,
+    step: &Step,
+    deployment_env_id: Option<&str>,
+) -> Result<String> {
+    match step {
+        Step::CreateDatabase { name, region } => {
+            // Scripts are often run again, so an existing database is not an error.
+            if list_databases(client)
+                .await?
+                .iter()
+                .any(|d| &d.name == name)
+            {
+                return Ok("already exists".to_owned());
+            }
+            create_database(client, name, region.as_deref()).await?;
+            Ok("created".to_owned())
+        }
+        Step::SetVariables { app, variables } => {
+            let app_id = app_id(client, app).await?;
+            let variables = variables
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>();
+            set_variables(client, app_id, &variables).await?;
+            Ok("set".to_owned())
+        }
+        Step::Deploy { from, args } => {
+            let mut deploy_args = vec!["deploy".to_owned()];
+            if let Some(from) = from {
+                deploy_args.extend(["--from".to_owned(), from.clone()]);
+            }
+            if let Some(env) = deployment_env_id {
+                deploy_args.extend(["--environment-name".to_owned(), env.to_owned()]);
+            }
+            deploy_args.extend(args.iter().cloned());
+            DeployCommand::try_parse_from(deploy_args)?.run().await?;
+            Ok("deployed".to_owned())
+        }
+        Step::Link {
+            app,
+            label,
+            database,
+            replace,
+        } => {
+            let app_id = app_id(client, app).await?;
+            let plan = plan_sqlite_link(client, app_id, label, database).await?;
+            // A script cannot be asked, so as with `link sqlite` when not
+            // interactive, a label is only re-pointed if the script says so.
+            let summary = match &plan {
+                SqliteLinkPlan::Create => "linked".to_owned(),
+                SqliteLinkPlan::Replace(link) if *replace => {
+                    format!("relinked from {}", link.resource)
+                }
+                SqliteLinkPlan::Replace(link) => bail!(
+                    r#"Label "{label}" of app "{app}" is already linked to database "{}". Set `replace` to true on the step to relink it."#,
+                    link.resource
+                ),
+            };
+            apply_sqlite_link(client, app_id, label, database, plan).await?;
+            Ok(summary)
+        }
+        Step::ExecuteSql {
+            database,
+            statement,
+        } => {
+            let target = ExecuteTarget::Database(database.clone());
+            execute(client, &target, statement.clone()).await?;
+            Ok("executed".to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod run_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+    use cloud_openapi::models::{Database, ResourceLabel};
+
+    fn steps() -> Vec<Step> {
+        vec![
+            Step::CreateDatabase {
+                name: "todo-db".to_owned(),
+                region: None,
+            },
+            Step::ExecuteSql {
+                database: "missing".to_owned(),
+                statement: "SELECT 1".to_owned(),
+            },
+            Step::ExecuteSql {
+                database: "todo-db".to_owned(),
+                statement: "CREATE TABLE t (c)".to_owned(),
+            },
+        ]
+    }
+
+    fn mock() -> MockCloudClientInterface {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(|_| Ok(vec![Database::new("todo-db".to_owned(), vec![])]));
+        mock
+    }
+
+    #[tokio::test]
+    async fn steps_after_a_failure_are_skipped() {
+        let outcomes = run_steps(&mock(), &steps(), None, false).await;
+        assert!(matches!(&outcomes[0], StepOutcome::Succeeded(s) if s == "already exists"));
+        assert!(matches!(outcomes[1], StepOutcome::Failed(_)));
+        assert!(matches!(outcomes[2], StepOutcome::Skipped));
+    }
+
+    #[tokio::test]
+    async fn linked_labels_are_only_replaced_when_the_step_says_so() -> Result<()> {
+        let app_id = uuid::Uuid::new_v4();
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_apps().returning(move |_, _| {
+            Ok(cloud_openapi::models::AppItemPage {
+                items: vec![cloud_openapi::models::AppItem {
+                    id: app_id,
+                    name: "todo".to_owned(),
+                    ..Default::default()
+                }],
+                is_last_page: true,
+                ..Default::default()
+            })
+        });
+        mock.expect_get_databases().returning(move |_| {
+            Ok(vec![
+                Database::new(
+                    "old-db".to_owned(),
+                    vec![ResourceLabel {
+                        app_id,
+                        label: "default".to_owned(),
+                        app_name: Some("todo".to_owned()),
+                    }],
+                ),
+                Database::new("new-db".to_owned(), vec![]),
+            ])
+        });
+        mock.expect_remove_database_link()
+            .withf(|database, _| database == "old-db")
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mock.expect_create_database_link()
+            .withf(|database, _| database == "new-db")
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let link = |replace| Step::Link {
+            app: "todo".to_owned(),
+            label: "default".to_owned(),
+            database: "new-db".to_owned(),
+            replace,
+        };
+        let err = run_step(&mock, &link(false), None).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(r#"already linked to database "old-db""#));
+        assert_eq!(
+            run_step(&mock, &link(true), None).await?,
+            "relinked from old-db"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn steps_can_continue_past_a_failure() {
+        let mut mock = mock();
+        mock.expect_execute_sql()
+            .withf(|db, _| db == "todo-db")
+            .returning(|_, _| Ok(()));
+        let outcomes = run_steps(&mock, &steps(), None, true).await;
+        assert!(matches!(outcomes[1], StepOutcome::Failed(_)));
+        assert!(matches!(&outcomes[2], StepOutcome::Succeeded(s) if s == "executed"));
+    }
+}
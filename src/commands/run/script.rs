@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// A sequence of operations, read from a YAML, TOML or JSON file such as:
+///
+/// ```yaml
+/// step:
+///   - action: create-database
+///     name: todo-db
+///   - action: deploy
+///     from: .
+///     args: ["--build"]
+/// ```
+///
+/// or, in TOML:
+///
+/// ```toml
+/// [[step]]
+/// action = "create-database"
+/// name = "todo-db"
+///
+/// [[step]]
+/// action = "deploy"
+/// from = "."
+/// args = ["--build"]
+/// ```
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(super) struct Script {
+    #[serde(rename = "step", default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "kebab-case", deny_unknown_fields)]
+pub(super) enum Step {
+    /// Creates a database, unless one with the name already exists
+    CreateDatabase {
+        name: String,
+        region: Option<String>,
+    },
+    SetVariables {
+        app: String,
+        variables: BTreeMap<String, String>,
+    },
+    /// Deploys as `spin cloud deploy` would with the same options
+    Deploy {
+        from: Option<String>,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Links an app's label to a database. If the label is already linked
+    /// to another database the step fails, unless `replace` is set.
+    Link {
+        app: String,
+        label: String,
+        database: String,
+        #[serde(default)]
+        replace: bool,
+    },
+    ExecuteSql {
+        database: String,
+        statement: String,
+    },
+}
+
+impl Script {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let script: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&text)?,
+            Some("toml") => toml::from_str(&text)?,
+            Some("json") => serde_json::from_str(&text)?,
+            _ => bail!(
+                "Cannot tell the format of {}. Scripts must be .yaml, .toml or .json files",
+                path.display()
+            ),
+        };
+        if script.steps.is_empty() {
+            bail!("{} has no steps", path.display());
+        }
+        Ok(script)
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateDatabase { name, .. } => write!(f, "create database {name}"),
+            Self::SetVariables { app, variables } => {
+                write!(f, "set {} variable(s) of {app}", variables.len())
+            }
+            Self::Deploy { from, .. } => write!(f, "deploy {}", from.as_deref().unwrap_or(".")),
+            Self::Link {
+                app,
+                label,
+                database,
+                ..
+            } => write!(f, "link {app} {label} to {database}"),
+            Self::ExecuteSql { database, .. } => write!(f, "execute SQL on {database}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn steps_are_read_in_order() -> Result<()> {
+        let script: Script = toml::from_str(
+            r#"
+            [[step]]
+            action = "create-database"
+            name = "todo-db"
+
+            [[step]]
+            action = "link"
+            app = "todo"
+            label = "default"
+            database = "todo-db"
+
+            [[step]]
+            action = "set-variables"
+            app = "todo"
+            variables = { api_url = "https://example.com" }
+            "#,
+        )?;
+        assert_eq!(
+            script.steps[0],
+            Step::CreateDatabase {
+                name: "todo-db".to_owned(),
+                region: None
+            }
+        );
+        assert_eq!(script.steps[1].to_string(), "link todo default to todo-db");
+        assert_eq!(script.steps[2].to_string(), "set 1 variable(s) of todo");
+
+        let misspelt = toml::from_str::<Script>(
+            r#"
+            [[step]]
+            action = "execute-sql"
+            database = "todo-db"
+            statment = "SELECT 1"
+            "#,
+        );
+        assert!(misspelt.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_scripts_are_read() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("script.yaml");
+        std::fs::write(
+            &path,
+            r#"
+step:
+  - action: deploy
+    from: .
+    args: ["--build"]
+  - action: execute-sql
+    database: todo-db
+    statement: "INSERT INTO todos VALUES ('a: b')"
+"#,
+        )?;
+        let script = Script::from_file(&path)?;
+        assert_eq!(
+            script.steps,
+            vec![
+                Step::Deploy {
+                    from: Some(".".to_owned()),
+                    args: vec!["--build".to_owned()],
+                },
+                Step::ExecuteSql {
+                    database: "todo-db".to_owned(),
+                    statement: "INSERT INTO todos VALUES ('a: b')".to_owned(),
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Parser;
+use cloud::CloudClientInterface;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::task::JoinHandle;
+
+use crate::commands::logs::{fetch_entries, log_lines, parse_duration, parse_interval, LogLine};
+use crate::commands::{create_cloud_client, CommonArgs};
+use crate::ops::apps::{app_id, list_apps};
+use crate::ops::sqlite::query;
+use crate::opts::CLOUD_NON_INTERACTIVE_ENV;
+
+mod protocol;
+
+use protocol::{
+    notification, params, parse_request, response, RpcError, INVALID_PARAMS, METHOD_NOT_FOUND,
+};
+
+const DEFAULT_LOG_TAIL: i32 = 10;
+const DEFAULT_FOLLOW_INTERVAL_SECS: u64 = 2;
+
+/// Serve the plugin's operations over JSON-RPC, for editors and other tools
+///
+/// Requests and responses are JSON-RPC 2.0 messages, one per line. The
+/// methods are apps/list, sqlite/query, logs/fetch, logs/follow,
+/// logs/unfollow, deploy and shutdown. Followed logs and deploy progress are
+/// sent as logs/line and deploy/progress notifications.
+#[derive(Parser, Debug)]
+pub struct ServeApiCommand {
+    /// Read requests from stdin and write responses to stdout
+    #[clap(long = "stdio", takes_value = false, required = true)]
+    pub stdio: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl ServeApiCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let (out, mut outgoing) = unbounded_channel::<Value>();
+        let writer = tokio::spawn(async move {
+            let mut stdout = tokio::io::stdout();
+            while let Some(message) = outgoing.recv().await {
+                let line = format!("{message}\n");
+                if stdout.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+                let _ = stdout.flush().await;
+            }
+        });
+
+        let mut server = Server::new(Arc::new(client), out, self.common.deployment_env_id.clone());
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if server.handle_line(&line).await == Flow::Stop {
+                break;
+            }
+        }
+        // The writer stops once every sender is gone, which is after any
+        // deployments still running have replied.
+        server.stop();
+        drop(server);
+        let _ = writer.await;
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Continue,
+    Stop,
+}
+
+struct Server<C> {
+    client: Arc<C>,
+    out: UnboundedSender<Value>,
+    deployment_env_id: Option<String>,
+    /// Log following tasks, by app name
+    follows: HashMap<String, JoinHandle<()>>,
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    database: String,
+    statement: String,
+}
+
+#[derive(Deserialize)]
+struct LogsParams {
+    app: String,
+    /// How far back to fetch, such as "30m". Defaults to 7 days.
+    since: Option<String>,
+    tail: Option<i32>,
+    /// How often to poll for new lines when following. At least 2 seconds,
+    /// as with `spin cloud logs --interval`.
+    interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct DeployParams {
+    /// Options as they would be given to `spin cloud deploy`
+    #[serde(default)]
+    args: Vec<String>,
+    /// The directory to deploy from. Defaults to the server's own.
+    dir: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct LogLineJson {
+    time: String,
+    component: String,
+    line: String,
+}
+
+impl<C: CloudClientInterface + 'static> Server<C> {
+    fn new(client: Arc<C>, out: UnboundedSender<Value>, deployment_env_id: Option<String>) -> Self {
+        Self {
+            client,
+            out,
+            deployment_env_id,
+            follows: HashMap::new(),
+        }
+    }
+
+    /// Handles one line of input, replying unless it was a notification.
+    async fn handle_line(&mut self, line: &str) -> Flow {
+        let request = match parse_request(line) {
+            Ok(request) => request,
+            Err(error) => {
+                self.send(response(Value::Null, Err(error)));
+                return Flow::Continue;
+            }
+        };
+        let flow = if request.method == "shutdown" {
+            Flow::Stop
+        } else {
+            Flow::Continue
+        };
+        let id = request.id.clone();
+        match self.call(&request.method, request.params, id.clone()).await {
+            // The reply to a deploy is sent when it finishes.
+            Ok(None) => {}
+            Ok(Some(result)) => self.reply(id, Ok(result)),
+            Err(error) => self.reply(id, Err(error)),
+        }
+        flow
+    }
+
+    fn reply(&self, id: Option<Value>, result: Result<Value, RpcError>) {
+        if let Some(id) = id {
+            self.send(response(id, result));
+        }
+    }
+
+    fn send(&self, message: Value) {
+        // Only fails once the writer has stopped, when nobody is listening.
+        let _ = self.out.send(message);
+    }
+
+    async fn call(
+        &mut self,
+        method: &str,
+        params_value: Value,
+        id: Option<Value>,
+    ) -> Result<Option<Value>, RpcError> {
+        let result = match method {
+            "apps/list" => json!(list_apps(self.client.as_ref()).await?),
+            "sqlite/query" => {
+                let p: QueryParams = params(params_value)?;
+                json!(query(self.client.as_ref(), &p.database, p.statement).await?)
+            }
+            "logs/fetch" => {
+                let p: LogsParams = params(params_value)?;
+                json!({ "lines": self.fetch_logs(&p).await? })
+            }
+            "logs/follow" => {
+                let p: LogsParams = params(params_value)?;
+                self.follow_logs(p).await?
+            }
+            "logs/unfollow" => {
+                let p: LogsParams = params(params_value)?;
+                let followed = match self.follows.remove(&p.app) {
+                    Some(task) => {
+                        task.abort();
+                        true
+                    }
+                    None => false,
+                };
+                json!({ "app": p.app, "followed": followed })
+            }
+            "deploy" => {
+                let p: DeployParams = params(params_value)?;
+                self.deploy(p, id);
+                return Ok(None);
+            }
+            "shutdown" => Value::Null,
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("Method not found: {method}"),
+                ))
+            }
+        };
+        Ok(Some(result))
+    }
+
+    async fn fetch_logs(&self, p: &LogsParams) -> Result<Vec<LogLineJson>, RpcError> {
+        let since =
+            parse_duration(p.since.as_deref().unwrap_or("7d")).map_err(RpcError::invalid_params)?;
+        let since = (Utc::now() - since).to_rfc3339();
+        let app_id = app_id(self.client.as_ref(), &p.app).await?;
+        let entries = fetch_entries(
+            self.client.as_ref(),
+            app_id,
+            Some(p.tail.unwrap_or(DEFAULT_LOG_TAIL)),
+            since,
+        )
+        .await?;
+        Ok(log_lines(&entries).map(LogLineJson::from).collect())
+    }
+
+    async fn follow_logs(&mut self, p: LogsParams) -> Result<Value, RpcError> {
+        if self.follows.contains_key(&p.app) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!(r#"Logs of app "{}" are already being followed"#, p.app),
+            ));
+        }
+        let interval = p
+            .interval_secs
+            .unwrap_or(DEFAULT_FOLLOW_INTERVAL_SECS)
+            .to_string();
+        let interval = parse_interval(&interval).map_err(RpcError::invalid_params)?;
+        let app_id = app_id(self.client.as_ref(), &p.app).await?;
+        let client = self.client.clone();
+        let out = self.out.clone();
+        let app = p.app.clone();
+        let task = tokio::spawn(async move {
+            let mut since = Utc::now().to_rfc3339();
+            loop {
+                tokio::time::sleep(interval).await;
+                match fetch_entries(client.as_ref(), app_id, None, since.clone()).await {
+                    Ok(entries) => {
+                        for line in log_lines(&entries).map(LogLineJson::from) {
+                            since = line.time.clone();
+                            let _ = out.send(notification(
+                                "logs/line",
+                                json!({ "app": app, "line": line }),
+                            ));
+                        }
+                    }
+                    // Carried on from where it left off at the next interval
+                    Err(e) => {
+                        let _ = out.send(notification(
+                            "logs/error",
+                            json!({ "app": app, "message": format!("{e:#}") }),
+                        ));
+                    }
+                }
+            }
+        });
+        self.follows.insert(p.app.clone(), task);
+        Ok(json!({ "app": p.app, "followed": true }))
+    }
+
+    // Deploys by running the plugin itself, so that a deployment behaves
+    // exactly as `spin cloud deploy` does. The reply to the request is sent
+    // when the deployment finishes.
+    fn deploy(&self, p: DeployParams, id: Option<Value>) {
+        let out = self.out.clone();
+        let deployment_env_id = self.deployment_env_id.clone();
+        tokio::spawn(async move {
+            let result = run_deploy(p, deployment_env_id, id.clone(), &out)
+                .await
+                .map_err(RpcError::from);
+            if let Some(id) = id {
+                let _ = out.send(response(id, result));
+            }
+        });
+    }
+
+    fn stop(&mut self) {
+        for (_, task) in self.follows.drain() {
+            task.abort();
+        }
+    }
+}
+
+async fn run_deploy(
+    p: DeployParams,
+    deployment_env_id: Option<String>,
+    id: Option<Value>,
+    out: &UnboundedSender<Value>,
+) -> Result<Value> {
+    let exe = std::env::current_exe().context("Could not find the plugin executable")?;
+    let mut command = tokio::process::Command::new(exe);
+    command
+        .arg("deploy")
+        .args(["--progress", "json"])
+        .args(&p.args)
+        .env(CLOUD_NON_INTERACTIVE_ENV, "true")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(env) = deployment_env_id {
+        command.args(["--environment-name", &env]);
+    }
+    if let Some(dir) = &p.dir {
+        command.current_dir(dir);
+    }
+    let mut child = command.spawn().context("Could not start deployment")?;
+    let stdout = child.stdout.take().context("No output from deployment")?;
+    let stderr = child.stderr.take().context("No output from deployment")?;
+
+    let forward_progress = async {
+        let mut messages = vec![];
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Value>(&line) {
+                Ok(event) if event.is_object() => {
+                    let _ = out.send(notification(
+                        "deploy/progress",
+                        json!({ "id": id, "event": event }),
+                    ));
+                }
+                _ => messages.push(line),
+            }
+        }
+        messages
+    };
+    let (output, messages) = tokio::join!(read_lines(stdout), forward_progress);
+    let status = child.wait().await?;
+    Ok(json!({
+        "success": status.success(),
+        "exitCode": status.code(),
+        "output": output,
+        "messages": messages,
+    }))
+}
+
+async fn read_lines(stream: impl AsyncRead + Unpin) -> Vec<String> {
+    let mut lines = BufReader::new(stream).lines();
+    let mut read = vec![];
+    while let Ok(Some(line)) = lines.next_line().await {
+        read.push(line);
+    }
+    read
+}
+
+impl From<LogLine<'_>> for LogLineJson {
+    fn from(line: LogLine) -> Self {
+        Self {
+            time: line.time.to_owned(),
+            component: line.component.to_owned(),
+            line: line.line.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod serve_api_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+    use cloud_openapi::models::AppItem;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    fn server(
+        mock: MockCloudClientInterface,
+    ) -> (Server<MockCloudClientInterface>, UnboundedReceiver<Value>) {
+        let (out, received) = unbounded_channel();
+        (Server::new(Arc::new(mock), out, None), received)
+    }
+
+    #[tokio::test]
+    async fn requests_get_replies_and_notifications_do_not() {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_apps().returning(|_, _| {
+            Ok(cloud_openapi::models::AppItemPage {
+                items: vec![AppItem {
+                    name: "todo".to_owned(),
+                    ..Default::default()
+                }],
+                is_last_page: true,
+                ..Default::default()
+            })
+        });
+        let (mut server, mut received) = server(mock);
+
+        server
+            .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"apps/list"}"#)
+            .await;
+        let reply = received.try_recv().unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"][0]["name"], "todo");
+
+        server
+            .handle_line(r#"{"jsonrpc":"2.0","method":"apps/list"}"#)
+            .await;
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn bad_requests_get_errors() {
+        let (mut server, mut received) = server(MockCloudClientInterface::new());
+
+        server.handle_line("not json").await;
+        assert_eq!(received.try_recv().unwrap()["error"]["code"], -32700);
+
+        server
+            .handle_line(r#"{"jsonrpc":"2.0","id":"a","method":"apps/destroy"}"#)
+            .await;
+        let reply = received.try_recv().unwrap();
+        assert_eq!(reply["id"], "a");
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+
+        server
+            .handle_line(r#"{"jsonrpc":"2.0","id":2,"method":"sqlite/query","params":{}}"#)
+            .await;
+        assert_eq!(
+            received.try_recv().unwrap()["error"]["code"],
+            INVALID_PARAMS
+        );
+
+        // Validated before anything is asked of the API
+        server
+            .handle_line(
+                r#"{"jsonrpc":"2.0","id":4,"method":"logs/follow","params":{"app":"todo","interval_secs":0}}"#,
+            )
+            .await;
+        let reply = received.try_recv().unwrap();
+        assert_eq!(reply["error"]["code"], INVALID_PARAMS);
+        assert_eq!(
+            reply["error"]["message"],
+            "Invalid params: interval cannot be less than 2 seconds"
+        );
+
+        assert_eq!(
+            server
+                .handle_line(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#)
+                .await,
+            Flow::Stop
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const JSONRPC_VERSION: &str = "2.0";
+
+// Error codes defined by JSON-RPC 2.0
+pub(super) const PARSE_ERROR: i64 = -32700;
+pub(super) const INVALID_REQUEST: i64 = -32600;
+pub(super) const METHOD_NOT_FOUND: i64 = -32601;
+pub(super) const INVALID_PARAMS: i64 = -32602;
+/// The method was understood, but the operation it asked for failed
+pub(super) const OPERATION_FAILED: i64 = -32000;
+
+/// A request or, without an `id`, a notification.
+#[derive(Debug, Deserialize)]
+pub(super) struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_params(e: impl std::fmt::Display) -> Self {
+        Self::new(INVALID_PARAMS, format!("Invalid params: {e}"))
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(OPERATION_FAILED, format!("{e:#}"))
+    }
+}
+
+/// Reads one line of input as a request, or the error to reply with if it
+/// is not one. The reply to an unreadable request has a null `id`.
+pub(super) fn parse_request(line: &str) -> Result<Request, RpcError> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| RpcError::new(PARSE_ERROR, format!("Parse error: {e}")))?;
+    let request: Request = serde_json::from_value(value)
+        .map_err(|e| RpcError::new(INVALID_REQUEST, format!("Invalid request: {e}")))?;
+    if request.jsonrpc != JSONRPC_VERSION {
+        return Err(RpcError::new(
+            INVALID_REQUEST,
+            format!(r#"Invalid request: jsonrpc must be "{JSONRPC_VERSION}""#),
+        ));
+    }
+    Ok(request)
+}
+
+/// Reads a method's params into `T`, treating absent params as an empty object.
+pub(super) fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+pub(super) fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": JSONRPC_VERSION, "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": JSONRPC_VERSION, "id": id, "error": error }),
+    }
+}
+
+pub(super) fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": JSONRPC_VERSION, "method": method, "params": params })
+}
@@ -1,32 +1,132 @@
-use crate::commands::create_cloud_client;
-use crate::commands::link::Link;
+use crate::answers;
+use crate::commands::apps::load_active_revision;
+use crate::commands::deploy::{login_connection, DeployableApp};
+use crate::commands::link::SqliteLinkCommand;
+use crate::commands::{confirm_environment, create_cloud_client};
+use crate::diff::{unified, use_color, Diff, DiffFormat};
+use crate::local_db::LocalDatabase;
+use crate::ops::apps::app_id;
+use crate::ops::link::Link;
+use crate::ops::resolve::{find_by_name, not_found};
+use crate::ops::sqlite::{
+    app_database_links, check_rename, compare_labels, create_database, delete_database,
+    describe_tables, execute, execute_on_each, execute_transaction, find_database, list_databases,
+    list_tables, query, quote_identifier, returns_rows, row_count, BroadcastOutcome, ExecuteTarget,
+    LabelState, LabelStatus, TableSchema,
+};
 use crate::opts::*;
+use crate::progress::{Progress, ProgressFormat};
+use crate::project_config::{ProjectConfig, PROJECT_CONFIG_FILE};
+use crate::table::new_table;
 use anyhow::bail;
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::{Args, Parser, ValueEnum};
+use cloud::client::{Client as CloudClient, ConnectionConfig};
+use cloud::models::{DatabaseMetadata, QueryResult};
 use cloud::CloudClientInterface;
 use cloud_openapi::models::Database;
 use cloud_openapi::models::ResourceLabel;
-use comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED;
-use dialoguer::Input;
 use serde::Serialize;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+mod backup;
+mod dump;
+mod idempotent;
+mod import;
+mod migrate;
+mod paging;
+mod params;
+mod read_only;
+mod schema_diff;
+mod shell;
+
+/// The exit status when `sqlite execute --timeout` gives up on a statement,
+/// as the `timeout` command uses
+pub const STATEMENT_TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// The error when `sqlite execute --timeout` gives up on a statement. The
+/// plugin exits with [`STATEMENT_TIMEOUT_EXIT_CODE`] for it.
+#[derive(Debug)]
+pub struct StatementTimedOut {
+    secs: u64,
+}
+
+impl std::fmt::Display for StatementTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The statement did not finish within {}s", self.secs)
+    }
+}
+
+impl std::error::Error for StatementTimedOut {}
+
 /// Manage Fermyon Cloud SQLite databases
 #[derive(Parser, Debug)]
 #[clap(about = "Manage Fermyon Cloud SQLite databases")]
 pub enum SqliteCommand {
+    /// Take, list and restore backups of SQLite databases kept on this machine
+    #[clap(subcommand)]
+    Backup(BackupCommand),
+    /// Copy the tables and rows of a SQLite database into a new database
+    Copy(CopyCommand),
     /// Create a SQLite database
     Create(CreateCommand),
     /// Delete a SQLite database
     Delete(DeleteCommand),
+    /// Compare the schemas of two SQLite databases
+    Diff(DiffCommand),
+    /// Write a SQL script which recreates a SQLite database and its rows
+    Dump(DumpCommand),
     /// Execute SQL statements against a SQLite database
     Execute(ExecuteCommand),
+    /// Export the tables of a SQLite database as CSV or JSON files
+    Export(ExportCommand),
+    /// Load a SQL script or a CSV file into a SQLite database
+    Import(ImportCommand),
+    /// List the labels by which apps refer to a database, or compare the
+    /// database labels an app's deployed revision declares with those linked
+    /// to databases
+    Labels(LabelsCommand),
     /// List all your SQLite databases
     List(ListCommand),
+    /// Apply the migration files in a directory to a SQLite database
+    #[clap(subcommand)]
+    Migrate(MigrateCommand),
     /// Rename a SQLite database
     Rename(RenameCommand),
+    /// Show the columns and indexes of the tables in a SQLite database
+    Schema(SchemaCommand),
+    /// Open an interactive SQL prompt against a SQLite database
+    Shell(ShellCommand),
+    /// Show the size, tables, row counts and links of a SQLite database
+    Stats(StatsCommand),
+    /// List the tables in a SQLite database
+    Tables(TablesCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct CopyCommand {
+    /// Name of database to copy
+    source: String,
+
+    /// Name of the database to create as the copy. It must not exist yet.
+    target: String,
+
+    /// Region to create the copy in. If omitted, the platform's default
+    /// region is used.
+    #[clap(long = "region")]
+    region: Option<String>,
+
+    /// How to report progress. With json, progress events are written to
+    /// stderr as newline-delimited JSON.
+    #[clap(value_enum, long = "progress", default_value = "text")]
+    progress: ProgressFormat,
+
+    #[clap(flatten)]
+    common: CommonArgs,
 }
 
 #[derive(Parser, Debug)]
@@ -34,45 +134,395 @@ pub struct CreateCommand {
     /// Name of database to create
     name: String,
 
+    /// Region to create the database in. Run `spin cloud regions` to see
+    /// the choices. If omitted, the platform's default region is used.
+    #[clap(long = "region")]
+    region: Option<String>,
+
+    /// Succeed without creating anything if the database already exists
+    #[clap(long = "if-not-exists", takes_value = false)]
+    if_not_exists: bool,
+
+    /// Link the database to an app once it is created, given as APP:LABEL.
+    /// Without a label, as in APP, the label is chosen as `spin cloud link
+    /// sqlite` chooses it.
+    #[clap(long = "link", value_name = "APP:LABEL", value_parser = parse_app_label)]
+    link: Option<(String, Option<String>)>,
+
     #[clap(flatten)]
     common: CommonArgs,
 }
 
+fn parse_app_label(s: &str) -> Result<(String, Option<String>), String> {
+    let (app, label) = match s.split_once(':') {
+        Some((app, label)) => (app, Some(label)),
+        None => (s, None),
+    };
+    if app.is_empty() || label.is_some_and(str::is_empty) {
+        return Err(format!(
+            "'{s}' should be an app and label, such as 'todo:default'"
+        ));
+    }
+    Ok((app.to_owned(), label.map(str::to_owned)))
+}
+
 #[derive(Parser, Debug)]
 pub struct DeleteCommand {
-    /// Name of database to delete
-    name: String,
+    /// Names of databases to delete
+    #[clap(required_unless_present = "pattern")]
+    names: Vec<String>,
+
+    /// Delete every database whose name matches this glob pattern, such as
+    /// 'test-*'. The databases are listed and you are asked to confirm once,
+    /// unless --yes is given.
+    #[clap(long = "match", value_parser = glob::Pattern::new, conflicts_with = "names")]
+    pattern: Option<glob::Pattern>,
 
     /// Skips prompt to confirm deletion of database
     #[clap(short = 'y', long = "yes", takes_value = false)]
     yes: bool,
 
+    /// Succeed without deleting anything if a named database does not exist
+    #[clap(long = "if-exists", takes_value = false)]
+    if_exists: bool,
+
     #[clap(flatten)]
     common: CommonArgs,
 }
 
 #[derive(Parser, Debug)]
 pub struct ExecuteCommand {
-    /// Name of database to execute against
-    #[clap(name = "DATABASE", short = 'd', long = "database", value_parser = clap::builder::ValueParser::new(disallow_empty), group = "db", required_unless_present = "LABEL")]
-    database: Option<String>,
+    /// Name of database to execute against. Can be used multiple times to
+    /// run the statement against each database in turn. If no database,
+    /// label or app is given, the default_database set in the project's
+    /// spin-cloud.toml is used.
+    #[clap(name = "DATABASE", short = 'd', long = "database", value_parser = clap::builder::ValueParser::new(disallow_empty), group = "db")]
+    database: Vec<String>,
+
+    /// Run the statement against every database in the account
+    #[clap(long = "all-databases", group = "db", conflicts_with_all = &["APP", "to-local"])]
+    all_databases: bool,
+
+    /// When running against several databases, carry on with the rest after
+    /// one fails instead of stopping
+    #[clap(long = "continue-on-error")]
+    continue_on_error: bool,
 
     /// Label of database to execute against
-    #[clap(name = "LABEL", short = 'l', long = "label", value_parser = clap::builder::ValueParser::new(disallow_empty), group = "db", requires = "APP", required_unless_present = "DATABASE")]
+    #[clap(name = "LABEL", short = 'l', long = "label", value_parser = clap::builder::ValueParser::new(disallow_empty), group = "db", requires = "APP")]
     label: Option<String>,
 
-    /// App to which label relates
-    #[clap(name = "APP", short = 'a', long = "app", value_parser = clap::builder::ValueParser::new(disallow_empty), requires = "LABEL", conflicts_with = "DATABASE")]
+    /// App to which label relates. If no label is given, the app's only
+    /// linked database is used, or you are asked to choose one.
+    #[clap(name = "APP", short = 'a', long = "app", value_parser = clap::builder::ValueParser::new(disallow_empty), conflicts_with = "DATABASE")]
     app: Option<String>,
 
+    /// Never prompt. An app with more than one linked database then
+    /// requires a label.
+    #[clap(long = "non-interactive", takes_value = false)]
+    non_interactive: bool,
+
     ///Statement to execute
-    #[clap(value_parser = clap::builder::ValueParser::new(disallow_empty))]
-    statement: String,
+    #[clap(value_parser = clap::builder::ValueParser::new(disallow_empty), required_unless_present = "to-local")]
+    statement: Option<String>,
+
+    /// Write the rows selected by the statement into a table of this local
+    /// SQLite file, creating the file if needed. Without a statement, every
+    /// table of the database is copied.
+    #[clap(long = "to-local")]
+    to_local: Option<PathBuf>,
+
+    /// Local table to write the statement's rows into
+    #[clap(long = "local-table", default_value = "results", requires = "to-local")]
+    local_table: String,
+
+    /// Format of the rows selected by a query
+    #[clap(
+        value_enum,
+        long = "format",
+        default_value = "table",
+        conflicts_with = "to-local"
+    )]
+    format: ResultFormat,
+
+    /// Write the rows selected by a query to this file, in the chosen
+    /// format, instead of to stdout. Its directory is created if needed.
+    #[clap(
+        short = 'o',
+        long = "output",
+        conflicts_with_all = &["to-local", "transaction", "all-databases"]
+    )]
+    output: Option<PathBuf>,
+
+    /// Run the statements of the script as one transaction, so that if one
+    /// fails none of them take effect. Rows selected by the script are not
+    /// shown.
+    #[clap(
+        long = "transaction",
+        takes_value = false,
+        conflicts_with_all = &["to-local", "all-databases"]
+    )]
+    transaction: bool,
+
+    /// Bind a value to a named parameter in the statement, written :name,
+    /// @name or $name. The value is bound as text, quoted so that it cannot
+    /// change the statement. Can be used multiple times.
+    #[clap(long = "param", value_name = "NAME=VALUE", value_parser = params::parse_param, requires = "statement")]
+    params: Vec<(String, serde_json::Value)>,
+
+    /// Like --param, but the value is JSON, so that numbers and null are
+    /// bound as themselves rather than as text
+    #[clap(long = "param-json", value_name = "NAME=JSON", value_parser = params::parse_json_param, requires = "statement")]
+    json_params: Vec<(String, serde_json::Value)>,
+
+    /// Give up on the statement if it has not finished after this many
+    /// seconds, asking the platform to cancel it, and exit with status 124
+    #[clap(long = "timeout", value_name = "SECS")]
+    timeout_secs: Option<u64>,
+
+    /// Fetch the rows selected by a query this many at a time, writing each
+    /// batch as it arrives rather than waiting for every row. Add an ORDER
+    /// BY so that the batches do not overlap.
+    #[clap(
+        long = "page-size",
+        value_name = "ROWS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with_all = &["to-local", "transaction", "all-databases"]
+    )]
+    page_size: Option<u64>,
+
+    /// Refuse to run anything but SELECT, VALUES, EXPLAIN and PRAGMA
+    /// statements which only read, checked before the statement is sent.
+    /// Useful for dashboards and support tooling, where writes must not
+    /// happen by accident.
+    #[clap(long = "read-only", takes_value = false)]
+    read_only: bool,
+
+    /// Rewrite CREATE TABLE, INDEX, VIEW and TRIGGER statements to CREATE
+    /// ... IF NOT EXISTS before running them, so that a setup script can be
+    /// run again. The rewritten statements are shown first.
+    #[clap(long = "if-not-exists", takes_value = false, requires = "statement")]
+    if_not_exists: bool,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub enum BackupCommand {
+    /// Dump a database to a backup file
+    Create(BackupCreateCommand),
+    /// List the backups taken on this machine
+    List(BackupListCommand),
+    /// Restore a backup into a new or existing database
+    Restore(BackupRestoreCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct BackupCreateCommand {
+    /// Name of database to back up
+    name: String,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct BackupListCommand {
+    /// Only list backups of this database
+    name: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BackupRestoreCommand {
+    /// Name of the database the backup was taken of
+    name: String,
+
+    /// The backup to restore. If omitted, the latest backup is restored.
+    #[clap(long = "backup")]
+    backup: Option<String>,
+
+    /// Restore into this database instead, creating it if it does not exist
+    #[clap(long = "to")]
+    to: Option<String>,
+
+    /// Skip the prompt to confirm replacing the tables of an existing database
+    #[clap(short = 'y', long = "yes", takes_value = false)]
+    yes: bool,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub enum MigrateCommand {
+    /// Apply the migrations not yet applied to a database, in order
+    Up(MigrateUpCommand),
+    /// Show which migrations have been applied to a database
+    Status(MigrateStatusCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct MigrateUpCommand {
+    /// Name of database to migrate
+    name: String,
+
+    /// Directory of migration files, named <version>_<description>.sql
+    #[clap(long = "dir", default_value = "migrations")]
+    dir: PathBuf,
+
+    /// List the migrations which would be applied, without applying them
+    #[clap(long = "dry-run", takes_value = false)]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct MigrateStatusCommand {
+    /// Name of database to show the migrations of
+    name: String,
+
+    /// Directory of migration files, named <version>_<description>.sql
+    #[clap(long = "dir", default_value = "migrations")]
+    dir: PathBuf,
+
+    /// Format of list
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    format: ListFormat,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct DiffCommand {
+    /// Name of the database to compare from, such as staging
+    first: String,
+
+    /// Name of the database to compare with, such as production. Changes
+    /// are shown from the first database to this one.
+    second: String,
+
+    /// Format of the differences. Text is a unified diff of the schemas;
+    /// json lists each table, index, trigger or view which was added,
+    /// removed or changed.
+    #[clap(value_enum, long = "format", default_value = "text")]
+    format: DiffFormat,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct DumpCommand {
+    /// Name of database to dump
+    name: String,
+
+    /// File to write the script to. If omitted, it is written to stdout.
+    #[clap(short = 'o', long = "output")]
+    output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportCommand {
+    /// Name of database to export
+    name: String,
+
+    /// Format of the exported files
+    #[clap(value_enum, long = "format", default_value = "csv")]
+    format: ExportFormat,
+
+    /// Table to export. Can be used multiple times. If omitted, every table is exported.
+    #[clap(short = 't', long = "table")]
+    tables: Vec<String>,
+
+    /// Directory to write the files to, one per table [default: <NAME>-export]
+    #[clap(short = 'o', long = "output-dir")]
+    output_dir: Option<PathBuf>,
+
+    /// How to report progress. With json, progress events are written to
+    /// stderr as newline-delimited JSON.
+    #[clap(value_enum, long = "progress", default_value = "text")]
+    progress: ProgressFormat,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ImportCommand {
+    /// Name of database to import into
+    name: String,
+
+    /// The file to import. Files ending in .csv are read as CSV, anything
+    /// else as a SQL script.
+    file: PathBuf,
+
+    /// Table to load CSV records into. It is created if the CSV file has a
+    /// header row and the table does not exist.
+    #[clap(short = 't', long = "table")]
+    table: Option<String>,
+
+    /// Treat the first CSV record as data even if it looks like a header
+    #[clap(long = "no-header", takes_value = false, requires = "table")]
+    no_header: bool,
+
+    /// What to do about tables which the import creates but which already
+    /// exist in the database
+    #[clap(value_enum, long = "on-conflict", default_value = "fail")]
+    on_conflict: OnConflict,
+
+    /// Number of statements to send in each request
+    #[clap(long = "batch-size", default_value = "100")]
+    batch_size: usize,
+
+    /// Carry on with the remaining statements after a batch fails instead
+    /// of stopping
+    #[clap(long = "continue-on-error")]
+    continue_on_error: bool,
+
+    /// How to report progress. With json, progress events are written to
+    /// stderr as newline-delimited JSON.
+    #[clap(value_enum, long = "progress", default_value = "text")]
+    progress: ProgressFormat,
 
     #[clap(flatten)]
     common: CommonArgs,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum OnConflict {
+    /// Import nothing if any of the tables exists
+    Fail,
+    /// Leave existing tables, and the rows for them, out of the import
+    Skip,
+    /// Drop existing tables and create them again from the import
+    Replace,
+    /// Keep existing tables, adding the columns they lack, and insert the
+    /// imported rows into them
+    MergeSchema,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct RenameCommand {
     /// Current name of database to rename
@@ -81,6 +531,72 @@ pub struct RenameCommand {
     /// New name for the database
     new_name: String,
 
+    /// Skip the prompt to confirm renaming a database linked to apps
+    #[clap(short = 'y', long = "yes", takes_value = false)]
+    yes: bool,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ShellCommand {
+    /// Name of database to open. If neither a database nor a label is given,
+    /// the default_database set in the project's spin-cloud.toml is used.
+    #[clap(name = "DATABASE", short = 'd', long = "database", value_parser = clap::builder::ValueParser::new(disallow_empty), group = "db")]
+    database: Option<String>,
+
+    /// Label of database to open
+    #[clap(name = "LABEL", short = 'l', long = "label", value_parser = clap::builder::ValueParser::new(disallow_empty), group = "db", requires = "APP")]
+    label: Option<String>,
+
+    /// App to which label relates
+    #[clap(name = "APP", short = 'a', long = "app", value_parser = clap::builder::ValueParser::new(disallow_empty), requires = "LABEL")]
+    app: Option<String>,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct StatsCommand {
+    /// Name of database to show statistics for
+    name: String,
+
+    /// Format of statistics
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    format: ListFormat,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct TablesCommand {
+    /// Name of database whose tables to list
+    name: String,
+
+    /// Format of list
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    format: ListFormat,
+
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SchemaCommand {
+    /// Name of database whose schema to show
+    name: String,
+
+    /// Only show this table
+    #[clap(short = 't', long = "table")]
+    table: Option<String>,
+
+    /// Format of schema
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    format: ListFormat,
+
     #[clap(flatten)]
     common: CommonArgs,
 }
@@ -102,14 +618,65 @@ pub struct ListCommand {
     /// Filter list by a database
     #[clap(short = 'd', long = "database")]
     database: Option<String>,
-    /// Grouping strategy of tabular list [default: app]
+    /// Grouping strategy of tabular list [default: app, or database if --sort-by is given]
     #[clap(value_enum, short = 'g', long = "group-by")]
     group_by: Option<GroupBy>,
     /// Format of list
-    #[clap(value_enum, long = "format", default_value = "table")]
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    format: ListFormat,
+    /// Only list databases created by the logged in user
+    #[clap(long = "mine", takes_value = false)]
+    mine: bool,
+    /// Only list databases created after this date (YYYY-MM-DD or RFC 3339)
+    #[clap(long = "created-after", value_parser = parse_date_filter)]
+    created_after: Option<DateTime<Utc>>,
+    /// Only list databases created before this date (YYYY-MM-DD or RFC 3339)
+    #[clap(long = "created-before", value_parser = parse_date_filter)]
+    created_before: Option<DateTime<Utc>>,
+    /// Order of listed databases. Size lists the largest first, age the
+    /// oldest first, and accessed the least recently used first.
+    #[clap(value_enum, long = "sort-by")]
+    sort_by: Option<SortBy>,
+    /// Only list databases which are not linked to any app
+    #[clap(long = "orphaned", takes_value = false, conflicts_with_all = &["linked", "app"])]
+    orphaned: bool,
+    /// Only list databases which are linked to at least one app
+    #[clap(long = "linked", takes_value = false)]
+    linked: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LabelsCommand {
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// The database whose labels to list. Takes precedence over --app, so
+    /// that an app set with CLOUD_APP does not get in the way.
+    database: Option<String>,
+    /// The app whose labels to compare. Ignored if a database is given.
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    app: Option<String>,
+    /// Format of list
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
     format: ListFormat,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+enum SortBy {
+    Name,
+    Size,
+    Age,
+    Accessed,
+}
+
+fn parse_date_filter(date: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    DateTime::parse_from_rfc3339(date)
+        .map(|d| d.with_timezone(&Utc))
+        .context("expected a date such as 2024-01-31 or 2024-01-31T12:00:00Z")
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum, Default)]
 enum GroupBy {
     #[default]
@@ -138,10 +705,22 @@ impl FromStr for GroupBy {
     }
 }
 
-#[derive(ValueEnum, Clone, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
 pub enum ListFormat {
     Table,
     Json,
+    /// GitHub-flavored Markdown tables, for pasting into issues and runbooks
+    Markdown,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ResultFormat {
+    Table,
+    /// A JSON array with one object per row
+    Json,
+    Csv,
+    /// A Markdown table, for pasting into issues and incident notes
+    Markdown,
 }
 
 #[derive(Debug, Default, Args)]
@@ -160,521 +739,3400 @@ struct CommonArgs {
 impl SqliteCommand {
     pub async fn run(self) -> Result<()> {
         match self {
+            Self::Backup(cmd) => cmd.run().await,
+            Self::Copy(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
             Self::Create(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
                 let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
                 cmd.run(client).await
             }
             Self::Delete(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
                 let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
                 cmd.run(client).await
             }
             Self::Execute(cmd) => {
+                // Read-only statements cannot change anything, like dump
+                if !cmd.read_only {
+                    confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                }
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Dump(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Diff(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Export(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Import(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
                 let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
                 cmd.run(client).await
             }
+            Self::Labels(cmd) => cmd.run().await,
             Self::List(cmd) => cmd.run().await,
-            Self::Rename(cmd) => cmd.run().await,
+            Self::Migrate(cmd) => cmd.run().await,
+            Self::Rename(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Schema(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Shell(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Stats(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Tables(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
         }
     }
 }
 
-impl CreateCommand {
+impl CopyCommand {
     pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
-        let list = client
-            .get_databases(None)
-            .await
-            .context("Problem fetching databases")?;
-        if list.iter().any(|d| d.name == self.name) {
-            anyhow::bail!(r#"Database "{}" already exists"#, self.name)
+        let databases = list_databases(&client).await?;
+        find_by_name(databases.iter(), &self.source, "database", |d| &d.name)?;
+        if databases.iter().any(|d| d.name == self.target) {
+            bail!(r#"Database "{}" already exists"#, self.target);
         }
-        client
-            .create_database(self.name.clone(), None)
-            .await
-            .with_context(|| format!("Problem creating database {}", self.name))?;
-        println!("Database \"{}\" created", self.name);
+
+        let mut script = vec![];
+        let summary = dump::dump(&client, &self.source, &mut script).await?;
+        let statements = backup::restore_statements(&String::from_utf8(script)?);
+
+        create_database(&client, &self.target, self.region.as_deref()).await?;
+        let failures = import::execute_batches(
+            &client,
+            &self.target,
+            import::batches(&statements, 100),
+            statements.len(),
+            false,
+            &Progress::new(self.progress),
+        )
+        .await;
+        if let Some(failure) = failures.into_iter().next() {
+            return Err(failure.error.context(format!(
+                r#"Could not copy statements {}-{} into database "{}". The partial copy was kept; remove it with `spin cloud sqlite delete {}`"#,
+                failure.first, failure.last, self.target, self.target
+            )));
+        }
+        println!(
+            r#"Copied {} table(s) and {} row(s) from database "{}" into new database "{}""#,
+            summary.tables, summary.rows, self.source, self.target
+        );
         Ok(())
     }
 }
 
-impl DeleteCommand {
+impl CreateCommand {
     pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
-        let list = client
-            .get_databases(None)
-            .await
-            .context("Problem fetching databases")?;
-        let found = list.iter().find(|d| d.name == self.name);
-        match found {
-            None => anyhow::bail!("No database found with name \"{}\"", self.name),
-            Some(db) => {
-                // TODO: Fail if apps exist that are currently using a database
-                if self.yes || prompt_delete_database(&self.name, &db.links)? {
-                    client
-                        .delete_database(self.name.clone())
-                        .await
-                        .with_context(|| format!("Problem deleting database {}", self.name))?;
-                    println!("Database \"{}\" deleted", self.name);
-                }
+        // Found before creating anything, so that a mistyped app does not
+        // leave an unlinked database behind.
+        let link_app_id = match &self.link {
+            Some((app, _)) => Some(app_id(&client, app).await?),
+            None => None,
+        };
+        let existing = match self.if_not_exists {
+            true => list_databases(&client)
+                .await?
+                .into_iter()
+                .find(|d| d.name == self.name),
+            false => None,
+        };
+        match (&existing, &self.region) {
+            (Some(_), _) => println!("Database \"{}\" already exists", self.name),
+            (None, region) => {
+                create_database(&client, &self.name, region.as_deref()).await?;
+                match region {
+                    Some(region) => {
+                        println!("Database \"{}\" created in region {region}", self.name)
+                    }
+                    None => println!("Database \"{}\" created", self.name),
+                }
+            }
+        }
+        let (Some((app, label)), Some(app_id)) = (self.link, link_app_id) else {
+            return Ok(());
+        };
+        let already_linked = existing.is_some_and(|db| {
+            db.links.iter().any(|l| {
+                l.app_id == app_id && (label.is_none() || label.as_ref() == Some(&l.label))
+            })
+        });
+        if already_linked {
+            println!(
+                "Database \"{}\" is already linked to app \"{app}\"",
+                self.name
+            );
+            return Ok(());
+        }
+        SqliteLinkCommand::new(app, label, self.name)
+            .link(client, app_id)
+            .await
+    }
+}
+
+impl DeleteCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let databases = list_databases(&client).await?;
+        let targets = self.targets(&databases)?;
+        match (targets.as_slice(), &self.pattern) {
+            ([], Some(pattern)) => {
+                println!("No databases match '{pattern}'");
+                Ok(())
+            }
+            ([], None) => Ok(()),
+            ([db], None) => self.delete_one(&client, db).await,
+            _ => self.delete_many(&client, &targets).await,
+        }
+    }
+
+    /// The databases to delete. Every database named explicitly must exist,
+    /// unless --if-exists is given.
+    fn targets<'a>(&self, databases: &'a [Database]) -> Result<Vec<&'a Database>> {
+        if let Some(pattern) = &self.pattern {
+            return Ok(databases
+                .iter()
+                .filter(|d| pattern.matches(&d.name))
+                .collect());
+        }
+        let mut targets = vec![];
+        for name in &self.names {
+            match find_by_name(databases.iter(), name, "database", |d| &d.name) {
+                Ok(db) => targets.push(db),
+                Err(_) if self.if_exists => println!("Database \"{name}\" does not exist"),
+                Err(e) => return Err(e),
             }
         }
+        Ok(targets)
+    }
+
+    async fn delete_one(&self, client: &impl CloudClientInterface, db: &Database) -> Result<()> {
+        // TODO: Fail if apps exist that are currently using a database
+        if !self.yes && !EnvSettings::from_env().interactive() {
+            bail!(
+                "Use --yes to delete database \"{}\" without confirmation",
+                db.name
+            );
+        }
+        if self.yes || prompt_delete_database(&db.name, &db.links)? {
+            delete_database(client, &db.name).await?;
+            println!("Database \"{}\" deleted", db.name);
+        }
+        Ok(())
+    }
+
+    /// Deletes each database in turn after a single confirmation, carrying
+    /// on past failures.
+    async fn delete_many(
+        &self,
+        client: &impl CloudClientInterface,
+        targets: &[&Database],
+    ) -> Result<()> {
+        if !self.yes && !confirm_bulk_delete(targets)? {
+            println!("No databases deleted");
+            return Ok(());
+        }
+        let mut results = Vec::with_capacity(targets.len());
+        for db in targets {
+            results.push(delete_database(client, &db.name).await);
+        }
+        let mut table = new_table();
+        table.set_header(vec!["Database", "Result"]);
+        table.add_rows(
+            targets
+                .iter()
+                .zip(&results)
+                .map(|(db, result)| match result {
+                    Ok(()) => [db.name.clone(), "deleted".to_owned()],
+                    Err(e) => [db.name.clone(), format!("failed: {e:#}")],
+                }),
+        );
+        println!("{table}");
+        let failed = results.iter().filter(|r| r.is_err()).count();
+        if failed > 0 {
+            bail!(
+                "{failed} of {} databases could not be deleted",
+                results.len()
+            );
+        }
         Ok(())
     }
 }
 
+fn confirm_bulk_delete(targets: &[&Database]) -> Result<bool> {
+    if !EnvSettings::from_env().interactive() {
+        bail!(
+            "Use --yes to delete {} database(s) without confirmation",
+            targets.len()
+        );
+    }
+    println!("The following databases will be deleted:");
+    for db in targets {
+        let apps = db
+            .links
+            .iter()
+            .map(|l| l.app_name.as_deref().unwrap_or("UNKNOWN"))
+            .collect::<Vec<_>>();
+        match apps.as_slice() {
+            [] => println!("  {}", db.name),
+            apps => println!("  {} (linked to {})", db.name, apps.join(", ")),
+        }
+    }
+    answers::confirm(
+        &format!(
+            "Delete {} database(s)? This cannot be undone.",
+            targets.len()
+        ),
+        false,
+    )
+}
+
 impl ExecuteCommand {
     pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
-        let target = self.target()?;
-        let list = client
-            .get_databases(None)
-            .await
-            .context("Problem fetching databases")?;
-        let database = target.find_in(list)?.name;
-        let statement = if let Some(path) = self.statement.strip_prefix('@') {
-            std::fs::read_to_string(path)
-                .with_context(|| format!("could not read sql file at '{path}'"))?
+        let mut statement = self.statement()?;
+        if let Some(script) = statement.as_deref().filter(|_| self.if_not_exists) {
+            match self.add_if_not_exists(script)? {
+                Some(script) => statement = Some(script),
+                None => return Ok(()),
+            }
+        }
+        if let Some(statement) = statement.as_deref().filter(|_| self.read_only) {
+            read_only::check_read_only(statement)?;
+        }
+        let broadcast = self.all_databases || self.database.len() > 1;
+        // Resolved before the timeout starts, since it may ask which
+        // database to use.
+        let target = match broadcast {
+            true => None,
+            false => Some(self.target(&client).await?),
+        };
+        let run = self.run_statement(&client, target.as_ref(), statement);
+        let Some(secs) = self.timeout_secs else {
+            return run.await;
+        };
+        match tokio::time::timeout(std::time::Duration::from_secs(secs), run).await {
+            Ok(result) => result,
+            Err(_) => {
+                eprintln!("Cancelling the statement...");
+                if let Err(e) = self.cancel(&client, target.as_ref()).await {
+                    eprintln!("Could not cancel the statement on the server: {e:#}");
+                }
+                Err(StatementTimedOut { secs }.into())
+            }
+        }
+    }
+
+    /// The statement to run, read from a file if given as `@path`, with
+    /// any parameters bound.
+    fn statement(&self) -> Result<Option<String>> {
+        let statement = match self.statement.as_deref() {
+            Some(statement) => Some(match statement.strip_prefix('@') {
+                Some(path) => std::fs::read_to_string(path)
+                    .with_context(|| format!("could not read sql file at '{path}'"))?,
+                None => statement.to_owned(),
+            }),
+            None => None,
+        };
+        let statement = match statement {
+            Some(statement) if !self.params.is_empty() || !self.json_params.is_empty() => {
+                let params = self
+                    .params
+                    .iter()
+                    .chain(&self.json_params)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                Some(params::bind(&statement, &params)?)
+            }
+            statement => statement,
+        };
+        Ok(statement)
+    }
+
+    /// Rewrites the script's CREATE statements to add IF NOT EXISTS, showing
+    /// what changed. Returns `None` if the user decides not to run it.
+    fn add_if_not_exists(&self, script: &str) -> Result<Option<String>> {
+        let (script, rewrites) = idempotent::add_if_not_exists(script);
+        if rewrites.is_empty() {
+            eprintln!("No CREATE statements needed IF NOT EXISTS");
+            return Ok(Some(script));
+        }
+        eprintln!("Adding IF NOT EXISTS to {} statement(s):", rewrites.len());
+        for rewrite in &rewrites {
+            eprintln!("- {}\n+ {}", rewrite.before, rewrite.after);
+        }
+        if self.non_interactive || !EnvSettings::from_env().interactive() {
+            return Ok(Some(script));
+        }
+        match answers::confirm("Run the rewritten script?", true)? {
+            true => Ok(Some(script)),
+            false => {
+                println!("Nothing was executed");
+                Ok(None)
+            }
+        }
+    }
+
+    async fn run_statement(
+        &self,
+        client: &impl CloudClientInterface,
+        target: Option<&ExecuteTarget>,
+        statement: Option<String>,
+    ) -> Result<()> {
+        let Some(target) = target else {
+            if self.to_local.is_some() {
+                bail!("--to-local can only copy from one database");
+            }
+            if self.transaction {
+                bail!("--transaction can only run against one database");
+            }
+            if self.output.is_some() {
+                bail!("--output can only write the rows of one database");
+            }
+            if self.page_size.is_some() {
+                bail!("--page-size can only fetch the rows of one database");
+            }
+            let statement = statement.context("No statement to execute")?;
+            return self.broadcast(client, &statement).await;
+        };
+        if let Some(path) = &self.to_local {
+            return self.copy_to_local(client, target, statement, path).await;
+        }
+        // clap requires a statement unless copying to a local file
+        let statement = statement.context("No statement to execute")?;
+        if self.transaction {
+            let database = target.find_in(list_databases(client).await?)?.name;
+            let statements = import::split_statements(&statement);
+            execute_transaction(client, &database, &statements).await?;
+            println!(
+                "Executed {} statement(s) in one transaction",
+                statements.len()
+            );
+        } else if returns_rows(&statement) {
+            let database = target.find_in(list_databases(client).await?)?.name;
+            if let Some(page_size) = self.page_size {
+                return self
+                    .stream_rows(client, &database, &statement, page_size as usize)
+                    .await;
+            }
+            let result = query(client, &database, statement).await?;
+            match &self.output {
+                Some(path) => save_rows(path, &result, self.format)?,
+                None => print_rows(&result, self.format)?,
+            }
         } else {
-            self.statement
+            execute(client, target, statement).await?;
+        }
+        Ok(())
+    }
+
+    async fn stream_rows(
+        &self,
+        client: &impl CloudClientInterface,
+        database: &str,
+        statement: &str,
+        page_size: usize,
+    ) -> Result<()> {
+        let Some(path) = &self.output else {
+            let rows = paging::stream_query(
+                client,
+                database,
+                statement,
+                page_size,
+                &mut std::io::stdout().lock(),
+                self.format,
+            )
+            .await?;
+            if self.format == ResultFormat::Table {
+                eprintln!("{rows} row(s)");
+            }
+            return Ok(());
         };
-        client
-            .execute_sql(database, statement)
-            .await
-            .context("Problem executing SQL")?;
+        let mut out = create_output(path)?;
+        let rows = paging::stream_query(
+            client,
+            database,
+            statement,
+            page_size,
+            &mut out,
+            self.format,
+        )
+        .await
+        .with_context(|| format!("Could not write {}", path.display()))?;
+        eprintln!("Wrote {rows} row(s) to {}", path.display());
+        Ok(())
+    }
+
+    /// Asks the platform to cancel whatever is running on the databases the
+    /// statement was sent to.
+    async fn cancel(
+        &self,
+        client: &impl CloudClientInterface,
+        target: Option<&ExecuteTarget>,
+    ) -> Result<()> {
+        let databases = match target {
+            Some(target) => vec![target.find_in(list_databases(client).await?)?.name],
+            None if self.all_databases => list_databases(client)
+                .await?
+                .into_iter()
+                .map(|d| d.name)
+                .collect(),
+            None => self.database.clone(),
+        };
+        for database in databases {
+            client
+                .cancel_sql_statements(database.clone())
+                .await
+                .with_context(|| format!("Problem cancelling statements on {database}"))?;
+        }
+        Ok(())
+    }
+
+    async fn copy_to_local(
+        &self,
+        client: &impl CloudClientInterface,
+        target: &ExecuteTarget,
+        statement: Option<String>,
+        path: &Path,
+    ) -> Result<()> {
+        let database = target.find_in(list_databases(client).await?)?.name;
+        let tables = match statement {
+            Some(statement) => vec![(
+                self.local_table.clone(),
+                query(client, &database, statement).await?,
+            )],
+            None => {
+                let mut tables = vec![];
+                for table in list_tables(client, &database).await? {
+                    let result = query(
+                        client,
+                        &database,
+                        format!("SELECT * FROM {}", quote_identifier(&table)),
+                    )
+                    .await?;
+                    tables.push((table, result));
+                }
+                tables
+            }
+        };
+
+        let mut local = LocalDatabase::open(path)?;
+        for (table, result) in &tables {
+            local.write_table(table, result)?;
+            println!(
+                "Wrote {} row(s) to table \"{table}\" in {}",
+                result.rows.len(),
+                path.display()
+            );
+        }
+        Ok(())
+    }
+
+    async fn broadcast(&self, client: &impl CloudClientInterface, statement: &str) -> Result<()> {
+        let databases = list_databases(client).await?;
+        let names = if self.all_databases {
+            databases.into_iter().map(|d| d.name).collect()
+        } else {
+            // Check every name first, so that a typo does not leave the
+            // statement applied to only some of the databases.
+            for database in &self.database {
+                ExecuteTarget::Database(database.clone()).find_in(databases.clone())?;
+            }
+            self.database.clone()
+        };
+        if names.is_empty() {
+            bail!("There are no databases to execute the statement against");
+        }
+
+        let outcomes = execute_on_each(client, &names, statement, self.continue_on_error).await;
+        let mut table = new_table();
+        table.set_header(vec!["Database", "Result"]);
+        let mut failures = 0;
+        for (database, outcome) in &outcomes {
+            let result = match outcome {
+                BroadcastOutcome::Succeeded => "succeeded".to_owned(),
+                BroadcastOutcome::Failed(e) => {
+                    failures += 1;
+                    format!("failed: {e:#}")
+                }
+                BroadcastOutcome::Skipped => "skipped".to_owned(),
+            };
+            table.add_row(vec![database.as_str(), result.as_str()]);
+        }
+        println!("{table}");
+        if failures > 0 {
+            bail!(
+                "The statement failed on {failures} of {} databases",
+                outcomes.len()
+            );
+        }
+        println!(
+            "The statement succeeded on all {} databases",
+            outcomes.len()
+        );
         Ok(())
     }
 
-    fn target(&self) -> anyhow::Result<ExecuteTarget> {
-        match (&self.database, &self.label, &self.app) {
-            (Some(d), None, None) => Ok(ExecuteTarget::Database(d.to_owned())),
-            (None, Some(l), Some(a)) => Ok(ExecuteTarget::Label {
+    async fn target(&self, client: &impl CloudClientInterface) -> anyhow::Result<ExecuteTarget> {
+        match (self.database.as_slice(), &self.label, &self.app) {
+            ([d], None, None) => Ok(ExecuteTarget::Database(d.to_owned())),
+            ([], Some(l), Some(a)) => Ok(ExecuteTarget::Label {
                 label: l.to_owned(),
                 app: a.to_owned(),
             }),
+            ([], None, Some(a)) => self.infer_target(client, a).await,
+            ([], None, None) => Ok(ExecuteTarget::Database(project_default_database(
+                Path::new("."),
+            )?)),
             _ => Err(anyhow::anyhow!("Invalid combination of arguments")), // Should be prevented by clap
         }
     }
+
+    async fn infer_target(
+        &self,
+        client: &impl CloudClientInterface,
+        app: &str,
+    ) -> anyhow::Result<ExecuteTarget> {
+        let databases = list_databases(client).await?;
+        let mut links = app_database_links(&databases, app);
+        let link = match links.len() {
+            0 => bail!(r#"App "{app}" is not linked to any databases"#),
+            1 => links.remove(0),
+            _ if self.non_interactive || !EnvSettings::from_env().interactive() => bail!(
+                r#"App "{app}" is linked to more than one database. Use --label to choose one of: {}"#,
+                links
+                    .iter()
+                    .map(|l| l.resource_label.label.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            _ => {
+                let items = links
+                    .iter()
+                    .map(|l| format!("{} ({})", l.resource_label.label, l.resource))
+                    .collect::<Vec<_>>();
+                let index = answers::select(
+                    &format!(
+                        r#"App "{app}" is linked to several databases. Which label should the statement run against?"#
+                    ),
+                    &items,
+                    0,
+                )?
+                .context("No database selected")?;
+                links.remove(index)
+            }
+        };
+        Ok(ExecuteTarget::Label {
+            label: link.resource_label.label,
+            app: app.to_owned(),
+        })
+    }
+}
+
+impl ShellCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        if !EnvSettings::from_env().interactive() {
+            bail!("The SQL shell needs a terminal. Use `spin cloud sqlite execute` in scripts");
+        }
+        let target = match (self.database, self.label, self.app) {
+            (Some(database), _, _) => ExecuteTarget::Database(database),
+            (None, Some(label), Some(app)) => ExecuteTarget::Label { label, app },
+            (None, None, None) => {
+                ExecuteTarget::Database(project_default_database(Path::new("."))?)
+            }
+            _ => bail!("Invalid combination of arguments"), // Should be prevented by clap
+        };
+        let database = target.find_in(list_databases(&client).await?)?.name;
+        shell::run(&client, &database).await
+    }
 }
 
-enum ExecuteTarget {
-    Database(String),
-    Label { label: String, app: String },
+/// The database set as `default_database` in the project config in `dir`,
+/// for commands given no database on the command line. Options always take
+/// precedence over it.
+fn project_default_database(dir: &Path) -> Result<String> {
+    ProjectConfig::load_from_dir(dir)?
+        .default_database
+        .with_context(|| {
+            format!("No database given. Use --database, or set default_database in {PROJECT_CONFIG_FILE}")
+        })
 }
 
-impl ExecuteTarget {
-    fn find_in(&self, databases: Vec<Database>) -> anyhow::Result<Database> {
+impl BackupCommand {
+    pub async fn run(self) -> Result<()> {
         match self {
-            Self::Database(database) => databases
-                .into_iter()
-                .find(|d| &d.name == database)
-                .ok_or_else(|| anyhow::anyhow!("No database found with name \"{database}\"")),
-            Self::Label { label, app } => databases
-                .into_iter()
-                .find(|d| database_has_link(d, label, Some(app.as_str())))
-                .ok_or_else(|| {
-                    anyhow::anyhow!(r#"No database found with label "{label}" for app "{app}""#)
-                }),
+            Self::Create(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::List(cmd) => cmd.run(),
+            Self::Restore(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
         }
     }
 }
 
-impl ListCommand {
+impl MigrateCommand {
     pub async fn run(self) -> Result<()> {
-        if let (ListFormat::Json, Some(_)) = (&self.format, self.group_by) {
-            bail!("Grouping is not supported with JSON format output")
+        match self {
+            Self::Up(cmd) => {
+                if !cmd.dry_run {
+                    confirm_environment(cmd.common.deployment_env_id.as_deref())?;
+                }
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
+            Self::Status(cmd) => {
+                let client = create_cloud_client(cmd.common.deployment_env_id.as_deref()).await?;
+                cmd.run(client).await
+            }
         }
+    }
+}
 
-        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
-        let mut databases = client
-            .get_databases(None)
-            .await
-            .context("Problem listing databases")?;
-
-        if databases.is_empty() {
-            println!("No databases");
+impl MigrateUpCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let migrations = migrate::read_migrations(&self.dir)?;
+        find_database(&client, &self.name).await?;
+        let applied = migrate::applied_migrations(&client, &self.name).await?;
+        let pending = migrate::pending(&migrations, &applied)?;
+        if pending.is_empty() {
+            println!(r#"Database "{}" is up to date"#, self.name);
             return Ok(());
         }
-        if let Some(name) = &self.database {
-            databases.retain(|db| db.name == *name);
-            if databases.is_empty() {
-                println!("No database with name '{name}'");
-                return Ok(());
+        if self.dry_run {
+            println!(
+                r#"Would apply {} migration(s) to database "{}":"#,
+                pending.len(),
+                self.name
+            );
+            for migration in &pending {
+                println!(
+                    "  {} ({} statement(s))",
+                    migration.name,
+                    migration.statements.len()
+                );
             }
+            return Ok(());
+        }
+        for migration in &pending {
+            migrate::apply(&client, &self.name, migration).await?;
+            println!("Applied {}", migration.name);
         }
+        println!(
+            r#"Applied {} migration(s) to database "{}""#,
+            pending.len(),
+            self.name
+        );
+        Ok(())
+    }
+}
 
+impl MigrateStatusCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let migrations = migrate::read_migrations(&self.dir)?;
+        find_database(&client, &self.name).await?;
+        let applied = migrate::applied_migrations(&client, &self.name).await?;
+        let statuses = migrate::status(&migrations, &applied);
         match self.format {
-            ListFormat::Json => self.print_json(databases),
-            ListFormat::Table => self.print_table(databases),
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&statuses)?),
+            ListFormat::Table | ListFormat::Markdown if statuses.is_empty() => {
+                println!("No migrations in {}", self.dir.display())
+            }
+            ListFormat::Table | ListFormat::Markdown => {
+                let mut table = new_table();
+                table.set_header(vec!["Version", "Migration", "State", "Applied at"]);
+                table.add_rows(statuses.iter().map(|s| {
+                    [
+                        s.version.to_string(),
+                        s.name.clone(),
+                        match s.state {
+                            migrate::MigrationState::Applied => "applied",
+                            migrate::MigrationState::Pending => "pending",
+                            migrate::MigrationState::Changed => "changed since applied",
+                            migrate::MigrationState::Missing => "applied, file missing",
+                        }
+                        .to_owned(),
+                        s.applied_at.clone().unwrap_or_default(),
+                    ]
+                }));
+                println!("{table}");
+            }
         }
+        Ok(())
     }
+}
 
-    fn print_json(&self, mut databases: Vec<Database>) -> Result<()> {
-        if let Some(app) = &self.app {
-            databases.retain(|d| {
-                d.links
-                    .iter()
-                    .any(|l| l.app_name.as_deref().unwrap_or("UNKNOWN") == app)
-            });
+impl BackupCreateCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        find_database(&client, &self.name).await?;
+        let path = backup::new_backup_path(&backup::backups_dir()?, &self.name, Utc::now());
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create directory {}", dir.display()))?;
         }
-        let json_vals: Vec<_> = databases.iter().map(json_list_format).collect();
-        let json_text = serde_json::to_string_pretty(&json_vals)?;
-        println!("{}", json_text);
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Could not create {}", path.display()))?;
+        let mut out = std::io::BufWriter::new(file);
+        let written = match dump::dump(&client, &self.name, &mut out).await {
+            Ok(summary) => out.flush().map(|_| summary).map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+        let summary = match written {
+            Ok(summary) => summary,
+            Err(e) => {
+                // Leave no partial backup to be restored by mistake.
+                _ = std::fs::remove_file(&path);
+                return Err(e);
+            }
+        };
+        println!(
+            r#"Backed up {} table(s) and {} row(s) of database "{}" to {}"#,
+            summary.tables,
+            summary.rows,
+            self.name,
+            path.display()
+        );
         Ok(())
     }
+}
 
-    fn print_table(&self, databases: Vec<Database>) -> Result<()> {
-        let databases_without_links = databases.iter().filter(|db| db.links.is_empty());
+impl BackupListCommand {
+    pub fn run(self) -> Result<()> {
+        let backups = backup::list_backups(&backup::backups_dir()?, self.name.as_deref())?;
+        if backups.is_empty() {
+            println!("No backups");
+            return Ok(());
+        }
+        let mut table = new_table();
+        table.set_header(vec!["Database", "Backup", "Size"]);
+        table.add_rows(
+            backups
+                .iter()
+                .map(|b| [b.database.clone(), b.id.clone(), format_size(b.size)]),
+        );
+        println!("{table}");
+        Ok(())
+    }
+}
 
-        let mut links = databases
+impl BackupRestoreCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let backup =
+            backup::find_backup(&backup::backups_dir()?, &self.name, self.backup.as_deref())?;
+        let script = std::fs::read_to_string(&backup.path)
+            .with_context(|| format!("Could not read {}", backup.path.display()))?;
+        let target = self.to.as_deref().unwrap_or(&self.name);
+
+        let exists = list_databases(&client)
+            .await?
             .iter()
-            .flat_map(|db| {
-                db.links.iter().map(|l| Link {
-                    resource: db.name.clone(),
-                    resource_label: l.clone(),
-                })
-            })
-            .collect::<Vec<_>>();
-        if let Some(name) = &self.app {
-            links.retain(|l| l.app_name() == *name);
-            if links.is_empty() {
-                println!("No databases linked to an app named '{name}'");
+            .any(|d| d.name == target);
+        if exists {
+            let tables = list_tables(&client, target).await?;
+            if !tables.is_empty() && !self.yes && !confirm_restore_over(target, &tables)? {
+                println!("Backup not restored");
                 return Ok(());
             }
+        } else {
+            create_database(&client, target, None).await?;
+            println!(r#"Database "{target}" created"#);
         }
-        match self.group_by.unwrap_or_default() {
-            GroupBy::App => print_apps(links, databases_without_links),
-            GroupBy::Database => print_databases(links, databases_without_links),
-        }
+
+        restore_backup(&client, target, exists, &script)
+            .await
+            .with_context(|| {
+                format!(
+                    r#"Could not restore backup {} into database "{target}""#,
+                    backup.id
+                )
+            })?;
+        println!(
+            r#"Restored backup {} of database "{}" into database "{target}""#,
+            backup.id, self.name
+        );
         Ok(())
     }
 }
 
-fn json_list_format(database: &Database) -> DatabasesListJson<'_> {
-    DatabasesListJson {
-        database: &database.name,
-        links: database
-            .links
-            .iter()
-            .map(|l| ResourceLabelJson {
-                label: &l.label,
-                app: l.app_name.as_deref().unwrap_or("UNKNOWN"),
-            })
-            .collect(),
+fn confirm_restore_over(database: &str, tables: &[String]) -> Result<bool> {
+    if !EnvSettings::from_env().interactive() {
+        bail!(r#"Use --yes to replace the tables of database "{database}""#);
     }
+    println!(
+        r#"Database "{database}" has {} table(s): {}"#,
+        tables.len(),
+        tables.join(", ")
+    );
+    answers::confirm(
+        "Replace them with the backup? Their current rows will be lost.",
+        false,
+    )
 }
 
-#[derive(Serialize)]
-struct DatabasesListJson<'a> {
-    database: &'a str,
-    links: Vec<ResourceLabelJson<'a>>,
+/// Restores a backup script in one transaction, so that if any statement
+/// fails the database is left as it was rather than cleared or half
+/// restored. When `replace` is set the database's views and tables are
+/// dropped first, in the same transaction.
+async fn restore_backup(
+    client: &impl CloudClientInterface,
+    database: &str,
+    replace: bool,
+    script: &str,
+) -> Result<()> {
+    let mut statements = if replace {
+        drop_schema_statements(client, database).await?
+    } else {
+        vec![]
+    };
+    statements.extend(backup::restore_statements(script));
+    execute_transaction(client, database, &statements).await
 }
 
-/// A ResourceLabel type without app ID for JSON output
-#[derive(Serialize)]
-struct ResourceLabelJson<'a> {
-    label: &'a str,
-    app: &'a str,
+/// The statements which drop every view and table, so that a backup can be
+/// restored over them.
+async fn drop_schema_statements(
+    client: &impl CloudClientInterface,
+    database: &str,
+) -> Result<Vec<String>> {
+    let objects = query(
+        client,
+        database,
+        "SELECT type, name FROM sqlite_master WHERE type IN ('view', 'table') AND name NOT LIKE 'sqlite_%' ORDER BY type DESC"
+            .to_owned(),
+    )
+    .await?;
+    Ok(objects
+        .rows
+        .iter()
+        .filter_map(|row| match row.as_slice() {
+            [serde_json::Value::String(kind), serde_json::Value::String(name)] => Some(format!(
+                "DROP {} IF EXISTS {};",
+                kind.to_uppercase(),
+                quote_identifier(name)
+            )),
+            _ => None,
+        })
+        .collect())
+}
+
+impl DiffCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let databases = list_databases(&client).await?;
+        for name in [&self.first, &self.second] {
+            find_by_name(databases.iter(), name, "database", |d| &d.name)?;
+        }
+        let first = schema_diff::read_schema(&client, &self.first).await?;
+        let second = schema_diff::read_schema(&client, &self.second).await?;
+        match self.format {
+            DiffFormat::Json => Diff::between(
+                &schema_diff::schema_definitions(&first),
+                &schema_diff::schema_definitions(&second),
+            )
+            .print(DiffFormat::Json)?,
+            DiffFormat::Text => {
+                let lines = unified(
+                    &self.first,
+                    &self.second,
+                    &schema_diff::schema_lines(&first),
+                    &schema_diff::schema_lines(&second),
+                    use_color(),
+                );
+                if lines.is_empty() {
+                    println!(
+                        r#"The schemas of databases "{}" and "{}" are the same"#,
+                        self.first, self.second
+                    );
+                }
+                for line in lines {
+                    println!("{line}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DumpCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        find_database(&client, &self.name).await?;
+        let summary = match &self.output {
+            Some(path) => {
+                let file = std::fs::File::create(path)
+                    .with_context(|| format!("Could not create {}", path.display()))?;
+                let mut out = std::io::BufWriter::new(file);
+                let summary = dump::dump(&client, &self.name, &mut out).await?;
+                out.flush()
+                    .with_context(|| format!("Could not write {}", path.display()))?;
+                summary
+            }
+            None => dump::dump(&client, &self.name, &mut std::io::stdout().lock()).await?,
+        };
+        eprintln!(
+            r#"Dumped {} table(s) and {} row(s) from database "{}""#,
+            summary.tables, summary.rows, self.name
+        );
+        Ok(())
+    }
 }
 
-impl RenameCommand {
-    pub async fn run(self) -> Result<()> {
-        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
-        let list = client
-            .get_databases(None)
+#[derive(Debug, PartialEq, Serialize)]
+struct DatabaseStats {
+    name: String,
+    size_bytes: Option<u64>,
+    links: usize,
+    tables: Vec<TableStats>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct TableStats {
+    name: String,
+    rows: u64,
+}
+
+impl StatsCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let stats = self.stats(&client).await?;
+        match self.format {
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            ListFormat::Table | ListFormat::Markdown => print_stats(&stats, self.format),
+        }
+        Ok(())
+    }
+
+    async fn stats(&self, client: &impl CloudClientInterface) -> Result<DatabaseStats> {
+        let database = find_database(client, &self.name).await?;
+        let mut tables = vec![];
+        for name in list_tables(client, &self.name).await? {
+            let rows = row_count(client, &self.name, &name).await?;
+            tables.push(TableStats { name, rows });
+        }
+        Ok(DatabaseStats {
+            size_bytes: self.size(client).await,
+            links: database.links.len(),
+            name: database.name,
+            tables,
+        })
+    }
+
+    // The platform reports sizes from newer versions on. Otherwise the
+    // database is asked for its page count, which is best effort.
+    async fn size(&self, client: &impl CloudClientInterface) -> Option<u64> {
+        if let Ok(metadata) = client.get_database_metadata().await {
+            if let Some(size) = metadata
+                .into_iter()
+                .find(|m| m.name == self.name)
+                .and_then(|m| m.size_bytes)
+            {
+                return Some(size);
+            }
+        }
+        let result = query(
+            client,
+            &self.name,
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()".to_owned(),
+        )
+        .await
+        .ok()?;
+        result.rows.first()?.first()?.as_u64()
+    }
+}
+
+fn print_stats(stats: &DatabaseStats, format: ListFormat) {
+    let mut summary = new_table();
+    summary.add_row(vec!["Database", &stats.name]);
+    summary.add_row(vec![
+        "Size",
+        &stats
+            .size_bytes
+            .map(format_size)
+            .unwrap_or_else(|| "-".to_owned()),
+    ]);
+    summary.add_row(vec!["Tables", &stats.tables.len().to_string()]);
+    summary.add_row(vec!["Links", &stats.links.to_string()]);
+    print_list_table(&summary, format);
+    if stats.tables.is_empty() {
+        return;
+    }
+    let mut tables = new_table();
+    tables.set_header(vec!["Table", "Rows"]);
+    for table in &stats.tables {
+        tables.add_row(vec![table.name.clone(), table.rows.to_string()]);
+    }
+    print_list_table(&tables, format);
+}
+
+impl TablesCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        find_database(&client, &self.name).await?;
+        let tables = list_tables(&client, &self.name).await?;
+        let schemas = describe_tables(&client, &self.name, &tables).await?;
+        match self.format {
+            ListFormat::Json => {
+                let names = schemas.iter().map(|s| &s.name).collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&names)?);
+            }
+            ListFormat::Table | ListFormat::Markdown if schemas.is_empty() => {
+                println!(r#"Database "{}" has no tables"#, self.name)
+            }
+            ListFormat::Table | ListFormat::Markdown => {
+                let mut table = new_table();
+                table.set_header(vec!["Table", "Columns", "Indexes"]);
+                for schema in &schemas {
+                    table.add_row(vec![
+                        schema.name.clone(),
+                        schema.columns.len().to_string(),
+                        schema.indexes.len().to_string(),
+                    ]);
+                }
+                print_list_table(&table, self.format);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SchemaCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        find_database(&client, &self.name).await?;
+        let tables = list_tables(&client, &self.name).await?;
+        let tables = match &self.table {
+            Some(table) => vec![tables
+                .iter()
+                .find(|t| *t == table)
+                .cloned()
+                .ok_or_else(|| not_found("table", table, tables.iter().map(String::as_str)))?],
+            None => tables,
+        };
+        let schemas = describe_tables(&client, &self.name, &tables).await?;
+        match self.format {
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&schemas)?),
+            ListFormat::Table | ListFormat::Markdown if schemas.is_empty() => {
+                println!(r#"Database "{}" has no tables"#, self.name)
+            }
+            ListFormat::Table | ListFormat::Markdown => {
+                for (i, schema) in schemas.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    print_table_schema(schema, self.format);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_table_schema(schema: &TableSchema, format: ListFormat) {
+    println!("{}", schema.name);
+    let mut columns = new_table();
+    columns.set_header(vec!["Column", "Type", "Not null", "Default", "Primary key"]);
+    for column in &schema.columns {
+        columns.add_row(vec![
+            column.name.clone(),
+            column.decl_type.clone(),
+            yes_or_blank(column.not_null),
+            column.default.clone().unwrap_or_default(),
+            yes_or_blank(column.primary_key),
+        ]);
+    }
+    print_list_table(&columns, format);
+    if !schema.indexes.is_empty() {
+        let mut indexes = new_table();
+        indexes.set_header(vec!["Index", "Unique", "Columns"]);
+        for index in &schema.indexes {
+            indexes.add_row(vec![
+                index.name.clone(),
+                yes_or_blank(index.unique),
+                index.columns.join(", "),
+            ]);
+        }
+        print_list_table(&indexes, format);
+    }
+}
+
+fn yes_or_blank(flag: bool) -> String {
+    if flag { "yes" } else { "" }.to_owned()
+}
+
+impl ExportCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        find_database(&client, &self.name).await?;
+        let tables = if self.tables.is_empty() {
+            list_tables(&client, &self.name).await?
+        } else {
+            self.tables.clone()
+        };
+        if tables.is_empty() {
+            bail!(r#"Database "{}" has no tables to export"#, self.name);
+        }
+
+        let dir = self
+            .output_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}-export", self.name)));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create directory {}", dir.display()))?;
+        let progress = Progress::new(self.progress);
+        for (index, table) in tables.iter().enumerate() {
+            progress.item("exporting", table, index, tables.len());
+            let result = query(
+                &client,
+                &self.name,
+                format!("SELECT * FROM {}", quote_identifier(table)),
+            )
+            .await
+            .with_context(|| format!(r#"Could not export table "{table}""#))?;
+            let contents = match self.format {
+                ExportFormat::Csv => to_csv(&result),
+                ExportFormat::Json => to_json(&result)?,
+            };
+            let path = dir.join(export_file_name(table, self.format.extension()));
+            std::fs::write(&path, contents)
+                .with_context(|| format!("Could not write {}", path.display()))?;
+            println!(
+                "Exported {} row(s) from table \"{table}\" to {}",
+                result.rows.len(),
+                path.display()
+            );
+        }
+        progress.phase("done", 100);
+        Ok(())
+    }
+}
+
+/// The name of the file a table is exported to. Table names can hold any
+/// character, so those which could lead out of the output directory or are
+/// not allowed in file names are escaped as `%` and their hex bytes.
+fn export_file_name(table: &str, extension: &str) -> String {
+    let mut name = String::with_capacity(table.len());
+    for (index, c) in table.char_indices() {
+        let keep = c.is_alphanumeric() || matches!(c, '_' | '-' | ' ') || (c == '.' && index > 0);
+        if keep {
+            name.push(c);
+        } else {
+            for byte in c.to_string().bytes() {
+                name.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+    format!("{name}.{extension}")
+}
+
+impl ImportCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let text = std::fs::read_to_string(&self.file)
+            .with_context(|| format!("Could not read {}", self.file.display()))?;
+        let statements = self.statements(&text)?;
+        if statements.is_empty() {
+            bail!("{} has nothing to import", self.file.display());
+        }
+        find_database(&client, &self.name).await?;
+
+        // Conflicts are resolved up front, so that an import never stops
+        // part way through because a table exists.
+        let existing = list_tables(&client, &self.name).await?;
+        let conflicts = import::conflicting_tables(&statements, &existing);
+        let statements = match self.on_conflict {
+            _ if conflicts.is_empty() => statements,
+            OnConflict::Fail => bail!(
+                r#"Database "{}" already has the table(s) {}. Use --on-conflict to skip, replace or merge them"#,
+                self.name,
+                conflicts.join(", ")
+            ),
+            OnConflict::Skip => {
+                eprintln!("Skipping existing table(s): {}", conflicts.join(", "));
+                import::skip_tables(statements, &conflicts)
+            }
+            OnConflict::Replace => import::replace_tables(statements, &conflicts),
+            OnConflict::MergeSchema => {
+                let mut existing_columns = BTreeMap::new();
+                for table in &conflicts {
+                    existing_columns.insert(table.clone(), self.columns(&client, table).await?);
+                }
+                import::merge_schema(statements, &existing_columns)
+            }
+        };
+        let tables = import::target_tables(&statements);
+        let mut rows_before = BTreeMap::new();
+        for table in &tables {
+            let exists = existing.iter().any(|e| e.eq_ignore_ascii_case(table));
+            let replaced = self.on_conflict == OnConflict::Replace && conflicts.contains(table);
+            if exists && !replaced {
+                rows_before.insert(table.clone(), row_count(&client, &self.name, table).await?);
+            }
+        }
+
+        let batches = import::batches(&statements, self.batch_size);
+        let progress = Progress::new(self.progress);
+        let failures = import::execute_batches(
+            &client,
+            &self.name,
+            batches,
+            statements.len(),
+            self.continue_on_error,
+            &progress,
+        )
+        .await;
+        self.print_row_counts(&client, &tables, &rows_before).await;
+        if failures.is_empty() {
+            progress.phase("done", 100);
+            println!(
+                r#"Imported {} statement(s) from {} into database "{}""#,
+                statements.len(),
+                self.file.display(),
+                self.name
+            );
+            return Ok(());
+        }
+
+        let mut table = new_table();
+        table.set_header(vec!["Statements", "Error"]);
+        for failure in &failures {
+            table.add_row(vec![
+                format!("{}-{}", failure.first, failure.last),
+                format!("{:#}", failure.error),
+            ]);
+        }
+        println!("{table}");
+        bail!(
+            "{} batch(es) of statements failed to import into database \"{}\"",
+            failures.len(),
+            self.name
+        )
+    }
+
+    async fn columns(
+        &self,
+        client: &impl CloudClientInterface,
+        table: &str,
+    ) -> Result<Vec<String>> {
+        let info = query(
+            client,
+            &self.name,
+            format!("PRAGMA table_info({})", quote_identifier(table)),
+        )
+        .await?;
+        let name = info.columns.iter().position(|c| c == "name");
+        Ok(info
+            .rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().nth(name?)?.as_str().map(str::to_owned))
+            .collect())
+    }
+
+    /// Prints how many rows each table gained. Counting is best effort: a
+    /// table the import failed to create has no row count.
+    async fn print_row_counts(
+        &self,
+        client: &impl CloudClientInterface,
+        tables: &[String],
+        rows_before: &BTreeMap<String, u64>,
+    ) {
+        if tables.is_empty() {
+            return;
+        }
+        let mut table = new_table();
+        table.set_header(vec!["Table", "Rows imported"]);
+        for name in tables {
+            let imported = match row_count(client, &self.name, name).await {
+                Ok(after) => after
+                    .saturating_sub(rows_before.get(name).copied().unwrap_or_default())
+                    .to_string(),
+                Err(_) => "-".to_owned(),
+            };
+            table.add_row(vec![name.clone(), imported]);
+        }
+        println!("{table}");
+    }
+
+    fn statements(&self, text: &str) -> Result<Vec<String>> {
+        let is_csv = self
+            .file
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if !is_csv {
+            if self.table.is_some() {
+                bail!("--table can only be used when importing a CSV file");
+            }
+            return Ok(import::split_statements(text));
+        }
+        let table = self
+            .table
+            .as_deref()
+            .context("Use --table to choose the table to load the CSV file into")?;
+        let records = crate::csv::parse_csv(text)
+            .with_context(|| format!("Invalid CSV file {}", self.file.display()))?;
+        let has_header = !self.no_header
+            && records
+                .first()
+                .is_some_and(|r| import::looks_like_header(r));
+        let (header, records) = match records.split_first() {
+            Some((header, records)) if has_header => (Some(header.as_slice()), records),
+            _ => (None, records.as_slice()),
+        };
+        Ok(import::csv_statements(table, header, records))
+    }
+}
+
+fn print_rows(result: &QueryResult, format: ResultFormat) -> Result<()> {
+    write_rows(&mut std::io::stdout().lock(), result, format)?;
+    if format == ResultFormat::Table {
+        eprintln!("{} row(s)", result.rows.len());
+    }
+    Ok(())
+}
+
+/// Writes query results to a file in the given format, creating the
+/// file's directory if needed.
+fn save_rows(path: &Path, result: &QueryResult, format: ResultFormat) -> Result<()> {
+    let mut out = create_output(path)?;
+    write_rows(&mut out, result, format)
+        .and_then(|_| Ok(out.flush()?))
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    eprintln!("Wrote {} row(s) to {}", result.rows.len(), path.display());
+    Ok(())
+}
+
+fn create_output(path: &Path) -> Result<std::io::BufWriter<std::fs::File>> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create directory {}", dir.display()))?;
+    }
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Could not create {}", path.display()))?;
+    Ok(std::io::BufWriter::new(file))
+}
+
+fn write_rows(out: &mut impl Write, result: &QueryResult, format: ResultFormat) -> Result<()> {
+    match format {
+        ResultFormat::Table => {
+            if !result.columns.is_empty() {
+                let mut table = new_table();
+                table.set_header(result.columns.clone());
+                table.add_rows(
+                    result
+                        .rows
+                        .iter()
+                        .map(|row| row.iter().map(cell_text).collect::<Vec<_>>()),
+                );
+                writeln!(out, "{table}")?;
+            }
+        }
+        ResultFormat::Json => writeln!(out, "{}", to_json(result)?)?,
+        ResultFormat::Csv => write!(out, "{}", to_csv(result))?,
+        ResultFormat::Markdown => write!(out, "{}", to_markdown(result))?,
+    }
+    Ok(())
+}
+
+// NULLs are shown as empty cells.
+fn cell_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Formats query results as CSV with a header row. NULLs are written as
+/// empty fields.
+fn to_csv(result: &QueryResult) -> String {
+    let mut csv = csv_record(&result.columns);
+    for row in &result.rows {
+        csv.push_str(&csv_record(&row.iter().map(cell_text).collect::<Vec<_>>()));
+    }
+    csv
+}
+
+fn csv_record(fields: &[String]) -> String {
+    let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+    format!("{}\n", fields.join(","))
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Formats query results as a Markdown table. Pipes are escaped and line
+/// breaks become `<br>`, so that each row stays on one line; NULLs are
+/// shown as empty cells.
+fn to_markdown(result: &QueryResult) -> String {
+    if result.columns.is_empty() {
+        return String::new();
+    }
+    let mut markdown = markdown_header(&result.columns);
+    for row in &result.rows {
+        markdown.push_str(&markdown_row(row));
+    }
+    markdown
+}
+
+fn markdown_header(columns: &[String]) -> String {
+    let mut header = markdown_line(columns.iter().map(|c| markdown_cell(c)).collect());
+    header.push_str(&markdown_line(vec!["---".to_owned(); columns.len()]));
+    header
+}
+
+fn markdown_row(row: &[serde_json::Value]) -> String {
+    markdown_line(row.iter().map(|v| markdown_cell(&cell_text(v))).collect())
+}
+
+fn markdown_line(cells: Vec<String>) -> String {
+    format!("| {} |\n", cells.join(" | "))
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+/// Prints a listing's table in the chosen table style, or as Markdown for
+/// --format markdown.
+fn print_list_table(table: &comfy_table::Table, format: ListFormat) {
+    if format == ListFormat::Markdown {
+        print!("{}", table_markdown(table));
+    } else {
+        println!("{table}");
+    }
+}
+
+/// Formats a table the same way as query results. Markdown tables need a
+/// header, so a table without one gets an empty header.
+fn table_markdown(table: &comfy_table::Table) -> String {
+    let columns = match table.header() {
+        Some(header) => header.cell_iter().map(|c| c.content()).collect(),
+        None => {
+            let count = table.row_iter().map(|r| r.cell_count()).max().unwrap_or(0);
+            vec![String::new(); count]
+        }
+    };
+    let mut markdown = markdown_header(&columns);
+    for row in table.row_iter() {
+        markdown.push_str(&markdown_line(
+            row.cell_iter()
+                .map(|c| markdown_cell(&c.content()))
+                .collect(),
+        ));
+    }
+    markdown
+}
+
+/// Formats query results as a JSON array with one object per row.
+fn to_json(result: &QueryResult) -> Result<String> {
+    let rows = result
+        .rows
+        .iter()
+        .map(|row| json_row(&result.columns, row))
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&rows)?)
+}
+
+fn json_row(
+    columns: &[String],
+    row: &[serde_json::Value],
+) -> serde_json::Map<String, serde_json::Value> {
+    columns.iter().cloned().zip(row.iter().cloned()).collect()
+}
+
+/// A label by which an app refers to a database, for listing
+#[derive(Debug, PartialEq, Serialize)]
+struct DatabaseLabel {
+    label: String,
+    app: Option<String>,
+    app_id: uuid::Uuid,
+}
+
+impl LabelsCommand {
+    pub async fn run(self) -> Result<()> {
+        // Not a conflict: clap would count an app from CLOUD_APP as given,
+        // and refuse every database named while it is set.
+        match (&self.database, &self.app) {
+            (Some(database), _) => {
+                let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+                self.list_database_labels(&client, database).await
+            }
+            (None, Some(app)) => self.compare_app_labels(app).await,
+            (None, None) => bail!("Give the database whose labels to list, or an app with --app"),
+        }
+    }
+
+    async fn list_database_labels(
+        &self,
+        client: &impl CloudClientInterface,
+        database: &str,
+    ) -> Result<()> {
+        let labels = database_labels(&find_database(client, database).await?);
+        match self.format {
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&labels)?),
+            ListFormat::Table | ListFormat::Markdown if labels.is_empty() => {
+                println!(r#"Database "{database}" is not linked to any apps"#)
+            }
+            ListFormat::Table | ListFormat::Markdown => {
+                let mut table = new_table();
+                table.set_header(vec!["Label", "App", "App ID"]);
+                table.add_rows(labels.iter().map(|l| {
+                    [
+                        l.label.clone(),
+                        l.app.clone().unwrap_or_else(|| "-".to_owned()),
+                        l.app_id.to_string(),
+                    ]
+                }));
+                print_list_table(&table, self.format);
+            }
+        }
+        Ok(())
+    }
+
+    async fn compare_app_labels(&self, app_name: &str) -> Result<()> {
+        let login_connection = login_connection(self.common.deployment_env_id.as_deref()).await?;
+        let connection_config = ConnectionConfig {
+            url: login_connection.url.to_string(),
+            insecure: login_connection.danger_accept_invalid_certs,
+            token: login_connection.token,
+        };
+        let client = CloudClient::new(connection_config.clone());
+        let app_id = app_id(&client, app_name).await?;
+        let app = client
+            .get_app(app_id.to_string())
+            .await
+            .with_context(|| format!("Error: could not get details about {app_name}"))?;
+        let dir = tempfile::tempdir()?;
+        let (revision, locked_app) =
+            load_active_revision(&app, app_name, dir.path(), &connection_config).await?;
+
+        let databases = list_databases(&client).await?;
+        let states = compare_labels(
+            &DeployableApp(locked_app)
+                .sqlite_databases()
+                .into_iter()
+                .collect(),
+            &app_database_links(&databases, app_name),
+        );
+        match self.format {
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&states)?),
+            ListFormat::Table | ListFormat::Markdown => {
+                print_label_states(app_name, &revision, &states, self.format)
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_label_states(app: &str, revision: &str, states: &[LabelState], format: ListFormat) {
+    if states.is_empty() {
+        println!(
+            r#"Revision {revision} of app "{app}" declares no databases, and none are linked"#
+        );
+        return;
+    }
+    println!("Database labels of revision {revision}:");
+    let mut table = new_table();
+    table.set_header(vec!["Label", "Database", "Status"]);
+    table.add_rows(states.iter().map(|s| {
+        let status = match s.status {
+            LabelStatus::Linked => "linked",
+            LabelStatus::Unlinked => "MISMATCH: not linked",
+            LabelStatus::Undeclared => "MISMATCH: not in manifest",
+        };
+        [
+            s.label.clone(),
+            s.database.clone().unwrap_or_else(|| "-".to_owned()),
+            status.to_owned(),
+        ]
+    }));
+    print_list_table(&table, format);
+    if states.iter().any(|s| s.status == LabelStatus::Unlinked) {
+        eprintln!(
+            "Link each unlinked label with `spin cloud link sqlite --app {app} --database <DATABASE> <LABEL>`"
+        );
+    }
+}
+
+/// The labels by which apps refer to a database, ordered by app.
+fn database_labels(database: &Database) -> Vec<DatabaseLabel> {
+    let mut labels = database
+        .links
+        .iter()
+        .map(|l| DatabaseLabel {
+            label: l.label.clone(),
+            app: l.app_name.clone(),
+            app_id: l.app_id,
+        })
+        .collect::<Vec<_>>();
+    labels.sort_by(|a, b| (&a.app, &a.label).cmp(&(&b.app, &b.label)));
+    labels
+}
+
+/// The database labels declared by any component of the app.
+impl ListCommand {
+    pub async fn run(self) -> Result<()> {
+        if let (ListFormat::Json, Some(_)) = (&self.format, self.group_by) {
+            bail!("Grouping is not supported with JSON format output")
+        }
+
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let mut databases = list_databases(&client).await?;
+
+        if databases.is_empty() {
+            println!("No databases");
+            return Ok(());
+        }
+        if let Some(name) = &self.database {
+            databases.retain(|db| db.name == *name);
+            if databases.is_empty() {
+                println!("No database with name '{name}'");
+                return Ok(());
+            }
+        }
+
+        if self.orphaned || self.linked {
+            databases.retain(|db| self.matches_links(db));
+            // Cleanup scripts read the JSON, so an empty result is still JSON.
+            if databases.is_empty() && self.format != ListFormat::Json {
+                if self.orphaned {
+                    println!("No databases without links")
+                } else {
+                    println!("No linked databases")
+                }
+                return Ok(());
+            }
+        }
+
+        let metadata = self.database_metadata(&client).await?;
+        if self.filters_by_metadata() {
+            databases
+                .retain(|db| self.matches_filters(metadata.as_ref().and_then(|m| m.get(&db.name))));
+            if databases.is_empty() {
+                println!("No databases match the given filters");
+                return Ok(());
+            }
+        }
+        sort_databases(
+            &mut databases,
+            self.sort_by.unwrap_or(SortBy::Name),
+            metadata.as_ref(),
+        );
+
+        match self.format {
+            ListFormat::Json => self.print_json(databases, metadata.as_ref()),
+            ListFormat::Table | ListFormat::Markdown => {
+                self.print_table(databases, metadata.as_ref())
+            }
+        }
+    }
+
+    fn matches_links(&self, db: &Database) -> bool {
+        match (self.orphaned, self.linked) {
+            (true, _) => db.links.is_empty(),
+            (_, true) => !db.links.is_empty(),
+            _ => true,
+        }
+    }
+
+    fn filters_by_metadata(&self) -> bool {
+        self.mine || self.created_after.is_some() || self.created_before.is_some()
+    }
+
+    fn needs_metadata(&self) -> bool {
+        self.filters_by_metadata()
+            || matches!(
+                self.sort_by,
+                Some(SortBy::Size | SortBy::Age | SortBy::Accessed)
+            )
+    }
+
+    // Only JSON and the list grouped by database show the metadata.
+    fn shows_metadata(&self) -> bool {
+        self.format == ListFormat::Json || matches!(self.group_by(), GroupBy::Database)
+    }
+
+    fn group_by(&self) -> GroupBy {
+        match (self.group_by, self.sort_by) {
+            (Some(group_by), _) => group_by,
+            (None, Some(_)) => GroupBy::Database,
+            (None, None) => GroupBy::App,
+        }
+    }
+
+    // Fetched only if it is used. Older platform versions do not report
+    // database metadata, which is an error if the user asked to filter or
+    // sort by it, and otherwise leaves it out of the list.
+    async fn database_metadata(
+        &self,
+        client: &impl CloudClientInterface,
+    ) -> Result<Option<HashMap<String, DatabaseMetadata>>> {
+        if !self.needs_metadata() && !self.shows_metadata() {
+            return Ok(None);
+        }
+        match client.get_database_metadata().await {
+            Ok(metadata) => Ok(Some(
+                metadata.into_iter().map(|m| (m.name.clone(), m)).collect(),
+            )),
+            Err(e) if self.needs_metadata() => {
+                Err(e.context("Database ownership and size details are not available"))
+            }
+            Err(e) => {
+                eprintln!("Warning: database ownership and size details are not available: {e:#}");
+                Ok(None)
+            }
+        }
+    }
+
+    // Databases with no record of a filtered property are excluded.
+    fn matches_filters(&self, metadata: Option<&DatabaseMetadata>) -> bool {
+        let Some(metadata) = metadata else {
+            return false;
+        };
+        if self.mine && !metadata.created_by_current_user {
+            return false;
+        }
+        if self.created_after.is_none() && self.created_before.is_none() {
+            return true;
+        }
+        let Some(created_at) = created_at(metadata) else {
+            return false;
+        };
+        !matches!(self.created_after, Some(after) if created_at <= after)
+            && !matches!(self.created_before, Some(before) if created_at >= before)
+    }
+
+    fn print_json(
+        &self,
+        mut databases: Vec<Database>,
+        metadata: Option<&HashMap<String, DatabaseMetadata>>,
+    ) -> Result<()> {
+        if let Some(app) = &self.app {
+            databases.retain(|d| {
+                d.links
+                    .iter()
+                    .any(|l| l.app_name.as_deref().unwrap_or("UNKNOWN") == app)
+            });
+        }
+        let json_vals: Vec<_> = databases
+            .iter()
+            .map(|db| json_list_format(db, metadata.and_then(|m| m.get(&db.name))))
+            .collect();
+        let json_text = serde_json::to_string_pretty(&json_vals)?;
+        println!("{}", json_text);
+        Ok(())
+    }
+
+    fn print_table(
+        &self,
+        databases: Vec<Database>,
+        metadata: Option<&HashMap<String, DatabaseMetadata>>,
+    ) -> Result<()> {
+        let databases_without_links = databases.iter().filter(|db| db.links.is_empty());
+
+        let mut links = databases
+            .iter()
+            .flat_map(|db| {
+                db.links.iter().map(|l| Link {
+                    resource: db.name.clone(),
+                    resource_label: l.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        if let Some(name) = &self.app {
+            links.retain(|l| l.app_name() == *name);
+            if links.is_empty() {
+                println!("No databases linked to an app named '{name}'");
+                return Ok(());
+            }
+        }
+        match self.group_by() {
+            GroupBy::App => print_apps(links, databases_without_links, self.format),
+            GroupBy::Database => print_databases(&databases, links, metadata, self.format),
+        }
+        Ok(())
+    }
+}
+
+fn created_at(metadata: &DatabaseMetadata) -> Option<DateTime<Utc>> {
+    parse_timestamp(metadata.created_at.as_deref()?)
+}
+
+fn last_accessed_at(metadata: &DatabaseMetadata) -> Option<DateTime<Utc>> {
+    parse_timestamp(metadata.last_accessed_at.as_deref()?)
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// Sorts databases in place. Databases without a known size, age or last
+/// access are listed last.
+fn sort_databases(
+    databases: &mut [Database],
+    sort_by: SortBy,
+    metadata: Option<&HashMap<String, DatabaseMetadata>>,
+) {
+    let metadata = |db: &Database| metadata.and_then(|m| m.get(&db.name));
+    match sort_by {
+        SortBy::Name => databases.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortBy::Size => databases.sort_by_key(|db| {
+            let size = metadata(db).and_then(|m| m.size_bytes);
+            (size.is_none(), std::cmp::Reverse(size))
+        }),
+        SortBy::Age => databases.sort_by_key(|db| {
+            let created_at = metadata(db).and_then(created_at);
+            (created_at.is_none(), created_at)
+        }),
+        SortBy::Accessed => databases.sort_by_key(|db| {
+            let accessed = metadata(db).and_then(last_accessed_at);
+            (accessed.is_none(), accessed)
+        }),
+    }
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+fn metadata_cells(metadata: Option<&DatabaseMetadata>) -> [String; 5] {
+    let unknown = || "-".to_owned();
+    let Some(metadata) = metadata else {
+        return [unknown(), unknown(), unknown(), unknown(), unknown()];
+    };
+    let time = |time: Option<DateTime<Utc>>| {
+        time.map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(unknown)
+    };
+    [
+        metadata.created_by.clone().unwrap_or_else(unknown),
+        time(created_at(metadata)),
+        time(last_accessed_at(metadata)),
+        metadata.size_bytes.map(format_size).unwrap_or_else(unknown),
+        metadata.region.clone().unwrap_or_else(unknown),
+    ]
+}
+
+fn json_list_format<'a>(
+    database: &'a Database,
+    metadata: Option<&'a DatabaseMetadata>,
+) -> DatabasesListJson<'a> {
+    DatabasesListJson {
+        database: &database.name,
+        created_by: metadata.and_then(|m| m.created_by.as_deref()),
+        created_at: metadata.and_then(|m| m.created_at.as_deref()),
+        last_accessed_at: metadata.and_then(|m| m.last_accessed_at.as_deref()),
+        size_bytes: metadata.and_then(|m| m.size_bytes),
+        region: metadata.and_then(|m| m.region.as_deref()),
+        links: database
+            .links
+            .iter()
+            .map(|l| ResourceLabelJson {
+                label: &l.label,
+                app: l.app_name.as_deref().unwrap_or("UNKNOWN"),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Serialize)]
+struct DatabasesListJson<'a> {
+    database: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_by: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_accessed_at: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<&'a str>,
+    links: Vec<ResourceLabelJson<'a>>,
+}
+
+/// A ResourceLabel type without app ID for JSON output
+#[derive(Serialize)]
+struct ResourceLabelJson<'a> {
+    label: &'a str,
+    app: &'a str,
+}
+
+impl RenameCommand {
+    pub async fn run(self, client: impl CloudClientInterface) -> Result<()> {
+        let databases = list_databases(&client).await?;
+        let database = check_rename(databases, &self.name, &self.new_name)?;
+        let labels = database_labels(&database);
+        if !labels.is_empty() {
+            // Links follow the database, but anything naming it does not.
+            eprintln!(
+                "Warning: database \"{}\" is linked to {}. The links keep working, but scripts and commands which refer to the database by name must be updated.",
+                self.name,
+                labels
+                    .iter()
+                    .map(|l| format!("{}:{}", l.app.as_deref().unwrap_or("UNKNOWN"), l.label))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if !self.yes {
+                if !EnvSettings::from_env().interactive() {
+                    bail!(
+                        "Use --yes to rename linked database \"{}\" without confirmation",
+                        self.name
+                    );
+                }
+                if !answers::confirm(&format!("Rename to \"{}\"?", self.new_name), false)? {
+                    println!("Database \"{}\" was not renamed", self.name);
+                    return Ok(());
+                }
+            }
+        }
+        client
+            .rename_database(self.name.clone(), self.new_name.clone())
+            .await
+            .with_context(|| format!("Problem renaming database \"{}\"", self.name))?;
+        println!(
+            "Database \"{}\" is now named \"{}\"",
+            self.name, self.new_name
+        );
+        Ok(())
+    }
+}
+
+/// Print apps optionally filtering to a specifically supplied app and/or database
+fn print_apps<'a>(
+    mut links: Vec<Link>,
+    databases_without_links: impl Iterator<Item = &'a Database>,
+    format: ListFormat,
+) {
+    links.sort_by(|l1, l2| l1.app_name().cmp(l2.app_name()));
+
+    let mut table = new_table();
+    table.set_header(vec!["App", "Label", "Database"]);
+
+    let rows = links.iter().map(|link| {
+        [
+            link.app_name(),
+            link.resource_label.label.as_str(),
+            link.resource.as_str(),
+        ]
+    });
+    table.add_rows(rows);
+    print_list_table(&table, format);
+
+    let mut databases_without_links = databases_without_links.peekable();
+    if databases_without_links.peek().is_none() {
+        return;
+    }
+
+    let mut table = new_table();
+    if format == ListFormat::Markdown {
+        // Otherwise the heading would be read as a row of the table above
+        println!();
+    }
+    println!("Databases not linked to any app");
+    table.set_header(vec!["Database"]);
+    table.add_rows(databases_without_links.map(|d| [&d.name]));
+    print_list_table(&table, format);
+}
+
+/// Print databases optionally filtering to a specifically supplied app and/or database,
+/// in the order given. Ownership and size columns are shown when metadata is available.
+fn print_databases(
+    databases: &[Database],
+    links: Vec<Link>,
+    metadata: Option<&HashMap<String, DatabaseMetadata>>,
+    format: ListFormat,
+) {
+    let mut table = new_table();
+    let mut header = vec!["Database", "Links"];
+    if metadata.is_some() {
+        header.extend(["Created by", "Created", "Last accessed", "Size", "Region"]);
+    }
+    table.set_header(header);
+
+    let mut map = BTreeMap::new();
+    for link in &links {
+        let app_name = link.app_name();
+        map.entry(&link.resource)
+            .and_modify(|v| *v = format!("{}, {}:{}", *v, app_name, link.resource_label.label))
+            .or_insert(format!("{}:{}", app_name, link.resource_label.label));
+    }
+    for database in databases {
+        let links = match map.get(&database.name) {
+            Some(links) => links.clone(),
+            None if database.links.is_empty() => "-".to_owned(),
+            // Linked, but not to the app being filtered on
+            None => continue,
+        };
+        let mut row = vec![database.name.clone(), links];
+        if let Some(metadata) = metadata {
+            row.extend(metadata_cells(metadata.get(&database.name)));
+        }
+        table.add_row(row);
+    }
+    print_list_table(&table, format);
+}
+
+fn prompt_delete_database(database: &str, links: &[ResourceLabel]) -> Result<bool> {
+    let existing_links = links
+        .iter()
+        .map(|l| l.app_name.as_deref().unwrap_or("UNKNOWN"))
+        .collect::<Vec<&str>>()
+        .join(", ");
+    let mut prompt = String::new();
+    if !existing_links.is_empty() {
+        // TODO: use warning color text
+        prompt.push_str(&format!("Database \"{database}\" is currently linked to the following apps: {existing_links}.\n\
+        It is recommended to use `spin cloud link sqlite` to link to another database to those apps before deleting.\n"))
+    }
+    prompt.push_str(&format!(
+        "The action is irreversible. Please type \"{database}\" for confirmation"
+    ));
+    let answer = answers::input(&prompt, None)?;
+    if answer != database {
+        println!("Invalid confirmation. Will not delete database.");
+        Ok(false)
+    } else {
+        println!("Deleting database ...");
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod sqlite_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    #[test]
+    fn database_labels_are_listed_by_app() {
+        let label = |label: &str, app: &str| ResourceLabel {
+            app_id: uuid::Uuid::nil(),
+            label: label.to_owned(),
+            app_name: Some(app.to_owned()),
+        };
+        let database = Database::new(
+            "todo-db".to_owned(),
+            vec![
+                label("default", "todo"),
+                label("reports", "admin"),
+                label("audit", "todo"),
+            ],
+        );
+        assert_eq!(
+            database_labels(&database)
+                .iter()
+                .map(|l| format!("{}:{}", l.app.as_deref().unwrap_or_default(), l.label))
+                .collect::<Vec<_>>(),
+            vec!["admin:reports", "todo:audit", "todo:default"]
+        );
+    }
+
+    #[test]
+    fn labels_are_compared_with_links() {
+        let link = |label: &str, database: &str| {
+            Link::new(
+                ResourceLabel {
+                    app_id: uuid::Uuid::new_v4(),
+                    label: label.to_owned(),
+                    app_name: Some("todo".to_owned()),
+                },
+                database.to_owned(),
+            )
+        };
+        let declared = ["default".to_owned(), "audit".to_owned()].into();
+        let links = [link("default", "todo-db"), link("cache", "old-cache")];
+
+        let states = compare_labels(&declared, &links);
+        assert_eq!(
+            states
+                .iter()
+                .map(|s| (s.label.as_str(), s.status, s.database.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("audit", LabelStatus::Unlinked, None),
+                ("cache", LabelStatus::Undeclared, Some("old-cache")),
+                ("default", LabelStatus::Linked, Some("todo-db")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_if_db_already_exists_then_error() -> Result<()> {
+        let command = CreateCommand {
+            name: "db1".to_string(),
+            region: None,
+            if_not_exists: false,
+            link: None,
+            common: Default::default(),
+        };
+        let dbs = vec![
+            Database::new("db1".to_string(), vec![]),
+            Database::new("db2".to_string(), vec![]),
+        ];
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().return_once(move |_| Ok(dbs));
+
+        let result = command.run(mock).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"Database "db1" already exists"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_if_db_does_not_exist_db_is_created() -> Result<()> {
+        let command = CreateCommand {
+            name: "db1".to_string(),
+            region: None,
+            if_not_exists: false,
+            link: None,
+            common: Default::default(),
+        };
+        let dbs = vec![Database::new("db2".to_string(), vec![])];
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().return_once(move |_| Ok(dbs));
+        mock.expect_create_database()
+            .withf(move |db, rl, region| db == "db1" && rl.is_none() && region.is_none())
+            .returning(|_, _, _| Ok(()));
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_delete_if_db_does_not_exist_then_error() -> Result<()> {
+        let command = DeleteCommand {
+            names: vec!["db1".to_string()],
+            pattern: None,
+            common: Default::default(),
+            yes: true,
+            if_exists: false,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().returning(move |_| Ok(vec![]));
+
+        let result = command.run(mock).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"No database found with name "db1""#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_if_not_exists_leaves_an_existing_db() -> Result<()> {
+        let command = CreateCommand {
+            name: "db1".to_string(),
+            region: None,
+            if_not_exists: true,
+            link: None,
+            common: Default::default(),
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        mock.expect_create_database().never();
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_create_with_link_links_the_new_db() -> Result<()> {
+        let app_id = uuid::Uuid::new_v4();
+        let command = CreateCommand {
+            name: "db1".to_string(),
+            region: None,
+            if_not_exists: false,
+            link: Some(parse_app_label("todo:data").unwrap()),
+            common: Default::default(),
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_apps().returning(move |_, _| {
+            Ok(cloud_openapi::models::AppItemPage {
+                items: vec![cloud_openapi::models::AppItem {
+                    id: app_id,
+                    name: "todo".to_owned(),
+                    ..Default::default()
+                }],
+                is_last_page: true,
+                ..Default::default()
+            })
+        });
+        mock.expect_get_databases()
+            .times(1)
+            .returning(|_| Ok(vec![]));
+        mock.expect_create_database()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock.expect_get_databases()
+            .returning(|_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        mock.expect_create_database_link()
+            .withf(move |db, rl| db == "db1" && rl.app_id == app_id && rl.label == "data")
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        command.run(mock).await?;
+        assert_eq!(parse_app_label("todo"), Ok(("todo".to_owned(), None)));
+        assert!(parse_app_label("todo:").is_err());
+        assert!(parse_app_label(":data").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_if_exists_succeeds_when_db_does_not_exist() -> Result<()> {
+        let command = DeleteCommand {
+            names: vec!["db1".to_string()],
+            pattern: None,
+            common: Default::default(),
+            yes: true,
+            if_exists: true,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().returning(move |_| Ok(vec![]));
+        mock.expect_delete_database().never();
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_delete_by_pattern_deletes_only_matching_dbs() -> Result<()> {
+        let command = DeleteCommand::try_parse_from(["delete", "--match", "test-*", "--yes"])?;
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().returning(move |_| {
+            Ok(vec![
+                Database::new("test-amber-owl".to_string(), vec![]),
+                Database::new("prod-db".to_string(), vec![]),
+                Database::new("test-brave-fox".to_string(), vec![]),
+            ])
+        });
+        mock.expect_delete_database()
+            .withf(|name| name.starts_with("test-"))
+            .times(2)
+            .returning(|_| Ok(()));
+
+        command.run(mock).await
+    }
+
+    #[test]
+    fn test_delete_needs_names_or_a_pattern() {
+        assert!(DeleteCommand::try_parse_from(["delete"]).is_err());
+        assert!(DeleteCommand::try_parse_from(["delete", "db1", "--match", "db*"]).is_err());
+        let command = DeleteCommand::try_parse_from(["delete", "db1", "db2"]).unwrap();
+        assert_eq!(command.names, vec!["db1", "db2"]);
+    }
+
+    #[tokio::test]
+    async fn rename_refuses_a_name_already_taken() -> Result<()> {
+        let client = |renames: usize| {
+            let mut mock = MockCloudClientInterface::new();
+            mock.expect_get_databases().returning(|_| {
+                Ok(vec![
+                    Database::new("todo-db".to_owned(), vec![]),
+                    Database::new("todo-db-2".to_owned(), vec![]),
+                ])
+            });
+            mock.expect_rename_database()
+                .withf(|name, new_name| name == "todo-db" && new_name == "todo-db-3")
+                .times(renames)
+                .returning(|_, _| Ok(()));
+            mock
+        };
+        let rename = |new_name: &str| RenameCommand {
+            name: "todo-db".to_owned(),
+            new_name: new_name.to_owned(),
+            yes: false,
+            common: Default::default(),
+        };
+
+        let err = rename("todo-db-2").run(client(0)).await.unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        // Unlinked databases are renamed without a prompt.
+        rename("todo-db-3").run(client(1)).await
+    }
+
+    #[tokio::test]
+    async fn test_delete_if_db_exists_then_it_is_deleted() -> Result<()> {
+        let command = DeleteCommand {
+            names: vec!["db1".to_string()],
+            pattern: None,
+            common: Default::default(),
+            yes: true,
+            if_exists: false,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        mock.expect_delete_database().returning(|_| Ok(()));
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_db_if_db_exists_then_statement_is_executed() -> Result<()> {
+        let db = "db1";
+        let sql = "CREATE TABLE test (message TEXT)";
+
+        let command = ExecuteCommand {
+            database: vec![db.to_string()],
+            all_databases: false,
+            continue_on_error: false,
+            label: None,
+            app: None,
+            non_interactive: false,
+            common: Default::default(),
+            statement: Some(sql.to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(vec![Database::new(db.to_string(), vec![])]));
+        mock.expect_execute_sql()
+            .withf(move |dbarg, sqlarg| dbarg == db && sqlarg == sql)
+            .returning(|_, _| Ok(()));
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_execute_select_fetches_rows() -> Result<()> {
+        let db = "db1";
+        let sql = "  -- recent messages\n select * from test";
+
+        let command = ExecuteCommand {
+            database: vec![db.to_string()],
+            all_databases: false,
+            continue_on_error: false,
+            label: None,
+            app: None,
+            non_interactive: false,
+            common: Default::default(),
+            statement: Some(sql.to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(vec![Database::new(db.to_string(), vec![])]));
+        mock.expect_query_sql()
+            .withf(move |q| q.database == db && q.statement == sql)
+            .returning(|_| {
+                Ok(QueryResult {
+                    columns: vec!["message".to_owned()],
+                    rows: vec![vec!["hello".into()]],
+                })
+            });
+        mock.expect_execute_sql().never();
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_execute_transaction_reports_the_failing_statement() {
+        let command = ExecuteCommand {
+            database: vec!["db1".to_string()],
+            all_databases: false,
+            continue_on_error: false,
+            label: None,
+            app: None,
+            non_interactive: false,
+            common: Default::default(),
+            statement: Some(
+                "CREATE TABLE t (c TEXT); INSERT INTO t VALUES ('a;b'); INSERT INTO missing VALUES (1); INSERT INTO t VALUES ('c');"
+                    .to_owned(),
+            ),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: true,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(|_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        let scripts = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = scripts.clone();
+        mock.expect_execute_sql().returning(move |_, script| {
+            recorded.lock().unwrap().push(script.clone());
+            if script.contains("missing") {
+                Err(anyhow::anyhow!("no such table: missing"))
+            } else {
+                Ok(())
+            }
+        });
+
+        let err = command.run(mock).await.unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Statement 3 of 4 failed, so the transaction was rolled back: INSERT INTO missing VALUES (1): no such table: missing"
+        );
+        let scripts = scripts.lock().unwrap();
+        assert!(scripts[0].starts_with(
+            "BEGIN TRANSACTION;\nCREATE TABLE t (c TEXT);\nINSERT INTO t VALUES ('a;b');"
+        ));
+        assert!(scripts[0].ends_with("COMMIT;"));
+        assert!(scripts[1..].iter().all(|s| s.ends_with("ROLLBACK;")));
+    }
+
+    #[tokio::test]
+    async fn failed_restores_leave_the_database_as_it_was() {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_query_sql().returning(|_| {
+            Ok(QueryResult {
+                columns: vec!["type".to_owned(), "name".to_owned()],
+                rows: vec![vec!["table".into(), "todos".into()]],
+            })
+        });
+        let scripts = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = scripts.clone();
+        mock.expect_execute_sql().returning(move |_, script| {
+            recorded.lock().unwrap().push(script.clone());
+            if script.contains("INSERT INTO todos VALUES (2)") {
+                Err(anyhow::anyhow!("UNIQUE constraint failed"))
+            } else {
+                Ok(())
+            }
+        });
+
+        let mut script = String::from(
+            "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nCREATE TABLE todos (id INTEGER PRIMARY KEY);\n",
+        );
+        for id in [1, 2, 2] {
+            script.push_str(&format!("INSERT INTO todos VALUES ({id});\n"));
+        }
+        script.push_str("COMMIT;\n");
+        let err = restore_backup(&mock, "todo-db", true, &script)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Statement 4 of 5 failed, so the transaction was rolled back: INSERT INTO todos VALUES (2): UNIQUE constraint failed"
+        );
+        let scripts = scripts.lock().unwrap();
+        assert!(scripts[0].starts_with(
+            "BEGIN TRANSACTION;\nDROP TABLE IF EXISTS \"todos\";\nCREATE TABLE todos"
+        ));
+        assert!(scripts[0].ends_with("COMMIT;"));
+        assert!(
+            scripts[1..].iter().all(|s| s.ends_with("ROLLBACK;")),
+            "nothing is dropped or restored outside the failed transaction"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_transaction_reports_a_failed_commit() {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_execute_sql().returning(|_, script| {
+            if script.ends_with("COMMIT;") {
+                Err(anyhow::anyhow!("database is locked"))
+            } else {
+                Ok(())
+            }
+        });
+        let statements = vec!["INSERT INTO t VALUES (1)".to_owned()];
+        let err = execute_transaction(&mock, "db1", &statements)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Every statement succeeded, but the transaction failed to commit: database is locked"
+        );
+
+        let err = execute_transaction(&mock, "db1", &[]).await.unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "The transaction failed to commit: database is locked"
+        );
+    }
+
+    #[test]
+    fn statements_returning_rows_are_recognised() {
+        assert!(returns_rows("SELECT 1"));
+        assert!(returns_rows(
+            "/* count */ with t as (select 1) select * from t"
+        ));
+        assert!(returns_rows("PRAGMA table_info(test)"));
+        assert!(!returns_rows("INSERT INTO test VALUES ('hi')"));
+        assert!(!returns_rows("-- select\nDELETE FROM test"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_db_if_db_does_not_exist_then_error() -> Result<()> {
+        let askeddb = "asked-for";
+        let actualdb = "actual";
+        let sql = "CREATE TABLE test (message TEXT)";
+
+        let command = ExecuteCommand {
+            database: vec![askeddb.to_string()],
+            all_databases: false,
+            continue_on_error: false,
+            label: None,
+            app: None,
+            non_interactive: false,
+            common: Default::default(),
+            statement: Some(sql.to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(vec![Database::new(actualdb.to_string(), vec![])]));
+
+        let err = command
+            .run(mock)
+            .await
+            .expect_err("exec should have errored but did not");
+        assert_eq!(
+            err.to_string(),
+            r#"No database found with name "asked-for""#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_label_if_label_linked_then_statement_is_executed() -> Result<()> {
+        let label = "email";
+        let app = "messaging";
+        let sql = "CREATE TABLE test (message TEXT)";
+
+        let command = ExecuteCommand {
+            database: vec![],
+            all_databases: false,
+            continue_on_error: false,
+            label: Some(label.to_string()),
+            app: Some(app.to_string()),
+            non_interactive: false,
+            common: Default::default(),
+            statement: Some(sql.to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(fake_dbs()));
+        mock.expect_execute_sql()
+            .withf(move |dbarg, sqlarg| dbarg == "db2" && sqlarg == sql)
+            .returning(|_, _| Ok(()));
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_label_if_label_not_linked_then_error() -> Result<()> {
+        let label = "snailmail";
+        let app = "messaging";
+        let sql = "CREATE TABLE test (message TEXT)";
+
+        let command = ExecuteCommand {
+            database: vec![],
+            all_databases: false,
+            continue_on_error: false,
+            label: Some(label.to_string()),
+            app: Some(app.to_string()),
+            non_interactive: false,
+            common: Default::default(),
+            statement: Some(sql.to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(fake_dbs()));
+
+        let err = command
+            .run(mock)
+            .await
+            .expect_err("exec should have errored but did not");
+        assert_eq!(
+            err.to_string(),
+            r#"No database found with label "snailmail" for app "messaging""#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_app_with_one_linked_db_then_it_is_inferred() -> Result<()> {
+        let sql = "CREATE TABLE test (message TEXT)";
+
+        let command = ExecuteCommand {
+            database: vec![],
+            all_databases: false,
+            continue_on_error: false,
+            label: None,
+            app: Some("docs".to_string()),
+            non_interactive: true,
+            common: Default::default(),
+            statement: Some(sql.to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(fake_dbs()));
+        mock.expect_execute_sql()
+            .withf(move |dbarg, sqlarg| dbarg == "db2" && sqlarg == sql)
+            .returning(|_, _| Ok(()));
+
+        command.run(mock).await
+    }
+
+    #[tokio::test]
+    async fn test_execute_by_app_with_several_linked_dbs_non_interactive_then_error() -> Result<()>
+    {
+        let command = ExecuteCommand {
+            database: vec![],
+            all_databases: false,
+            continue_on_error: false,
+            label: None,
+            app: Some("messaging".to_string()),
+            non_interactive: true,
+            common: Default::default(),
+            statement: Some("SELECT 1".to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        };
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(move |_| Ok(fake_dbs()));
+
+        let err = command
+            .run(mock)
+            .await
+            .expect_err("exec should have errored but did not");
+        assert_eq!(
+            err.to_string(),
+            r#"App "messaging" is linked to more than one database. Use --label to choose one of: email, voicemail"#
+        );
+        Ok(())
+    }
+
+    fn broadcast_command(databases: &[&str], continue_on_error: bool) -> ExecuteCommand {
+        ExecuteCommand {
+            database: databases.iter().map(|d| d.to_string()).collect(),
+            all_databases: false,
+            continue_on_error,
+            label: None,
+            app: None,
+            non_interactive: true,
+            common: Default::default(),
+            statement: Some("CREATE INDEX idx ON t (c)".to_owned()),
+            to_local: None,
+            local_table: "results".to_owned(),
+            format: ResultFormat::Table,
+            transaction: false,
+            params: vec![],
+            json_params: vec![],
+            timeout_secs: None,
+            page_size: None,
+            read_only: false,
+            if_not_exists: false,
+            output: None,
+        }
+    }
+
+    fn mock_with_three_dbs() -> MockCloudClientInterface {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().returning(move |_| {
+            Ok(["db1", "db2", "db3"]
+                .iter()
+                .map(|d| Database::new(d.to_string(), vec![]))
+                .collect())
+        });
+        mock
+    }
+
+    #[tokio::test]
+    async fn timed_out_statements_are_cancelled_where_they_were_sent() -> Result<()> {
+        let mut mock = mock_with_three_dbs();
+        mock.expect_cancel_sql_statements()
+            .withf(|db| db == "db1" || db == "db3")
+            .times(2)
+            .returning(|_| Ok(()));
+        broadcast_command(&["db1", "db3"], false)
+            .cancel(&mock, None)
+            .await?;
+
+        let mut mock = mock_with_three_dbs();
+        mock.expect_cancel_sql_statements()
+            .withf(|db| db == "db2")
+            .times(1)
+            .returning(|_| Ok(()));
+        broadcast_command(&[], false)
+            .cancel(&mock, Some(&ExecuteTarget::Database("db2".to_owned())))
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_on_several_dbs_stops_at_first_failure() -> Result<()> {
+        let mut mock = mock_with_three_dbs();
+        mock.expect_execute_sql()
+            .withf(|db, _| db == "db1")
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mock.expect_execute_sql()
+            .withf(|db, _| db == "db2")
+            .times(1)
+            .returning(|_, _| Err(anyhow::anyhow!("no such table: t")));
+
+        let err = broadcast_command(&["db1", "db2", "db3"], false)
+            .run(mock)
+            .await
+            .expect_err("exec should have errored but did not");
+        assert_eq!(err.to_string(), "The statement failed on 1 of 3 databases");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_on_several_dbs_can_continue_on_error() -> Result<()> {
+        let mut mock = mock_with_three_dbs();
+        mock.expect_execute_sql()
+            .withf(|db, _| db != "db2")
+            .times(2)
+            .returning(|_, _| Ok(()));
+        mock.expect_execute_sql()
+            .withf(|db, _| db == "db2")
+            .returning(|_, _| Err(anyhow::anyhow!("no such table: t")));
+
+        assert!(broadcast_command(&["db1", "db2", "db3"], true)
+            .run(mock)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_on_several_dbs_checks_names_first() -> Result<()> {
+        let mock = mock_with_three_dbs();
+        let err = broadcast_command(&["db1", "typo"], true)
+            .run(mock)
             .await
-            .context("Problem fetching databases")?;
-        let found = list.iter().find(|d| d.name == self.name);
-        if found.is_none() {
-            anyhow::bail!("No database found with name \"{}\"", self.name);
-        }
-        client
-            .rename_database(self.name.clone(), self.new_name.clone())
-            .await?;
-        println!(
-            "Database \"{}\" is now named \"{}\"",
-            self.name, self.new_name
-        );
+            .expect_err("exec should have errored but did not");
+        assert_eq!(err.to_string(), r#"No database found with name "typo""#);
         Ok(())
     }
-}
 
-/// Print apps optionally filtering to a specifically supplied app and/or database
-fn print_apps<'a>(
-    mut links: Vec<Link>,
-    databases_without_links: impl Iterator<Item = &'a Database>,
-) {
-    links.sort_by(|l1, l2| l1.app_name().cmp(l2.app_name()));
+    fn metadata(name: &str, mine: bool, created_at: &str, size: u64) -> DatabaseMetadata {
+        DatabaseMetadata {
+            name: name.to_owned(),
+            created_by: Some("someone".to_owned()),
+            created_by_current_user: mine,
+            created_at: Some(created_at.to_owned()),
+            size_bytes: Some(size),
+            region: None,
+            last_accessed_at: None,
+        }
+    }
 
-    let mut table = comfy_table::Table::new();
-    table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
-    table.set_header(vec!["App", "Label", "Database"]);
+    #[tokio::test]
+    async fn list_fetches_metadata_only_when_it_is_used() -> Result<()> {
+        let command = |args: &[&str]| ListCommand::try_parse_from([&["list"], args].concat());
 
-    let rows = links.iter().map(|link| {
-        [
-            link.app_name(),
-            link.resource_label.label.as_str(),
-            link.resource.as_str(),
-        ]
-    });
-    table.add_rows(rows);
-    println!("{table}");
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_database_metadata().times(0);
+        assert!(command(&[])?.database_metadata(&mock).await?.is_none());
 
-    let mut databases_without_links = databases_without_links.peekable();
-    if databases_without_links.peek().is_none() {
-        return;
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_database_metadata()
+            .times(2)
+            .returning(|| Err(anyhow::anyhow!("not supported")));
+        assert!(command(&["--group-by", "database"])?
+            .database_metadata(&mock)
+            .await?
+            .is_none());
+        assert!(command(&["--sort-by", "size"])?
+            .database_metadata(&mock)
+            .await
+            .is_err());
+        Ok(())
     }
 
-    let mut table = comfy_table::Table::new();
-    println!("Databases not linked to any app");
-    table.set_header(vec!["Database"]);
-    table.add_rows(databases_without_links.map(|d| [&d.name]));
-    println!("{table}");
-}
+    #[test]
+    fn list_filters_by_owner_and_creation_date() {
+        let command = ListCommand::try_parse_from([
+            "list",
+            "--mine",
+            "--created-after",
+            "2024-01-01",
+            "--created-before",
+            "2024-06-01T00:00:00Z",
+        ])
+        .unwrap();
+        assert!(command.matches_filters(Some(&metadata("a", true, "2024-03-01T10:00:00Z", 1))));
+        assert!(!command.matches_filters(Some(&metadata("b", false, "2024-03-01T10:00:00Z", 1))));
+        assert!(!command.matches_filters(Some(&metadata("c", true, "2023-12-31T23:59:59Z", 1))));
+        assert!(!command.matches_filters(Some(&metadata("d", true, "2024-07-01T00:00:00Z", 1))));
+        assert!(!command.matches_filters(None));
+    }
 
-/// Print databases optionally filtering to a specifically supplied app and/or database
-fn print_databases<'a>(
-    mut links: Vec<Link>,
-    databases_without_links: impl Iterator<Item = &'a Database>,
-) {
-    links.sort_by(|l1, l2| l1.resource.cmp(&l2.resource));
+    #[test]
+    fn databases_sort_by_size_and_age() {
+        let mut dbs = vec![
+            Database::new("small".to_string(), vec![]),
+            Database::new("unknown".to_string(), vec![]),
+            Database::new("large".to_string(), vec![]),
+        ];
+        let metadata = HashMap::from([
+            (
+                "small".to_owned(),
+                metadata("small", true, "2024-02-01T00:00:00Z", 10),
+            ),
+            (
+                "large".to_owned(),
+                metadata("large", true, "2024-03-01T00:00:00Z", 2048),
+            ),
+        ]);
+        let names = |dbs: &[Database]| dbs.iter().map(|d| d.name.clone()).collect::<Vec<_>>();
 
-    let mut table = comfy_table::Table::new();
-    table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
-    table.set_header(vec!["Database", "Links"]);
-    table.add_rows(databases_without_links.map(|d| [&d.name, "-"]));
+        sort_databases(&mut dbs, SortBy::Size, Some(&metadata));
+        assert_eq!(names(&dbs), ["large", "small", "unknown"]);
+        sort_databases(&mut dbs, SortBy::Age, Some(&metadata));
+        assert_eq!(names(&dbs), ["small", "large", "unknown"]);
+        sort_databases(&mut dbs, SortBy::Name, None);
+        assert_eq!(names(&dbs), ["large", "small", "unknown"]);
+    }
 
-    let mut map = BTreeMap::new();
-    for link in &links {
-        let app_name = link.app_name();
-        map.entry(&link.resource)
-            .and_modify(|v| *v = format!("{}, {}:{}", *v, app_name, link.resource_label.label))
-            .or_insert(format!("{}:{}", app_name, link.resource_label.label));
+    #[test]
+    fn databases_sort_by_last_access() {
+        let mut dbs = vec![
+            Database::new("busy".to_string(), vec![]),
+            Database::new("never".to_string(), vec![]),
+            Database::new("stale".to_string(), vec![]),
+        ];
+        let accessed = |name: &str, at: &str| DatabaseMetadata {
+            last_accessed_at: Some(at.to_owned()),
+            ..metadata(name, true, "2024-01-01T00:00:00Z", 1)
+        };
+        let metadata = HashMap::from([
+            ("busy".to_owned(), accessed("busy", "2024-06-01T12:00:00Z")),
+            (
+                "stale".to_owned(),
+                accessed("stale", "2024-02-01T08:30:00Z"),
+            ),
+        ]);
+        sort_databases(&mut dbs, SortBy::Accessed, Some(&metadata));
+        assert_eq!(
+            dbs.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+            ["stale", "busy", "never"]
+        );
+        assert_eq!(
+            metadata_cells(metadata.get("stale"))[2],
+            "2024-02-01 08:30".to_owned()
+        );
     }
-    table.add_rows(map.iter().map(|(d, l)| [d, l]));
-    println!("{table}");
-}
 
-fn prompt_delete_database(database: &str, links: &[ResourceLabel]) -> std::io::Result<bool> {
-    let existing_links = links
-        .iter()
-        .map(|l| l.app_name.as_deref().unwrap_or("UNKNOWN"))
-        .collect::<Vec<&str>>()
-        .join(", ");
-    let mut prompt = String::new();
-    if !existing_links.is_empty() {
-        // TODO: use warning color text
-        prompt.push_str(&format!("Database \"{database}\" is currently linked to the following apps: {existing_links}.\n\
-        It is recommended to use `spin cloud link sqlite` to link to another database to those apps before deleting.\n"))
+    #[test]
+    fn databases_can_be_filtered_by_whether_they_are_linked() {
+        let linked = Database::new(
+            "todo-db".to_string(),
+            vec![ResourceLabel {
+                app_id: uuid::Uuid::new_v4(),
+                label: "default".to_owned(),
+                app_name: Some("todo".to_owned()),
+            }],
+        );
+        let orphaned = Database::new("old-db".to_string(), vec![]);
+        let list = |args: &[&str]| {
+            ListCommand::try_parse_from([&["list"], args].concat()).map(|cmd| {
+                [&linked, &orphaned]
+                    .into_iter()
+                    .filter(|db| cmd.matches_links(db))
+                    .map(|db| db.name.as_str())
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(list(&["--orphaned"]).unwrap(), ["old-db"]);
+        assert_eq!(list(&["--linked"]).unwrap(), ["todo-db"]);
+        assert_eq!(list(&[]).unwrap(), ["todo-db", "old-db"]);
+        assert!(list(&["--orphaned", "--linked"]).is_err());
+        assert!(list(&["--orphaned", "--app", "todo"]).is_err());
     }
-    prompt.push_str(&format!(
-        "The action is irreversible. Please type \"{database}\" for confirmation"
-    ));
-    let mut input = Input::<String>::new();
-    input.with_prompt(prompt);
-    let answer = input.interact_text()?;
-    if answer != database {
-        println!("Invalid confirmation. Will not delete database.");
-        Ok(false)
-    } else {
-        println!("Deleting database ...");
-        Ok(true)
+
+    #[test]
+    fn sizes_are_human_readable() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
     }
-}
 
-pub fn find_database_link(db: &Database, label: &str) -> Option<Link> {
-    db.links.iter().find_map(|r| {
-        if r.label == label {
-            Some(Link::new(r.clone(), db.name.clone()))
-        } else {
-            None
+    fn users_table() -> QueryResult {
+        QueryResult {
+            columns: vec!["id".to_owned(), "name".to_owned(), "bio".to_owned()],
+            rows: vec![
+                vec![1.into(), "Ada".into(), "Wrote \"notes\", mostly".into()],
+                vec![2.into(), "Grace".into(), serde_json::Value::Null],
+            ],
         }
-    })
-}
+    }
 
-pub fn database_has_link(database: &Database, label: &str, app: Option<&str>) -> bool {
-    database
-        .links
-        .iter()
-        .any(|l| l.label == label && l.app_name.as_deref() == app)
-}
+    #[test]
+    fn execute_formats_cannot_be_combined_with_to_local() {
+        let command =
+            ExecuteCommand::try_parse_from(["execute", "-d", "db1", "--format", "csv", "SELECT 1"])
+                .unwrap();
+        assert_eq!(command.format, ResultFormat::Csv);
+        assert!(ExecuteCommand::try_parse_from([
+            "execute",
+            "-d",
+            "db1",
+            "--format",
+            "json",
+            "--to-local",
+            "copy.db",
+        ])
+        .is_err());
+    }
 
-#[cfg(test)]
-mod sqlite_tests {
-    use super::*;
-    use cloud::MockCloudClientInterface;
+    #[test]
+    fn query_results_export_as_csv() {
+        assert_eq!(
+            to_csv(&users_table()),
+            "id,name,bio\n1,Ada,\"Wrote \"\"notes\"\", mostly\"\n2,Grace,\n"
+        );
+    }
 
-    #[tokio::test]
-    async fn test_create_if_db_already_exists_then_error() -> Result<()> {
-        let command = CreateCommand {
-            name: "db1".to_string(),
-            common: Default::default(),
-        };
-        let dbs = vec![
-            Database::new("db1".to_string(), vec![]),
-            Database::new("db2".to_string(), vec![]),
-        ];
+    #[test]
+    fn query_results_export_as_markdown() {
+        let mut result = users_table();
+        result
+            .rows
+            .push(vec![3.into(), "Linus".into(), "a|b\nc".into()]);
+        assert_eq!(
+            to_markdown(&result),
+            "| id | name | bio |\n| --- | --- | --- |\n| 1 | Ada | Wrote \"notes\", mostly |\n| 2 | Grace |  |\n| 3 | Linus | a\\|b<br>c |\n"
+        );
+        let command = ExecuteCommand::try_parse_from([
+            "execute", "-d", "db1", "--format", "markdown", "SELECT 1",
+        ])
+        .unwrap();
+        assert_eq!(command.format, ResultFormat::Markdown);
+    }
 
-        let mut mock = MockCloudClientInterface::new();
-        mock.expect_get_databases().return_once(move |_| Ok(dbs));
+    #[test]
+    fn listings_render_markdown_like_query_results() {
+        let mut table = new_table();
+        table.set_header(vec!["App", "Label", "Database"]);
+        table.add_row(vec!["todo", "a|b", "todo-db"]);
+        assert_eq!(
+            table_markdown(&table),
+            "| App | Label | Database |\n| --- | --- | --- |\n| todo | a\\|b | todo-db |\n"
+        );
+        let mut summary = new_table();
+        summary.add_row(vec!["Tables", "2"]);
+        assert_eq!(
+            table_markdown(&summary),
+            "|  |  |\n| --- | --- |\n| Tables | 2 |\n"
+        );
+    }
 
-        let result = command.run(mock).await;
+    #[test]
+    fn project_default_database_is_used_without_options() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let err = project_default_database(dir.path()).unwrap_err();
         assert_eq!(
-            result.unwrap_err().to_string(),
-            r#"Database "db1" already exists"#
+            err.to_string(),
+            "No database given. Use --database, or set default_database in spin-cloud.toml"
         );
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            r#"default_database = "prod-db""#,
+        )?;
+        assert_eq!(project_default_database(dir.path())?, "prod-db");
+
+        let command = ExecuteCommand::try_parse_from(["execute", "SELECT 1"])?;
+        assert!(command.database.is_empty());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_create_if_db_does_not_exist_db_is_created() -> Result<()> {
-        let command = CreateCommand {
-            name: "db1".to_string(),
-            common: Default::default(),
-        };
-        let dbs = vec![Database::new("db2".to_string(), vec![])];
+    async fn execute_output_writes_rows_to_a_new_directory() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("reports").join("users.csv");
+        let mut command = ExecuteCommand::try_parse_from([
+            "execute",
+            "-d",
+            "db1",
+            "--format",
+            "csv",
+            "SELECT * FROM users",
+        ])?;
+        command.output = Some(path.clone());
 
         let mut mock = MockCloudClientInterface::new();
-        mock.expect_get_databases().return_once(move |_| Ok(dbs));
-        mock.expect_create_database()
-            .withf(move |db, rl| db == "db1" && rl.is_none())
-            .returning(|_, _| Ok(()));
+        mock.expect_get_databases()
+            .returning(|_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        mock.expect_query_sql().returning(|_| Ok(users_table()));
+        command.run(mock).await?;
 
-        command.run(mock).await
+        assert_eq!(std::fs::read_to_string(path)?, to_csv(&users_table()));
+        Ok(())
     }
 
-    #[tokio::test]
-    async fn test_delete_if_db_does_not_exist_then_error() -> Result<()> {
-        let command = DeleteCommand {
-            name: "db1".to_string(),
-            common: Default::default(),
-            yes: true,
-        };
-
-        let mut mock = MockCloudClientInterface::new();
-        mock.expect_get_databases().returning(move |_| Ok(vec![]));
-
-        let result = command.run(mock).await;
+    #[test]
+    fn query_results_export_as_json() -> Result<()> {
+        let json: serde_json::Value = serde_json::from_str(&to_json(&users_table())?)?;
         assert_eq!(
-            result.unwrap_err().to_string(),
-            r#"No database found with name "db1""#
+            json,
+            serde_json::json!([
+                { "id": 1, "name": "Ada", "bio": "Wrote \"notes\", mostly" },
+                { "id": 2, "name": "Grace", "bio": null },
+            ])
         );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_delete_if_db_exists_then_it_is_deleted() -> Result<()> {
-        let command = DeleteCommand {
-            name: "db1".to_string(),
+    async fn test_export_without_tables_exports_every_table() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let command = ExportCommand {
+            name: "db1".to_owned(),
+            format: ExportFormat::Csv,
+            tables: vec![],
+            output_dir: Some(dir.path().join("out")),
+            progress: ProgressFormat::Text,
             common: Default::default(),
-            yes: true,
         };
 
         let mut mock = MockCloudClientInterface::new();
         mock.expect_get_databases()
             .returning(move |_| Ok(vec![Database::new("db1".to_string(), vec![])]));
-        mock.expect_delete_database().returning(|_| Ok(()));
+        mock.expect_query_sql().returning(|query| {
+            Ok(if query.statement.contains("sqlite_master") {
+                QueryResult {
+                    columns: vec!["name".to_owned()],
+                    rows: vec![vec!["posts".into()], vec!["users".into()]],
+                }
+            } else {
+                users_table()
+            })
+        });
 
-        command.run(mock).await
+        command.run(mock).await?;
+        assert!(dir.path().join("out/posts.csv").exists());
+        assert!(dir.path().join("out/users.csv").exists());
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_execute_by_db_if_db_exists_then_statement_is_executed() -> Result<()> {
-        let db = "db1";
-        let sql = "CREATE TABLE test (message TEXT)";
-
-        let command = ExecuteCommand {
-            database: Some(db.to_string()),
-            label: None,
-            app: None,
+    async fn exported_files_stay_in_the_output_directory() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let command = ExportCommand {
+            name: "db1".to_owned(),
+            format: ExportFormat::Json,
+            tables: vec!["../../x".to_owned(), "a/b".to_owned()],
+            output_dir: Some(dir.path().join("a/out")),
+            progress: ProgressFormat::Text,
             common: Default::default(),
-            statement: sql.to_owned(),
         };
 
         let mut mock = MockCloudClientInterface::new();
         mock.expect_get_databases()
-            .returning(move |_| Ok(vec![Database::new(db.to_string(), vec![])]));
-        mock.expect_execute_sql()
-            .withf(move |dbarg, sqlarg| dbarg == db && sqlarg == sql)
-            .returning(|_, _| Ok(()));
+            .returning(move |_| Ok(vec![Database::new("db1".to_string(), vec![])]));
+        mock.expect_query_sql().returning(|_| Ok(users_table()));
 
-        command.run(mock).await
+        command.run(mock).await?;
+        let mut written = std::fs::read_dir(dir.path().join("a/out"))?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>>>()?;
+        written.sort();
+        assert_eq!(written, vec!["%2E.%2F..%2Fx.json", "a%2Fb.json"]);
+        assert!(!dir.path().join("x.json").exists());
+        assert_eq!(export_file_name("my table.v2", "csv"), "my table.v2.csv");
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_execute_by_db_if_db_does_not_exist_then_error() -> Result<()> {
-        let askeddb = "asked-for";
-        let actualdb = "actual";
-        let sql = "CREATE TABLE test (message TEXT)";
-
-        let command = ExecuteCommand {
-            database: Some(askeddb.to_string()),
-            label: None,
-            app: None,
-            common: Default::default(),
-            statement: sql.to_owned(),
-        };
-
+    async fn table_schemas_read_columns_and_indexes() -> Result<()> {
         let mut mock = MockCloudClientInterface::new();
-        mock.expect_get_databases()
-            .returning(move |_| Ok(vec![Database::new(actualdb.to_string(), vec![])]));
+        mock.expect_query_sql().returning(|query| {
+            let result = |columns: &[&str], rows: Vec<Vec<serde_json::Value>>| QueryResult {
+                columns: columns.iter().map(|c| c.to_string()).collect(),
+                rows,
+            };
+            Ok(match query.statement.as_str() {
+                r#"PRAGMA table_info("todos")"# => result(
+                    &["cid", "name", "type", "notnull", "dflt_value", "pk"],
+                    vec![
+                        vec![
+                            0.into(),
+                            "id".into(),
+                            "INTEGER".into(),
+                            0.into(),
+                            serde_json::Value::Null,
+                            1.into(),
+                        ],
+                        vec![
+                            1.into(),
+                            "done".into(),
+                            "INTEGER".into(),
+                            1.into(),
+                            "0".into(),
+                            0.into(),
+                        ],
+                    ],
+                ),
+                r#"PRAGMA index_list("todos")"# => result(
+                    &["seq", "name", "unique", "origin", "partial"],
+                    vec![vec![
+                        0.into(),
+                        "idx_done".into(),
+                        0.into(),
+                        "c".into(),
+                        0.into(),
+                    ]],
+                ),
+                r#"PRAGMA index_info("idx_done")"# => result(
+                    &["seqno", "cid", "name"],
+                    vec![vec![0.into(), 1.into(), "done".into()]],
+                ),
+                other => panic!("unexpected statement {other}"),
+            })
+        });
 
-        let err = command
-            .run(mock)
-            .await
-            .expect_err("exec should have errored but did not");
+        let schemas = describe_tables(&mock, "todo-db", &["todos".to_owned()]).await?;
         assert_eq!(
-            err.to_string(),
-            r#"No database found with name "asked-for""#
+            serde_json::to_value(&schemas)?,
+            serde_json::json!([{
+                "name": "todos",
+                "columns": [
+                    { "name": "id", "type": "INTEGER", "not_null": false, "default": null, "primary_key": true },
+                    { "name": "done", "type": "INTEGER", "not_null": true, "default": "0", "primary_key": false },
+                ],
+                "indexes": [{ "name": "idx_done", "unique": false, "columns": ["done"] }],
+            }])
         );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_execute_by_label_if_label_linked_then_statement_is_executed() -> Result<()> {
-        let label = "email";
-        let app = "messaging";
-        let sql = "CREATE TABLE test (message TEXT)";
-
-        let command = ExecuteCommand {
-            database: None,
-            label: Some(label.to_string()),
-            app: Some(app.to_string()),
+    async fn copy_recreates_tables_and_rows_in_a_new_database() -> Result<()> {
+        let command = CopyCommand {
+            source: "prod-db".to_owned(),
+            target: "staging-db".to_owned(),
+            region: None,
+            progress: ProgressFormat::Json,
             common: Default::default(),
-            statement: sql.to_owned(),
         };
-
         let mut mock = MockCloudClientInterface::new();
         mock.expect_get_databases()
-            .returning(move |_| Ok(fake_dbs()));
+            .returning(|_| Ok(vec![Database::new("prod-db".to_string(), vec![])]));
+        mock.expect_query_sql().returning(|query| {
+            Ok(if query.statement.contains("sqlite_master") {
+                QueryResult {
+                    columns: vec!["type".into(), "name".into(), "sql".into()],
+                    rows: vec![vec![
+                        "table".into(),
+                        "todos".into(),
+                        "CREATE TABLE todos (id INTEGER, title TEXT)".into(),
+                    ]],
+                }
+            } else {
+                QueryResult {
+                    columns: vec!["id".into(), "title".into()],
+                    rows: vec![vec![1.into(), "Ship it".into()]],
+                }
+            })
+        });
+        mock.expect_create_database()
+            .withf(|db, _, _| db == "staging-db")
+            .times(1)
+            .returning(|_, _, _| Ok(()));
         mock.expect_execute_sql()
-            .withf(move |dbarg, sqlarg| dbarg == "db2" && sqlarg == sql)
+            .withf(|db, sql| {
+                db == "staging-db"
+                    && sql == "CREATE TABLE todos (id INTEGER, title TEXT);\nINSERT INTO \"todos\" VALUES(1,'Ship it');"
+            })
+            .times(1)
             .returning(|_, _| Ok(()));
 
         command.run(mock).await
     }
 
     #[tokio::test]
-    async fn test_execute_by_label_if_label_not_linked_then_error() -> Result<()> {
-        let label = "snailmail";
-        let app = "messaging";
-        let sql = "CREATE TABLE test (message TEXT)";
-
-        let command = ExecuteCommand {
-            database: None,
-            label: Some(label.to_string()),
-            app: Some(app.to_string()),
+    async fn copy_refuses_to_overwrite_an_existing_database() {
+        let command = CopyCommand {
+            source: "prod-db".to_owned(),
+            target: "staging-db".to_owned(),
+            region: None,
+            progress: ProgressFormat::Text,
             common: Default::default(),
-            statement: sql.to_owned(),
         };
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases().returning(|_| {
+            Ok(vec![
+                Database::new("prod-db".to_string(), vec![]),
+                Database::new("staging-db".to_string(), vec![]),
+            ])
+        });
+
+        let err = command.run(mock).await.unwrap_err();
+        assert_eq!(err.to_string(), r#"Database "staging-db" already exists"#);
+    }
 
+    #[tokio::test]
+    async fn stats_fall_back_to_page_count_for_size() -> Result<()> {
+        let command = StatsCommand {
+            name: "todo-db".to_owned(),
+            format: ListFormat::Json,
+            common: Default::default(),
+        };
         let mut mock = MockCloudClientInterface::new();
-        mock.expect_get_databases()
-            .returning(move |_| Ok(fake_dbs()));
+        mock.expect_get_databases().returning(|_| {
+            Ok(vec![Database::new(
+                "todo-db".to_string(),
+                vec![resource_label("default", "todo")],
+            )])
+        });
+        mock.expect_get_database_metadata()
+            .returning(|| Err(anyhow::anyhow!("not supported")));
+        mock.expect_query_sql().returning(|query| {
+            let value: serde_json::Value = if query.statement.contains("sqlite_master") {
+                "todos".into()
+            } else if query.statement.contains("COUNT(*)") {
+                42.into()
+            } else {
+                8192.into()
+            };
+            Ok(QueryResult {
+                columns: vec!["value".to_owned()],
+                rows: vec![vec![value]],
+            })
+        });
 
-        let err = command
-            .run(mock)
-            .await
-            .expect_err("exec should have errored but did not");
         assert_eq!(
-            err.to_string(),
-            r#"No database found with label "snailmail" for app "messaging""#
+            command.stats(&mock).await?,
+            DatabaseStats {
+                name: "todo-db".to_owned(),
+                size_bytes: Some(8192),
+                links: 1,
+                tables: vec![TableStats {
+                    name: "todos".to_owned(),
+                    rows: 42,
+                }],
+            }
         );
         Ok(())
     }
@@ -705,4 +4163,62 @@ mod sqlite_tests {
             app_name: Some(app.to_owned()),
         }
     }
+
+    #[tokio::test]
+    async fn migrate_up_applies_only_pending_migrations() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let create = "CREATE TABLE todos (id INTEGER PRIMARY KEY);";
+        std::fs::write(dir.path().join("1_create_todos.sql"), create)?;
+        std::fs::write(
+            dir.path().join("2_add_done.sql"),
+            "ALTER TABLE todos ADD COLUMN done INTEGER;",
+        )?;
+        let checksum = migrate::read_migrations(dir.path())?[0].checksum.clone();
+
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_databases()
+            .returning(|_| Ok(vec![Database::new("todo-db".to_string(), vec![])]));
+        mock.expect_query_sql().returning(move |query| {
+            let column = |name: &str| vec![name.to_owned()];
+            Ok(if query.statement.contains("sqlite_master") {
+                QueryResult {
+                    columns: column("name"),
+                    rows: vec![vec!["_cloud_migrations".into(), "todos".into()]],
+                }
+            } else {
+                QueryResult {
+                    columns: vec![],
+                    rows: vec![vec![
+                        1.into(),
+                        "1_create_todos.sql".into(),
+                        checksum.clone().into(),
+                        "2024-03-01T10:00:00+00:00".into(),
+                    ]],
+                }
+            })
+        });
+        let scripts = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = scripts.clone();
+        mock.expect_execute_sql().returning(move |_, script| {
+            recorded.lock().unwrap().push(script);
+            Ok(())
+        });
+
+        MigrateUpCommand {
+            name: "todo-db".to_owned(),
+            dir: dir.path().to_owned(),
+            dry_run: false,
+            common: Default::default(),
+        }
+        .run(mock)
+        .await?;
+
+        let scripts = scripts.lock().unwrap();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].contains("ALTER TABLE todos ADD COLUMN done INTEGER;"));
+        assert!(!scripts[0].contains(create));
+        assert!(scripts[0].contains("INSERT INTO _cloud_migrations"));
+        assert!(scripts[0].ends_with("COMMIT;"));
+        Ok(())
+    }
 }
@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use super::import::split_statements;
+use crate::ops::resolve::not_found;
+
+/// Backup IDs are the UTC time the backup was taken, so that they sort in
+/// the order the backups were taken.
+const ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A dump of a database kept on this machine
+#[derive(Debug, PartialEq)]
+pub(super) struct Backup {
+    pub database: String,
+    pub id: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Where backups are kept, one directory per database.
+pub(super) fn backups_dir() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .context("Cannot find data directory")?
+        .join("fermyon")
+        .join("cloud-plugin")
+        .join("sqlite-backups"))
+}
+
+pub(super) fn new_backup_path(root: &Path, database: &str, taken_at: DateTime<Utc>) -> PathBuf {
+    root.join(database)
+        .join(format!("{}.sql", taken_at.format(ID_FORMAT)))
+}
+
+/// Lists backups ordered by database, then oldest first.
+pub(super) fn list_backups(root: &Path, database: Option<&str>) -> Result<Vec<Backup>> {
+    if !root.exists() {
+        return Ok(vec![]);
+    }
+    let mut backups = vec![];
+    for dir in std::fs::read_dir(root)
+        .with_context(|| format!("Could not read {}", root.display()))?
+        .flatten()
+    {
+        let name = dir.file_name().to_string_lossy().into_owned();
+        if database.is_some_and(|d| d != name) || !dir.path().is_dir() {
+            continue;
+        }
+        for file in std::fs::read_dir(dir.path())?.flatten() {
+            let path = file.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("sql") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_owned) else {
+                continue;
+            };
+            backups.push(Backup {
+                database: name.clone(),
+                id,
+                size: file.metadata().map(|m| m.len()).unwrap_or_default(),
+                path,
+            });
+        }
+    }
+    backups.sort_by(|a, b| (&a.database, &a.id).cmp(&(&b.database, &b.id)));
+    Ok(backups)
+}
+
+/// Finds a backup of the database by ID, or its latest backup.
+pub(super) fn find_backup(root: &Path, database: &str, id: Option<&str>) -> Result<Backup> {
+    let backups = list_backups(root, Some(database))?;
+    match id {
+        Some(id) => {
+            let ids = backups.iter().map(|b| b.id.clone()).collect::<Vec<_>>();
+            backups
+                .into_iter()
+                .find(|b| b.id == id)
+                .ok_or_else(|| not_found("backup", id, ids.iter().map(String::as_str)))
+        }
+        None => backups.into_iter().last().with_context(|| {
+            format!(
+                r#"There are no backups of database "{database}". Take one with `spin cloud sqlite backup create {database}`"#
+            )
+        }),
+    }
+}
+
+/// The statements which restore a backup. They are run in a transaction of
+/// their own, so the dump's transaction is left out.
+pub(super) fn restore_statements(script: &str) -> Vec<String> {
+    split_statements(script)
+        .into_iter()
+        .filter(|s| {
+            let s = s.trim_end_matches(';').trim();
+            !(s.eq_ignore_ascii_case("BEGIN TRANSACTION")
+                || s.eq_ignore_ascii_case("COMMIT")
+                || s.eq_ignore_ascii_case("PRAGMA foreign_keys=OFF"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn latest_backup_is_restored_by_default() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let taken_at = |time: &str| {
+            DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        for (database, time) in [
+            ("todo-db", "2024-03-01T10:00:00Z"),
+            ("todo-db", "2024-03-02T09:30:00Z"),
+            ("shop-db", "2024-03-05T00:00:00Z"),
+        ] {
+            let path = new_backup_path(root.path(), database, taken_at(time));
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "CREATE TABLE t (c);")?;
+        }
+
+        let all = list_backups(root.path(), None)?;
+        assert_eq!(
+            all.iter()
+                .map(|b| format!("{}/{}", b.database, b.id))
+                .collect::<Vec<_>>(),
+            vec![
+                "shop-db/20240305T000000Z",
+                "todo-db/20240301T100000Z",
+                "todo-db/20240302T093000Z",
+            ]
+        );
+        assert_eq!(
+            find_backup(root.path(), "todo-db", None)?.id,
+            "20240302T093000Z"
+        );
+        assert_eq!(
+            find_backup(root.path(), "todo-db", Some("20240301T100000Z"))?.id,
+            "20240301T100000Z"
+        );
+        assert!(find_backup(root.path(), "todo-db", Some("yesterday")).is_err());
+        assert!(find_backup(root.path(), "other-db", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn restore_leaves_out_the_dump_transaction() {
+        let script = "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\nCREATE TABLE t (c);\nINSERT INTO \"t\" VALUES(1);\nCOMMIT;\n";
+        assert_eq!(
+            restore_statements(script),
+            vec!["CREATE TABLE t (c);", "INSERT INTO \"t\" VALUES(1);"]
+        );
+    }
+}
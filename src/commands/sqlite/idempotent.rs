@@ -0,0 +1,106 @@
+use super::import::split_statements;
+use crate::sql::{significant_tokens, TokenKind};
+
+/// A CREATE statement rewritten to do nothing if its object already exists
+#[derive(Debug, PartialEq)]
+pub(super) struct Rewrite {
+    pub before: String,
+    pub after: String,
+}
+
+/// Rewrites each CREATE TABLE, INDEX, VIEW or TRIGGER statement of a script
+/// which lacks IF NOT EXISTS to have it, so that the script can be run
+/// again without failing. Returns the script and what was rewritten.
+pub(super) fn add_if_not_exists(script: &str) -> (String, Vec<Rewrite>) {
+    let mut statements = vec![];
+    let mut rewrites = vec![];
+    for statement in split_statements(script) {
+        match if_not_exists_position(&statement) {
+            Some(at) => {
+                let after = format!("{} IF NOT EXISTS{}", &statement[..at], &statement[at..]);
+                rewrites.push(Rewrite {
+                    before: first_line(&statement),
+                    after: first_line(&after),
+                });
+                statements.push(after);
+            }
+            None => statements.push(statement),
+        }
+    }
+    (statements.join("\n"), rewrites)
+}
+
+/// Where IF NOT EXISTS belongs in a CREATE statement: after the kind of
+/// object, as in `CREATE UNIQUE INDEX`. `None` if the statement is not a
+/// CREATE or already has IF NOT EXISTS.
+fn if_not_exists_position(statement: &str) -> Option<usize> {
+    let words = leading_words(statement);
+    let mut words = words
+        .iter()
+        .map(|(word, end)| (word.to_ascii_uppercase(), *end));
+    if words.next()?.0 != "CREATE" {
+        return None;
+    }
+    let mut word = words.next()?;
+    while ["TEMP", "TEMPORARY", "UNIQUE", "VIRTUAL"].contains(&word.0.as_str()) {
+        word = words.next()?;
+    }
+    if !["TABLE", "INDEX", "VIEW", "TRIGGER"].contains(&word.0.as_str()) {
+        return None;
+    }
+    match words.next() {
+        Some((next, _)) if next == "IF" => None,
+        _ => Some(word.1),
+    }
+}
+
+/// The words at the start of a statement, each with the offset just past
+/// it, up to the first token that is not a word. Comments before and
+/// between them are skipped.
+fn leading_words(statement: &str) -> Vec<(&str, usize)> {
+    significant_tokens(statement)
+        .into_iter()
+        .take_while(|t| t.kind == TokenKind::Word)
+        .map(|t| (t.text, t.end()))
+        .collect()
+}
+
+fn first_line(statement: &str) -> String {
+    let statement = statement
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with("--"))
+        .unwrap_or_default();
+    statement.to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn create_statements_gain_if_not_exists() {
+        let script = "-- setup\ncreate table todos (id INTEGER, title TEXT);\n\
+            CREATE UNIQUE INDEX idx_title ON todos (title);\n\
+            CREATE TABLE IF NOT EXISTS tags (name TEXT);\n\
+            CREATE TEMP VIEW open_todos AS SELECT * FROM todos;\n\
+            INSERT INTO todos VALUES (1, 'CREATE TABLE');";
+        let (rewritten, rewrites) = add_if_not_exists(script);
+        assert_eq!(
+            rewritten,
+            "-- setup\ncreate table IF NOT EXISTS todos (id INTEGER, title TEXT);\n\
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_title ON todos (title);\n\
+            CREATE TABLE IF NOT EXISTS tags (name TEXT);\n\
+            CREATE TEMP VIEW IF NOT EXISTS open_todos AS SELECT * FROM todos;\n\
+            INSERT INTO todos VALUES (1, 'CREATE TABLE');"
+        );
+        assert_eq!(rewrites.len(), 3);
+        assert_eq!(
+            rewrites[0],
+            Rewrite {
+                before: "create table todos (id INTEGER, title TEXT);".to_owned(),
+                after: "create table IF NOT EXISTS todos (id INTEGER, title TEXT);".to_owned(),
+            }
+        );
+    }
+}
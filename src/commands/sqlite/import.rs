@@ -0,0 +1,544 @@
+use std::collections::BTreeMap;
+
+use cloud::CloudClientInterface;
+
+use crate::ops::sqlite::quote_identifier;
+use crate::progress::Progress;
+use crate::sql::{significant_tokens, tokenize, Cursor, Token, TokenKind};
+
+/// Statements sent to Cloud in one request, numbered from 1 in the order
+/// they appear in the imported file.
+#[derive(Debug, PartialEq)]
+pub(super) struct Batch {
+    pub first: usize,
+    pub last: usize,
+    pub sql: String,
+}
+
+/// A batch which Cloud rejected
+pub(super) struct FailedBatch {
+    pub first: usize,
+    pub last: usize,
+    pub error: anyhow::Error,
+}
+
+/// Splits a SQL script into statements at each `;` that is not inside a
+/// quoted string, identifier, comment or the body of a trigger. Comments are
+/// kept with the statement that follows them; a trailing comment on its own
+/// is dropped.
+pub(super) fn split_statements(script: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut body = TriggerBody::default();
+    for token in tokenize(script) {
+        match token.kind {
+            TokenKind::Word => body.word(token.text),
+            TokenKind::Punct(';') if !body.is_open() => {
+                let statement = script[start..token.end()].trim();
+                if statement != ";" {
+                    statements.push(statement.to_owned());
+                }
+                start = token.end();
+                body = TriggerBody::default();
+            }
+            _ => {}
+        }
+    }
+    let rest = &script[start..];
+    if !tokenize(rest).iter().all(Token::is_trivia) {
+        statements.push(rest.trim().to_owned());
+    }
+    statements
+}
+
+/// Follows the words of a statement to tell when it is inside the
+/// BEGIN ... END body of a CREATE TRIGGER, whose own statements end with
+/// `;`. CASE ... END is counted too, so that its END does not close the
+/// body early.
+#[derive(Default)]
+struct TriggerBody {
+    leading: Vec<String>,
+    trigger: bool,
+    depth: usize,
+}
+
+impl TriggerBody {
+    fn word(&mut self, word: &str) {
+        let word = word.to_ascii_uppercase();
+        // As in CREATE TEMP TRIGGER
+        if self.leading.len() < 3 {
+            self.leading.push(word.clone());
+            self.trigger =
+                self.leading[0] == "CREATE" && self.leading[1..].iter().any(|w| w == "TRIGGER");
+        }
+        if self.trigger {
+            match word.as_str() {
+                "BEGIN" | "CASE" => self.depth += 1,
+                "END" => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.depth > 0
+    }
+}
+
+/// Whether the first CSV record names the columns rather than holding data:
+/// every field must look like a column name, and none may be a number.
+pub(super) fn looks_like_header(record: &[String]) -> bool {
+    record.iter().all(|field| {
+        let mut chars = field.chars();
+        matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | ' ' | '-'))
+    })
+}
+
+/// The statements which load CSV records into a table: a CREATE TABLE for
+/// the named columns, if there is a header, then one INSERT per record.
+pub(super) fn csv_statements(
+    table: &str,
+    header: Option<&[String]>,
+    records: &[Vec<String>],
+) -> Vec<String> {
+    let table = quote_identifier(table);
+    let mut statements = vec![];
+    let columns = header.map(|header| {
+        header
+            .iter()
+            .map(|c| quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+    if let Some(columns) = &columns {
+        statements.push(format!("CREATE TABLE {table} ({columns});"));
+    }
+    let into = match &columns {
+        Some(columns) => format!("{table} ({columns})"),
+        None => table,
+    };
+    for record in records {
+        let values = record
+            .iter()
+            .map(|v| csv_literal(v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        statements.push(format!("INSERT INTO {into} VALUES ({values});"));
+    }
+    statements
+}
+
+// Empty fields are NULL, and numbers are stored as numbers. Digits with a
+// leading zero, such as postal codes, stay text.
+fn csv_literal(value: &str) -> String {
+    let leading_zero = value.len() > 1 && value.starts_with('0') && !value.starts_with("0.");
+    if value.is_empty() {
+        "NULL".to_owned()
+    } else if !leading_zero
+        && (value.parse::<i64>().is_ok() || value.parse::<f64>().is_ok_and(f64::is_finite))
+    {
+        value.to_owned()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+pub(super) fn batches(statements: &[String], batch_size: usize) -> Vec<Batch> {
+    statements
+        .chunks(batch_size.max(1))
+        .enumerate()
+        .map(|(index, chunk)| {
+            let first = index * batch_size.max(1) + 1;
+            Batch {
+                first,
+                last: first + chunk.len() - 1,
+                sql: chunk
+                    .iter()
+                    .map(|s| {
+                        if s.ends_with(';') {
+                            s.clone()
+                        } else {
+                            format!("{s};")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            }
+        })
+        .collect()
+}
+
+/// Executes the batches in order. After a failure the remaining batches
+/// are only executed if `continue_on_error` is set.
+pub(super) async fn execute_batches(
+    client: &impl CloudClientInterface,
+    database: &str,
+    batches: Vec<Batch>,
+    total_statements: usize,
+    continue_on_error: bool,
+    progress: &Progress,
+) -> Vec<FailedBatch> {
+    let mut failures = vec![];
+    let count = batches.len();
+    for (index, batch) in batches.into_iter().enumerate() {
+        progress.item(
+            "importing",
+            &format!("{}-{}", batch.first, batch.last),
+            index,
+            count,
+        );
+        match client.execute_sql(database.to_owned(), batch.sql).await {
+            Ok(()) => eprintln!(
+                "Executed statements {}-{} of {total_statements}",
+                batch.first, batch.last
+            ),
+            Err(error) => {
+                failures.push(FailedBatch {
+                    first: batch.first,
+                    last: batch.last,
+                    error,
+                });
+                if !continue_on_error {
+                    break;
+                }
+            }
+        }
+    }
+    failures
+}
+
+/// What a statement does to a table, as far as conflicts with existing
+/// tables are concerned
+#[derive(Debug, PartialEq)]
+pub(super) enum TableStatement {
+    Create { table: String },
+    Insert { table: String },
+}
+
+impl TableStatement {
+    pub fn table(&self) -> &str {
+        match self {
+            Self::Create { table } | Self::Insert { table } => table,
+        }
+    }
+
+    /// Recognises `CREATE TABLE` and `INSERT`/`REPLACE INTO` statements.
+    /// Other statements are run as they are.
+    pub fn parse(statement: &str) -> Option<Self> {
+        let mut sql = Cursor::new(statement);
+        if sql.keyword("CREATE") {
+            let _ = sql.keyword("TEMPORARY") || sql.keyword("TEMP");
+            if !sql.keyword("TABLE") {
+                return None;
+            }
+            if sql.keyword("IF") && !(sql.keyword("NOT") && sql.keyword("EXISTS")) {
+                return None;
+            }
+            let table = sql.table_name()?;
+            return Some(Self::Create { table });
+        }
+        if sql.keyword("INSERT") {
+            if sql.keyword("OR") {
+                sql.name()?;
+            }
+        } else if !sql.keyword("REPLACE") {
+            return None;
+        }
+        if !sql.keyword("INTO") {
+            return None;
+        }
+        let table = sql.table_name()?;
+        Some(Self::Insert { table })
+    }
+}
+
+/// A column declared by a `CREATE TABLE` statement
+#[derive(Debug, PartialEq)]
+pub(super) struct ColumnDef {
+    pub name: String,
+    pub decl_type: Option<String>,
+}
+
+/// The columns declared by a `CREATE TABLE` statement. Table constraints
+/// such as `PRIMARY KEY (a, b)` are left out.
+pub(super) fn create_columns(statement: &str) -> Vec<ColumnDef> {
+    let tokens = significant_tokens(statement);
+    let Some(open) = tokens.iter().position(|t| t.is_punct('(')) else {
+        return vec![];
+    };
+    let mut depth = 0;
+    let mut definitions = vec![];
+    let mut current = vec![];
+    for token in &tokens[open + 1..] {
+        match token.kind {
+            TokenKind::Punct('(') => depth += 1,
+            TokenKind::Punct(')') if depth == 0 => break,
+            TokenKind::Punct(')') => depth -= 1,
+            TokenKind::Punct(',') if depth == 0 => {
+                definitions.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(*token);
+    }
+    definitions.push(current);
+    definitions
+        .iter()
+        .filter_map(|definition| {
+            let first = definition.first()?;
+            let is_constraint = ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+                .iter()
+                .any(|k| first.is_keyword(k));
+            if is_constraint {
+                return None;
+            }
+            let name = first.name()?;
+            let decl_type = definition.get(1).and_then(Token::name).filter(|t| {
+                ![
+                    "NOT",
+                    "NULL",
+                    "DEFAULT",
+                    "COLLATE",
+                    "REFERENCES",
+                    "GENERATED",
+                    "AS",
+                ]
+                .iter()
+                .any(|k| t.eq_ignore_ascii_case(k))
+            });
+            Some(ColumnDef { name, decl_type })
+        })
+        .collect()
+}
+
+/// The tables an import writes to, in the order they first appear.
+pub(super) fn target_tables(statements: &[String]) -> Vec<String> {
+    let mut tables: Vec<String> = vec![];
+    for statement in statements.iter().filter_map(|s| TableStatement::parse(s)) {
+        if !tables
+            .iter()
+            .any(|t| t.eq_ignore_ascii_case(statement.table()))
+        {
+            tables.push(statement.table().to_owned());
+        }
+    }
+    tables
+}
+
+/// The tables which the import creates but which already exist.
+pub(super) fn conflicting_tables(statements: &[String], existing: &[String]) -> Vec<String> {
+    let mut conflicts: Vec<String> = vec![];
+    for statement in statements.iter().filter_map(|s| TableStatement::parse(s)) {
+        if let TableStatement::Create { table } = statement {
+            let exists = existing.iter().any(|e| e.eq_ignore_ascii_case(&table));
+            if exists && !conflicts.iter().any(|c| c.eq_ignore_ascii_case(&table)) {
+                conflicts.push(table);
+            }
+        }
+    }
+    conflicts
+}
+
+fn is_one_of(table: &str, tables: &[String]) -> bool {
+    tables.iter().any(|t| t.eq_ignore_ascii_case(table))
+}
+
+/// Leaves out every statement which creates or writes to the given tables.
+pub(super) fn skip_tables(statements: Vec<String>, tables: &[String]) -> Vec<String> {
+    statements
+        .into_iter()
+        .filter(|s| !TableStatement::parse(s).is_some_and(|t| is_one_of(t.table(), tables)))
+        .collect()
+}
+
+/// Drops the given tables before the import creates them again.
+pub(super) fn replace_tables(statements: Vec<String>, tables: &[String]) -> Vec<String> {
+    tables
+        .iter()
+        .map(|t| format!("DROP TABLE IF EXISTS {};", quote_identifier(t)))
+        .chain(statements)
+        .collect()
+}
+
+/// Keeps the existing tables, creating only the tables which do not exist
+/// and adding the columns which the existing tables lack. `existing_columns`
+/// holds the columns of each conflicting table.
+pub(super) fn merge_schema(
+    statements: Vec<String>,
+    existing_columns: &BTreeMap<String, Vec<String>>,
+) -> Vec<String> {
+    statements
+        .into_iter()
+        .flat_map(|statement| {
+            let table = match TableStatement::parse(&statement) {
+                Some(TableStatement::Create { table }) => table,
+                _ => return vec![statement],
+            };
+            let Some((_, columns)) = existing_columns
+                .iter()
+                .find(|(t, _)| t.eq_ignore_ascii_case(&table))
+            else {
+                return vec![statement];
+            };
+            create_columns(&statement)
+                .into_iter()
+                .filter(|c| !columns.iter().any(|e| e.eq_ignore_ascii_case(&c.name)))
+                .map(|c| {
+                    let decl_type = c.decl_type.map(|t| format!(" {t}")).unwrap_or_default();
+                    format!(
+                        "ALTER TABLE {} ADD COLUMN {}{decl_type};",
+                        quote_identifier(&table),
+                        quote_identifier(&c.name)
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csv::parse_csv;
+    use anyhow::Result;
+
+    #[test]
+    fn statements_split_outside_quotes_and_comments() {
+        let statements = split_statements(
+            "CREATE TABLE t (a TEXT); -- a; comment\nINSERT INTO t VALUES ('x;y');\n/* end; */ INSERT INTO t VALUES (\"z\")\n-- trailing",
+        );
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE TABLE t (a TEXT);",
+                "-- a; comment\nINSERT INTO t VALUES ('x;y');",
+                "/* end; */ INSERT INTO t VALUES (\"z\")\n-- trailing",
+            ]
+        );
+    }
+
+    #[test]
+    fn trigger_bodies_are_kept_whole() {
+        let statements = split_statements(
+            "CREATE TRIGGER stamp AFTER UPDATE ON t BEGIN\n  UPDATE t SET a = CASE WHEN a IS NULL THEN 'x' ELSE a END;\n  INSERT INTO log VALUES ('end;');\nEND;\nBEGIN; DELETE FROM t; COMMIT;",
+        );
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE TRIGGER stamp AFTER UPDATE ON t BEGIN\n  UPDATE t SET a = CASE WHEN a IS NULL THEN 'x' ELSE a END;\n  INSERT INTO log VALUES ('end;');\nEND;",
+                "BEGIN;",
+                "DELETE FROM t;",
+                "COMMIT;",
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_records_become_inserts() -> Result<()> {
+        assert_eq!(csv_literal("02134"), "'02134'");
+        assert_eq!(csv_literal("0.5"), "0.5");
+        let records =
+            parse_csv("id,name,bio\r\n1,Ada,\"Wrote \"\"notes\"\",\nmostly\"\n2,Grace,\n")?;
+        assert!(looks_like_header(&records[0]));
+        assert!(!looks_like_header(&records[1]));
+        let statements = csv_statements("people", Some(&records[0]), &records[1..]);
+        assert_eq!(
+            statements,
+            vec![
+                r#"CREATE TABLE "people" ("id", "name", "bio");"#,
+                "INSERT INTO \"people\" (\"id\", \"name\", \"bio\") VALUES (1, 'Ada', 'Wrote \"notes\",\nmostly');",
+                r#"INSERT INTO "people" ("id", "name", "bio") VALUES (2, 'Grace', NULL);"#,
+            ]
+        );
+        assert!(parse_csv("a,\"unterminated\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn batches_are_numbered_by_statement() {
+        let statements = ["A", "B;", "C"].map(str::to_owned);
+        assert_eq!(
+            batches(&statements, 2),
+            vec![
+                Batch {
+                    first: 1,
+                    last: 2,
+                    sql: "A;\nB;".to_owned()
+                },
+                Batch {
+                    first: 3,
+                    last: 3,
+                    sql: "C;".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn statements_name_the_tables_they_write() {
+        assert_eq!(
+            TableStatement::parse(
+                "-- people\nCREATE TEMP TABLE IF NOT EXISTS main.\"my people\" (id)"
+            ),
+            Some(TableStatement::Create {
+                table: "my people".to_owned()
+            })
+        );
+        assert_eq!(
+            TableStatement::parse("insert or ignore into [people] values (1)"),
+            Some(TableStatement::Insert {
+                table: "people".to_owned()
+            })
+        );
+        assert_eq!(
+            TableStatement::parse("CREATE INDEX idx ON people (id)"),
+            None
+        );
+        assert_eq!(
+            create_columns("CREATE TABLE t (id INTEGER PRIMARY KEY, \"full name\" TEXT NOT NULL, note, PRIMARY KEY (id, note), price DECIMAL(10, 2))"),
+            vec![
+                ColumnDef { name: "id".to_owned(), decl_type: Some("INTEGER".to_owned()) },
+                ColumnDef { name: "full name".to_owned(), decl_type: Some("TEXT".to_owned()) },
+                ColumnDef { name: "note".to_owned(), decl_type: None },
+                ColumnDef { name: "price".to_owned(), decl_type: Some("DECIMAL".to_owned()) },
+            ]
+        );
+    }
+
+    #[test]
+    fn conflicts_are_skipped_replaced_or_merged() {
+        let statements = [
+            "CREATE TABLE people (id INTEGER, name TEXT, email TEXT);",
+            "INSERT INTO people VALUES (1, 'Ada', 'ada@example.com');",
+            "CREATE TABLE pets (name TEXT);",
+            "INSERT INTO pets VALUES ('Rex');",
+        ]
+        .map(str::to_owned)
+        .to_vec();
+        let existing = ["PEOPLE".to_owned(), "orders".to_owned()];
+        let conflicts = conflicting_tables(&statements, &existing);
+        assert_eq!(conflicts, vec!["people"]);
+        assert_eq!(target_tables(&statements), vec!["people", "pets"]);
+
+        assert_eq!(skip_tables(statements.clone(), &conflicts), statements[2..]);
+        assert_eq!(
+            replace_tables(statements.clone(), &conflicts)[0],
+            r#"DROP TABLE IF EXISTS "people";"#
+        );
+        let existing_columns = BTreeMap::from([(
+            "PEOPLE".to_owned(),
+            vec!["id".to_owned(), "Name".to_owned()],
+        )]);
+        assert_eq!(
+            merge_schema(statements.clone(), &existing_columns),
+            vec![
+                r#"ALTER TABLE "people" ADD COLUMN "email" TEXT;"#,
+                statements[1].as_str(),
+                statements[2].as_str(),
+                statements[3].as_str(),
+            ]
+        );
+    }
+}
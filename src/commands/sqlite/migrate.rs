@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use cloud::CloudClientInterface;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::dump::sql_literal;
+use super::import::split_statements;
+use crate::ops::sqlite::{execute_transaction, list_tables, query};
+
+/// The table, in each migrated database, recording which migrations have
+/// been applied.
+pub(super) const MIGRATIONS_TABLE: &str = "_cloud_migrations";
+
+/// A migration file. Files are named `<version>_<description>.sql`, and are
+/// applied in order of version.
+#[derive(Debug)]
+pub(super) struct Migration {
+    pub version: u64,
+    pub name: String,
+    pub checksum: String,
+    pub statements: Vec<String>,
+}
+
+/// A migration as recorded in the migrations table
+#[derive(Debug, PartialEq)]
+pub(super) struct AppliedMigration {
+    pub version: u64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the file has changed since
+    Changed,
+    /// Applied, but there is no longer a file for it
+    Missing,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(super) struct MigrationStatus {
+    pub version: u64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<String>,
+}
+
+/// Reads the migration files in a directory, ordered by version. Files
+/// other than `.sql` files are ignored.
+pub(super) fn read_migrations(dir: &Path) -> Result<Vec<Migration>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Could not read migrations directory {}", dir.display()))?;
+    let mut migrations = BTreeMap::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some("sql") {
+            continue;
+        }
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let version = migration_version(&name).with_context(|| {
+            format!("Migration file {name} must be named <version>_<description>.sql, for example 0001_create_todos.sql")
+        })?;
+        let script = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let migration = Migration {
+            version,
+            name: name.clone(),
+            checksum: format!("sha256:{:x}", Sha256::digest(&script)),
+            statements: split_statements(&script),
+        };
+        if let Some(other) = migrations.insert(version, migration) {
+            bail!(
+                "Migrations {} and {name} have the same version {version}",
+                other.name
+            );
+        }
+    }
+    Ok(migrations.into_values().collect())
+}
+
+fn migration_version(file_name: &str) -> Option<u64> {
+    let digits = file_name
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .filter(|d| !d.is_empty())?;
+    digits.parse().ok()
+}
+
+/// Reads the migrations recorded as applied to a database. A database
+/// which has never been migrated has none.
+pub(super) async fn applied_migrations(
+    client: &impl CloudClientInterface,
+    database: &str,
+) -> Result<Vec<AppliedMigration>> {
+    if !list_tables(client, database)
+        .await?
+        .iter()
+        .any(|t| t == MIGRATIONS_TABLE)
+    {
+        return Ok(vec![]);
+    }
+    let result = query(
+        client,
+        database,
+        format!(
+            "SELECT version, name, checksum, applied_at FROM {MIGRATIONS_TABLE} ORDER BY version"
+        ),
+    )
+    .await?;
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_owned();
+    Ok(result
+        .rows
+        .iter()
+        .filter_map(|row| match row.as_slice() {
+            [version, name, checksum, applied_at] => Some(AppliedMigration {
+                version: version.as_u64()?,
+                name: text(name),
+                checksum: text(checksum),
+                applied_at: text(applied_at),
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Compares the migration files with the migrations applied to a database.
+pub(super) fn status(
+    migrations: &[Migration],
+    applied: &[AppliedMigration],
+) -> Vec<MigrationStatus> {
+    let applied_by_version = applied
+        .iter()
+        .map(|a| (a.version, a))
+        .collect::<HashMap<_, _>>();
+    let mut statuses = migrations
+        .iter()
+        .map(|m| {
+            let applied = applied_by_version.get(&m.version);
+            let state = match applied {
+                None => MigrationState::Pending,
+                Some(a) if a.checksum != m.checksum => MigrationState::Changed,
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: m.version,
+                name: m.name.clone(),
+                state,
+                applied_at: applied.map(|a| a.applied_at.clone()),
+            }
+        })
+        .collect::<Vec<_>>();
+    statuses.extend(
+        applied
+            .iter()
+            .filter(|a| !migrations.iter().any(|m| m.version == a.version))
+            .map(|a| MigrationStatus {
+                version: a.version,
+                name: a.name.clone(),
+                state: MigrationState::Missing,
+                applied_at: Some(a.applied_at.clone()),
+            }),
+    );
+    statuses.sort_by_key(|s| s.version);
+    statuses
+}
+
+/// The migrations still to apply. Fails if an applied migration's file has
+/// changed, since applying later migrations on top of it may not give the
+/// schema the files describe.
+pub(super) fn pending<'a>(
+    migrations: &'a [Migration],
+    applied: &[AppliedMigration],
+) -> Result<Vec<&'a Migration>> {
+    let statuses = status(migrations, applied);
+    if let Some(changed) = statuses.iter().find(|s| s.state == MigrationState::Changed) {
+        bail!(
+            "Migration {} was changed after it was applied. Restore the file as it was, and put further changes in a new migration.",
+            changed.name
+        );
+    }
+    Ok(migrations
+        .iter()
+        .filter(|m| {
+            statuses
+                .iter()
+                .any(|s| s.version == m.version && s.state == MigrationState::Pending)
+        })
+        .collect())
+}
+
+/// Applies a migration and records it in a single transaction, so that a
+/// failed migration leaves neither its changes nor a record behind.
+pub(super) async fn apply(
+    client: &impl CloudClientInterface,
+    database: &str,
+    migration: &Migration,
+) -> Result<()> {
+    let mut statements = vec![format!(
+        "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (version INTEGER PRIMARY KEY, name TEXT NOT NULL, checksum TEXT NOT NULL, applied_at TEXT NOT NULL);"
+    )];
+    statements.extend(migration.statements.iter().cloned());
+    statements.push(format!(
+        "INSERT INTO {MIGRATIONS_TABLE} (version, name, checksum, applied_at) VALUES ({}, {}, {}, {});",
+        migration.version,
+        sql_literal(&migration.name.as_str().into()),
+        sql_literal(&migration.checksum.as_str().into()),
+        sql_literal(&Utc::now().to_rfc3339().into()),
+    ));
+    execute_transaction(client, database, &statements)
+        .await
+        .with_context(|| format!("Migration {} failed", migration.name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_migrations(dir: &Path, files: &[(&str, &str)]) -> Result<()> {
+        for (name, script) in files {
+            std::fs::write(dir.join(name), script)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn migrations_are_ordered_by_version() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_migrations(
+            dir.path(),
+            &[
+                ("10_add_index.sql", "CREATE INDEX idx_done ON todos (done);"),
+                (
+                    "2_add_done.sql",
+                    "ALTER TABLE todos ADD COLUMN done INTEGER;",
+                ),
+                (
+                    "0001_create_todos.sql",
+                    "CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT);",
+                ),
+                ("README.md", "Migrations for the todo app"),
+            ],
+        )?;
+        let migrations = read_migrations(dir.path())?;
+        assert_eq!(
+            migrations
+                .iter()
+                .map(|m| (m.version, m.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, "0001_create_todos.sql"),
+                (2, "2_add_done.sql"),
+                (10, "10_add_index.sql"),
+            ]
+        );
+
+        write_migrations(dir.path(), &[("002_again.sql", "SELECT 1;")])?;
+        assert!(read_migrations(dir.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unversioned_files_are_rejected() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_migrations(dir.path(), &[("create_todos.sql", "SELECT 1;")])?;
+        let err = read_migrations(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("<version>_<description>.sql"));
+        Ok(())
+    }
+
+    #[test]
+    fn status_compares_files_with_applied_migrations() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        write_migrations(
+            dir.path(),
+            &[
+                ("1_create.sql", "CREATE TABLE t (c);"),
+                ("2_index.sql", "CREATE INDEX i ON t (c);"),
+                ("3_more.sql", "ALTER TABLE t ADD COLUMN d;"),
+            ],
+        )?;
+        let migrations = read_migrations(dir.path())?;
+        let applied = |version: u64, name: &str, checksum: &str| AppliedMigration {
+            version,
+            name: name.to_owned(),
+            checksum: checksum.to_owned(),
+            applied_at: "2024-03-01T10:00:00+00:00".to_owned(),
+        };
+        let mut applied = vec![
+            applied(1, "1_create.sql", &migrations[0].checksum),
+            applied(2, "2_index.sql", &migrations[1].checksum),
+            applied(4, "4_gone.sql", "sha256:0"),
+        ];
+
+        let states = status(&migrations, &applied)
+            .into_iter()
+            .map(|s| (s.version, s.state))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            vec![
+                (1, MigrationState::Applied),
+                (2, MigrationState::Applied),
+                (3, MigrationState::Pending),
+                (4, MigrationState::Missing),
+            ]
+        );
+        assert_eq!(
+            pending(&migrations, &applied)?
+                .iter()
+                .map(|m| m.version)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+
+        applied[1].checksum = "sha256:edited".to_owned();
+        let err = pending(&migrations, &applied).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Migration 2_index.sql was changed"));
+        Ok(())
+    }
+}
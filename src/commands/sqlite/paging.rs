@@ -0,0 +1,197 @@
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use cloud::models::QueryResult;
+use cloud::CloudClientInterface;
+
+use super::import::split_statements;
+use super::{
+    cell_text, csv_record, json_row, markdown_header, markdown_row, write_rows, ResultFormat,
+};
+use crate::ops::sqlite::{query, returns_rows};
+use crate::sql::first_keyword;
+
+/// Wraps a query so that it selects one page of its rows. Only a single
+/// SELECT, WITH or VALUES statement can be paged.
+pub(super) fn page_statement(statement: &str, page_size: usize, offset: usize) -> Result<String> {
+    let statements = split_statements(statement);
+    let [statement] = statements.as_slice() else {
+        bail!("Only a single statement can be fetched in pages");
+    };
+    let statement = statement.trim().trim_end_matches(';').trim_end();
+    let keyword = first_keyword(statement).unwrap_or_default();
+    if !returns_rows(statement)
+        || ["PRAGMA", "EXPLAIN"]
+            .iter()
+            .any(|k| keyword.eq_ignore_ascii_case(k))
+    {
+        bail!("Only SELECT, WITH and VALUES statements can be fetched in pages");
+    }
+    // The statement goes on lines of its own, so that a trailing comment
+    // cannot swallow the closing parenthesis.
+    Ok(format!(
+        "SELECT * FROM (\n{statement}\n) LIMIT {page_size} OFFSET {offset}"
+    ))
+}
+
+/// Runs a query a page of rows at a time, writing each page as it arrives
+/// rather than holding every row in memory. Returns how many rows were
+/// written.
+///
+/// Pages are separate requests, so rows changed between them can be missed
+/// or repeated. An ORDER BY on a unique column keeps the pages consistent.
+pub(super) async fn stream_query(
+    client: &impl CloudClientInterface,
+    database: &str,
+    statement: &str,
+    page_size: usize,
+    out: &mut impl Write,
+    format: ResultFormat,
+) -> Result<usize> {
+    let mut writer = PageWriter::new(format);
+    let mut offset = 0;
+    loop {
+        let page = query(
+            client,
+            database,
+            page_statement(statement, page_size, offset)?,
+        )
+        .await?;
+        writer.write_page(out, &page)?;
+        out.flush()?;
+        if page.rows.len() < page_size {
+            break;
+        }
+        offset += page_size;
+    }
+    writer.finish(out)?;
+    Ok(writer.rows)
+}
+
+/// Writes pages of rows as one result. Table output is drawn a page at a
+/// time, since a table's column widths depend on every row in it.
+struct PageWriter {
+    format: ResultFormat,
+    rows: usize,
+    started: bool,
+}
+
+impl PageWriter {
+    fn new(format: ResultFormat) -> Self {
+        Self {
+            format,
+            rows: 0,
+            started: false,
+        }
+    }
+
+    fn write_page(&mut self, out: &mut impl Write, page: &QueryResult) -> Result<()> {
+        match self.format {
+            ResultFormat::Table if !page.rows.is_empty() || !self.started => {
+                write_rows(out, page, ResultFormat::Table)?
+            }
+            ResultFormat::Table => {}
+            ResultFormat::Csv => {
+                if !self.started {
+                    write!(out, "{}", csv_record(&page.columns))?;
+                }
+                for row in &page.rows {
+                    let fields = row.iter().map(cell_text).collect::<Vec<_>>();
+                    write!(out, "{}", csv_record(&fields))?;
+                }
+            }
+            ResultFormat::Markdown => {
+                if !self.started && !page.columns.is_empty() {
+                    write!(out, "{}", markdown_header(&page.columns))?;
+                }
+                for row in &page.rows {
+                    write!(out, "{}", markdown_row(row))?;
+                }
+            }
+            // Laid out as `to_json` lays out the whole result
+            ResultFormat::Json => {
+                for row in &page.rows {
+                    let object = serde_json::to_string_pretty(&json_row(&page.columns, row))?;
+                    let separator = if self.rows == 0 { "[\n" } else { ",\n" };
+                    write!(out, "{separator}  {}", object.replace('\n', "\n  "))?;
+                    self.rows += 1;
+                }
+                self.started = true;
+                return Ok(());
+            }
+        }
+        self.rows += page.rows.len();
+        self.started = true;
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut impl Write) -> Result<()> {
+        if self.format == ResultFormat::Json {
+            match self.rows {
+                0 => writeln!(out, "[]")?,
+                _ => writeln!(out, "\n]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    #[test]
+    fn only_single_queries_are_paged() -> Result<()> {
+        assert_eq!(
+            page_statement("SELECT * FROM todos -- all of them\n;", 100, 200)?,
+            "SELECT * FROM (\nSELECT * FROM todos -- all of them\n) LIMIT 100 OFFSET 200"
+        );
+        assert!(page_statement("SELECT 1; SELECT 2;", 100, 0).is_err());
+        assert!(page_statement("PRAGMA table_info(todos)", 100, 0).is_err());
+        assert!(page_statement("DELETE FROM todos", 100, 0).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pages_are_written_as_one_result() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_query_sql().returning(|query| {
+            let rows = match query.statement.rsplit_once("OFFSET ").unwrap().1 {
+                "0" => vec![vec![1.into()], vec![2.into()]],
+                "2" => vec![vec![3.into()]],
+                other => panic!("unexpected offset {other}"),
+            };
+            Ok(QueryResult {
+                columns: vec!["id".to_owned()],
+                rows,
+            })
+        });
+
+        let all = QueryResult {
+            columns: vec!["id".to_owned()],
+            rows: vec![vec![1.into()], vec![2.into()], vec![3.into()]],
+        };
+        for format in [
+            ResultFormat::Json,
+            ResultFormat::Csv,
+            ResultFormat::Markdown,
+        ] {
+            let mut streamed = vec![];
+            let rows = stream_query(
+                &mock,
+                "todo-db",
+                "SELECT id FROM todos",
+                2,
+                &mut streamed,
+                format,
+            )
+            .await?;
+            assert_eq!(rows, 3);
+            let mut whole = vec![];
+            write_rows(&mut whole, &all, format)?;
+            assert_eq!(String::from_utf8(streamed)?, String::from_utf8(whole)?);
+        }
+        Ok(())
+    }
+}
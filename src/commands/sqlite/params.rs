@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+use super::dump::sql_literal;
+use crate::sql::{tokenize, TokenKind};
+
+/// Parses `--param name=value`, binding the value as text.
+pub(super) fn parse_param(s: &str) -> Result<(String, Value), String> {
+    let (name, value) = split_param(s)?;
+    Ok((name, Value::String(value.to_owned())))
+}
+
+/// Parses `--param-json name=value`, binding the value as the JSON type it
+/// is written as, such as a number or null.
+pub(super) fn parse_json_param(s: &str) -> Result<(String, Value), String> {
+    let (name, value) = split_param(s)?;
+    let value = serde_json::from_str(value)
+        .map_err(|e| format!("The value of parameter '{name}' is not valid JSON: {e}"))?;
+    Ok((name, value))
+}
+
+fn split_param(s: &str) -> Result<(String, &str), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Parameters must be given as name=value, but got '{s}'"))?;
+    let name = name.trim_start_matches([':', '@', '$']);
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!(
+            "'{name}' is not a valid parameter name: use letters, digits and underscores"
+        ));
+    }
+    Ok((name.to_owned(), value))
+}
+
+/// Replaces each named parameter in a statement, written `:name`, `@name`
+/// or `$name`, with its value as a SQL literal. Text in quoted strings,
+/// quoted identifiers and comments is left alone, so values are never
+/// parsed as SQL. Every parameter in the statement must have a value, and
+/// every value must be used.
+pub(super) fn bind(statement: &str, params: &[(String, Value)]) -> Result<String> {
+    let mut values = BTreeMap::new();
+    for (name, value) in params {
+        if values.insert(name.as_str(), value).is_some() {
+            bail!("Parameter '{name}' is given more than once");
+        }
+    }
+    let mut used = vec![];
+    let mut bound = String::with_capacity(statement.len());
+    for token in tokenize(statement) {
+        if token.kind != TokenKind::Parameter {
+            bound.push_str(token.text);
+            continue;
+        }
+        let name = &token.text[1..];
+        let Some(value) = values.get(name) else {
+            bail!(
+                "The statement uses parameter {}, but it has no value. Pass one with --param {name}=VALUE",
+                token.text
+            );
+        };
+        bound.push_str(&sql_literal(value));
+        used.push(name);
+    }
+    if let Some(unused) = values.keys().find(|name| !used.iter().any(|u| u == *name)) {
+        bail!("Parameter '{unused}' is not used by the statement");
+    }
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(args: &[&str]) -> Vec<(String, Value)> {
+        args.iter().map(|a| parse_param(a).unwrap()).collect()
+    }
+
+    #[test]
+    fn values_are_bound_as_literals() -> Result<()> {
+        let bound = bind(
+            "SELECT * FROM todos WHERE owner = :owner AND title <> @title AND owner = $owner",
+            &params(&["owner=o'brien", ":title='); DROP TABLE todos; --"]),
+        )?;
+        assert_eq!(
+            bound,
+            "SELECT * FROM todos WHERE owner = 'o''brien' AND title <> '''); DROP TABLE todos; --' AND owner = 'o''brien'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn quoted_text_and_comments_are_not_bound() -> Result<()> {
+        let bound = bind(
+            "SELECT ':id', \"@id\" -- :id\nFROM t /* $id */ WHERE id = :id",
+            &[parse_json_param("id=3").unwrap()],
+        )?;
+        assert_eq!(
+            bound,
+            "SELECT ':id', \"@id\" -- :id\nFROM t /* $id */ WHERE id = 3"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_values_keep_their_type() {
+        assert_eq!(
+            parse_json_param("done=null").unwrap(),
+            ("done".to_owned(), Value::Null)
+        );
+        assert_eq!(
+            parse_json_param("title=\"write docs\"").unwrap(),
+            ("title".to_owned(), Value::String("write docs".to_owned()))
+        );
+        assert!(parse_json_param("title=write docs").is_err());
+        assert!(parse_param("no-equals").is_err());
+        assert!(parse_param("1st=x").is_err());
+    }
+
+    #[test]
+    fn parameters_must_match_the_statement() {
+        let err = bind("DELETE FROM t WHERE id = :id", &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The statement uses parameter :id, but it has no value. Pass one with --param id=VALUE"
+        );
+        let err = bind("DELETE FROM t", &params(&["id=1"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Parameter 'id' is not used by the statement"
+        );
+        let err = bind("DELETE FROM t WHERE id = :id", &params(&["id=1", "id=2"])).unwrap_err();
+        assert_eq!(err.to_string(), "Parameter 'id' is given more than once");
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{bail, Result};
+
+use super::import::split_statements;
+use crate::sql::{significant_tokens, Token, TokenKind};
+
+/// Keywords which only appear in statements that change a database.
+/// REPLACE is also the name of a string function, so it only counts when
+/// it is not called.
+const WRITE_KEYWORDS: &[&str] = &[
+    "ALTER", "ANALYZE", "ATTACH", "CREATE", "DELETE", "DETACH", "DROP", "INSERT", "REINDEX",
+    "REPLACE", "UPDATE", "VACUUM",
+];
+
+/// Pragmas which read the schema or check the database when given an
+/// argument. Any other pragma given an argument may change a setting.
+const QUERY_PRAGMAS: &[&str] = &[
+    "foreign_key_check",
+    "foreign_key_list",
+    "index_info",
+    "index_list",
+    "index_xinfo",
+    "integrity_check",
+    "quick_check",
+    "table_info",
+    "table_xinfo",
+];
+
+/// Refuses a script unless every statement in it only reads: a SELECT,
+/// VALUES or EXPLAIN, a WITH leading to a SELECT, or a PRAGMA which does
+/// not change a setting. The check errs on the side of refusing, so a
+/// column which shares its name with a keyword such as UPDATE must be
+/// quoted.
+pub(super) fn check_read_only(script: &str) -> Result<()> {
+    for statement in split_statements(script) {
+        let mut tokens = significant_tokens(&statement);
+        if tokens.last().is_some_and(|t| t.is_punct(';')) {
+            tokens.pop();
+        }
+        if let Err(reason) = read_only_statement(&tokens) {
+            bail!("Refusing to run a statement in read-only mode: {reason}\n  {statement}");
+        }
+    }
+    Ok(())
+}
+
+fn read_only_statement(tokens: &[Token]) -> Result<(), String> {
+    let word = |i: usize| {
+        tokens
+            .get(i)
+            .filter(|t| t.kind == TokenKind::Word)
+            .map(|t| t.text.to_ascii_uppercase())
+    };
+    let first = word(0).ok_or("it does not start with a keyword")?;
+    match first.as_str() {
+        "SELECT" | "VALUES" | "WITH" | "EXPLAIN" => {}
+        "PRAGMA" => return read_only_pragma(&tokens[1..]),
+        _ => return Err(format!("{first} statements can change the database")),
+    }
+    for i in 0..tokens.len() {
+        let Some(keyword) = word(i) else { continue };
+        let called = tokens.get(i + 1).is_some_and(|t| t.is_punct('('));
+        let qualified = (i > 0 && tokens[i - 1].is_punct('.'))
+            || tokens.get(i + 1).is_some_and(|t| t.is_punct('.'));
+        if WRITE_KEYWORDS.contains(&keyword.as_str())
+            && !(keyword == "REPLACE" && called)
+            && !qualified
+        {
+            return Err(format!("it contains {keyword}"));
+        }
+    }
+    Ok(())
+}
+
+fn read_only_pragma(tokens: &[Token]) -> Result<(), String> {
+    // Skip a schema name, as in `PRAGMA main.table_info(todos)`
+    let tokens = match tokens {
+        [schema, dot, rest @ ..] if schema.kind == TokenKind::Word && dot.is_punct('.') => rest,
+        tokens => tokens,
+    };
+    let Some(name) = tokens.first().filter(|t| t.kind == TokenKind::Word) else {
+        return Err("it is not a valid PRAGMA".to_owned());
+    };
+    match tokens.get(1) {
+        None => Ok(()),
+        Some(open)
+            if open.is_punct('(')
+                && QUERY_PRAGMAS.contains(&name.text.to_ascii_lowercase().as_str()) =>
+        {
+            Ok(())
+        }
+        Some(_) => Err(format!(
+            "PRAGMA {} with a value can change a setting",
+            name.text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_reading_statements_are_allowed() {
+        for script in [
+            "SELECT replace(title, 'a', 'b') FROM todos WHERE \"update\" = 1",
+            "SELECT CASE WHEN t.delete THEN 'gone' END FROM todos t",
+            "-- DELETE everything\nSELECT 'DROP TABLE todos'; VALUES (1)",
+            "WITH open AS (SELECT * FROM todos WHERE done = 0) SELECT count(*) FROM open",
+            "EXPLAIN QUERY PLAN SELECT * FROM todos",
+            "PRAGMA user_version; PRAGMA main.table_info(todos)",
+        ] {
+            assert!(check_read_only(script).is_ok(), "{script}");
+        }
+        for script in [
+            "DELETE FROM todos",
+            "SELECT 1; DROP TABLE todos",
+            "WITH old AS (SELECT id FROM todos) DELETE FROM todos WHERE id IN old",
+            "WITH t AS (SELECT 1) REPLACE INTO todos VALUES (1)",
+            "PRAGMA journal_mode = WAL",
+            "PRAGMA foreign_keys(0)",
+            "ATTACH 'other.db' AS other",
+        ] {
+            assert!(check_read_only(script).is_err(), "{script}");
+        }
+    }
+}
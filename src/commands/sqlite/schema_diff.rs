@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use cloud::CloudClientInterface;
+use serde_json::Value;
+
+use crate::ops::sqlite::query;
+
+/// A table, index, trigger or view as recorded in sqlite_master
+#[derive(Debug, PartialEq)]
+pub(super) struct SchemaObject {
+    pub kind: String,
+    pub name: String,
+    pub sql: String,
+}
+
+/// Reads the definitions of a database's tables, indexes, triggers and
+/// views, in that order and by name within each kind. SQLite's own tables
+/// are left out.
+pub(super) async fn read_schema(
+    client: &impl CloudClientInterface,
+    database: &str,
+) -> Result<Vec<SchemaObject>> {
+    let result = query(
+        client,
+        database,
+        "SELECT type, name, sql FROM sqlite_master WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'"
+            .to_owned(),
+    )
+    .await?;
+    let mut objects = result
+        .rows
+        .into_iter()
+        .filter_map(|row| match row.as_slice() {
+            [Value::String(kind), Value::String(name), Value::String(sql)] => Some(SchemaObject {
+                kind: kind.clone(),
+                name: name.clone(),
+                sql: sql.clone(),
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    objects.sort_by(|a, b| (kind_order(&a.kind), &a.name).cmp(&(kind_order(&b.kind), &b.name)));
+    Ok(objects)
+}
+
+fn kind_order(kind: &str) -> usize {
+    match kind {
+        "table" => 0,
+        "index" => 1,
+        "trigger" => 2,
+        _ => 3,
+    }
+}
+
+/// The schema as the lines of a SQL script, with a blank line between
+/// definitions. Trailing spaces are dropped, since SQLite keeps each
+/// definition as it was written.
+pub(super) fn schema_lines(objects: &[SchemaObject]) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        lines.extend(object.sql.lines().map(|l| l.trim_end().to_owned()));
+        if let Some(last) = lines.last_mut() {
+            last.push(';');
+        }
+    }
+    lines
+}
+
+/// Each definition keyed by its kind and name, such as `table todos`, for
+/// comparing definition by definition.
+pub(super) fn schema_definitions(objects: &[SchemaObject]) -> BTreeMap<String, Option<String>> {
+    objects
+        .iter()
+        .map(|o| (format!("{} {}", o.kind, o.name), Some(o.sql.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud::models::QueryResult;
+    use cloud::MockCloudClientInterface;
+
+    #[tokio::test]
+    async fn schema_is_ordered_by_kind_then_name() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_query_sql().returning(|_| {
+            Ok(QueryResult {
+                columns: vec!["type".into(), "name".into(), "sql".into()],
+                rows: vec![
+                    vec![
+                        "view".into(),
+                        "open_todos".into(),
+                        "CREATE VIEW open_todos AS SELECT * FROM todos WHERE done = 0".into(),
+                    ],
+                    vec![
+                        "index".into(),
+                        "idx_done".into(),
+                        "CREATE INDEX idx_done ON todos (done)".into(),
+                    ],
+                    vec![
+                        "table".into(),
+                        "todos".into(),
+                        "CREATE TABLE todos (  \n  id INTEGER,\n  done INTEGER\n)".into(),
+                    ],
+                ],
+            })
+        });
+
+        let objects = read_schema(&mock, "todo-db").await?;
+        assert_eq!(
+            schema_lines(&objects),
+            vec![
+                "CREATE TABLE todos (",
+                "  id INTEGER,",
+                "  done INTEGER",
+                ");",
+                "",
+                "CREATE INDEX idx_done ON todos (done);",
+                "",
+                "CREATE VIEW open_todos AS SELECT * FROM todos WHERE done = 0;",
+            ]
+        );
+        assert_eq!(
+            schema_definitions(&objects).keys().collect::<Vec<_>>(),
+            vec!["index idx_done", "table todos", "view open_todos"]
+        );
+        Ok(())
+    }
+}
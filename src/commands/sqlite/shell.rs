@@ -0,0 +1,356 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use cloud::models::QueryResult;
+use cloud::CloudClientInterface;
+use dialoguer::Input;
+
+use super::{print_rows, save_rows, write_rows, ResultFormat};
+use crate::ops::sqlite::{list_tables, query, returns_rows};
+
+/// How many entered lines are remembered between sessions
+const MAX_HISTORY: usize = 500;
+
+const HELP: &str = "\
+Enter SQL statements terminated by \";\". A statement may span several lines.
+.tables          List the tables in the database
+.schema [TABLE]  Show the CREATE statements for all tables, or for TABLE
+.mode [MODE]     Show query results as table, json, csv or markdown
+.output [FILE]   Write query results to FILE, or to the screen if omitted
+.once FILE       Write the results of the next statement only to FILE
+.help            Show this message
+.quit            Leave the shell";
+
+/// Runs statements against a database until the user quits.
+pub(super) async fn run(client: &impl CloudClientInterface, database: &str) -> Result<()> {
+    println!(r#"Connected to database "{database}". Enter .help for usage hints."#);
+    let mut history = ShellHistory::load();
+    let mut buffer = StatementBuffer::default();
+    let mut output = ShellOutput::default();
+    loop {
+        let prompt = if buffer.is_empty() { database } else { "...>" };
+        // Reading fails at end of input, for example on Ctrl+D.
+        let Ok(line) = Input::<String>::new()
+            .with_prompt(prompt)
+            .allow_empty(true)
+            .history_with(&mut history)
+            .interact_text()
+        else {
+            break;
+        };
+        let outcome = match buffer.push_line(&line) {
+            ShellInput::Incomplete => continue,
+            ShellInput::Meta(MetaCommand::Quit) => break,
+            ShellInput::Meta(command) => {
+                run_meta_command(client, database, command, &mut output).await
+            }
+            ShellInput::Statement(statement) => {
+                run_statement(client, database, statement, &mut output).await
+            }
+        };
+        // A mistyped statement should not end the session.
+        if let Err(e) = outcome {
+            eprintln!("Error: {e:#}");
+        }
+    }
+    history.save();
+    Ok(())
+}
+
+async fn run_statement(
+    client: &impl CloudClientInterface,
+    database: &str,
+    statement: String,
+    output: &mut ShellOutput,
+) -> Result<()> {
+    // As in sqlite3, .once applies to the next statement whether or not it
+    // selects rows.
+    let once = output.once.take();
+    if returns_rows(&statement) {
+        let result = query(client, database, statement).await?;
+        match once {
+            Some(path) => save_rows(&path, &result, output.mode),
+            None => output.write(&result),
+        }
+    } else {
+        client
+            .execute_sql(database.to_owned(), statement)
+            .await
+            .context("Problem executing SQL")
+    }
+}
+
+async fn run_meta_command(
+    client: &impl CloudClientInterface,
+    database: &str,
+    command: MetaCommand,
+    output: &mut ShellOutput,
+) -> Result<()> {
+    match command {
+        MetaCommand::Tables => {
+            let tables = list_tables(client, database).await?;
+            if tables.is_empty() {
+                println!("No tables");
+            }
+            for table in tables {
+                println!("{table}");
+            }
+        }
+        MetaCommand::Schema(table) => {
+            let result = query(client, database, schema_query(table.as_deref())).await?;
+            for row in result.rows {
+                if let Some(sql) = row.first().and_then(|sql| sql.as_str()) {
+                    println!("{sql};");
+                }
+            }
+        }
+        MetaCommand::Mode(None) => println!("Current mode: {}", mode_name(output.mode)),
+        MetaCommand::Mode(Some(mode)) => {
+            output.mode = ResultFormat::from_str(&mode, true).map_err(|_| {
+                anyhow::anyhow!("Unknown mode {mode}. Use table, json, csv or markdown")
+            })?;
+        }
+        MetaCommand::Output(None) => output.file = None,
+        MetaCommand::Output(Some(path)) => {
+            let file = File::create(&path)
+                .with_context(|| format!("Could not create {}", path.display()))?;
+            output.file = Some((path, file));
+        }
+        MetaCommand::Once(None) => bail!("Give the file to write to, as in .once results.csv"),
+        MetaCommand::Once(Some(path)) => output.once = Some(path),
+        MetaCommand::Help => println!("{HELP}"),
+        MetaCommand::Unknown(command) => {
+            anyhow::bail!("Unknown command {command}. Enter .help for usage hints")
+        }
+        MetaCommand::Quit => {}
+    }
+    Ok(())
+}
+
+fn mode_name(mode: ResultFormat) -> &'static str {
+    mode.to_possible_value()
+        .map(|v| v.get_name())
+        .unwrap_or_default()
+}
+
+/// Where the rows selected by statements go, and in what form, as set by
+/// .mode, .output and .once
+struct ShellOutput {
+    mode: ResultFormat,
+    /// The file set by .output, which each result is added to
+    file: Option<(PathBuf, File)>,
+    /// The file set by .once, for the next statement only
+    once: Option<PathBuf>,
+}
+
+impl Default for ShellOutput {
+    fn default() -> Self {
+        Self {
+            mode: ResultFormat::Table,
+            file: None,
+            once: None,
+        }
+    }
+}
+
+impl ShellOutput {
+    fn write(&mut self, result: &QueryResult) -> Result<()> {
+        match &mut self.file {
+            Some((path, file)) => write_rows(file, result, self.mode)
+                .with_context(|| format!("Could not write {}", path.display())),
+            None => print_rows(result, self.mode),
+        }
+    }
+}
+
+fn schema_query(table: Option<&str>) -> String {
+    let filter = match table {
+        Some(table) => format!(" AND tbl_name = '{}'", table.replace('\'', "''")),
+        None => String::new(),
+    };
+    format!(
+        "SELECT sql FROM sqlite_master WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'{filter} ORDER BY tbl_name, type DESC, name"
+    )
+}
+
+/// What a line typed into the shell asks for
+#[derive(Debug, PartialEq)]
+enum ShellInput {
+    Statement(String),
+    Meta(MetaCommand),
+    /// The line continues a statement which is not yet terminated
+    Incomplete,
+}
+
+#[derive(Debug, PartialEq)]
+enum MetaCommand {
+    Tables,
+    Schema(Option<String>),
+    Mode(Option<String>),
+    Output(Option<PathBuf>),
+    Once(Option<PathBuf>),
+    Help,
+    Quit,
+    Unknown(String),
+}
+
+impl MetaCommand {
+    fn parse(line: &str) -> Self {
+        let mut words = line.split_whitespace();
+        match words.next().unwrap_or_default() {
+            ".tables" => Self::Tables,
+            ".schema" => Self::Schema(words.next().map(str::to_owned)),
+            ".mode" => Self::Mode(words.next().map(str::to_owned)),
+            ".output" => Self::Output(words.next().map(PathBuf::from)),
+            ".once" => Self::Once(words.next().map(PathBuf::from)),
+            ".help" => Self::Help,
+            ".quit" | ".exit" => Self::Quit,
+            other => Self::Unknown(other.to_owned()),
+        }
+    }
+}
+
+/// Collects lines until they make up a statement terminated by `;`.
+#[derive(Default)]
+struct StatementBuffer {
+    pending: String,
+}
+
+impl StatementBuffer {
+    fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn push_line(&mut self, line: &str) -> ShellInput {
+        let line = line.trim();
+        // Meta-commands are only recognised at the start of a statement, so
+        // that a line of a statement can begin with a dot.
+        if self.is_empty() && line.starts_with('.') {
+            return ShellInput::Meta(MetaCommand::parse(line));
+        }
+        if line.is_empty() {
+            return ShellInput::Incomplete;
+        }
+        if !self.is_empty() {
+            self.pending.push('\n');
+        }
+        self.pending.push_str(line);
+        if self.pending.ends_with(';') {
+            ShellInput::Statement(std::mem::take(&mut self.pending))
+        } else {
+            ShellInput::Incomplete
+        }
+    }
+}
+
+/// Lines entered in the shell, most recent first, kept in a file between
+/// sessions. History is a convenience, so problems reading or writing the
+/// file are ignored.
+struct ShellHistory {
+    entries: VecDeque<String>,
+    path: Option<PathBuf>,
+}
+
+impl ShellHistory {
+    fn load() -> Self {
+        let path = dirs::data_dir().map(|dir| {
+            dir.join("fermyon")
+                .join("cloud-plugin")
+                .join("sqlite_history")
+        });
+        let entries = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| text.lines().rev().map(str::to_owned).collect())
+            .unwrap_or_default();
+        Self { entries, path }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            _ = std::fs::create_dir_all(dir);
+        }
+        let lines = self.entries.iter().rev().cloned().collect::<Vec<_>>();
+        _ = std::fs::write(path, lines.join("\n"));
+    }
+}
+
+impl dialoguer::History<String> for ShellHistory {
+    fn read(&self, pos: usize) -> Option<String> {
+        self.entries.get(pos).cloned()
+    }
+
+    fn write(&mut self, val: &String) {
+        if val.trim().is_empty() || self.entries.front() == Some(val) {
+            return;
+        }
+        self.entries.push_front(val.clone());
+        self.entries.truncate(MAX_HISTORY);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn statements_can_span_lines() {
+        let mut buffer = StatementBuffer::default();
+        assert_eq!(buffer.push_line("SELECT *"), ShellInput::Incomplete);
+        assert_eq!(buffer.push_line("  .5 AS half"), ShellInput::Incomplete);
+        assert_eq!(
+            buffer.push_line("FROM todos;"),
+            ShellInput::Statement("SELECT *\n.5 AS half\nFROM todos;".to_owned())
+        );
+        assert!(buffer.is_empty());
+        assert_eq!(
+            buffer.push_line(".schema todos"),
+            ShellInput::Meta(MetaCommand::Schema(Some("todos".to_owned())))
+        );
+        assert_eq!(
+            buffer.push_line(".drop"),
+            ShellInput::Meta(MetaCommand::Unknown(".drop".to_owned()))
+        );
+    }
+
+    #[test]
+    fn results_follow_mode_and_output() -> Result<()> {
+        assert_eq!(
+            MetaCommand::parse(".once out/todos.csv"),
+            MetaCommand::Once(Some(PathBuf::from("out/todos.csv")))
+        );
+        assert_eq!(MetaCommand::parse(".output"), MetaCommand::Output(None));
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("results.csv");
+        let mut output = ShellOutput {
+            mode: ResultFormat::from_str("CSV", true).unwrap(),
+            file: Some((path.clone(), File::create(&path)?)),
+            once: None,
+        };
+        assert_eq!(mode_name(output.mode), "csv");
+        let result = |title: &str| QueryResult {
+            columns: vec!["title".to_owned()],
+            rows: vec![vec![title.into()]],
+        };
+        output.write(&result("write docs"))?;
+        output.write(&result("ship it"))?;
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "title\nwrite docs\ntitle\nship it\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn schema_can_be_limited_to_a_table() {
+        assert!(schema_query(Some("it's")).contains("AND tbl_name = 'it''s'"));
+        assert!(!schema_query(None).contains("tbl_name ="));
+    }
+}
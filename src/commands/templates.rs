@@ -0,0 +1,237 @@
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use cloud::{models::Template, CloudClientInterface};
+
+use crate::commands::deploy::{parse_app_name, DeployCommand};
+use crate::commands::{create_cloud_client, CommonArgs};
+use crate::ops::resolve::find_by_name;
+use crate::spin;
+use crate::table::new_table;
+
+/// Browse the app templates in the Fermyon Cloud catalog
+#[derive(Parser, Debug)]
+pub enum TemplatesCommand {
+    /// List the templates in the catalog
+    List(ListCommand),
+}
+
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Only list templates with this tag, such as a language
+    #[clap(long = "tag")]
+    pub tag: Option<String>,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+/// Create an app from a template in the Fermyon Cloud catalog
+#[derive(Parser, Debug)]
+pub struct NewCommand {
+    /// Name of the new app
+    #[clap(value_parser = parse_app_name)]
+    pub name: String,
+    /// The catalog template to create the app from. Run `spin cloud
+    /// templates list` to see the choices.
+    #[clap(short = 't', long = "template")]
+    pub template: String,
+    /// The directory to create the app in. If omitted, a directory named
+    /// after the app is created in the current directory.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+    /// Build and deploy the app as soon as it is created
+    #[clap(long = "deploy", takes_value = false)]
+    pub deploy: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl TemplatesCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::List(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl ListCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let mut templates = list_templates(&client).await?;
+        if let Some(tag) = &self.tag {
+            templates.retain(|t| has_tag(t, tag));
+        }
+        if templates.is_empty() {
+            println!("No templates");
+            return Ok(());
+        }
+        print_templates(&templates);
+        Ok(())
+    }
+}
+
+impl NewCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let template = find_template(&client, &self.template).await?;
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&self.name));
+        if output.exists() {
+            bail!(
+                "{} already exists. Use --output to create the app somewhere else",
+                output.display()
+            );
+        }
+
+        let mut install = vec!["templates", "install", "--git", &template.repository];
+        if let Some(branch) = &template.branch {
+            install.extend(["--branch", branch]);
+        }
+        install.push("--upgrade");
+        run_spin(&install)
+            .await
+            .with_context(|| format!("Could not install template {}", template.id))?;
+        run_spin(&[
+            OsStr::new("new"),
+            OsStr::new("-t"),
+            OsStr::new(&template.id),
+            OsStr::new(&self.name),
+            OsStr::new("-o"),
+            output.as_os_str(),
+            OsStr::new("--accept-defaults"),
+        ])
+        .await
+        .with_context(|| format!("Could not create app {}", self.name))?;
+        println!(
+            r#"Created app "{}" from template {} in {}"#,
+            self.name,
+            template.id,
+            output.display()
+        );
+
+        if !self.deploy {
+            return Ok(());
+        }
+        let mut deploy_args = vec![
+            OsStr::new("deploy"),
+            OsStr::new("--from"),
+            output.as_os_str(),
+            OsStr::new("--build"),
+        ];
+        if let Some(env) = &self.common.deployment_env_id {
+            deploy_args.extend([OsStr::new("--environment-name"), OsStr::new(env)]);
+        }
+        DeployCommand::try_parse_from(deploy_args)?.run().await
+    }
+}
+
+async fn list_templates(client: &impl CloudClientInterface) -> Result<Vec<Template>> {
+    let mut templates = client
+        .list_templates()
+        .await
+        .context("Problem fetching the template catalog")?;
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(templates)
+}
+
+/// Finds the catalog template with the ID `id`, suggesting similar IDs if
+/// there is none.
+async fn find_template(client: &impl CloudClientInterface, id: &str) -> Result<Template> {
+    find_by_name(list_templates(client).await?, id, "template", |t| &t.id)
+}
+
+/// Whether the template has the tag, ignoring case.
+fn has_tag(template: &Template, tag: &str) -> bool {
+    template.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+}
+
+async fn run_spin(args: &[impl AsRef<OsStr>]) -> Result<()> {
+    let status = tokio::process::Command::new(spin::bin_path()?)
+        .args(args)
+        .status()
+        .await
+        .context("Failed to execute spin")?;
+    if !status.success() {
+        bail!("spin exited with {status}");
+    }
+    Ok(())
+}
+
+fn print_templates(templates: &[Template]) {
+    let mut table = new_table();
+    table.set_header(vec!["Template", "Description", "Tags"]);
+    table.add_rows(
+        templates
+            .iter()
+            .map(|t| [t.id.clone(), t.description.clone(), t.tags.join(", ")]),
+    );
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    fn template(id: &str, tags: &[&str]) -> Template {
+        Template {
+            id: id.to_owned(),
+            repository: format!("https://github.com/example/{id}"),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn catalog() -> MockCloudClientInterface {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_templates().returning(|| {
+            Ok(vec![
+                template("http-rust", &["rust", "http"]),
+                template("http-go", &["Go", "http"]),
+                template("redis-rust", &["rust"]),
+            ])
+        });
+        mock
+    }
+
+    #[tokio::test]
+    async fn templates_are_listed_by_id() -> Result<()> {
+        let ids = list_templates(&catalog())
+            .await?
+            .into_iter()
+            .map(|t| t.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["http-go", "http-rust", "redis-rust"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn templates_are_found_by_id() -> Result<()> {
+        let found = find_template(&catalog(), "http-rust").await?;
+        assert_eq!(found, template("http-rust", &["rust", "http"]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_templates_suggest_close_ids() {
+        let err = find_template(&catalog(), "http-rsut").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"No template found with name "http-rsut". Did you mean "http-rust"?"#
+        );
+        let err = find_template(&catalog(), "python").await.unwrap_err();
+        assert_eq!(err.to_string(), r#"No template found with name "python""#);
+    }
+
+    #[test]
+    fn tags_match_ignoring_case() {
+        let go = template("http-go", &["Go", "http"]);
+        assert!(has_tag(&go, "go"));
+        assert!(has_tag(&go, "HTTP"));
+        assert!(!has_tag(&go, "rust"));
+    }
+}
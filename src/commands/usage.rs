@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use cloud::models::{AccountQuotas, Quota};
+use cloud::CloudClientInterface;
+
+use crate::commands::apps::{print_limits, ListFormat};
+use crate::commands::{create_cloud_client, CommonArgs};
+use crate::opts::CLOUD_FORMAT_ENV;
+
+const BAR_WIDTH: usize = 20;
+
+/// Show the account's plan, and how much of what it allows is in use
+#[derive(Parser, Debug)]
+pub struct UsageCommand {
+    /// Format of output
+    #[clap(value_enum, long = "format", env = CLOUD_FORMAT_ENV, default_value = "table")]
+    pub format: ListFormat,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl UsageCommand {
+    pub async fn run(self) -> Result<()> {
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let quotas = client
+            .get_account_quotas()
+            .await
+            .context("Problem fetching the account's usage")?;
+        match self.format {
+            ListFormat::Json => println!("{}", serde_json::to_string_pretty(&quotas)?),
+            ListFormat::Table => print_usage(&quotas),
+        }
+        Ok(())
+    }
+}
+
+fn print_usage(quotas: &AccountQuotas) {
+    print_plan(quotas, "");
+    if let Some(max) = &quotas.max_app_limits {
+        println!("Most each app's limits can be set to:");
+        print_limits(max, None, "  ");
+    }
+}
+
+/// Prints the plan name and the account's use of each quota.
+pub(crate) fn print_plan(quotas: &AccountQuotas, indent: &str) {
+    println!(
+        "{indent}Plan: {}",
+        quotas.plan.as_deref().unwrap_or("not reported")
+    );
+    println!("{indent}Apps:      {}", usage_bar(&quotas.apps));
+    println!("{indent}Databases: {}", usage_bar(&quotas.databases));
+}
+
+/// Renders use of a quota as a bar, such as `[################----] 4 of 5 (80%)`.
+fn usage_bar(quota: &Quota) -> String {
+    let Some(limit) = quota.limit else {
+        return format!("{} (no limit)", quota.used);
+    };
+    let filled = match limit {
+        0 => BAR_WIDTH,
+        limit => (quota.used as usize * BAR_WIDTH / limit as usize).min(BAR_WIDTH),
+    };
+    let percent = match limit {
+        0 => 100,
+        limit => quota.used as u64 * 100 / limit as u64,
+    };
+    format!(
+        "[{}{}] {} of {limit} ({percent}%)",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        quota.used
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bars_show_use_against_the_limit() {
+        let quota = |used, limit| Quota { used, limit };
+        assert_eq!(
+            usage_bar(&quota(4, Some(5))),
+            "[################----] 4 of 5 (80%)"
+        );
+        assert_eq!(
+            usage_bar(&quota(0, Some(3))),
+            "[--------------------] 0 of 3 (0%)"
+        );
+        assert_eq!(
+            usage_bar(&quota(7, Some(5))),
+            "[####################] 7 of 5 (140%)"
+        );
+        assert_eq!(usage_bar(&quota(12, None)), "12 (no limit)");
+    }
+}
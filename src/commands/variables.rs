@@ -1,17 +1,26 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use chrono::Local;
 use clap::Parser;
-use cloud::{client::Client as CloudClient, CloudClientInterface};
-use serde::Deserialize;
+use cloud::{client::Client as CloudClient, models::SetVariablePair, CloudClientInterface};
 use serde_json::from_str;
 use spin_common::arg_parser::parse_kv;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
 use uuid::Uuid;
 
-use crate::commands::{client_and_app_id, CommonArgs};
+use crate::answers;
+use crate::commands::logs::parse_interval;
+use crate::commands::{client_and_app_id, confirm_environment, CommonArgs};
+use crate::diff::{use_color, Diff, DiffFormat};
+use crate::ops::variables::{
+    apply_variables, get_variables, get_variables_json, Variable, VariableOutcome, VariableResult,
+};
+use crate::opts::{EnvSettings, CLOUD_APP_ENV};
+use crate::table::new_table;
 
-#[derive(Deserialize)]
-pub(crate) struct Variable {
-    pub key: String,
-}
+/// How secret values are shown in listings
+const MASKED_VALUE: &str = "********";
 
 /// Manage Spin application variables
 #[derive(Parser, Debug)]
@@ -23,6 +32,14 @@ pub enum VariablesCommand {
     Delete(DeleteCommand),
     /// List all variables of an application
     List(ListCommand),
+    /// Show the value of a secret variable
+    Reveal(RevealCommand),
+    /// Poll an application's variables and print changes as they happen
+    Watch(WatchCommand),
+    /// Compare the variables of two applications
+    Diff(DiffCommand),
+    /// Rename a variable, or every variable with a prefix, keeping its value
+    Rename(RenameCommand),
 }
 
 #[derive(Parser, Debug)]
@@ -30,10 +47,18 @@ pub struct SetCommand {
     /// Variable pair to set
     #[clap(parse(try_from_str = parse_kv))]
     pub variables_to_set: Vec<(String, String)>,
+    /// File of variable pairs to set, one KEY=VALUE per line. Blank lines
+    /// and lines starting with # are ignored.
+    #[clap(short = 'f', long = "file")]
+    pub file: Option<PathBuf>,
+    /// Store the variables as secrets. Their values are masked in listings
+    /// and can only be read back with `variables reveal`.
+    #[clap(long = "secret", takes_value = false)]
+    pub secret: bool,
     #[clap(flatten)]
     common: CommonArgs,
     /// Name of Spin app
-    #[clap(name = "app", long = "app")]
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
     pub app: String,
 }
 
@@ -44,7 +69,7 @@ pub struct DeleteCommand {
     #[clap(flatten)]
     common: CommonArgs,
     /// Name of Spin app
-    #[clap(name = "app", long = "app")]
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
     pub app: String,
 }
 
@@ -53,19 +78,86 @@ pub struct ListCommand {
     #[clap(flatten)]
     common: CommonArgs,
     /// Name of Spin app
-    #[clap(name = "app", long = "app")]
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct RevealCommand {
+    /// Variable whose value to show
+    pub key: String,
+    /// Skip the prompt to confirm showing the value
+    #[clap(short = 'y', long = "yes", takes_value = false)]
+    pub yes: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// Name of Spin app
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct WatchCommand {
+    /// Interval in seconds between checks for changes
+    #[clap(parse(try_from_str = parse_interval), long = "interval", default_value = "5")]
+    pub interval: Duration,
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// Name of Spin app
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct DiffCommand {
+    /// The app to compare with. Changes are shown from --app to this app.
+    pub other_app: String,
+    /// Format of the differences
+    #[clap(value_enum, long = "format", default_value = "text")]
+    pub format: DiffFormat,
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// Name of Spin app
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct RenameCommand {
+    /// Variable to rename
+    #[clap(requires = "new", required_unless_present = "prefix-rename")]
+    pub old: Option<String>,
+    /// New name of the variable
+    pub new: Option<String>,
+    /// Rename every variable starting with OLD_PREFIX so that it starts
+    /// with NEW_PREFIX instead
+    #[clap(
+        name = "prefix-rename",
+        long = "prefix-rename",
+        number_of_values = 2,
+        value_names = &["OLD_PREFIX", "NEW_PREFIX"],
+        conflicts_with = "old"
+    )]
+    pub prefix_rename: Option<Vec<String>>,
+    /// Replace variables which already have the new names
+    #[clap(long = "overwrite", takes_value = false)]
+    pub overwrite: bool,
+    /// List the renames without making them
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+    /// Name of Spin app
+    #[clap(name = "app", long = "app", env = CLOUD_APP_ENV)]
     pub app: String,
 }
 
 impl VariablesCommand {
     pub async fn run(self) -> Result<()> {
         match self {
-            Self::Set(cmd) => {
-                let (client, app_id) =
-                    client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
-                set_variables(&client, app_id, &cmd.variables_to_set).await?;
-            }
+            Self::Set(cmd) => cmd.run().await?,
             Self::Delete(cmd) => {
+                confirm_environment(cmd.common.deployment_env_id.as_deref())?;
                 let (client, app_id) =
                     client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
                 delete_variables(&client, app_id, &cmd.variables_to_delete).await?;
@@ -75,27 +167,362 @@ impl VariablesCommand {
                     client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
                 let var_names = get_variables(&client, app_id).await?;
                 for v in var_names {
-                    println!("{}", v.key);
+                    println!("{}", describe_variable(&v));
                 }
             }
+            Self::Reveal(cmd) => cmd.run().await?,
+            Self::Rename(cmd) => cmd.run().await?,
+            Self::Watch(cmd) => cmd.run().await?,
+            Self::Diff(cmd) => {
+                let (client, app_id) =
+                    client_and_app_id(cmd.common.deployment_env_id.as_deref(), &cmd.app).await?;
+                let other_app_id = crate::ops::apps::app_id(&client, &cmd.other_app).await?;
+                let diff = Diff::between(
+                    &variables_snapshot(&client, app_id).await?,
+                    &variables_snapshot(&client, other_app_id).await?,
+                );
+                diff.print(cmd.format)?;
+            }
         }
         Ok(())
     }
 }
 
-pub(crate) async fn set_variables(
-    client: &CloudClient,
+impl SetCommand {
+    async fn run(self) -> Result<()> {
+        let mut variables = match &self.file {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Could not read variables file {}", path.display()))?;
+                parse_variables_file(&text)
+                    .with_context(|| format!("Invalid variables file {}", path.display()))?
+            }
+            None => vec![],
+        };
+        // Variables given on the command line take precedence over the file.
+        variables.extend(self.variables_to_set.iter().cloned());
+        if variables.is_empty() {
+            bail!("No variables to set. Pass KEY=VALUE pairs or use --file");
+        }
+
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let results = apply_variables(&client, app_id, &variables, self.secret).await?;
+        print_variable_results(&results);
+
+        let failed = results
+            .iter()
+            .filter(|r| matches!(r.outcome, VariableOutcome::Failed(_)))
+            .count();
+        if failed > 0 {
+            bail!("{failed} of {} variables could not be set", results.len());
+        }
+        Ok(())
+    }
+}
+
+impl RevealCommand {
+    async fn run(self) -> Result<()> {
+        if !self.yes {
+            if !EnvSettings::from_env().interactive() {
+                bail!("Use --yes to show the value of variable {}", self.key);
+            }
+            let confirmed = answers::confirm(
+                &format!("Show the value of variable {} in the terminal?", self.key),
+                false,
+            )?;
+            if !confirmed {
+                return Ok(());
+            }
+        }
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let value = client
+            .reveal_variable(app_id, &self.key)
+            .await
+            .with_context(|| format!("Problem revealing variable {}", self.key))?;
+        println!("{value}");
+        Ok(())
+    }
+}
+
+impl RenameCommand {
+    async fn run(self) -> Result<()> {
+        if !self.dry_run {
+            confirm_environment(self.common.deployment_env_id.as_deref())?;
+        }
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let renames = self.renames(&get_variables(&client, app_id).await?)?;
+        if self.dry_run {
+            println!("Would rename {} variable(s):", renames.len());
+            for rename in &renames {
+                println!("  {} -> {}", rename.from, rename.to);
+            }
+            return Ok(());
+        }
+        rename_variables(&client, app_id, &renames).await?;
+        for rename in &renames {
+            println!("Renamed {} to {}", rename.from, rename.to);
+        }
+        Ok(())
+    }
+
+    /// The renames to make, checked against the app's variables.
+    fn renames(&self, variables: &[Variable]) -> Result<Vec<VariableRename>> {
+        let existing = |key: &str| variables.iter().find(|v| v.key == key);
+        let renames = match (&self.old, &self.new, self.prefix_rename.as_deref()) {
+            (Some(old), Some(new), _) => {
+                let variable = existing(old)
+                    .with_context(|| format!("App {} has no variable named {old}", self.app))?;
+                vec![VariableRename {
+                    from: old.clone(),
+                    to: new.clone(),
+                    secret: variable.secret,
+                    replaces: existing(new).map(|v| v.secret),
+                }]
+            }
+            (None, None, Some([old_prefix, new_prefix])) => {
+                let renames = variables
+                    .iter()
+                    .filter_map(|v| {
+                        let rest = v.key.strip_prefix(old_prefix.as_str())?;
+                        let to = format!("{new_prefix}{rest}");
+                        Some(VariableRename {
+                            from: v.key.clone(),
+                            replaces: existing(&to).map(|v| v.secret),
+                            to,
+                            secret: v.secret,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                if renames.is_empty() {
+                    bail!(
+                        "App {} has no variables starting with {old_prefix}",
+                        self.app
+                    );
+                }
+                renames
+            }
+            _ => bail!("Invalid combination of arguments"), // Should be prevented by clap
+        };
+        for rename in &renames {
+            if rename.from == rename.to {
+                bail!("Variable {} already has that name", rename.from);
+            }
+            if renames.iter().any(|r| r.from == rename.to) {
+                bail!(
+                    "Cannot rename {} to {}, which is itself being renamed",
+                    rename.from,
+                    rename.to
+                );
+            }
+            if !self.overwrite && rename.replaces.is_some() {
+                bail!(
+                    "Variable {} already exists. Use --overwrite to replace it with {}",
+                    rename.to,
+                    rename.from
+                );
+            }
+        }
+        Ok(renames)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct VariableRename {
+    pub from: String,
+    pub to: String,
+    pub secret: bool,
+    /// Whether a variable named `to` is being overwritten, and if so
+    /// whether it is secret
+    pub replaces: Option<bool>,
+}
+
+/// Copies each variable's value to its new name, then deletes the old
+/// names. Cloud cannot change several variables at once, so the values are
+/// all read before anything changes, including those of variables about to
+/// be overwritten. If a new name cannot be written, those already written
+/// are deleted or given back their old values, leaving the variables as
+/// they were.
+async fn rename_variables(
+    client: &impl CloudClientInterface,
     app_id: Uuid,
-    variables: &[(String, String)],
+    renames: &[VariableRename],
 ) -> Result<()> {
-    for var in variables {
-        CloudClient::add_variable_pair(client, app_id, var.0.to_owned(), var.1.to_owned())
+    let mut values = Vec::with_capacity(renames.len());
+    for rename in renames {
+        let value = client
+            .reveal_variable(app_id, &rename.from)
+            .await
+            .with_context(|| format!("Problem reading variable {}", rename.from))?;
+        let replaced = match rename.replaces {
+            Some(secret) => Some((
+                client
+                    .reveal_variable(app_id, &rename.to)
+                    .await
+                    .with_context(|| format!("Problem reading variable {}", rename.to))?,
+                secret,
+            )),
+            None => None,
+        };
+        values.push((value, replaced));
+    }
+
+    let mut written = vec![];
+    for (rename, (value, replaced)) in renames.iter().zip(values) {
+        if let Err(e) = set_variable(client, app_id, &rename.to, value, rename.secret).await {
+            for (key, replaced) in written {
+                // The original variables are untouched, so a leftover copy
+                // is only clutter; report it rather than mask the failure.
+                // An overwritten variable is given its old value back.
+                let result = match replaced {
+                    Some((value, secret)) => set_variable(client, app_id, key, value, secret)
+                        .await
+                        .with_context(|| format!("Could not restore variable {key}")),
+                    None => client
+                        .delete_variable_pair(app_id, key.to_owned())
+                        .await
+                        .context("Could not remove partial rename"),
+                };
+                if let Err(e) = result {
+                    eprintln!("{e:#}");
+                }
+            }
+            return Err(e.context(format!(
+                "Problem setting variable {}. No variables were renamed",
+                rename.to
+            )));
+        }
+        written.push((rename.to.as_str(), replaced));
+    }
+
+    for rename in renames {
+        client
+            .delete_variable_pair(app_id, rename.from.clone())
             .await
-            .with_context(|| format!("Problem creating variable {}", var.0))?;
+            .with_context(|| {
+                format!(
+                    "Problem deleting variable {} after copying it to {}",
+                    rename.from, rename.to
+                )
+            })?;
     }
     Ok(())
 }
 
+async fn set_variable(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    key: &str,
+    value: String,
+    secret: bool,
+) -> Result<()> {
+    if secret {
+        client
+            .set_variable_pair(SetVariablePair {
+                app_id,
+                variable: key.to_owned(),
+                value,
+                secret: true,
+            })
+            .await
+    } else {
+        client
+            .add_variable_pair(app_id, key.to_owned(), value)
+            .await
+    }
+}
+
+impl WatchCommand {
+    async fn run(self) -> Result<()> {
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let mut current = variables_snapshot(&client, app_id).await?;
+        println!(
+            "Watching {} variable(s) of {}. Press Ctrl+C to stop.",
+            current.len(),
+            self.app
+        );
+        loop {
+            tokio::time::sleep(self.interval).await;
+            // Someone may be changing configuration while this runs, so a
+            // failed check is reported and retried rather than ending the watch.
+            let latest = match variables_snapshot(&client, app_id).await {
+                Ok(latest) => latest,
+                Err(e) => {
+                    eprintln!("Could not check variables: {e:#}");
+                    continue;
+                }
+            };
+            let now = Local::now().format("%H:%M:%S");
+            for line in Diff::between(&current, &latest).render(use_color()) {
+                println!("[{now}] {line}");
+            }
+            current = latest;
+        }
+    }
+}
+
+/// The variables of an app keyed by name. Cloud does not return values, so
+/// each variable is described by the other attributes of its entry, such as
+/// `secret=true`.
+async fn variables_snapshot(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+) -> Result<BTreeMap<String, Option<String>>> {
+    get_variables_json(client, app_id)
+        .await?
+        .iter()
+        .map(|var| {
+            let mut entry: serde_json::Map<String, serde_json::Value> =
+                from_str(var).context("could not parse variable")?;
+            let key = match entry.remove("key") {
+                Some(serde_json::Value::String(key)) => key,
+                _ => bail!("variable has no key"),
+            };
+            let attributes = entry
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>();
+            Ok((key, (!attributes.is_empty()).then(|| attributes.join(", "))))
+        })
+        .collect()
+}
+
+fn parse_variables_file(text: &str) -> Result<Vec<(String, String)>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(index, line)| {
+            parse_kv(line.trim()).with_context(|| format!("Line {} is not KEY=VALUE", index + 1))
+        })
+        .collect()
+}
+
+fn describe_variable(variable: &Variable) -> String {
+    if variable.secret {
+        format!("{} = {MASKED_VALUE} (secret)", variable.key)
+    } else {
+        variable.key.clone()
+    }
+}
+
+fn print_variable_results(results: &[VariableResult]) {
+    let mut table = new_table();
+    table.set_header(vec!["Variable", "Result", "Reason"]);
+    table.add_rows(results.iter().map(|r| match &r.outcome {
+        VariableOutcome::Set => [r.key.as_str(), "set", ""],
+        VariableOutcome::Updated => [r.key.as_str(), "updated", ""],
+        VariableOutcome::Failed(reason) => [r.key.as_str(), "failed", reason.as_str()],
+    }));
+    println!("{table}");
+}
+
 pub(crate) async fn delete_variables(
     client: &CloudClient,
     app_id: Uuid,
@@ -109,19 +536,235 @@ pub(crate) async fn delete_variables(
     Ok(())
 }
 
-async fn get_variables_json(client: &CloudClient, app_id: Uuid) -> Result<Vec<String>> {
-    let vars = CloudClient::get_variable_pairs(client, app_id)
-        .await
-        .context("Problem listing variables")?;
-    Ok(vars)
-}
+#[cfg(test)]
+mod variables_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
 
-pub(crate) async fn get_variables(client: &CloudClient, app_id: Uuid) -> Result<Vec<Variable>> {
-    let vars = get_variables_json(client, app_id).await?;
-    let var_names = vars
-        .iter()
-        .map(|var| from_str(var))
-        .collect::<Result<Vec<Variable>, _>>()
-        .context("could not parse variable")?;
-    Ok(var_names)
+    #[test]
+    fn variables_file_skips_comments_and_blank_lines() -> Result<()> {
+        let variables = parse_variables_file("# database\nDB_HOST=db.example\n\n  DB_PORT=5432\n")?;
+        assert_eq!(
+            variables,
+            vec![
+                ("DB_HOST".to_owned(), "db.example".to_owned()),
+                ("DB_PORT".to_owned(), "5432".to_owned()),
+            ]
+        );
+        let err = parse_variables_file("A=1\nnot a pair").unwrap_err();
+        assert_eq!(err.to_string(), "Line 2 is not KEY=VALUE");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failures_do_not_stop_the_batch() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_variable_pairs()
+            .returning(|_| Ok(vec![r#"{"key":"existing"}"#.to_owned()]));
+        mock.expect_add_variable_pair()
+            .returning(|_, key, _| match key.as_str() {
+                "broken" => Err(anyhow::anyhow!("invalid variable name")),
+                _ => Ok(()),
+            });
+
+        let variables = [
+            ("existing", "1"),
+            ("broken", "2"),
+            ("new", "old"),
+            ("new", "3"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        let results = apply_variables(&mock, Uuid::new_v4(), &variables, false).await?;
+        assert_eq!(
+            results,
+            vec![
+                VariableResult {
+                    key: "existing".to_owned(),
+                    outcome: VariableOutcome::Updated,
+                },
+                VariableResult {
+                    key: "broken".to_owned(),
+                    outcome: VariableOutcome::Failed("invalid variable name".to_owned()),
+                },
+                VariableResult {
+                    key: "new".to_owned(),
+                    outcome: VariableOutcome::Set,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn secrets_are_marked_and_masked() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_get_variable_pairs().returning(|_| Ok(vec![]));
+        mock.expect_set_variable_pair()
+            .withf(|pair| pair.variable == "api_key" && pair.secret)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let variables = [("api_key".to_owned(), "hunter2".to_owned())];
+        apply_variables(&mock, Uuid::new_v4(), &variables, true).await?;
+
+        let listed: Vec<Variable> = [r#"{"key":"api_key","secret":true}"#, r#"{"key":"region"}"#]
+            .iter()
+            .map(|v| from_str(v))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(describe_variable(&listed[0]), "api_key = ******** (secret)");
+        assert_eq!(describe_variable(&listed[1]), "region");
+        Ok(())
+    }
+
+    fn variables(keys: &[(&str, bool)]) -> Vec<Variable> {
+        keys.iter()
+            .map(|(key, secret)| Variable {
+                key: key.to_string(),
+                secret: *secret,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prefix_renames_keep_the_rest_of_the_name() -> Result<()> {
+        let command = RenameCommand::try_parse_from([
+            "rename",
+            "--prefix-rename",
+            "db_",
+            "database_",
+            "--app",
+            "todo",
+        ])?;
+        let renames = command.renames(&variables(&[
+            ("db_host", false),
+            ("db_password", true),
+            ("region", false),
+        ]))?;
+        assert_eq!(
+            renames
+                .iter()
+                .map(|r| (r.from.as_str(), r.to.as_str(), r.secret))
+                .collect::<Vec<_>>(),
+            vec![
+                ("db_host", "database_host", false),
+                ("db_password", "database_password", true),
+            ]
+        );
+
+        let command =
+            RenameCommand::try_parse_from(["rename", "db_host", "region", "--app", "todo"])?;
+        let err = command
+            .renames(&variables(&[("db_host", false), ("region", false)]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Variable region already exists. Use --overwrite to replace it with db_host"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_renames_are_rolled_back() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_reveal_variable()
+            .returning(|_, key| Ok(format!("value of {key}")));
+        mock.expect_add_variable_pair()
+            .returning(|_, key, _| match key.as_str() {
+                "database_port" => Err(anyhow::anyhow!("quota exceeded")),
+                _ => Ok(()),
+            });
+        mock.expect_delete_variable_pair()
+            .withf(|_, key| key == "database_host")
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let renames = ["host", "port"].map(|name| VariableRename {
+            from: format!("db_{name}"),
+            to: format!("database_{name}"),
+            secret: false,
+            replaces: None,
+        });
+        let err = rename_variables(&mock, Uuid::new_v4(), &renames)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Problem setting variable database_port. No variables were renamed"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_renames_restore_overwritten_variables() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_reveal_variable()
+            .returning(|_, key| Ok(format!("value of {key}")));
+        mock.expect_add_variable_pair()
+            .withf(|_, key, value| key == "database_host" && value == "value of db_host")
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock.expect_set_variable_pair()
+            .withf(|pair| pair.variable == "database_port")
+            .times(1)
+            .returning(|_| Err(anyhow::anyhow!("quota exceeded")));
+        mock.expect_set_variable_pair()
+            .withf(|pair| {
+                pair.variable == "database_host"
+                    && pair.value == "value of database_host"
+                    && pair.secret
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        mock.expect_delete_variable_pair().never();
+
+        let renames = [
+            VariableRename {
+                from: "db_host".to_owned(),
+                to: "database_host".to_owned(),
+                secret: false,
+                replaces: Some(true),
+            },
+            VariableRename {
+                from: "db_port".to_owned(),
+                to: "database_port".to_owned(),
+                secret: true,
+                replaces: None,
+            },
+        ];
+        let err = rename_variables(&mock, Uuid::new_v4(), &renames)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Problem setting variable database_port. No variables were renamed"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watch_reports_added_removed_and_changed_variables() -> Result<()> {
+        let mut mock = MockCloudClientInterface::new();
+        let mut polls = 0;
+        mock.expect_get_variable_pairs().returning(move |_| {
+            polls += 1;
+            Ok(match polls {
+                1 => vec![r#"{"key":"api_key"}"#, r#"{"key":"region"}"#],
+                _ => vec![r#"{"key":"api_key","secret":true}"#, r#"{"key":"db_url"}"#],
+            }
+            .into_iter()
+            .map(str::to_owned)
+            .collect())
+        });
+
+        let app_id = Uuid::new_v4();
+        let before = variables_snapshot(&mock, app_id).await?;
+        let after = variables_snapshot(&mock, app_id).await?;
+        let diff = Diff::between(&before, &after);
+        assert_eq!(
+            diff.render(false),
+            vec!["~ api_key  (none) -> secret=true", "+ db_url", "- region"]
+        );
+        assert!(Diff::between(&after, &after).is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use cloud::models::ServerVersion;
+use cloud::CloudClientInterface;
+use semver::{Version, VersionReq};
+
+use crate::commands::{create_cloud_client, CommonArgs};
+use crate::VERSION;
+
+/// Show the versions of the plugin, Spin and, with --remote, Fermyon Cloud
+#[derive(Parser, Debug)]
+pub struct VersionCommand {
+    /// Also ask Fermyon Cloud for its version, and report any known
+    /// incompatibilities between it and this plugin
+    #[clap(long = "remote", takes_value = false)]
+    remote: bool,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl VersionCommand {
+    pub async fn run(self) -> Result<()> {
+        println!("Plugin:     {VERSION}");
+        println!(
+            "Spin:       {}",
+            std::env::var("SPIN_VERSION").unwrap_or_else(|_| "unknown".to_owned())
+        );
+        println!(
+            "Platform:   {} {}",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        println!("User-Agent: {}", cloud::client::user_agent());
+        if !self.remote {
+            return Ok(());
+        }
+
+        let client = create_cloud_client(self.common.deployment_env_id.as_deref()).await?;
+        let server = client
+            .get_server_version()
+            .await
+            .context("Problem fetching the version of Fermyon Cloud")?;
+        println!("Server:     {}", server.version);
+        let plugin = Version::parse(env!("CARGO_PKG_VERSION"))?;
+        match version_skew(&plugin, &server).as_slice() {
+            [] => println!("No known incompatibilities"),
+            problems => {
+                println!("Known incompatibilities:");
+                for problem in problems {
+                    println!("  - {problem}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What the platform says is wrong with using `plugin` against it. Version
+/// requirements the plugin cannot parse are ignored rather than failing.
+fn version_skew(plugin: &Version, server: &ServerVersion) -> Vec<String> {
+    let mut problems = vec![];
+    if let Some(minimum) = server
+        .minimum_plugin_version
+        .as_deref()
+        .and_then(|v| Version::parse(v).ok())
+    {
+        if plugin < &minimum {
+            problems.push(format!(
+                "Fermyon Cloud {} requires plugin {minimum} or later. Upgrade with `spin plugins upgrade cloud`.",
+                server.version
+            ));
+        }
+    }
+    problems.extend(
+        server
+            .known_issues
+            .iter()
+            .filter(|issue| {
+                VersionReq::parse(&issue.plugin_versions).is_ok_and(|req| req.matches(plugin))
+            })
+            .map(|issue| issue.description.clone()),
+    );
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud::models::KnownIssue;
+
+    #[test]
+    fn skew_reports_old_plugins_and_matching_issues() {
+        let server = ServerVersion {
+            version: "2024.6.1".to_owned(),
+            minimum_plugin_version: Some("0.5.0".to_owned()),
+            known_issues: vec![
+                KnownIssue {
+                    plugin_versions: "<0.6".to_owned(),
+                    description: "sqlite export truncates BLOBs".to_owned(),
+                },
+                KnownIssue {
+                    plugin_versions: "not a requirement".to_owned(),
+                    description: "ignored".to_owned(),
+                },
+            ],
+        };
+        assert_eq!(
+            version_skew(&Version::new(0, 4, 2), &server),
+            vec![
+                "Fermyon Cloud 2024.6.1 requires plugin 0.5.0 or later. Upgrade with `spin plugins upgrade cloud`.",
+                "sqlite export truncates BLOBs",
+            ]
+        );
+        assert_eq!(
+            version_skew(&Version::new(0, 5, 1), &server),
+            vec!["sqlite export truncates BLOBs"]
+        );
+        assert!(version_skew(&Version::new(0, 6, 0), &server).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use cloud::{
+    models::{CreateWebhook, Webhook},
+    CloudClientInterface,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::commands::{client_and_app_id, confirm_environment, CommonArgs};
+use crate::opts::CLOUD_APP_ENV;
+use crate::table::new_table;
+
+/// Manage webhooks which Fermyon Cloud calls on app lifecycle events
+#[derive(Parser, Debug)]
+pub enum WebhooksCommand {
+    /// Register a webhook for an app
+    Add(AddCommand),
+    /// List the webhooks registered for an app
+    List(ListCommand),
+    /// Remove a webhook from an app
+    Remove(RemoveCommand),
+}
+
+/// App lifecycle events which can trigger a webhook
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum WebhookEvent {
+    #[clap(name = "deploy.started")]
+    DeployStarted,
+    #[clap(name = "deploy.succeeded")]
+    DeploySucceeded,
+    #[clap(name = "deploy.failed")]
+    DeployFailed,
+    #[clap(name = "app.deleted")]
+    AppDeleted,
+}
+
+impl WebhookEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::DeployStarted => "deploy.started",
+            Self::DeploySucceeded => "deploy.succeeded",
+            Self::DeployFailed => "deploy.failed",
+            Self::AppDeleted => "app.deleted",
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct AddCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// Event which triggers the webhook. Can be used multiple times.
+    #[clap(value_enum, short = 'e', long = "event", required = true)]
+    pub events: Vec<WebhookEvent>,
+    /// The URL which will receive a POST request when an event occurs
+    #[clap(short = 'u', long = "url", value_parser = parse_webhook_url)]
+    pub url: Url,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct RemoveCommand {
+    /// Name of Spin app
+    #[clap(short = 'a', long = "app", env = CLOUD_APP_ENV)]
+    pub app: String,
+    /// ID or URL of the webhook to remove
+    pub webhook: String,
+    #[clap(flatten)]
+    common: CommonArgs,
+}
+
+impl WebhooksCommand {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Add(cmd) => cmd.run().await,
+            Self::List(cmd) => cmd.run().await,
+            Self::Remove(cmd) => cmd.run().await,
+        }
+    }
+}
+
+impl AddCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let mut events = self
+            .events
+            .iter()
+            .map(|e| e.as_str().to_owned())
+            .collect::<Vec<_>>();
+        events.sort();
+        events.dedup();
+        let webhook = client
+            .add_webhook(
+                app_id,
+                CreateWebhook {
+                    url: self.url.to_string(),
+                    events,
+                },
+            )
+            .await
+            .with_context(|| format!("Problem adding webhook for app {}", &self.app))?;
+        println!(
+            "Added webhook {} for app \"{}\" on {}",
+            webhook.id,
+            &self.app,
+            webhook.events.join(", ")
+        );
+        Ok(())
+    }
+}
+
+impl ListCommand {
+    pub async fn run(self) -> Result<()> {
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let webhooks = client
+            .list_webhooks(app_id)
+            .await
+            .with_context(|| format!("Problem listing webhooks for app {}", &self.app))?;
+        if webhooks.is_empty() {
+            eprintln!("No webhooks registered for app \"{}\"", &self.app);
+        } else {
+            print_webhooks(&webhooks);
+        }
+        Ok(())
+    }
+}
+
+impl RemoveCommand {
+    pub async fn run(self) -> Result<()> {
+        confirm_environment(self.common.deployment_env_id.as_deref())?;
+        let (client, app_id) =
+            client_and_app_id(self.common.deployment_env_id.as_deref(), &self.app).await?;
+        let webhook_id = find_webhook(&client, app_id, &self.app, &self.webhook).await?;
+        client
+            .remove_webhook(app_id, webhook_id)
+            .await
+            .with_context(|| format!("Problem removing webhook from app {}", &self.app))?;
+        println!("Removed webhook {webhook_id} from app \"{}\"", &self.app);
+        Ok(())
+    }
+}
+
+// Webhooks can be referred to by ID or, more memorably, by their URL.
+async fn find_webhook(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    app: &str,
+    webhook: &str,
+) -> Result<Uuid> {
+    let webhooks = client.list_webhooks(app_id).await?;
+    let matches = webhooks
+        .iter()
+        .filter(|w| w.id.to_string() == webhook || w.url == webhook)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => bail!(r#"No webhook "{webhook}" found for app "{app}""#),
+        [w] => Ok(w.id),
+        _ => bail!(
+            r#"More than one webhook for app "{app}" uses the URL "{webhook}". Remove it by ID instead."#
+        ),
+    }
+}
+
+fn parse_webhook_url(url: &str) -> Result<Url> {
+    let url = Url::parse(url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("webhook URLs must use http or https");
+    }
+    Ok(url)
+}
+
+fn print_webhooks(webhooks: &[Webhook]) {
+    let mut table = new_table();
+    table.set_header(vec!["ID", "URL", "Events"]);
+    table.add_rows(
+        webhooks
+            .iter()
+            .map(|w| [w.id.to_string(), w.url.clone(), w.events.join(", ")]),
+    );
+    println!("{table}");
+}
+
+#[cfg(test)]
+mod webhooks_tests {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    fn webhook(url: &str) -> Webhook {
+        Webhook {
+            id: Uuid::new_v4(),
+            url: url.to_owned(),
+            events: vec!["deploy.succeeded".to_owned()],
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_webhook_by_id_or_url() -> Result<()> {
+        let hooks = vec![webhook("https://a.example/"), webhook("https://b.example/")];
+        let id = hooks[1].id;
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_list_webhooks()
+            .returning(move |_| Ok(hooks.clone()));
+
+        let app_id = Uuid::new_v4();
+        assert_eq!(
+            id,
+            find_webhook(&mock, app_id, "app", &id.to_string()).await?
+        );
+        assert_eq!(
+            id,
+            find_webhook(&mock, app_id, "app", "https://b.example/").await?
+        );
+        let err = find_webhook(&mock, app_id, "app", "https://c.example/")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"No webhook "https://c.example/" found for app "app""#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_non_http_urls() {
+        assert!(parse_webhook_url("https://hooks.example/deploys").is_ok());
+        assert!(parse_webhook_url("ftp://hooks.example/deploys").is_err());
+        assert!(parse_webhook_url("not a url").is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Upgrades saved login files written by older versions of the plugin.
+//!
+//! Each saved login records the schema `version` it was written with. On
+//! startup, files with an older version are run through the migrations
+//! between that version and [`CONFIG_VERSION`], after the original has been
+//! backed up next to it. To change the schema, add a migration to the end of
+//! [`MIGRATIONS`] and bump [`CONFIG_VERSION`].
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::commands::login::config_root_dir;
+
+/// The schema version of login files written by this version of the plugin.
+pub const CONFIG_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Migrations in order; the migration at index N upgrades version N to N + 1.
+const MIGRATIONS: &[Migration] = &[drop_bindle_settings];
+
+// Version 0 files predate versioning. Logins saved in the Bindle era also
+// carried registry credentials, which Cloud no longer uses.
+fn drop_bindle_settings(config: &mut Map<String, Value>) -> Result<()> {
+    for key in ["bindle_url", "bindle_username", "bindle_password"] {
+        config.remove(key);
+    }
+    Ok(())
+}
+
+/// Migrates every saved login to the current schema version. A login which
+/// cannot be migrated does not stop the others from being migrated; the
+/// error lists each one that failed.
+pub fn migrate_config_files() -> Result<()> {
+    let root = config_root_dir()?;
+    if !root.is_dir() {
+        return Ok(());
+    }
+    migrate_dir(&root)
+}
+
+fn migrate_dir(root: &Path) -> Result<()> {
+    let entries = std::fs::read_dir(root)
+        .with_context(|| format!("Failed to read config directory {}", root.display()))?;
+    let mut failures = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if !is_saved_login(&path) {
+            continue;
+        }
+        if let Err(e) = migrate_file(&path) {
+            failures.push(format!("{}: {e:#}", path.display()));
+        }
+    }
+    if !failures.is_empty() {
+        bail!("Failed to migrate {}", failures.join("; "));
+    }
+    Ok(())
+}
+
+/// Logins are saved as `config.json`, or as `<name>.json` with
+/// --environment-name. Spin keeps its own files in the same directory, so
+/// any other file only counts if it holds a login's url and token.
+fn is_saved_login(path: &Path) -> bool {
+    if path.extension() != Some(OsStr::new("json")) {
+        return false;
+    }
+    if path.file_name().is_some_and(|name| name == "config.json") {
+        return true;
+    }
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+        .is_some_and(|value| value.get("url").is_some() && value.get("token").is_some())
+}
+
+/// Migrates a single file, returning whether it needed to change.
+fn migrate_file(path: &Path) -> Result<bool> {
+    let text = std::fs::read_to_string(path)?;
+    let mut value: Value = serde_json::from_str(&text)?;
+    let Some(config) = value.as_object_mut() else {
+        bail!("Expected a JSON object");
+    };
+    let version = config_version(config);
+    // Files from newer plugins are left for those plugins to understand.
+    if version >= CONFIG_VERSION {
+        return Ok(false);
+    }
+
+    migrate(config, version)?;
+
+    let backup = path.with_extension(format!("json.v{version}.bak"));
+    std::fs::copy(path, &backup)
+        .with_context(|| format!("Failed to back up to {}", backup.display()))?;
+    std::fs::write(path, serde_json::to_string_pretty(&value)?)?;
+    Ok(true)
+}
+
+fn config_version(config: &Map<String, Value>) -> u32 {
+    config
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .map_or(0, |v| v as u32)
+}
+
+fn migrate(config: &mut Map<String, Value>, from: u32) -> Result<()> {
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(config).with_context(|| format!("Failed to migrate from version {version}"))?;
+    }
+    config.insert("version".to_owned(), CONFIG_VERSION.into());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len(), CONFIG_VERSION as usize);
+    }
+
+    #[test]
+    fn unversioned_file_is_migrated_and_backed_up() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.json");
+        let original = json!({
+            "url": "https://cloud.fermyon.com/",
+            "danger_accept_invalid_certs": false,
+            "token": "abc",
+            "bindle_url": "https://bindle.example/v1",
+            "bindle_username": "user",
+        });
+        std::fs::write(&path, original.to_string())?;
+
+        assert!(migrate_file(&path)?);
+
+        let migrated: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(
+            migrated,
+            json!({
+                "url": "https://cloud.fermyon.com/",
+                "danger_accept_invalid_certs": false,
+                "token": "abc",
+                "version": CONFIG_VERSION,
+            })
+        );
+        let backup: Value = serde_json::from_str(&std::fs::read_to_string(
+            dir.path().join("config.json.v0.bak"),
+        )?)?;
+        assert_eq!(backup, original);
+        Ok(())
+    }
+
+    #[test]
+    fn only_saved_logins_are_migrated_and_failures_do_not_stop_others() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let spin_file = json!({ "auths": { "ghcr.io": { "username": "me" } } });
+        std::fs::write(dir.path().join("registry-auth.json"), spin_file.to_string())?;
+        std::fs::write(dir.path().join("config.json"), "not json")?;
+        std::fs::write(
+            dir.path().join("staging.json"),
+            json!({ "url": "https://cloud.example/", "token": "abc" }).to_string(),
+        )?;
+
+        let err = migrate_dir(dir.path()).unwrap_err();
+        assert!(err.to_string().contains("config.json"));
+        assert!(!err.to_string().contains("registry-auth.json"));
+
+        let staging: Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("staging.json"))?)?;
+        assert_eq!(staging["version"], CONFIG_VERSION);
+        let spin: Value = serde_json::from_str(&std::fs::read_to_string(
+            dir.path().join("registry-auth.json"),
+        )?)?;
+        assert_eq!(spin, spin_file);
+        assert!(!dir.path().join("registry-auth.json.v0.bak").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn current_file_is_untouched() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("config.json");
+        std::fs::write(
+            &path,
+            json!({ "token": "abc", "version": CONFIG_VERSION }).to_string(),
+        )?;
+
+        assert!(!migrate_file(&path)?);
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+}
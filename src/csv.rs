@@ -0,0 +1,58 @@
+use anyhow::{bail, Result};
+
+/// Splits CSV text into records of fields, following RFC 4180: fields may be
+/// quoted with `"`, and quoted fields may contain commas, line breaks and
+/// doubled quotes, which stand for a quote. Blank lines are skipped.
+pub(crate) fn parse_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if in_quotes {
+        bail!("The CSV file ends inside a quoted field");
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| !(r.len() == 1 && r[0].is_empty()));
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn csv_fields_may_be_quoted() -> Result<()> {
+        let records = parse_csv("id,name\r\n1,\"Smith, \"\"Ada\"\"\"\n\n2,\"multi\nline\"")?;
+        assert_eq!(
+            records,
+            vec![
+                vec!["id", "name"],
+                vec!["1", "Smith, \"Ada\""],
+                vec!["2", "multi\nline"],
+            ]
+        );
+        assert!(parse_csv("id\n\"unterminated").is_err());
+        Ok(())
+    }
+}
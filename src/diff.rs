@@ -0,0 +1,376 @@
+//! Differences between two sets of named values, such as the variables of two
+//! apps, and how they are printed.
+//!
+//! Commands that compare things build a [`Diff`] and print it with
+//! [`Diff::render`], or as JSON with `--format json`, so that every comparison
+//! reads the same way: `+` for added, `-` for removed and `~` for changed
+//! entries, with the names aligned in a column. Text which is compared line
+//! by line, such as a database schema, is shown as a [`unified`] diff.
+
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::opts::EnvSettings;
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum DiffFormat {
+    /// One line per change, coloured when printed to a terminal
+    #[default]
+    Text,
+    /// A JSON array with one object per change
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A change to one named value. Values which are not known, such as those
+/// Cloud does not return, are `None`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub name: String,
+    pub change: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    /// Compares two sets of values by name. Changes are ordered by name.
+    pub fn between(
+        before: &BTreeMap<String, Option<String>>,
+        after: &BTreeMap<String, Option<String>>,
+    ) -> Self {
+        let mut names = before.keys().chain(after.keys()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let changes = names
+            .into_iter()
+            .filter_map(|name| {
+                let (change, before, after) = match (before.get(name), after.get(name)) {
+                    (None, Some(after)) => (ChangeKind::Added, None, after.clone()),
+                    (Some(before), None) => (ChangeKind::Removed, before.clone(), None),
+                    (Some(before), Some(after)) if before != after => {
+                        (ChangeKind::Changed, before.clone(), after.clone())
+                    }
+                    _ => return None,
+                };
+                Some(Change {
+                    name: name.clone(),
+                    change,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// One line per change, with the names padded to the same width.
+    pub fn render(&self, color: bool) -> Vec<String> {
+        let width = self
+            .changes
+            .iter()
+            .map(|c| c.name.chars().count())
+            .max()
+            .unwrap_or_default();
+        self.changes
+            .iter()
+            .map(|c| {
+                let (sign, colour) = match c.change {
+                    ChangeKind::Added => ('+', GREEN),
+                    ChangeKind::Removed => ('-', RED),
+                    ChangeKind::Changed => ('~', YELLOW),
+                };
+                let value = match (c.change, &c.before, &c.after) {
+                    (ChangeKind::Changed, before, after) => {
+                        format!("{} -> {}", shown(before), shown(after))
+                    }
+                    (_, Some(value), _) | (_, None, Some(value)) => value.clone(),
+                    _ => String::new(),
+                };
+                let line = format!("{sign} {:width$}  {value}", c.name);
+                let line = line.trim_end();
+                if color {
+                    format!("{colour}{line}{RESET}")
+                } else {
+                    line.to_owned()
+                }
+            })
+            .collect()
+    }
+
+    /// Prints the diff in the given format, or a note that there is no
+    /// difference.
+    pub fn print(&self, format: DiffFormat) -> anyhow::Result<()> {
+        match format {
+            DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&self.changes)?),
+            DiffFormat::Text if self.is_empty() => println!("No differences"),
+            DiffFormat::Text => {
+                for line in self.render(use_color()) {
+                    println!("{line}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of unchanged lines shown around each change in a unified diff
+const CONTEXT_LINES: usize = 3;
+
+/// Compares two texts line by line, in the unified format of `diff -u`.
+/// Returns no lines if the texts are the same.
+pub fn unified(
+    before_name: &str,
+    after_name: &str,
+    before: &[String],
+    after: &[String],
+    color: bool,
+) -> Vec<String> {
+    let edits = line_edits(before, after);
+    if edits.iter().all(|e| matches!(e, Edit::Same(..))) {
+        return vec![];
+    }
+    let paint = |line: String, colour: &str| {
+        if color {
+            format!("{colour}{line}{RESET}")
+        } else {
+            line
+        }
+    };
+    let mut lines = vec![
+        paint(format!("--- {before_name}"), RED),
+        paint(format!("+++ {after_name}"), GREEN),
+    ];
+    for hunk in hunks(&edits) {
+        let (mut old_start, mut old_len, mut new_start, mut new_len) = (None, 0, None, 0);
+        for edit in hunk {
+            match edit {
+                Edit::Same(old, new) => {
+                    old_start.get_or_insert(*old);
+                    new_start.get_or_insert(*new);
+                    old_len += 1;
+                    new_len += 1;
+                }
+                Edit::Removed(old, new) => {
+                    old_start.get_or_insert(*old);
+                    new_start.get_or_insert(*new);
+                    old_len += 1;
+                }
+                Edit::Added(old, new) => {
+                    old_start.get_or_insert(*old);
+                    new_start.get_or_insert(*new);
+                    new_len += 1;
+                }
+            }
+        }
+        // Like diff, an empty range is numbered by the line before it.
+        let start = |start: Option<usize>, len: usize| match len {
+            0 => start.unwrap_or_default(),
+            _ => start.unwrap_or_default() + 1,
+        };
+        lines.push(paint(
+            format!(
+                "@@ -{},{old_len} +{},{new_len} @@",
+                start(old_start, old_len),
+                start(new_start, new_len)
+            ),
+            YELLOW,
+        ));
+        for edit in hunk {
+            lines.push(match edit {
+                Edit::Same(old, _) => format!(" {}", before[*old]),
+                Edit::Removed(old, _) => paint(format!("-{}", before[*old]), RED),
+                Edit::Added(_, new) => paint(format!("+{}", after[*new]), GREEN),
+            });
+        }
+    }
+    lines
+}
+
+/// One step in turning the old lines into the new, with the index of the
+/// line in each text at that point.
+#[derive(Debug, PartialEq)]
+enum Edit {
+    Same(usize, usize),
+    Removed(usize, usize),
+    Added(usize, usize),
+}
+
+/// The shortest edit between two texts, from their longest common
+/// subsequence of lines.
+fn line_edits(before: &[String], after: &[String]) -> Vec<Edit> {
+    let (n, m) = (before.len(), after.len());
+    // common[i][j] is the length of the longest common subsequence of
+    // before[i..] and after[j..].
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut edits = vec![];
+    while i < n || j < m {
+        if i < n && j < m && before[i] == after[j] {
+            edits.push(Edit::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || common[i][j + 1] >= common[i + 1][j]) {
+            edits.push(Edit::Added(i, j));
+            j += 1;
+        } else {
+            edits.push(Edit::Removed(i, j));
+            i += 1;
+        }
+    }
+    // Show removals before the additions which replace them.
+    for run in edits.split_mut(|e| matches!(e, Edit::Same(..))) {
+        run.sort_by_key(|e| matches!(e, Edit::Added(..)));
+    }
+    edits
+}
+
+/// Groups changes, with the unchanged lines around them, into hunks.
+/// Changes closer together than twice the context share a hunk.
+fn hunks(edits: &[Edit]) -> Vec<&[Edit]> {
+    let changed = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Same(..)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for i in changed {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(edits.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| &edits[start..end])
+        .collect()
+}
+
+fn shown(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("(none)")
+}
+
+/// Whether diffs printed to stdout should be coloured. Setting `NO_COLOR`
+/// turns colour off; otherwise the `CLOUD_COLOR` setting can force it on or
+/// off.
+pub fn use_color() -> bool {
+    if std::env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    EnvSettings::from_env()
+        .color
+        .unwrap_or_else(|| std::io::stdout().is_terminal())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(pairs: &[(&str, Option<&str>)]) -> BTreeMap<String, Option<String>> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.map(str::to_owned)))
+            .collect()
+    }
+
+    #[test]
+    fn changes_are_aligned_by_name() {
+        let diff = Diff::between(
+            &values(&[("api_url", Some("v1")), ("region", None), ("db", None)]),
+            &values(&[
+                ("api_url", Some("v2")),
+                ("db", None),
+                ("database_url", None),
+            ]),
+        );
+        assert_eq!(
+            diff.render(false),
+            vec!["~ api_url       v1 -> v2", "+ database_url", "- region"]
+        );
+        assert!(diff.render(true)[0].starts_with(YELLOW));
+        assert!(Diff::between(&values(&[("a", None)]), &values(&[("a", None)])).is_empty());
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn texts_are_compared_line_by_line() {
+        let before = lines("CREATE TABLE todos (\n  id INTEGER,\n  title TEXT\n);\nCREATE INDEX a ON todos (id);\n\n\n\n\n\nCREATE VIEW v AS SELECT 1;");
+        let after = lines("CREATE TABLE todos (\n  id INTEGER,\n  title TEXT,\n  done INTEGER\n);\nCREATE INDEX a ON todos (id);\n\n\n\n\n\n");
+        assert_eq!(
+            unified("staging", "production", &before, &after, false),
+            vec![
+                "--- staging",
+                "+++ production",
+                "@@ -1,6 +1,7 @@",
+                " CREATE TABLE todos (",
+                "   id INTEGER,",
+                "-  title TEXT",
+                "+  title TEXT,",
+                "+  done INTEGER",
+                " );",
+                " CREATE INDEX a ON todos (id);",
+                " ",
+                "@@ -8,4 +9,3 @@",
+                " ",
+                " ",
+                " ",
+                "-CREATE VIEW v AS SELECT 1;",
+            ]
+        );
+        assert!(unified("a", "b", &before, &before, false).is_empty());
+        assert_eq!(
+            unified("a", "b", &[], &lines("x"), false)[2],
+            "@@ -0,0 +1,1 @@"
+        );
+    }
+
+    #[test]
+    fn json_leaves_out_unknown_values() -> anyhow::Result<()> {
+        let diff = Diff::between(&values(&[]), &values(&[("token", None)]));
+        assert_eq!(
+            serde_json::to_value(&diff.changes)?,
+            serde_json::json!([{ "name": "token", "change": "added" }])
+        );
+        Ok(())
+    }
+}
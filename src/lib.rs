@@ -0,0 +1,34 @@
+//! The Fermyon Cloud plugin for Spin.
+//!
+//! The `spin cloud` binary is a thin command line layer over this library. Tools
+//! that want to drive Fermyon Cloud without shelling out (TUIs, editor extensions,
+//! test harnesses) can use the typed operations in [`ops`], which return data
+//! rather than printing it.
+
+pub mod answers;
+pub mod cache;
+pub mod commands;
+pub mod config_migrations;
+mod csv;
+pub mod diff;
+mod local_db;
+pub mod ops;
+pub mod opts;
+mod plugin_config;
+pub mod progress;
+mod project_config;
+mod random_name;
+mod spin;
+mod sql;
+pub mod table;
+pub mod timing;
+
+/// Returns build information, similar to: 0.1.0 (2be4034 2022-03-31).
+pub const VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("VERGEN_GIT_SHA"),
+    " ",
+    env!("VERGEN_GIT_COMMIT_DATE"),
+    ")"
+);
@@ -0,0 +1,155 @@
+//! Copies query results from Cloud databases into local SQLite files, so that
+//! they can be explored with local tools.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use cloud::models::QueryResult;
+use rusqlite::{types::Value as SqlValue, Connection};
+use serde_json::Value;
+
+use crate::ops::sqlite::quote_identifier;
+
+/// A local SQLite database file, created if it does not exist.
+pub struct LocalDatabase {
+    connection: Connection,
+}
+
+impl LocalDatabase {
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Could not open local database {}", path.display()))?;
+        Ok(Self { connection })
+    }
+
+    /// Creates `table` with a schema inferred from the results, and inserts
+    /// every row. Fails if the table already exists.
+    pub fn write_table(&mut self, table: &str, result: &QueryResult) -> Result<()> {
+        if result.columns.is_empty() {
+            bail!(r#"The statement for table "{table}" returned no columns"#);
+        }
+        let columns = result
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let column_type =
+                    infer_column_type(result.rows.iter().filter_map(|r| r.get(index)));
+                format!("{} {column_type}", quote_identifier(column))
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        let placeholders = vec!["?"; result.columns.len()].join(", ");
+
+        let transaction = self.connection.transaction()?;
+        transaction
+            .execute(
+                &format!(
+                    "CREATE TABLE {} ({})",
+                    quote_identifier(table),
+                    columns.join(", ")
+                ),
+                [],
+            )
+            .with_context(|| format!(r#"Could not create local table "{table}""#))?;
+        {
+            let mut insert = transaction.prepare(&format!(
+                "INSERT INTO {} VALUES ({placeholders})",
+                quote_identifier(table)
+            ))?;
+            for row in &result.rows {
+                insert
+                    .execute(rusqlite::params_from_iter(row.iter().map(to_sql_value)))
+                    .with_context(|| format!(r#"Could not insert into local table "{table}""#))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+/// The declared type for a column holding the given values. Columns whose
+/// values are of mixed types, or all NULL, are left untyped.
+fn infer_column_type<'a>(values: impl Iterator<Item = &'a Value>) -> &'static str {
+    let mut inferred = None;
+    for value in values {
+        let value_type = match value {
+            Value::Null => continue,
+            Value::Bool(_) => "INTEGER",
+            Value::Number(n) if n.is_i64() || n.is_u64() => "INTEGER",
+            Value::Number(_) => "REAL",
+            Value::String(_) | Value::Array(_) | Value::Object(_) => "TEXT",
+        };
+        inferred = match (inferred, value_type) {
+            (None, t) => Some(t),
+            (Some(a), b) if a == b => Some(a),
+            (Some("INTEGER" | "REAL"), "INTEGER" | "REAL") => Some("REAL"),
+            _ => return "",
+        };
+    }
+    inferred.unwrap_or("")
+}
+
+fn to_sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn column_types_are_inferred_from_values() {
+        let values = |v: Value| v.as_array().unwrap().clone();
+        assert_eq!(
+            infer_column_type(values(json!([1, null, 2])).iter()),
+            "INTEGER"
+        );
+        assert_eq!(infer_column_type(values(json!([1, 2.5])).iter()), "REAL");
+        assert_eq!(infer_column_type(values(json!(["a", "b"])).iter()), "TEXT");
+        assert_eq!(infer_column_type(values(json!([1, "a"])).iter()), "");
+        assert_eq!(infer_column_type(values(json!([null])).iter()), "");
+    }
+
+    #[test]
+    fn writes_results_to_new_table() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("out.db");
+        let result = QueryResult {
+            columns: vec!["id".to_owned(), "name".to_owned()],
+            rows: vec![vec![json!(1), json!("Ada")], vec![json!(2), Value::Null]],
+        };
+
+        let mut db = LocalDatabase::open(&path)?;
+        db.write_table("users", &result)?;
+        assert!(db.write_table("users", &result).is_err());
+
+        let connection = Connection::open(&path)?;
+        let schema: String = connection.query_row(
+            "SELECT sql FROM sqlite_master WHERE name = 'users'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(
+            schema,
+            r#"CREATE TABLE "users" ("id" INTEGER, "name" TEXT)"#
+        );
+        let count: i64 =
+            connection.query_row("SELECT COUNT(*) FROM users WHERE name IS NULL", [], |row| {
+                row.get(0)
+            })?;
+        assert_eq!(count, 1);
+        Ok(())
+    }
+}
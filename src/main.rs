@@ -1,29 +1,34 @@
-mod commands;
-mod opts;
-mod random_name;
-mod spin;
-
 use anyhow::{Error, Result};
 use clap::{FromArgMatches, Parser};
-use commands::{
-    apps::AppsCommand,
-    deploy::DeployCommand,
-    link::{LinkCommand, UnlinkCommand},
-    login::{LoginCommand, LogoutCommand},
-    logs::LogsCommand,
-    sqlite::SqliteCommand,
-    variables::VariablesCommand,
+use cloud_plugin::{
+    answers, cache,
+    commands::{
+        apps::AppsCommand,
+        cache::CacheCommand,
+        config::ConfigCommand,
+        deploy::{DeployCommand, DeployedNotReady, DEPLOYED_NOT_READY_EXIT_CODE},
+        key_value::KeyValueCommand,
+        link::{LinkCommand, UnlinkCommand},
+        log_drains::LogDrainsCommand,
+        login::{LoginCommand, LogoutCommand},
+        logs::LogsCommand,
+        regions::RegionsCommand,
+        run::RunCommand,
+        serve_api::ServeApiCommand,
+        sqlite::{SqliteCommand, StatementTimedOut, STATEMENT_TIMEOUT_EXIT_CODE},
+        templates::{NewCommand, TemplatesCommand},
+        usage::UsageCommand,
+        variables::VariablesCommand,
+        version::VersionCommand,
+        webhooks::WebhooksCommand,
+    },
+    config_migrations::migrate_config_files,
+    opts::{apply_saved_settings, EnvSettings, CLOUD_TABLE_STYLE_ENV},
+    table::{set_style, TableStyle},
+    timing, VERSION,
 };
-
-/// Returns build information, similar to: 0.1.0 (2be4034 2022-03-31).
-const VERSION: &str = concat!(
-    env!("CARGO_PKG_VERSION"),
-    " (",
-    env!("VERGEN_GIT_SHA"),
-    " ",
-    env!("VERGEN_GIT_COMMIT_DATE"),
-    ")"
-);
+use std::path::PathBuf;
+use std::time::Instant;
 
 #[derive(Parser)]
 #[clap(author, version = VERSION, about, long_about = None)]
@@ -52,18 +57,116 @@ enum CloudCli {
     /// Unlink apps from resources
     #[clap(subcommand)]
     Unlink(UnlinkCommand),
+    /// Manage webhooks for app lifecycle events
+    #[clap(subcommand)]
+    Webhooks(WebhooksCommand),
+    /// Manage key value stores and their contents
+    #[clap(subcommand, alias = "kv")]
+    KeyValue(KeyValueCommand),
+    /// Forward app logs to external endpoints
+    #[clap(subcommand, name = "logdrains", alias = "log-drains")]
+    LogDrains(LogDrainsCommand),
+    /// List the regions in which resources can be created
+    Regions(RegionsCommand),
+    /// Show the account's plan and how much of it is in use
+    Usage(UsageCommand),
+    /// Inspect and clear the files the plugin caches between runs
+    #[clap(subcommand)]
+    Cache(CacheCommand),
+    /// View and change the plugin's saved settings
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+    /// Browse the app templates in the Fermyon Cloud catalog
+    #[clap(subcommand)]
+    Templates(TemplatesCommand),
+    /// Create an app from a template in the Fermyon Cloud catalog
+    New(NewCommand),
+    /// Serve the plugin's operations over JSON-RPC, for editors and other tools
+    ServeApi(ServeApiCommand),
+    /// Run a sequence of operations from a script file
+    Run(RunCommand),
+    /// Show the versions of the plugin and Spin and, with --remote, of
+    /// Fermyon Cloud, for reporting problems
+    Version(VersionCommand),
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let start = Instant::now();
     env_logger::init();
+    apply_saved_settings()?;
     let mut app = CloudCli::clap();
     // Plugin should always be invoked from Spin so set binary name accordingly
     app.set_bin_name("spin cloud");
+    let app = app
+        .arg(
+            clap::Arg::new("table-style")
+                .long("table-style")
+                .help("How to draw tables in command output")
+                .global(true)
+                .takes_value(true)
+                .value_parser(clap::value_parser!(TableStyle))
+                .env(CLOUD_TABLE_STYLE_ENV),
+        )
+        .arg(
+            clap::Arg::new("record-answers")
+                .long("record-answers")
+                .help("Record the answers given to prompts in this JSON file, to replay with --answers")
+                .global(true)
+                .takes_value(true)
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with("answers"),
+        )
+        .arg(
+            clap::Arg::new("answers")
+                .long("answers")
+                .help("Answer prompts from a file recorded with --record-answers instead of asking")
+                .global(true)
+                .takes_value(true)
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("no-cache")
+                .long("no-cache")
+                .help("Send every lookup to Fermyon Cloud rather than reusing recent responses")
+                .global(true)
+                .takes_value(false),
+        )
+        .arg(
+            clap::Arg::new("profile-cli")
+                .long("profile-cli")
+                .help("Print how long each step of the command took, including each API call")
+                .global(true)
+                .takes_value(false),
+        );
     let matches = app.get_matches();
     let cli = CloudCli::from_arg_matches(&matches)?;
+    if matches.contains_id("profile-cli") {
+        timing::enable();
+        timing::record("Parse arguments", start.elapsed());
+    }
+    if let Some(style) = matches.get_one::<TableStyle>("table-style") {
+        set_style(*style);
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("record-answers") {
+        answers::record_to(path);
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("answers") {
+        answers::replay_from(path)?;
+    }
+    cache::configure_api_cache(matches.contains_id("no-cache"));
+
+    // Bring logins saved by older versions of the plugin up to date before anything reads them.
+    // A token from the environment means saved logins are not used at all.
+    if EnvSettings::from_env().token.is_none() {
+        if let Err(e) = migrate_config_files() {
+            eprintln!("Warning: could not migrate saved logins: {e:#}");
+        }
+    }
 
-    match cli {
+    let result = match cli {
         CloudCli::Apps(cmd) => cmd.run().await,
         CloudCli::Deploy(cmd) => cmd.run().await,
         CloudCli::Login(cmd) => cmd.run().await,
@@ -73,5 +176,29 @@ async fn main() -> Result<(), Error> {
         CloudCli::Sqlite(cmd) => cmd.run().await,
         CloudCli::Link(cmd) => cmd.run().await,
         CloudCli::Unlink(cmd) => cmd.run().await,
+        CloudCli::Webhooks(cmd) => cmd.run().await,
+        CloudCli::KeyValue(cmd) => cmd.run().await,
+        CloudCli::LogDrains(cmd) => cmd.run().await,
+        CloudCli::Regions(cmd) => cmd.run().await,
+        CloudCli::Usage(cmd) => cmd.run().await,
+        CloudCli::Cache(cmd) => cmd.run().await,
+        CloudCli::Config(cmd) => cmd.run().await,
+        CloudCli::Templates(cmd) => cmd.run().await,
+        CloudCli::New(cmd) => cmd.run().await,
+        CloudCli::ServeApi(cmd) => cmd.run().await,
+        CloudCli::Run(cmd) => cmd.run().await,
+        CloudCli::Version(cmd) => cmd.run().await,
+    };
+    timing::report(start.elapsed());
+    if let Err(e) = &result {
+        if e.downcast_ref::<StatementTimedOut>().is_some() {
+            eprintln!("Error: {e:?}");
+            std::process::exit(STATEMENT_TIMEOUT_EXIT_CODE);
+        }
+        if e.downcast_ref::<DeployedNotReady>().is_some() {
+            eprintln!("Error: {e:?}");
+            std::process::exit(DEPLOYED_NOT_READY_EXIT_CODE);
+        }
     }
+    result
 }
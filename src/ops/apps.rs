@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use cloud::{CloudClientExt, CloudClientInterface, DEFAULT_APPLIST_PAGE_SIZE};
+use cloud_openapi::models::{AppItem, RevisionItem};
+use uuid::Uuid;
+
+use crate::ops::resolve::find_by_name;
+
+/// Lists every app in the account, following pagination to the end.
+pub async fn list_apps(client: &impl CloudClientInterface) -> Result<Vec<AppItem>> {
+    let mut app_list_page = client.list_apps(DEFAULT_APPLIST_PAGE_SIZE, None).await?;
+    let mut apps = std::mem::take(&mut app_list_page.items);
+    let mut page_index = 1;
+    while !app_list_page.is_last_page {
+        app_list_page = client
+            .list_apps(DEFAULT_APPLIST_PAGE_SIZE, Some(page_index))
+            .await?;
+        apps.append(&mut app_list_page.items);
+        page_index += 1;
+    }
+    Ok(apps)
+}
+
+/// Looks up the ID of the app with the given name. Every app is listed only
+/// if the name is not found straight away, to suggest names close to it.
+pub async fn app_id(client: &impl CloudClientInterface, app: &str) -> Result<Uuid> {
+    let context = || format!("Error finding app_id for app '{}'", app);
+    if let Some(app_id) = client.get_app_id(app).await.with_context(context)? {
+        return Ok(app_id);
+    }
+    let apps = list_apps(client).await.with_context(context)?;
+    Ok(find_by_name(apps, app, "app", |a| &a.name)?.id)
+}
+
+/// Fetches the details of the app with the given name.
+pub async fn get_app(client: &impl CloudClientInterface, app: &str) -> Result<AppItem> {
+    let app_id = app_id(client, app).await?;
+    client
+        .get_app(app_id.to_string())
+        .await
+        .with_context(|| format!("Error: could not get details about {}", app))
+}
+
+/// Deletes the app with the given ID.
+pub async fn delete_app(client: &impl CloudClientInterface, app: &str, app_id: Uuid) -> Result<()> {
+    client
+        .remove_app(app_id.to_string())
+        .await
+        .with_context(|| format!("Problem deleting app named {}", app))
+}
+
+/// Lists the revisions which have been deployed for the app, oldest first.
+pub async fn list_app_revisions(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+) -> Result<Vec<RevisionItem>> {
+    let mut page = client
+        .list_revisions()
+        .await
+        .context("Problem listing revisions")?;
+    let mut revisions = vec![];
+    loop {
+        revisions.extend(page.items.iter().filter(|r| r.app_id == app_id).cloned());
+        if page.is_last_page {
+            break;
+        }
+        page = client
+            .list_revisions_next(&page)
+            .await
+            .context("Problem listing revisions")?;
+    }
+    Ok(revisions)
+}
+
+/// Picks the revisions to delete so that only the `keep` most recently
+/// deployed remain, given revisions oldest first. Revisions which are active
+/// in a channel are never picked, and do not count towards `keep`.
+pub fn revisions_to_prune(
+    mut revisions: Vec<RevisionItem>,
+    keep: usize,
+    active: &[String],
+) -> Vec<RevisionItem> {
+    revisions.retain(|r| !active.contains(&r.revision_number));
+    let prune = revisions.len().saturating_sub(keep);
+    revisions.truncate(prune);
+    revisions
+}
+
+/// A page of an app's revisions, newest first.
+pub struct RevisionPage {
+    pub revisions: Vec<RevisionItem>,
+    /// Whether there are older revisions beyond this page
+    pub more: bool,
+}
+
+/// Picks a page of revisions, given revisions oldest first: up to `limit` of
+/// the most recently deployed, or of those deployed before the revision
+/// numbered `before` if it is given.
+pub fn revision_page(
+    mut revisions: Vec<RevisionItem>,
+    before: Option<&str>,
+    limit: Option<usize>,
+) -> Result<RevisionPage> {
+    if let Some(before) = before {
+        let index = revisions
+            .iter()
+            .position(|r| r.revision_number == before)
+            .with_context(|| format!(r#"No revision "{before}" found"#))?;
+        revisions.truncate(index);
+    }
+    let older = limit.map_or(0, |limit| revisions.len().saturating_sub(limit));
+    let mut page = revisions.split_off(older);
+    page.reverse();
+    Ok(RevisionPage {
+        revisions: page,
+        more: older > 0,
+    })
+}
+
+/// Deletes a revision of an app, along with its stored artifact.
+pub async fn delete_revision(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    revision: &RevisionItem,
+) -> Result<()> {
+    client
+        .delete_revision(app_id, revision.id)
+        .await
+        .with_context(|| format!("Problem deleting revision {}", revision.revision_number))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn revisions(numbers: &[&str]) -> Vec<RevisionItem> {
+        numbers
+            .iter()
+            .map(|n| RevisionItem {
+                id: Uuid::new_v4(),
+                app_id: Uuid::nil(),
+                revision_number: n.to_string(),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn apps_are_all_listed_only_for_suggestions() -> Result<()> {
+        let mut mock = cloud::MockCloudClientInterface::new();
+        let todo_id = Uuid::new_v4();
+        mock.expect_list_apps().times(1).returning(move |_, _| {
+            Ok(cloud_openapi::models::AppItemPage {
+                items: vec![AppItem {
+                    id: todo_id,
+                    name: "todo".to_owned(),
+                    ..Default::default()
+                }],
+                is_last_page: false,
+                ..Default::default()
+            })
+        });
+
+        assert_eq!(app_id(&mock, "todo").await?, todo_id);
+        mock.checkpoint();
+
+        mock.expect_list_apps().times(3).returning(|_, page| {
+            Ok(cloud_openapi::models::AppItemPage {
+                items: vec![AppItem {
+                    name: if page.is_none() { "todo" } else { "shop" }.to_owned(),
+                    ..Default::default()
+                }],
+                is_last_page: page.is_some(),
+                ..Default::default()
+            })
+        });
+        let err = app_id(&mock, "shp").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"No app found with name "shp". Did you mean "shop"?"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn newest_and_active_revisions_are_kept() {
+        let numbers = |rs: Vec<RevisionItem>| {
+            rs.into_iter()
+                .map(|r| r.revision_number)
+                .collect::<Vec<_>>()
+        };
+        let all = revisions(&["1", "2", "3", "4", "5"]);
+
+        assert_eq!(
+            numbers(revisions_to_prune(all.clone(), 2, &[])),
+            vec!["1", "2", "3"]
+        );
+        assert_eq!(
+            numbers(revisions_to_prune(all.clone(), 2, &["2".to_owned()])),
+            vec!["1", "3"]
+        );
+        assert!(revisions_to_prune(all, 10, &[]).is_empty());
+    }
+
+    #[test]
+    fn revisions_are_paged_newest_first() -> Result<()> {
+        let numbers = |page: RevisionPage| {
+            (
+                page.revisions
+                    .into_iter()
+                    .map(|r| r.revision_number)
+                    .collect::<Vec<_>>(),
+                page.more,
+            )
+        };
+        let all = revisions(&["1", "2", "3", "4", "5"]);
+
+        assert_eq!(
+            numbers(revision_page(all.clone(), None, Some(2))?),
+            (vec!["5".to_owned(), "4".to_owned()], true)
+        );
+        assert_eq!(
+            numbers(revision_page(all.clone(), Some("4"), Some(2))?),
+            (vec!["3".to_owned(), "2".to_owned()], true)
+        );
+        assert_eq!(
+            numbers(revision_page(all.clone(), Some("2"), Some(2))?),
+            (vec!["1".to_owned()], false)
+        );
+        assert_eq!(numbers(revision_page(all.clone(), None, None)?).0.len(), 5);
+        assert!(revision_page(all, Some("9"), None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use cloud::{client::ConnectionConfig, CloudClientInterface};
+use cloud_openapi::models::{AppItem, ResourceLabel};
+use oci_distribution::{token_cache, Reference, RegistryOperation};
+use spin_locked_app::locked::LockedApp;
+use url::Url;
+use uuid::Uuid;
+
+use crate::cache::registry_cache_dir;
+use crate::ops::variables::set_variables;
+
+/// The only key value store Cloud supports.
+pub const SPIN_DEFAULT_KV_STORE: &str = "default";
+
+/// The host of the OCI registry in which Cloud stores app artifacts.
+pub fn cloud_registry_host(cloud_url: &str) -> Result<String> {
+    let cloud_url = Url::parse(cloud_url).context("Unable to parse cloud URL")?;
+    let cloud_host = cloud_url
+        .host_str()
+        .context("Unable to derive host from cloud URL")?;
+    Ok(format!("registry.{cloud_host}"))
+}
+
+/// The reference under which a version of an app is stored in Cloud's registry.
+pub fn registry_reference(cloud_url: &str, name: &str, version: &str) -> Result<String> {
+    Ok(format!(
+        "{}/{name}:{version}",
+        cloud_registry_host(cloud_url)?
+    ))
+}
+
+/// Pushes an app's artifacts to Cloud's registry under `reference`, returning
+/// the digest of the pushed manifest if the registry reported one.
+pub async fn push_app(
+    connection_config: &ConnectionConfig,
+    app: LockedApp,
+    reference: &str,
+) -> Result<Option<String>> {
+    let mut client =
+        spin_oci::Client::new(connection_config.insecure, Some(registry_cache_dir()?)).await?;
+    let oci_ref = Reference::try_from(reference)
+        .with_context(|| format!("Could not parse reference '{reference}'"))?;
+    client.insert_token(
+        &oci_ref,
+        RegistryOperation::Push,
+        token_cache::RegistryTokenType::Bearer(token_cache::RegistryToken::Token {
+            token: connection_config.token.clone(),
+        }),
+    );
+    client.push_locked(app, reference).await
+}
+
+/// A version of an app whose artifacts have been pushed to Cloud's registry
+pub struct Release {
+    pub name: String,
+    pub version: String,
+    /// Pairs to set in the app's default key value store
+    pub key_values: Vec<(String, String)>,
+    pub variables: Vec<(String, String)>,
+}
+
+impl Release {
+    pub fn storage_id(&self) -> String {
+        format!("oci://{}", self.name)
+    }
+}
+
+/// Makes a release the app's new revision, creating the app first if
+/// `app_id` is `None`. `databases` are linked to the app, by label, before
+/// the revision is added. Returns the deployed app.
+pub async fn release(
+    client: &impl CloudClientInterface,
+    app_id: Option<Uuid>,
+    release: &Release,
+    databases: Vec<(String, String)>,
+) -> Result<AppItem> {
+    let app_id = match app_id {
+        Some(app_id) => app_id,
+        None => client
+            .add_app(&release.name, &release.storage_id())
+            .await
+            .context("Unable to create app")?,
+    };
+    link_databases(client, &release.name, app_id, databases).await?;
+
+    client
+        .add_revision(release.storage_id(), release.version.clone())
+        .await?;
+    for (key, value) in &release.key_values {
+        client
+            .add_key_value_pair(
+                app_id,
+                SPIN_DEFAULT_KV_STORE.to_string(),
+                key.clone(),
+                value.clone(),
+            )
+            .await
+            .context("Problem creating key/value")?;
+    }
+    set_variables(client, app_id, &release.variables).await?;
+
+    client
+        .get_app(app_id.to_string())
+        .await
+        .context("Problem getting app by id")
+}
+
+/// Links each (database, label) pair to the app.
+pub async fn link_databases(
+    client: &impl CloudClientInterface,
+    app_name: &str,
+    app_id: Uuid,
+    database_labels: Vec<(String, String)>,
+) -> Result<()> {
+    for (database, label) in database_labels {
+        let resource_label = ResourceLabel {
+            label,
+            app_id,
+            app_name: Some(app_name.to_owned()),
+        };
+        client
+            .create_database_link(&database, resource_label)
+            .await
+            .with_context(|| {
+                format!(
+                    r#"Failed to link database "{}" to app "{}""#,
+                    database, app_name
+                )
+            })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cloud::MockCloudClientInterface;
+
+    #[tokio::test]
+    async fn new_apps_are_created_and_linked_before_the_revision() -> Result<()> {
+        let app_id = Uuid::new_v4();
+        let mut seq = mockall::Sequence::new();
+        let mut mock = MockCloudClientInterface::new();
+        mock.expect_add_app()
+            .withf(|name, storage_id| name == "todo" && storage_id == "oci://todo")
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_, _| Ok(app_id));
+        mock.expect_create_database_link()
+            .withf(move |db, rl| db == "todo-db" && rl.app_id == app_id && rl.label == "data")
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        mock.expect_add_revision()
+            .withf(|storage_id, version| storage_id == "oci://todo" && version == "1.0.0")
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Ok(()));
+        mock.expect_add_key_value_pair()
+            .withf(|_, store, key, _| store == SPIN_DEFAULT_KV_STORE && key == "greeting")
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        mock.expect_get_variable_pairs().returning(|_| Ok(vec![]));
+        mock.expect_add_variable_pair()
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        mock.expect_get_app().returning(move |_| {
+            Ok(AppItem {
+                id: app_id,
+                name: "todo".to_owned(),
+                ..Default::default()
+            })
+        });
+
+        let app = release(
+            &mock,
+            None,
+            &Release {
+                name: "todo".to_owned(),
+                version: "1.0.0".to_owned(),
+                key_values: vec![("greeting".to_owned(), "hello".to_owned())],
+                variables: vec![("region".to_owned(), "eu".to_owned())],
+            },
+            vec![("todo-db".to_owned(), "data".to_owned())],
+        )
+        .await?;
+        assert_eq!(app.id, app_id);
+        Ok(())
+    }
+
+    #[test]
+    fn references_use_the_cloud_registry() -> Result<()> {
+        assert_eq!(
+            registry_reference("https://cloud.fermyon.com/", "todo", "1.0.0")?,
+            "registry.cloud.fermyon.com/todo:1.0.0"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::{bail, Context, Result};
+use cloud::models::{AppLink, CreateAppLink};
+use cloud::CloudClientInterface;
+use cloud_openapi::models::{Database, ResourceLabel};
+use uuid::Uuid;
+
+use crate::ops::resolve::not_found;
+use crate::ops::sqlite::find_database_link;
+
+/// A Link structure to ease grouping a resource with it's app and label
+#[derive(Clone, PartialEq)]
+pub struct Link {
+    pub resource_label: ResourceLabel,
+    pub resource: String,
+}
+
+impl Link {
+    pub fn new(resource_label: ResourceLabel, resource: String) -> Self {
+        Self {
+            resource_label,
+            resource,
+        }
+    }
+
+    pub fn app_name(&self) -> &str {
+        match self.resource_label.app_name.as_ref() {
+            Some(a) => a.as_str(),
+            _ => "UNKNOWN",
+        }
+    }
+}
+
+/// What linking a label to a database would involve
+pub enum SqliteLinkPlan {
+    /// The label is not linked yet, so a new link will be created
+    Create,
+    /// The label is currently linked to another database; that link will be
+    /// replaced
+    Replace(Link),
+}
+
+/// Works out how to link an app's label to a database, failing if the
+/// database does not exist or is already linked under that label.
+pub async fn plan_sqlite_link(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    label: &str,
+    database: &str,
+) -> Result<SqliteLinkPlan> {
+    let databases = client
+        .get_databases(None)
+        .await
+        .context("could not fetch databases")?;
+    if !databases.iter().any(|d| d.name == database) {
+        return Err(not_found(
+            "database",
+            database,
+            databases.iter().map(|d| d.name.as_str()),
+        ));
+    }
+    let databases_for_app = databases
+        .into_iter()
+        .filter(|d| d.links.iter().any(|l| l.app_id == app_id))
+        .collect::<Vec<Database>>();
+    let (this_db, other_dbs): (Vec<&Database>, Vec<&Database>) =
+        databases_for_app.iter().partition(|d| d.name == database);
+    let existing_link_for_database = this_db.iter().find_map(|d| find_database_link(d, label));
+    let existing_link_for_other_database =
+        other_dbs.iter().find_map(|d| find_database_link(d, label));
+    match (existing_link_for_database, existing_link_for_other_database) {
+        (Some(link), _) => {
+            anyhow::bail!(
+                r#"Database "{}" is already linked to app "{}" with the label "{}""#,
+                link.resource,
+                link.app_name(),
+                link.resource_label.label,
+            );
+        }
+        (_, Some(link)) => Ok(SqliteLinkPlan::Replace(link)),
+        (None, None) => Ok(SqliteLinkPlan::Create),
+    }
+}
+
+/// Carries out a link plan produced by [`plan_sqlite_link`].
+pub async fn apply_sqlite_link(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    label: &str,
+    database: &str,
+    plan: SqliteLinkPlan,
+) -> Result<()> {
+    if let SqliteLinkPlan::Replace(link) = plan {
+        // TODO: use a relink API to remove any downtime
+        client
+            .remove_database_link(&link.resource, link.resource_label)
+            .await?;
+    }
+    let resource_label = ResourceLabel {
+        app_id,
+        label: label.to_owned(),
+        app_name: None,
+    };
+    client.create_database_link(database, resource_label).await
+}
+
+/// Finds the database an app refers to by `label`, and the link by which it
+/// does.
+pub async fn find_sqlite_link(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    app: &str,
+    label: &str,
+) -> Result<(String, ResourceLabel)> {
+    client
+        .get_databases(Some(app_id))
+        .await
+        .context("could not fetch databases")?
+        .into_iter()
+        .find_map(|d| {
+            d.links
+                .into_iter()
+                .find(|l| {
+                    matches!(&l.app_name, Some(app_name) if app_name == app) && l.label == label
+                })
+                .map(|l| (d.name, l))
+        })
+        .with_context(|| {
+            format!(
+                "no database was linked to app '{}' with label '{}'",
+                app, label
+            )
+        })
+}
+
+/// Removes the link by which an app refers to a database, returning the name
+/// of the database that was unlinked.
+pub async fn unlink_sqlite(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    app: &str,
+    label: &str,
+) -> Result<String> {
+    let (database, resource_label) = find_sqlite_link(client, app_id, app, label).await?;
+    client
+        .remove_database_link(&database, resource_label)
+        .await?;
+    Ok(database)
+}
+
+/// Lets the app `caller_id` call the app `callee_id` by `label`, failing if
+/// the caller already uses that label for an app.
+pub async fn link_app(
+    client: &impl CloudClientInterface,
+    caller_id: Uuid,
+    callee_id: Uuid,
+    label: &str,
+) -> Result<AppLink> {
+    if caller_id == callee_id {
+        bail!("An app cannot be linked to itself");
+    }
+    let links = client
+        .list_app_links(caller_id)
+        .await
+        .context("could not fetch app links")?;
+    if let Some(link) = links.iter().find(|l| l.label == label) {
+        bail!(
+            r#"Label "{label}" is already linked to app "{}""#,
+            link.target_app_name
+        );
+    }
+    client
+        .create_app_link(
+            caller_id,
+            CreateAppLink {
+                label: label.to_owned(),
+                target_app_id: callee_id,
+            },
+        )
+        .await
+}
+
+/// Finds the link by which an app calls another by `label`.
+pub async fn find_app_link(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    app: &str,
+    label: &str,
+) -> Result<AppLink> {
+    client
+        .list_app_links(app_id)
+        .await
+        .context("could not fetch app links")?
+        .into_iter()
+        .find(|l| l.label == label)
+        .with_context(|| format!("no app was linked to app '{app}' with label '{label}'"))
+}
+
+/// Removes the link by which an app calls another, returning the name of the
+/// app that was unlinked.
+pub async fn unlink_app(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    app: &str,
+    label: &str,
+) -> Result<String> {
+    let link = find_app_link(client, app_id, app, label).await?;
+    client.remove_app_link(app_id, link.id).await?;
+    Ok(link.target_app_name)
+}
+
+/// One of the things an app refers to by a label.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct LabelledLink {
+    /// `sqlite` or `app`
+    pub kind: &'static str,
+    pub label: String,
+    pub resource: String,
+}
+
+/// Lists the databases and apps which an app refers to, sorted by label.
+pub async fn list_links(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+) -> Result<Vec<LabelledLink>> {
+    let databases = client
+        .get_databases(Some(app_id))
+        .await
+        .context("could not fetch databases")?;
+    let mut links = databases
+        .into_iter()
+        .flat_map(|d| {
+            d.links
+                .into_iter()
+                .filter(|l| l.app_id == app_id)
+                .map(move |l| LabelledLink {
+                    kind: "sqlite",
+                    label: l.label,
+                    resource: d.name.clone(),
+                })
+        })
+        .collect::<Vec<_>>();
+    let app_links = client
+        .list_app_links(app_id)
+        .await
+        .context("could not fetch app links")?;
+    links.extend(app_links.into_iter().map(|l| LabelledLink {
+        kind: "app",
+        label: l.label,
+        resource: l.target_app_name,
+    }));
+    links.sort_by(|a, b| a.label.cmp(&b.label));
+    Ok(links)
+}
@@ -0,0 +1,13 @@
+//! Typed Fermyon Cloud operations.
+//!
+//! Each operation takes a [`CloudClientInterface`](cloud::CloudClientInterface)
+//! and returns structured results or errors, leaving presentation and user
+//! interaction to the caller.
+
+pub mod apps;
+pub mod deploy;
+pub mod link;
+pub mod regions;
+pub mod resolve;
+pub mod sqlite;
+pub mod variables;
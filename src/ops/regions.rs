@@ -0,0 +1,20 @@
+use anyhow::{Context, Result};
+use cloud::models::Region;
+use cloud::CloudClientInterface;
+
+use crate::ops::resolve::find_by_name;
+
+/// Lists the regions in which resources can be created.
+pub async fn list_regions(client: &impl CloudClientInterface) -> Result<Vec<Region>> {
+    client.list_regions().await.context(
+        "Problem listing regions. This Fermyon Cloud instance may not offer a choice of region",
+    )
+}
+
+/// Checks that `region` exists before anything is created in it, so that a
+/// typo is reported with suggestions rather than as a failed creation.
+pub async fn check_region(client: &impl CloudClientInterface, region: &str) -> Result<()> {
+    let regions = list_regions(client).await?;
+    find_by_name(regions, region, "region", |r| &r.name)?;
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! Resolving names typed by the user to Cloud resources.
+//!
+//! When a name matches nothing, the error suggests the closest existing names
+//! so that typos are easy to fix.
+
+use anyhow::{anyhow, Error};
+
+/// The most suggestions offered for a single name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Finds the item named `name`, or fails with an error suggesting similarly
+/// named items. `kind` describes the items in the error, e.g. "database".
+pub fn find_by_name<T>(
+    items: impl IntoIterator<Item = T>,
+    name: &str,
+    kind: &str,
+    name_of: impl Fn(&T) -> &str,
+) -> Result<T, Error> {
+    let mut items = items.into_iter().collect::<Vec<_>>();
+    match items.iter().position(|i| name_of(i) == name) {
+        Some(index) => Ok(items.swap_remove(index)),
+        None => Err(not_found(kind, name, items.iter().map(name_of))),
+    }
+}
+
+/// An error saying no `kind` is named `name`, suggesting any of `candidates`
+/// which are close to it.
+pub fn not_found<'a>(
+    kind: &str,
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Error {
+    let message = format!(r#"No {kind} found with name "{name}""#);
+    match suggestions(name, candidates).as_slice() {
+        [] => anyhow!(message),
+        [only] => anyhow!(r#"{message}. Did you mean "{only}"?"#),
+        [rest @ .., last] => anyhow!(
+            r#"{message}. Did you mean {} or "{last}"?"#,
+            rest.iter()
+                .map(|s| format!(r#""{s}""#))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The candidates close enough to `name` to be likely typos of it, closest
+/// first. Names differing only in case always count as close.
+pub fn suggestions<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut close = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let distance = strsim::levenshtein(&name.to_lowercase(), &candidate.to_lowercase());
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+    close.sort();
+    close.dedup();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn suggests_closest_names_first() {
+        let names = ["users-db", "user-db", "orders", "Users"];
+        assert_eq!(suggestions("users-bd", names), vec!["users-db"]);
+        assert_eq!(suggestions("usr-db", names), vec!["user-db", "users-db"]);
+        assert_eq!(suggestions("users", names), vec!["Users"]);
+        assert!(suggestions("inventory", names).is_empty());
+    }
+
+    #[test]
+    fn not_found_error_lists_suggestions() {
+        assert_eq!(
+            not_found("database", "inventory", ["orders"]).to_string(),
+            r#"No database found with name "inventory""#
+        );
+        assert_eq!(
+            not_found("app", "shp", ["shop", "ship", "blog"]).to_string(),
+            r#"No app found with name "shp". Did you mean "ship" or "shop"?"#
+        );
+    }
+
+    #[test]
+    fn finds_exact_match() {
+        let names = vec!["a".to_owned(), "b".to_owned()];
+        assert_eq!(find_by_name(names, "b", "app", |n| n).unwrap(), "b");
+    }
+}
@@ -0,0 +1,493 @@
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Result};
+use cloud::models::{QueryResult, SqlQuery};
+use cloud::CloudClientInterface;
+use cloud_openapi::models::Database;
+use serde::Serialize;
+
+use crate::ops::link::Link;
+use crate::ops::regions::check_region;
+use crate::ops::resolve::find_by_name;
+use crate::sql::first_keyword;
+
+/// Lists all SQLite databases in the account.
+pub async fn list_databases(client: &impl CloudClientInterface) -> Result<Vec<Database>> {
+    client
+        .get_databases(None)
+        .await
+        .context("Problem listing databases")
+}
+
+/// Finds the database with the given name, failing if there is none.
+pub async fn find_database(client: &impl CloudClientInterface, name: &str) -> Result<Database> {
+    let list = client
+        .get_databases(None)
+        .await
+        .context("Problem fetching databases")?;
+    find_by_name(list, name, "database", |d| &d.name)
+}
+
+/// Creates a database, failing if one with the same name already exists.
+/// Without a region, the database is created in the platform's default region.
+pub async fn create_database(
+    client: &impl CloudClientInterface,
+    name: &str,
+    region: Option<&str>,
+) -> Result<()> {
+    let list = client
+        .get_databases(None)
+        .await
+        .context("Problem fetching databases")?;
+    if list.iter().any(|d| d.name == name) {
+        anyhow::bail!(r#"Database "{}" already exists"#, name)
+    }
+    if let Some(region) = region {
+        check_region(client, region).await?;
+    }
+    client
+        .create_database(name.to_owned(), None, region.map(str::to_owned))
+        .await
+        .with_context(|| format!("Problem creating database {}", name))
+}
+
+/// Deletes the database with the given name. Callers are responsible for
+/// confirming the deletion with the user.
+pub async fn delete_database(client: &impl CloudClientInterface, name: &str) -> Result<()> {
+    client
+        .delete_database(name.to_owned())
+        .await
+        .with_context(|| format!("Problem deleting database {}", name))
+}
+
+/// Finds the database to rename, failing if it does not exist or if another
+/// database already has the new name.
+pub fn check_rename(databases: Vec<Database>, name: &str, new_name: &str) -> Result<Database> {
+    if name == new_name {
+        bail!("Database \"{name}\" is already named \"{new_name}\"");
+    }
+    if databases.iter().any(|d| d.name == new_name) {
+        bail!("A database named \"{new_name}\" already exists. Choose another name.");
+    }
+    find_by_name(databases, name, "database", |d| &d.name)
+}
+
+/// Which database a statement should be executed against
+pub enum ExecuteTarget {
+    Database(String),
+    Label { label: String, app: String },
+}
+
+impl ExecuteTarget {
+    pub fn find_in(&self, databases: Vec<Database>) -> Result<Database> {
+        match self {
+            Self::Database(database) => find_by_name(databases, database, "database", |d| &d.name),
+            Self::Label { label, app } => databases
+                .into_iter()
+                .find(|d| database_has_link(d, label, Some(app.as_str())))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(r#"No database found with label "{label}" for app "{app}""#)
+                }),
+        }
+    }
+}
+
+/// Executes a statement against the target database, returning the name of
+/// the database it ran against.
+pub async fn execute(
+    client: &impl CloudClientInterface,
+    target: &ExecuteTarget,
+    statement: String,
+) -> Result<String> {
+    let list = client
+        .get_databases(None)
+        .await
+        .context("Problem fetching databases")?;
+    let database = target.find_in(list)?.name;
+    client
+        .execute_sql(database.clone(), statement)
+        .await
+        .context("Problem executing SQL")?;
+    Ok(database)
+}
+
+/// Executes statements as one transaction, so that either all of them take
+/// effect or none do.
+///
+/// Each request runs on its own connection, so the statements are sent in
+/// one request wrapped in BEGIN and COMMIT, and a failure leaves the
+/// transaction uncommitted. To report which statement failed, runs of the
+/// statements are then executed again in transactions which are always
+/// rolled back. If every statement succeeds that way, the failure is
+/// reported as the transaction's own.
+pub async fn execute_transaction(
+    client: &impl CloudClientInterface,
+    database: &str,
+    statements: &[String],
+) -> Result<()> {
+    let Err(error) = client
+        .execute_sql(
+            database.to_owned(),
+            transaction_script(statements, "COMMIT"),
+        )
+        .await
+    else {
+        return Ok(());
+    };
+    if statements.is_empty() {
+        return Err(error.context("The transaction failed to commit"));
+    }
+    let Err(mut failure) = client
+        .execute_sql(
+            database.to_owned(),
+            transaction_script(statements, "ROLLBACK"),
+        )
+        .await
+    else {
+        return Err(
+            error.context("Every statement succeeded, but the transaction failed to commit")
+        );
+    };
+
+    // Find the shortest run of statements which fails. The whole run is
+    // known to fail, and the empty run to pass.
+    let mut shortest = statements.len();
+    let mut longest_passing = 0;
+    while longest_passing + 1 < shortest {
+        let mid = (longest_passing + shortest) / 2;
+        match client
+            .execute_sql(
+                database.to_owned(),
+                transaction_script(&statements[..mid], "ROLLBACK"),
+            )
+            .await
+        {
+            Ok(()) => longest_passing = mid,
+            Err(e) => (shortest, failure) = (mid, e),
+        }
+    }
+    Err(failure.context(format!(
+        "Statement {shortest} of {} failed, so the transaction was rolled back: {}",
+        statements.len(),
+        statements[shortest - 1].trim_end_matches(';')
+    )))
+}
+
+fn transaction_script(statements: &[String], end: &str) -> String {
+    let mut script = String::from("BEGIN TRANSACTION;\n");
+    for statement in statements {
+        script.push_str(statement.trim_end_matches(';'));
+        script.push_str(";\n");
+    }
+    script.push_str(end);
+    script.push(';');
+    script
+}
+
+/// What happened when a statement was executed against one of several
+/// databases.
+pub enum BroadcastOutcome {
+    Succeeded,
+    Failed(anyhow::Error),
+    /// Not attempted, because an earlier database failed
+    Skipped,
+}
+
+/// Executes a statement against each of `databases` in turn. Unless
+/// `continue_on_error` is set, databases after the first failure are skipped.
+pub async fn execute_on_each(
+    client: &impl CloudClientInterface,
+    databases: &[String],
+    statement: &str,
+    continue_on_error: bool,
+) -> Vec<(String, BroadcastOutcome)> {
+    let mut outcomes = Vec::with_capacity(databases.len());
+    let mut failed = false;
+    for database in databases {
+        let outcome = if failed && !continue_on_error {
+            BroadcastOutcome::Skipped
+        } else {
+            match client
+                .execute_sql(database.clone(), statement.to_owned())
+                .await
+            {
+                Ok(()) => BroadcastOutcome::Succeeded,
+                Err(e) => {
+                    failed = true;
+                    BroadcastOutcome::Failed(e)
+                }
+            }
+        };
+        outcomes.push((database.clone(), outcome));
+    }
+    outcomes
+}
+
+/// Whether a statement is a query whose rows should be fetched, judging by
+/// its first keyword. Leading comments are skipped.
+pub fn returns_rows(statement: &str) -> bool {
+    let keyword = first_keyword(statement).unwrap_or_default();
+    ["SELECT", "WITH", "VALUES", "EXPLAIN", "PRAGMA"]
+        .iter()
+        .any(|k| keyword.eq_ignore_ascii_case(k))
+}
+
+/// Runs a read-only statement against a database, returning the rows it selects.
+pub async fn query(
+    client: &impl CloudClientInterface,
+    database: &str,
+    statement: String,
+) -> Result<QueryResult> {
+    client
+        .query_sql(SqlQuery {
+            database: database.to_owned(),
+            statement,
+        })
+        .await
+        .with_context(|| format!("Problem querying database {database}"))
+}
+
+/// Lists the tables created in a database, ordered by name. SQLite's own
+/// internal tables are left out.
+pub async fn list_tables(
+    client: &impl CloudClientInterface,
+    database: &str,
+) -> Result<Vec<String>> {
+    let result = query(
+        client,
+        database,
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            .to_owned(),
+    )
+    .await?;
+    Ok(result
+        .rows
+        .into_iter()
+        .filter_map(|row| row.into_iter().next()?.as_str().map(str::to_owned))
+        .collect())
+}
+
+/// Counts the rows in a table.
+pub async fn row_count(
+    client: &impl CloudClientInterface,
+    database: &str,
+    table: &str,
+) -> Result<u64> {
+    let result = query(
+        client,
+        database,
+        format!("SELECT COUNT(*) FROM {}", quote_identifier(table)),
+    )
+    .await?;
+    Ok(result
+        .rows
+        .first()
+        .and_then(|row| row.first())
+        .and_then(|count| count.as_u64())
+        .unwrap_or_default())
+}
+
+/// A column of a table, as reported by `PRAGMA table_info`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub decl_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    pub primary_key: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct IndexInfo {
+    pub name: String,
+    pub unique: bool,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+    pub indexes: Vec<IndexInfo>,
+}
+
+/// Describes the columns and indexes of each of the given tables.
+pub async fn describe_tables(
+    client: &impl CloudClientInterface,
+    database: &str,
+    tables: &[String],
+) -> Result<Vec<TableSchema>> {
+    let mut schemas = vec![];
+    for table in tables {
+        let info = query(
+            client,
+            database,
+            format!("PRAGMA table_info({})", quote_identifier(table)),
+        )
+        .await?;
+        let columns = pragma_rows(&info)
+            .map(|row| ColumnInfo {
+                name: row.text("name"),
+                decl_type: row.text("type"),
+                not_null: row.flag("notnull"),
+                default: Some(row.text("dflt_value")).filter(|d| !d.is_empty()),
+                primary_key: row.flag("pk"),
+            })
+            .collect();
+
+        let list = query(
+            client,
+            database,
+            format!("PRAGMA index_list({})", quote_identifier(table)),
+        )
+        .await?;
+        let mut indexes = vec![];
+        for row in pragma_rows(&list) {
+            let name = row.text("name");
+            let info = query(
+                client,
+                database,
+                format!("PRAGMA index_info({})", quote_identifier(&name)),
+            )
+            .await?;
+            indexes.push(IndexInfo {
+                unique: row.flag("unique"),
+                columns: pragma_rows(&info).map(|c| c.text("name")).collect(),
+                name,
+            });
+        }
+        schemas.push(TableSchema {
+            name: table.clone(),
+            columns,
+            indexes,
+        });
+    }
+    Ok(schemas)
+}
+
+/// A row of a PRAGMA's result, read by column name
+struct PragmaRow<'a> {
+    columns: &'a [String],
+    values: &'a [serde_json::Value],
+}
+
+impl PragmaRow<'_> {
+    fn value(&self, column: &str) -> Option<&serde_json::Value> {
+        let index = self.columns.iter().position(|c| c == column)?;
+        self.values.get(index)
+    }
+
+    fn text(&self, column: &str) -> String {
+        match self.value(column) {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        }
+    }
+
+    fn flag(&self, column: &str) -> bool {
+        self.value(column)
+            .and_then(|v| v.as_i64())
+            .is_some_and(|v| v != 0)
+    }
+}
+
+fn pragma_rows(result: &QueryResult) -> impl Iterator<Item = PragmaRow<'_>> {
+    result.rows.iter().map(|values| PragmaRow {
+        columns: &result.columns,
+        values,
+    })
+}
+
+/// Quotes a table or column name for use in a statement.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Finds the link, if any, by which the given label refers to a database.
+pub fn find_database_link(db: &Database, label: &str) -> Option<Link> {
+    db.links.iter().find_map(|r| {
+        if r.label == label {
+            Some(Link::new(r.clone(), db.name.clone()))
+        } else {
+            None
+        }
+    })
+}
+
+/// Lists the links by which the given app refers to databases, ordered by label.
+pub fn app_database_links(databases: &[Database], app: &str) -> Vec<Link> {
+    let mut links = databases
+        .iter()
+        .flat_map(|db| {
+            db.links
+                .iter()
+                .filter(|l| l.app_name.as_deref() == Some(app))
+                .map(|l| Link::new(l.clone(), db.name.clone()))
+        })
+        .collect::<Vec<_>>();
+    links.sort_by(|a, b| a.resource_label.label.cmp(&b.resource_label.label));
+    links
+}
+
+/// How a label stands between an app's manifest and its links.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelStatus {
+    /// Declared by the manifest and linked to a database
+    Linked,
+    /// Declared by the manifest but not linked, so the app cannot open it
+    Unlinked,
+    /// Linked to a database but no longer declared by the manifest
+    Undeclared,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LabelState {
+    pub label: String,
+    pub status: LabelStatus,
+    pub database: Option<String>,
+}
+
+/// Compares the labels an app's manifest declares with the app's links,
+/// ordered by label.
+pub fn compare_labels(declared: &BTreeSet<String>, links: &[Link]) -> Vec<LabelState> {
+    let mut states = declared
+        .iter()
+        .map(|label| {
+            let database = links
+                .iter()
+                .find(|l| &l.resource_label.label == label)
+                .map(|l| l.resource.clone());
+            LabelState {
+                label: label.clone(),
+                status: match database {
+                    Some(_) => LabelStatus::Linked,
+                    None => LabelStatus::Unlinked,
+                },
+                database,
+            }
+        })
+        .chain(
+            links
+                .iter()
+                .filter(|l| !declared.contains(&l.resource_label.label))
+                .map(|l| LabelState {
+                    label: l.resource_label.label.clone(),
+                    status: LabelStatus::Undeclared,
+                    database: Some(l.resource.clone()),
+                }),
+        )
+        .collect::<Vec<_>>();
+    states.sort_by(|a, b| a.label.cmp(&b.label));
+    states
+}
+
+/// Whether the database is linked to the given app under the given label.
+pub fn database_has_link(database: &Database, label: &str, app: Option<&str>) -> bool {
+    database
+        .links
+        .iter()
+        .any(|l| l.label == label && l.app_name.as_deref() == app)
+}
@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Context, Result};
+use cloud::{models::SetVariablePair, CloudClientInterface};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::from_str;
+use uuid::Uuid;
+
+/// A variable set for an app. Its value is not listed.
+#[derive(Deserialize)]
+pub struct Variable {
+    pub key: String,
+    /// Secret values are masked in listings and must be revealed explicitly.
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// How many variables are written to Cloud at once.
+const MAX_CONCURRENT_VARIABLE_WRITES: usize = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum VariableOutcome {
+    Set,
+    Updated,
+    Failed(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VariableResult {
+    pub key: String,
+    pub outcome: VariableOutcome,
+}
+
+/// Sets each variable, carrying on past individual failures. Results are in
+/// the order the variables were given; if a key is given more than once, only
+/// its last value is set.
+pub async fn apply_variables(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    variables: &[(String, String)],
+    secret: bool,
+) -> Result<Vec<VariableResult>> {
+    let existing = get_variables(client, app_id)
+        .await?
+        .into_iter()
+        .map(|v| v.key)
+        .collect::<HashSet<_>>();
+
+    let mut seen = HashSet::new();
+    let mut latest = variables
+        .iter()
+        .rev()
+        .filter(|(key, _)| seen.insert(key))
+        .collect::<Vec<_>>();
+    latest.reverse();
+
+    let results = stream::iter(latest)
+        .map(|(key, value)| {
+            let existing = &existing;
+            async move {
+                let written = if secret {
+                    client
+                        .set_variable_pair(SetVariablePair {
+                            app_id,
+                            variable: key.to_owned(),
+                            value: value.to_owned(),
+                            secret,
+                        })
+                        .await
+                } else {
+                    client
+                        .add_variable_pair(app_id, key.to_owned(), value.to_owned())
+                        .await
+                };
+                let outcome = match written {
+                    Ok(()) if existing.contains(key) => VariableOutcome::Updated,
+                    Ok(()) => VariableOutcome::Set,
+                    Err(e) => VariableOutcome::Failed(format!("{e:#}")),
+                };
+                VariableResult {
+                    key: key.to_owned(),
+                    outcome,
+                }
+            }
+        })
+        .buffered(MAX_CONCURRENT_VARIABLE_WRITES)
+        .collect()
+        .await;
+    Ok(results)
+}
+
+/// Sets each variable, failing if any of them could not be set.
+pub async fn set_variables(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+    variables: &[(String, String)],
+) -> Result<()> {
+    let failures = apply_variables(client, app_id, variables, false)
+        .await?
+        .into_iter()
+        .filter_map(|r| match r.outcome {
+            VariableOutcome::Failed(reason) => Some(format!("{}: {reason}", r.key)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !failures.is_empty() {
+        bail!("Problem setting variables:\n  {}", failures.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Lists the variables set for the app, each as the JSON Cloud returns.
+pub async fn get_variables_json(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+) -> Result<Vec<String>> {
+    let vars = client
+        .get_variable_pairs(app_id)
+        .await
+        .context("Problem listing variables")?;
+    Ok(vars)
+}
+
+/// Lists the variables set for the app.
+pub async fn get_variables(
+    client: &impl CloudClientInterface,
+    app_id: Uuid,
+) -> Result<Vec<Variable>> {
+    let vars = get_variables_json(client, app_id).await?;
+    let var_names = vars
+        .iter()
+        .map(|var| from_str(var))
+        .collect::<Result<Vec<Variable>, _>>()
+        .context("could not parse variable")?;
+    Ok(var_names)
+}
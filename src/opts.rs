@@ -1,3 +1,7 @@
+use anyhow::Result;
+
+use crate::plugin_config::PluginConfig;
+
 pub const DEFAULT_MANIFEST_FILE: &str = spin_common::paths::DEFAULT_MANIFEST_FILE;
 pub const APP_MANIFEST_FILE_OPT: &str = "APP_MANIFEST_FILE";
 pub const APPLICATION_OPT: &str = "APPLICATION";
@@ -8,3 +12,195 @@ pub const CLOUD_URL_ENV: &str = "CLOUD_URL";
 pub const DEPLOYMENT_ENV_NAME_ENV: &str = "FERMYON_DEPLOYMENT_ENVIRONMENT";
 pub const TOKEN: &str = "TOKEN";
 pub const SPIN_AUTH_TOKEN: &str = "SPIN_AUTH_TOKEN";
+pub const CLOUD_TOKEN_ENV: &str = "CLOUD_TOKEN";
+pub const CLOUD_OIDC_TOKEN_ENV: &str = "CLOUD_OIDC_TOKEN";
+pub const CLOUD_PROFILE_ENV: &str = "CLOUD_PROFILE";
+pub const CLOUD_APP_ENV: &str = "CLOUD_APP";
+pub const CLOUD_NON_INTERACTIVE_ENV: &str = "CLOUD_NON_INTERACTIVE";
+pub const CLOUD_TABLE_STYLE_ENV: &str = "CLOUD_TABLE_STYLE";
+pub const CLOUD_API_CACHE_ENV: &str = "CLOUD_API_CACHE";
+pub const CLOUD_COLOR_ENV: &str = "CLOUD_COLOR";
+pub const CLOUD_FORMAT_ENV: &str = "CLOUD_FORMAT";
+
+/// Settings resolved from environment variables, so that the plugin can be
+/// driven entirely from the environment in CI containers.
+///
+/// * `CLOUD_TOKEN`: a token to use instead of the saved login. No login files
+///   are read or written, so no writable home directory is needed.
+/// * `CLOUD_URL`: the Cloud instance the token belongs to.
+/// * `CLOUD_PROFILE`: the saved login to use when `--environment-name` is not given.
+/// * `CLOUD_APP`: the app to act on when a command's app argument is omitted.
+/// * `CLOUD_NON_INTERACTIVE`: never prompt; fail where a prompt would be needed.
+/// * `CLOUD_API_CACHE`: keep lookups such as the database list between
+///   commands for a few seconds, rather than only within one command.
+/// * `CLOUD_COLOR`: `always` or `never` to colour output whether or not it
+///   goes to a terminal; `auto`, the default, colours only terminals.
+///
+/// The CI system, if any, is recognised from the variables it sets. In CI
+/// the plugin never prompts unless `CLOUD_NON_INTERACTIVE` says otherwise.
+///
+/// Settings saved with `spin cloud config set` stand in for `CLOUD_PROFILE`,
+/// `CLOUD_NON_INTERACTIVE`, `CLOUD_API_CACHE` and `CLOUD_COLOR` when they are
+/// not set.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EnvSettings {
+    pub token: Option<String>,
+    pub url: Option<String>,
+    pub profile: Option<String>,
+    pub app: Option<String>,
+    pub non_interactive: bool,
+    pub api_cache: bool,
+    /// Whether to colour output, or `None` to colour only terminals
+    pub color: Option<bool>,
+    pub ci: Option<CiProvider>,
+}
+
+/// A CI system which the plugin recognises
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CiProvider {
+    GitHubActions,
+    GitLab,
+    Buildkite,
+}
+
+impl CiProvider {
+    /// Each system with the variable it sets to "true" in every job
+    const MARKERS: [(&'static str, CiProvider); 3] = [
+        ("GITHUB_ACTIONS", CiProvider::GitHubActions),
+        ("GITLAB_CI", CiProvider::GitLab),
+        ("BUILDKITE", CiProvider::Buildkite),
+    ];
+
+    fn detect(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        Self::MARKERS
+            .iter()
+            .find(|(var, _)| lookup(var).is_some_and(|v| v.trim() == "true"))
+            .map(|(_, provider)| *provider)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CiProvider::GitHubActions => "GitHub Actions",
+            CiProvider::GitLab => "GitLab CI",
+            CiProvider::Buildkite => "Buildkite",
+        }
+    }
+}
+
+/// Makes the settings saved with `spin cloud config set` the values of the
+/// environment variables they stand in for, where those are not set, so that
+/// options which read their defaults from the environment see them. Fails if
+/// the saved settings cannot be read.
+pub fn apply_saved_settings() -> Result<()> {
+    for (name, value) in PluginConfig::load()?.env_defaults() {
+        if std::env::var_os(name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+    Ok(())
+}
+
+impl EnvSettings {
+    pub fn from_env() -> Self {
+        let saved = PluginConfig::load().unwrap_or_default();
+        Self::from_lookup(|name| std::env::var(name).ok().or_else(|| saved.env_default(name)))
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let value = |name: &str| lookup(name).filter(|v| !v.trim().is_empty());
+        let flag = |name: &str| {
+            value(name).is_some_and(|v| {
+                !matches!(
+                    v.trim().to_ascii_lowercase().as_str(),
+                    "0" | "false" | "no" | "off"
+                )
+            })
+        };
+        let ci = CiProvider::detect(&lookup);
+        Self {
+            token: value(CLOUD_TOKEN_ENV),
+            url: value(CLOUD_URL_ENV),
+            profile: value(CLOUD_PROFILE_ENV),
+            app: value(CLOUD_APP_ENV),
+            non_interactive: match value(CLOUD_NON_INTERACTIVE_ENV) {
+                Some(_) => flag(CLOUD_NON_INTERACTIVE_ENV),
+                None => ci.is_some(),
+            },
+            api_cache: flag(CLOUD_API_CACHE_ENV),
+            color: match value(CLOUD_COLOR_ENV)
+                .map(|v| v.trim().to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("always") => Some(true),
+                Some("never") => Some(false),
+                _ => None,
+            },
+            ci,
+        }
+    }
+
+    /// Whether the user may be prompted. Prompts answered from an answers
+    /// file are allowed even when the user may not be prompted.
+    pub fn interactive(&self) -> bool {
+        !self.non_interactive || crate::answers::replaying()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn settings(vars: &[(&str, &str)]) -> EnvSettings {
+        let vars: HashMap<_, _> = vars.iter().cloned().collect();
+        EnvSettings::from_lookup(|name| vars.get(name).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn reads_settings_from_environment() {
+        let s = settings(&[
+            (CLOUD_TOKEN_ENV, "abc"),
+            (CLOUD_URL_ENV, "https://cloud.example/"),
+            (CLOUD_APP_ENV, "shop"),
+            (CLOUD_PROFILE_ENV, ""),
+        ]);
+        assert_eq!(s.token.as_deref(), Some("abc"));
+        assert_eq!(s.url.as_deref(), Some("https://cloud.example/"));
+        assert_eq!(s.app.as_deref(), Some("shop"));
+        assert_eq!(s.profile, None);
+        assert!(s.interactive());
+    }
+
+    #[test]
+    fn non_interactive_accepts_common_boolean_spellings() {
+        for value in ["1", "true", "YES", "on"] {
+            assert!(settings(&[(CLOUD_NON_INTERACTIVE_ENV, value)]).non_interactive);
+        }
+        for value in ["0", "false", "No", "off", ""] {
+            assert!(!settings(&[(CLOUD_NON_INTERACTIVE_ENV, value)]).non_interactive);
+        }
+    }
+
+    #[test]
+    fn color_can_be_forced_on_or_off() {
+        assert_eq!(settings(&[(CLOUD_COLOR_ENV, "Always")]).color, Some(true));
+        assert_eq!(settings(&[(CLOUD_COLOR_ENV, "never")]).color, Some(false));
+        assert_eq!(settings(&[(CLOUD_COLOR_ENV, "auto")]).color, None);
+        assert_eq!(settings(&[]).color, None);
+    }
+
+    #[test]
+    fn ci_systems_are_detected_and_never_prompted_in() {
+        let s = settings(&[("GITLAB_CI", "true")]);
+        assert_eq!(s.ci, Some(CiProvider::GitLab));
+        assert!(s.non_interactive);
+
+        let s = settings(&[("GITHUB_ACTIONS", "true"), (CLOUD_NON_INTERACTIVE_ENV, "0")]);
+        assert_eq!(s.ci, Some(CiProvider::GitHubActions));
+        assert!(!s.non_interactive);
+
+        let s = settings(&[("BUILDKITE", "false")]);
+        assert_eq!(s.ci, None);
+        assert!(!s.non_interactive);
+    }
+}
@@ -0,0 +1,267 @@
+//! Settings saved with `spin cloud config set`. They apply to every command,
+//! and environment variables and command line options take precedence over
+//! them.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+
+use crate::opts::{
+    CLOUD_API_CACHE_ENV, CLOUD_COLOR_ENV, CLOUD_FORMAT_ENV, CLOUD_NON_INTERACTIVE_ENV,
+    CLOUD_PROFILE_ENV,
+};
+use crate::table::TableStyle;
+
+// Saved logins are the JSON files in the same directory, so this must not be
+// JSON or it would be mistaken for one.
+const CONFIG_FILE: &str = "cloud-plugin.toml";
+
+/// A setting which can be saved, and how its values are checked.
+pub(crate) struct Setting {
+    pub key: &'static str,
+    pub description: &'static str,
+    /// Checks a value, returning it in the form it is saved in.
+    parse: fn(&str) -> Result<String>,
+    /// The environment variable the setting provides a default for, if any
+    env: Option<&'static str>,
+}
+
+/// Every setting which can be saved.
+pub(crate) const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "profile",
+        description: "The saved login to use when --environment-name is not given",
+        parse: parse_profile,
+        env: Some(CLOUD_PROFILE_ENV),
+    },
+    Setting {
+        key: "table-style",
+        description: "How to draw tables: ascii, utf8, markdown or borderless",
+        parse: parse_table_style,
+        env: None,
+    },
+    Setting {
+        key: "non-interactive",
+        description: "Whether to never prompt, failing where a prompt would be needed",
+        parse: parse_bool,
+        env: Some(CLOUD_NON_INTERACTIVE_ENV),
+    },
+    Setting {
+        key: "api-cache",
+        description:
+            "Whether to keep lookups such as the database list between commands for a few seconds",
+        parse: parse_bool,
+        env: Some(CLOUD_API_CACHE_ENV),
+    },
+    Setting {
+        key: "color",
+        description: "When to colour output: auto, for terminals only, always or never",
+        parse: parse_color,
+        env: Some(CLOUD_COLOR_ENV),
+    },
+    Setting {
+        key: "default-format",
+        description: "The format of listings when --format is not given: table or json",
+        parse: parse_format,
+        env: Some(CLOUD_FORMAT_ENV),
+    },
+    Setting {
+        key: "telemetry",
+        description: "Whether the plugin may send usage data. It sends none at present, so this only records the choice",
+        parse: parse_bool,
+        env: None,
+    },
+];
+
+fn setting(key: &str) -> Result<&'static Setting> {
+    match SETTINGS.iter().find(|s| s.key == key) {
+        Some(setting) => Ok(setting),
+        None => bail!(
+            r#"Unknown setting "{key}". Settings are: {}"#,
+            SETTINGS
+                .iter()
+                .map(|s| s.key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The saved settings.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct PluginConfig {
+    values: BTreeMap<String, String>,
+}
+
+impl PluginConfig {
+    pub fn path() -> Result<PathBuf> {
+        Ok(dirs::config_dir()
+            .context("Cannot find configuration directory")?
+            .join("fermyon")
+            .join(CONFIG_FILE))
+    }
+
+    /// Loads the saved settings, which are empty if none have been saved.
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::path()?)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let values =
+            toml::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))?;
+        Ok(Self { values })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path()?)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+        std::fs::write(path, toml::to_string(&self.values)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    /// The saved value of a setting, failing if there is no such setting.
+    pub fn get(&self, key: &str) -> Result<Option<&str>> {
+        Ok(self.values.get(setting(key)?.key).map(|v| v.as_str()))
+    }
+
+    /// Saves a value for a setting, returning it in the form it was saved in.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<&str> {
+        let setting = setting(key)?;
+        let value = (setting.parse)(value)
+            .with_context(|| format!(r#"Invalid value "{value}" for setting "{key}""#))?;
+        self.values.insert(setting.key.to_owned(), value.clone());
+        Ok(&self.values[setting.key])
+    }
+
+    /// Removes the saved value of a setting, returning whether there was one.
+    pub fn unset(&mut self, key: &str) -> Result<bool> {
+        Ok(self.values.remove(setting(key)?.key).is_some())
+    }
+
+    /// The saved values which stand in for environment variables, with the
+    /// variables' names.
+    pub fn env_defaults(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        SETTINGS
+            .iter()
+            .filter_map(|setting| Some((setting.env?, self.values.get(setting.key)?.as_str())))
+    }
+
+    /// The saved value standing in for an unset environment variable.
+    pub fn env_default(&self, name: &str) -> Option<String> {
+        let setting = SETTINGS.iter().find(|s| s.env == Some(name))?;
+        self.values.get(setting.key).cloned()
+    }
+
+    pub fn table_style(&self) -> Option<TableStyle> {
+        TableStyle::from_str(self.values.get("table-style")?, true).ok()
+    }
+}
+
+fn parse_profile(value: &str) -> Result<String> {
+    let value = value.trim();
+    if value.is_empty() || value.contains(['/', '\\']) {
+        bail!("must be the name of a saved login");
+    }
+    Ok(value.to_owned())
+}
+
+fn parse_table_style(value: &str) -> Result<String> {
+    let style = TableStyle::from_str(value, true)
+        .map_err(|_| anyhow::anyhow!("must be one of ascii, utf8, markdown or borderless"))?;
+    let name = style
+        .to_possible_value()
+        .context("table style has no name")?
+        .get_name()
+        .to_owned();
+    Ok(name)
+}
+
+fn parse_color(value: &str) -> Result<String> {
+    let value = value.trim().to_ascii_lowercase();
+    match value.as_str() {
+        "auto" | "always" | "never" => Ok(value),
+        _ => bail!("must be one of auto, always or never"),
+    }
+}
+
+fn parse_format(value: &str) -> Result<String> {
+    let value = value.trim().to_ascii_lowercase();
+    match value.as_str() {
+        "table" | "json" => Ok(value),
+        _ => bail!("must be table or json"),
+    }
+}
+
+fn parse_bool(value: &str) -> Result<String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok("true".to_owned()),
+        "0" | "false" | "no" | "off" => Ok("false".to_owned()),
+        _ => bail!("must be true or false"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn values_are_checked_and_saved() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE);
+
+        let mut config = PluginConfig::load_from(&path)?;
+        assert_eq!(config.set("table-style", "UTF8")?, "utf8");
+        assert_eq!(config.set("non-interactive", "yes")?, "true");
+        assert!(config.set("table-style", "fancy").is_err());
+        assert!(config.set("colour", "always").is_err());
+        assert!(config.get("colour").is_err());
+        assert_eq!(config.set("color", "Never")?, "never");
+        assert!(config.set("default-format", "yaml").is_err());
+        assert_eq!(config.set("default-format", "json")?, "json");
+        assert_eq!(config.set("telemetry", "off")?, "false");
+        config.save_to(&path)?;
+
+        let mut loaded = PluginConfig::load_from(&path)?;
+        assert_eq!(loaded, config);
+        assert_eq!(loaded.table_style(), Some(TableStyle::Utf8));
+        assert_eq!(
+            loaded.env_default(CLOUD_NON_INTERACTIVE_ENV).as_deref(),
+            Some("true")
+        );
+        assert_eq!(loaded.env_default(CLOUD_PROFILE_ENV), None);
+        assert_eq!(
+            loaded.env_defaults().collect::<Vec<_>>(),
+            vec![
+                (CLOUD_NON_INTERACTIVE_ENV, "true"),
+                (CLOUD_COLOR_ENV, "never"),
+                (CLOUD_FORMAT_ENV, "json"),
+            ]
+        );
+        assert!(loaded.unset("table-style")?);
+        assert_eq!(loaded.get("table-style")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_files_are_reported() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(CONFIG_FILE);
+        std::fs::write(&path, "table-style = \"utf8\"\ncolor = \n")?;
+        let err = PluginConfig::load_from(&path).unwrap_err();
+        assert!(format!("{err:#}").starts_with(&format!("Could not parse {}: ", path.display())));
+        Ok(())
+    }
+}
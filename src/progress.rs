@@ -0,0 +1,125 @@
+//! Machine-readable progress for long-running commands.
+//!
+//! With `--progress json`, a command writes one JSON object per line to
+//! stderr as it moves through its work, so that wrappers such as GUIs and CI
+//! scripts can show their own progress. Normal output on stdout is unchanged.
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProgressFormat {
+    /// Only the usual human-readable output
+    #[default]
+    Text,
+    /// Also write newline-delimited JSON progress events to stderr
+    Json,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct ProgressEvent<'a> {
+    phase: &'a str,
+    /// How far through the whole operation, from 0 to 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percent: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    /// The item, such as a table or app, the phase is working on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item: Option<&'a str>,
+}
+
+/// Reports progress in the format the user asked for.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    format: ProgressFormat,
+}
+
+impl Progress {
+    pub fn new(format: ProgressFormat) -> Self {
+        Self { format }
+    }
+
+    /// Reports that the operation has reached `phase`.
+    pub fn phase(&self, phase: &str, percent: u8) {
+        self.emit(ProgressEvent {
+            phase,
+            percent: Some(percent),
+            ..Default::default()
+        });
+    }
+
+    /// Reports that `phase` is transferring `bytes` in total.
+    pub fn transfer(&self, phase: &str, percent: u8, bytes: u64) {
+        self.emit(ProgressEvent {
+            phase,
+            percent: Some(percent),
+            bytes: Some(bytes),
+            ..Default::default()
+        });
+    }
+
+    /// Reports that `phase` is starting on `item`, the `done`th of `total`
+    /// items it works through.
+    pub fn item(&self, phase: &str, item: &str, done: usize, total: usize) {
+        self.emit(ProgressEvent {
+            phase,
+            percent: Some(percent_of(done, total)),
+            item: Some(item),
+            ..Default::default()
+        });
+    }
+
+    fn emit(&self, event: ProgressEvent) {
+        if self.format == ProgressFormat::Json {
+            // Progress is advisory, so failing to report it is not an error.
+            let _ = write_event(&mut std::io::stderr().lock(), &event);
+        }
+    }
+}
+
+fn write_event(out: &mut impl Write, event: &ProgressEvent) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *out, event)?;
+    writeln!(out)
+}
+
+fn percent_of(done: usize, total: usize) -> u8 {
+    if total == 0 {
+        return 100;
+    }
+    (done.min(total) * 100 / total) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_are_single_json_lines() {
+        let mut out = vec![];
+        write_event(
+            &mut out,
+            &ProgressEvent {
+                phase: "uploading",
+                percent: Some(25),
+                bytes: Some(2048),
+                item: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"phase\":\"uploading\",\"percent\":25,\"bytes\":2048}\n"
+        );
+    }
+
+    #[test]
+    fn item_progress_is_a_percentage() {
+        assert_eq!(percent_of(0, 4), 0);
+        assert_eq!(percent_of(3, 4), 75);
+        assert_eq!(percent_of(5, 4), 100);
+        assert_eq!(percent_of(0, 0), 100);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// The name of the per-project configuration file, which lives alongside the
+/// application manifest.
+pub const PROJECT_CONFIG_FILE: &str = "spin-cloud.toml";
+
+/// Cloud settings for a single Spin project, read from `spin-cloud.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// The saved login, as named by `--environment-name`, which commands run
+    /// in this project are expected to use.
+    pub environment: Option<String>,
+    /// The database which `sqlite execute` and `sqlite shell` use when run in
+    /// this project without --database, --label or --app.
+    pub default_database: Option<String>,
+    /// Existing resources which deployments of this project may link to.
+    #[serde(default)]
+    pub resources: ApprovedResources,
+    /// Settings for the checks made on files before they are uploaded.
+    #[serde(default)]
+    pub packaging: PackagingConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovedResources {
+    /// Pre-provisioned SQLite databases, keyed by the label the app uses for them.
+    #[serde(default)]
+    pub sqlite: BTreeMap<String, String>,
+    /// Pre-provisioned key value stores.
+    #[serde(default)]
+    pub key_value_stores: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackagingConfig {
+    /// Glob patterns of paths, as the app sees them, which are allowed to be
+    /// uploaded even if they look like secrets or are large.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Files larger than this many megabytes are reported.
+    pub max_file_size_mb: Option<u64>,
+}
+
+impl ProjectConfig {
+    /// Loads the project config from `dir`, falling back to an empty config if
+    /// the directory has no config file.
+    pub fn load_from_dir(dir: &Path) -> Result<Self> {
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load(&path)
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid project config {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_approved_resources() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [resources]
+            key_value_stores = ["default"]
+
+            [resources.sqlite]
+            main = "inventory"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.resources.sqlite["main"], "inventory");
+        assert_eq!(config.resources.key_value_stores, vec!["default"]);
+    }
+
+    #[test]
+    fn parses_packaging_allow_list() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [packaging]
+            allow = ["/static/*.mp4"]
+            max_file_size_mb = 50
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.packaging.allow, vec!["/static/*.mp4"]);
+        assert_eq!(config.packaging.max_file_size_mb, Some(50));
+    }
+
+    #[test]
+    fn parses_expected_environment() {
+        let config: ProjectConfig = toml::from_str(r#"environment = "staging""#).unwrap();
+        assert_eq!(config.environment.as_deref(), Some("staging"));
+    }
+
+    #[test]
+    fn parses_default_database() {
+        let config: ProjectConfig = toml::from_str(r#"default_database = "prod-db""#).unwrap();
+        assert_eq!(config.default_database.as_deref(), Some("prod-db"));
+    }
+
+    #[test]
+    fn missing_config_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ProjectConfig::load_from_dir(dir.path()).unwrap();
+        assert!(config.resources.sqlite.is_empty());
+        assert!(config.resources.key_value_stores.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(toml::from_str::<ProjectConfig>("[resources]\nqueues = []").is_err());
+    }
+}
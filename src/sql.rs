@@ -0,0 +1,269 @@
+//! The one SQL tokenizer which everything that looks inside statements
+//! uses, so that quoted text and comments are recognised the same way
+//! whether a script is being split, checked, rewritten or bound.
+//!
+//! Tokens cover the whole input, whitespace and comments included, so
+//! callers which rewrite a statement can copy the tokens they leave alone.
+
+/// What kind of text a [`Token`] is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TokenKind {
+    /// A keyword, an unquoted name or a number
+    Word,
+    /// A string in single quotes
+    String,
+    /// A name in double quotes, backticks or brackets
+    QuotedName,
+    /// A named parameter: `:name`, `@name` or `$name`
+    Parameter,
+    /// A `--` or `/* */` comment
+    Comment,
+    Whitespace,
+    /// Any other single character, such as `;` or `(`
+    Punct(char),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// The byte offset of the token in the tokenized text
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Whether the token is a comment or whitespace, which do not change
+    /// what a statement means
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Comment | TokenKind::Whitespace)
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    pub fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct(c)
+    }
+
+    /// The name the token stands for, if it is a word or a quoted name.
+    /// Quotes are removed, and doubled quotes inside them undone.
+    pub fn name(&self) -> Option<String> {
+        match self.kind {
+            TokenKind::Word => Some(self.text.to_owned()),
+            TokenKind::QuotedName => {
+                let mut chars = self.text.chars();
+                let open = chars.next()?;
+                let inner = chars.as_str();
+                let inner = inner.strip_suffix(close_quote(open)).unwrap_or(inner);
+                Some(match open {
+                    '[' => inner.to_owned(),
+                    quote => inner.replace(&format!("{quote}{quote}"), &quote.to_string()),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn close_quote(open: char) -> char {
+    if open == '[' {
+        ']'
+    } else {
+        open
+    }
+}
+
+/// Splits SQL into tokens. Unterminated quotes and comments run to the end
+/// of the text rather than failing, leaving errors for the database to
+/// report.
+pub(crate) fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut chars = sql.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let next = chars.peek().map(|(_, c)| *c);
+        let kind = match c {
+            '\'' | '"' | '`' | '[' => {
+                let close = close_quote(c);
+                while let Some((_, c)) = chars.next() {
+                    // Quotes other than brackets are escaped by doubling them
+                    if c == close && (c == ']' || chars.next_if(|(_, n)| *n == close).is_none()) {
+                        break;
+                    }
+                }
+                if c == '\'' {
+                    TokenKind::String
+                } else {
+                    TokenKind::QuotedName
+                }
+            }
+            '-' if next == Some('-') => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+                TokenKind::Comment
+            }
+            '/' if next == Some('*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                TokenKind::Comment
+            }
+            ':' | '@' | '$' if next.is_some_and(|c| c.is_ascii_alphabetic() || c == '_') => {
+                while chars
+                    .next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                    .is_some()
+                {}
+                TokenKind::Parameter
+            }
+            c if is_word_char(c) => {
+                while chars.next_if(|(_, c)| is_word_char(*c)).is_some() {}
+                TokenKind::Word
+            }
+            c if c.is_whitespace() => {
+                while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                TokenKind::Whitespace
+            }
+            c => TokenKind::Punct(c),
+        };
+        let end = chars.peek().map_or(sql.len(), |(end, _)| *end);
+        tokens.push(Token {
+            kind,
+            text: &sql[start..end],
+            start,
+        });
+    }
+    tokens
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The tokens which carry meaning, leaving out comments and whitespace.
+pub(crate) fn significant_tokens(sql: &str) -> Vec<Token<'_>> {
+    tokenize(sql)
+        .into_iter()
+        .filter(|t| !t.is_trivia())
+        .collect()
+}
+
+/// The keyword a statement starts with, after any comments.
+pub(crate) fn first_keyword(sql: &str) -> Option<&str> {
+    tokenize(sql)
+        .into_iter()
+        .find(|t| !t.is_trivia())
+        .filter(|t| t.kind == TokenKind::Word)
+        .map(|t| t.text)
+}
+
+/// Reads the significant tokens of a statement in order.
+pub(crate) struct Cursor<'a> {
+    tokens: Vec<Token<'a>>,
+    at: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(sql: &'a str) -> Self {
+        Self {
+            tokens: significant_tokens(sql),
+            at: 0,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.at)
+    }
+
+    /// Moves past the next token if it is the given keyword.
+    pub fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|t| t.is_keyword(keyword));
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    /// Reads a name, quoted or not.
+    pub fn name(&mut self) -> Option<String> {
+        let name = self.peek()?.name()?;
+        self.at += 1;
+        Some(name)
+    }
+
+    /// Reads a table name, leaving out any schema name before it.
+    pub fn table_name(&mut self) -> Option<String> {
+        let name = self.name()?;
+        if self.peek().is_some_and(|t| t.is_punct('.')) {
+            self.at += 1;
+            return self.name();
+        }
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(sql)
+            .into_iter()
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn quotes_and_comments_are_single_tokens() {
+        assert_eq!(
+            kinds("SELECT 'it''s; -- not', \"a\"\"b\" -- note\nFROM [t;] /* x */ WHERE id = :id;"),
+            vec![
+                (TokenKind::Word, "SELECT"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::String, "'it''s; -- not'"),
+                (TokenKind::Punct(','), ","),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::QuotedName, "\"a\"\"b\""),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Comment, "-- note"),
+                (TokenKind::Whitespace, "\n"),
+                (TokenKind::Word, "FROM"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::QuotedName, "[t;]"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Comment, "/* x */"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Word, "WHERE"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Word, "id"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Punct('='), "="),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Parameter, ":id"),
+                (TokenKind::Punct(';'), ";"),
+            ]
+        );
+        assert_eq!(
+            kinds("'open"),
+            vec![(TokenKind::String, "'open")],
+            "an unterminated string runs to the end"
+        );
+    }
+
+    #[test]
+    fn names_are_unquoted() {
+        let mut cursor = Cursor::new("/* c */ main.\"my \"\"table\"\" \" [x y] `z`");
+        assert_eq!(cursor.table_name().as_deref(), Some("my \"table\" "));
+        assert_eq!(cursor.name().as_deref(), Some("x y"));
+        assert_eq!(cursor.name().as_deref(), Some("z"));
+        assert!(cursor.peek().is_none());
+        assert_eq!(first_keyword("-- hi\n/* there */ select 1"), Some("select"));
+    }
+}
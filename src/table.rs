@@ -0,0 +1,80 @@
+//! The look of tables printed by listing commands.
+//!
+//! The style is chosen once per invocation, with `--table-style` or the
+//! `CLOUD_TABLE_STYLE` environment variable, falling back to the style saved
+//! with `spin cloud config set table-style`. It applies to every table the
+//! command prints.
+
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use comfy_table::{presets, Table};
+
+use crate::plugin_config::PluginConfig;
+
+static STYLE: OnceLock<TableStyle> = OnceLock::new();
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum TableStyle {
+    /// ASCII borders around the header and the table
+    #[default]
+    Ascii,
+    /// Box-drawing borders around the header and the table
+    Utf8,
+    /// A Markdown table, for pasting into pull requests and issues
+    Markdown,
+    /// Columns aligned with spaces only
+    Borderless,
+}
+
+impl TableStyle {
+    fn preset(&self) -> &'static str {
+        match self {
+            Self::Ascii => presets::ASCII_BORDERS_ONLY_CONDENSED,
+            Self::Utf8 => presets::UTF8_BORDERS_ONLY,
+            Self::Markdown => presets::ASCII_MARKDOWN,
+            Self::Borderless => presets::NOTHING,
+        }
+    }
+}
+
+/// Sets the style of every table printed from now on. Only the first call
+/// has any effect.
+pub fn set_style(style: TableStyle) {
+    let _ = STYLE.set(style);
+}
+
+/// Creates an empty table in the chosen style.
+pub fn new_table() -> Table {
+    let style = STYLE.get_or_init(|| {
+        PluginConfig::load()
+            .ok()
+            .and_then(|config| config.table_style())
+            .unwrap_or_default()
+    });
+    styled_table(*style)
+}
+
+fn styled_table(style: TableStyle) -> Table {
+    let mut table = Table::new();
+    table.load_preset(style.preset());
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn markdown_tables_have_a_header_separator() {
+        let mut table = styled_table(TableStyle::Markdown);
+        table.set_header(vec!["App", "Result"]);
+        table.add_row(vec!["shop", "deleted"]);
+        let output = table.to_string();
+        let lines = output.lines().map(str::trim).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("| App"));
+        assert!(lines[1].starts_with("|--"));
+        assert!(lines[2].starts_with("| shop"));
+    }
+}
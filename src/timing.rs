@@ -0,0 +1,100 @@
+//! Where the time went in one run of the plugin, reported with `--profile-cli`.
+//!
+//! Timing is off unless [`enable`] is called, and then records how long
+//! argument parsing, finding the login, and every Cloud API call took. Time
+//! not accounted for by those is spent running the command itself, which
+//! includes rendering its output.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMINGS: Mutex<Vec<(String, Duration)>> = Mutex::new(Vec::new());
+
+/// Starts recording timings.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+    cloud::timing::set_recorder(|call, duration| record(format!("API: {call}"), duration));
+}
+
+/// Records that a step took `duration`, if timing is enabled.
+pub fn record(step: impl Into<String>, duration: Duration) {
+    if ENABLED.load(Ordering::Relaxed) {
+        if let Ok(mut timings) = TIMINGS.lock() {
+            timings.push((step.into(), duration));
+        }
+    }
+}
+
+/// Times a step until the returned guard is dropped.
+pub fn step(name: &'static str) -> Step {
+    Step {
+        name,
+        start: Instant::now(),
+    }
+}
+
+pub struct Step {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for Step {
+    fn drop(&mut self) {
+        record(self.name, self.start.elapsed());
+    }
+}
+
+/// Prints the recorded timings to stderr, if timing is enabled. `total`
+/// is how long the whole run took.
+pub fn report(total: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let timings = TIMINGS.lock().map(|t| t.clone()).unwrap_or_default();
+    let mut table = crate::table::new_table();
+    table.set_header(vec!["Step", "Time"]);
+    for (step, duration) in &timings {
+        table.add_row(vec![step.clone(), format_duration(*duration)]);
+    }
+    table.add_row(vec![
+        "Command and rendering".to_owned(),
+        format_duration(unaccounted(total, &timings)),
+    ]);
+    table.add_row(vec!["Total".to_owned(), format_duration(total)]);
+    eprintln!("{table}");
+}
+
+// API calls can run concurrently, and any made while finding the login are
+// counted in both, so the recorded steps may add up to more than the total.
+fn unaccounted(total: Duration, timings: &[(String, Duration)]) -> Duration {
+    let recorded = timings.iter().map(|(_, d)| *d).sum();
+    total.saturating_sub(recorded)
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unaccounted_time_is_never_negative() {
+        let timings = vec![
+            ("API: list_apps".to_owned(), Duration::from_millis(300)),
+            ("API: get_app".to_owned(), Duration::from_millis(300)),
+        ];
+        assert_eq!(
+            unaccounted(Duration::from_millis(1000), &timings),
+            Duration::from_millis(400)
+        );
+        assert_eq!(
+            unaccounted(Duration::from_millis(500), &timings),
+            Duration::ZERO
+        );
+        assert_eq!(format_duration(Duration::from_micros(1234)), "1.2 ms");
+    }
+}
@@ -0,0 +1,445 @@
+//! An in-memory stand-in for the Fermyon Cloud API, implementing just enough
+//! of the cloud-openapi surface for the CLI flows under test. It also accepts
+//! the OCI registry pushes made by `deploy`, when used as the plugin's proxy.
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct FakeApp {
+    pub id: Uuid,
+    pub name: String,
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FakeLink {
+    pub app_id: Uuid,
+    pub label: String,
+}
+
+#[derive(Default)]
+pub struct State {
+    pub apps: Vec<FakeApp>,
+    pub databases: BTreeMap<String, Vec<FakeLink>>,
+    pub executed: Vec<(String, String)>,
+    /// Revisions added, as (storage id, version)
+    pub revisions: Vec<(String, String)>,
+    /// Manifests pushed to the registry, by `repository:tag`
+    pub manifests: BTreeMap<String, String>,
+}
+
+pub struct FakeCloud {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeCloud {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (addr_tx, addr_rx) = std::sync::mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let server_state = state.clone();
+        let thread = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("could not build fake cloud runtime");
+            runtime.block_on(async move {
+                let make_service = make_service_fn(move |_| {
+                    let state = server_state.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req)))
+                    }
+                });
+                let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+                addr_tx.send(server.local_addr()).unwrap();
+                server
+                    .with_graceful_shutdown(async {
+                        shutdown_rx.await.ok();
+                    })
+                    .await
+                    .expect("fake cloud server failed");
+            });
+        });
+
+        let addr = addr_rx.recv().expect("fake cloud server did not start");
+        Self {
+            addr,
+            state,
+            shutdown: Some(shutdown_tx),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    pub fn add_app(&self, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        self.state.lock().unwrap().apps.push(FakeApp {
+            id,
+            name: name.to_owned(),
+            variables: Default::default(),
+        });
+        id
+    }
+
+    pub fn add_database(&self, name: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .databases
+            .insert(name.to_owned(), vec![]);
+    }
+
+    pub fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for FakeCloud {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+    let query = req.uri().query().unwrap_or_default().to_owned();
+    let bytes = hyper::body::to_bytes(req.into_body())
+        .await
+        .unwrap_or_default();
+    let body: Value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+    let segments = path
+        .trim_matches('/')
+        .split('/')
+        .map(|s| s.to_owned())
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+    let mut state = state.lock().unwrap();
+    let response = match (&method, segments.as_slice()) {
+        (&Method::GET, ["api", "apps"]) => ok(json!({
+            "items": state.apps.iter().map(app_json).collect::<Vec<_>>(),
+            "totalItems": state.apps.len(),
+            "pageIndex": 0,
+            "pageSize": 50,
+            "isLastPage": true,
+        })),
+        (&Method::POST, ["api", "apps"]) => {
+            let id = Uuid::new_v4();
+            state.apps.push(FakeApp {
+                id,
+                name: str_field(&body, "name"),
+                variables: Default::default(),
+            });
+            ok(json!(id))
+        }
+        (&Method::GET, ["api", "apps", "name-availability"]) => {
+            let name = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("name="))
+                .unwrap_or_default();
+            ok(json!({ "available": !state.apps.iter().any(|a| a.name == name) }))
+        }
+        (&Method::GET, ["api", "apps", id]) => match state.find_app(id) {
+            Some(app) => ok(app_json(app)),
+            None => not_found("app"),
+        },
+        (&Method::DELETE, ["api", "apps", id]) => {
+            let before = state.apps.len();
+            state.apps.retain(|a| a.id.to_string() != *id);
+            if state.apps.len() == before {
+                not_found("app")
+            } else {
+                no_content()
+            }
+        }
+        (&Method::GET, ["api", "accounts", "quotas"]) => ok(json!({
+            "apps": { "used": state.apps.len() },
+            "databases": { "used": state.databases.len() },
+        })),
+        (&Method::POST, ["api", "revisions"]) => {
+            state.revisions.push((
+                str_field(&body, "appStorageId"),
+                str_field(&body, "revisionNumber"),
+            ));
+            no_content()
+        }
+        (&Method::GET, ["api", "sql-databases"]) => {
+            let app_id = body.get("appId").and_then(|v| v.as_str());
+            let databases = state
+                .databases
+                .iter()
+                .filter(|(_, links)| match app_id {
+                    Some(id) => links.iter().any(|l| l.app_id.to_string() == id),
+                    None => true,
+                })
+                .map(|(name, links)| state.database_json(name, links))
+                .collect::<Vec<_>>();
+            ok(json!({ "databases": databases }))
+        }
+        (&Method::POST, ["api", "sql-databases", "create"]) => {
+            let name = str_field(&body, "name");
+            match state.databases.entry(name) {
+                Entry::Occupied(e) => conflict(&format!("Database {} already exists", e.key())),
+                Entry::Vacant(e) => {
+                    let mut links = vec![];
+                    if let (Some(app_id), Some(label)) = (
+                        body.get("appId").and_then(|v| v.as_str()),
+                        body.get("label").and_then(|v| v.as_str()),
+                    ) {
+                        links.push(FakeLink {
+                            app_id: app_id.parse().unwrap(),
+                            label: label.to_owned(),
+                        });
+                    }
+                    e.insert(links);
+                    no_content()
+                }
+            }
+        }
+        (&Method::DELETE, ["api", "sql-databases"]) => {
+            match state.databases.remove(&str_field(&body, "name")) {
+                Some(_) => no_content(),
+                None => not_found("database"),
+            }
+        }
+        (&Method::POST, ["api", "sql-databases", "execute"]) => {
+            let database = str_field(&body, "database");
+            if state.databases.contains_key(&database) {
+                let statement = str_field(&body, "statement");
+                state.executed.push((database, statement));
+                ok(json!({}))
+            } else {
+                not_found("database")
+            }
+        }
+        (&Method::POST, ["api", "sql-databases", database, "links"]) => {
+            let link = FakeLink {
+                app_id: str_field(&body, "appId").parse().unwrap(),
+                label: str_field(&body, "label"),
+            };
+            match state.databases.get_mut(*database) {
+                Some(links) => {
+                    links.push(link);
+                    no_content()
+                }
+                None => not_found("database"),
+            }
+        }
+        (&Method::DELETE, ["api", "sql-databases", database, "links"]) => {
+            let label = str_field(&body, "label");
+            let app_id = str_field(&body, "appId");
+            match state.databases.get_mut(*database) {
+                Some(links) => {
+                    links.retain(|l| !(l.label == label && l.app_id.to_string() == app_id));
+                    no_content()
+                }
+                None => not_found("database"),
+            }
+        }
+        (&Method::PATCH, ["api", "sql-databases", database, "rename"]) => {
+            let new_name = body.as_str().unwrap_or_default().to_owned();
+            match state.databases.remove(*database) {
+                Some(links) => {
+                    state.databases.insert(new_name, links);
+                    no_content()
+                }
+                None => not_found("database"),
+            }
+        }
+        (&Method::GET, ["api", "variable-pairs"]) => {
+            let app_id = str_field(&body, "appId");
+            match state.find_app(&app_id) {
+                Some(app) => ok(json!({
+                    "vars": app
+                        .variables
+                        .keys()
+                        .map(|k| json!({ "key": k }).to_string())
+                        .collect::<Vec<_>>()
+                })),
+                None => not_found("app"),
+            }
+        }
+        (&Method::POST, ["api", "variable-pairs"]) => {
+            let app_id = str_field(&body, "appId");
+            let variable = str_field(&body, "variable");
+            let value = str_field(&body, "value");
+            match state.find_app_mut(&app_id) {
+                Some(app) => {
+                    app.variables.insert(variable, value);
+                    no_content()
+                }
+                None => not_found("app"),
+            }
+        }
+        (&Method::DELETE, ["api", "variable-pairs"]) => {
+            let app_id = str_field(&body, "appId");
+            let variable = str_field(&body, "variable");
+            match state.find_app_mut(&app_id) {
+                Some(app) => {
+                    app.variables.remove(&variable);
+                    no_content()
+                }
+                None => not_found("app"),
+            }
+        }
+        (&Method::GET, ["v2"]) => ok(json!({})),
+        (&Method::HEAD, ["v2", _, "blobs", _]) => not_found("blob"),
+        (&Method::POST, ["v2", repository, "blobs", "uploads"]) => registry(
+            StatusCode::ACCEPTED,
+            &format!("/v2/{repository}/blobs/uploads/{}", Uuid::new_v4()),
+            None,
+        ),
+        (&Method::PATCH, ["v2", repository, "blobs", "uploads", upload]) => {
+            let mut response = registry(
+                StatusCode::ACCEPTED,
+                &format!("/v2/{repository}/blobs/uploads/{upload}"),
+                None,
+            );
+            let range = format!("0-{}", bytes.len().saturating_sub(1));
+            response
+                .headers_mut()
+                .insert("range", range.parse().unwrap());
+            response
+        }
+        (&Method::PUT, ["v2", repository, "blobs", "uploads", _]) => {
+            let digest = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("digest="))
+                .unwrap_or_default()
+                .replace("%3A", ":");
+            registry(
+                StatusCode::CREATED,
+                &format!("/v2/{repository}/blobs/{digest}"),
+                Some(&digest),
+            )
+        }
+        (&Method::PUT, ["v2", repository, "manifests", tag]) => {
+            let digest = format!("sha256:{:x}", Sha256::digest(&bytes));
+            state
+                .manifests
+                .insert(format!("{repository}:{tag}"), digest.clone());
+            registry(
+                StatusCode::CREATED,
+                &format!("/v2/{repository}/manifests/{digest}"),
+                Some(&digest),
+            )
+        }
+        _ => not_found(&format!("route {method} {path}")),
+    };
+    Ok(response)
+}
+
+impl State {
+    fn find_app(&self, id: &str) -> Option<&FakeApp> {
+        self.apps.iter().find(|a| a.id.to_string() == id)
+    }
+
+    fn find_app_mut(&mut self, id: &str) -> Option<&mut FakeApp> {
+        self.apps.iter_mut().find(|a| a.id.to_string() == id)
+    }
+
+    fn database_json(&self, name: &str, links: &[FakeLink]) -> Value {
+        json!({
+            "name": name,
+            "links": links.iter().map(|l| json!({
+                "appId": l.app_id,
+                "label": l.label,
+                "appName": self.find_app(&l.app_id.to_string()).map(|a| a.name.clone()),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn app_json(app: &FakeApp) -> Value {
+    let domain = format!("{}.fermyon.app", app.name);
+    json!({
+        "id": app.id,
+        "name": app.name,
+        "storageId": format!("oci://{}", app.name),
+        "description": "",
+        "subdomain": domain,
+        "channels": [{
+            "id": Uuid::nil(),
+            "name": "production",
+            "domain": domain,
+        }],
+        "domain": null,
+    })
+}
+
+fn str_field(body: &Value, field: &str) -> String {
+    body.get(field)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn ok(body: Value) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn no_content() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// A registry response locating an upload, blob or manifest
+fn registry(status: StatusCode, location: &str, digest: Option<&str>) -> Response<Body> {
+    let mut response = Response::builder()
+        .status(status)
+        .header("location", location);
+    if let Some(digest) = digest {
+        response = response.header("docker-content-digest", digest);
+    }
+    response.body(Body::empty()).unwrap()
+}
+
+fn problem(status: StatusCode, detail: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/problem+json")
+        .body(Body::from(json!({ "detail": detail }).to_string()))
+        .unwrap()
+}
+
+fn not_found(what: &str) -> Response<Body> {
+    problem(StatusCode::NOT_FOUND, &format!("{what} not found"))
+}
+
+fn conflict(detail: &str) -> Response<Body> {
+    problem(StatusCode::CONFLICT, detail)
+}
@@ -0,0 +1,280 @@
+//! End-to-end tests which run the plugin binary against an in-memory fake of
+//! the Fermyon Cloud API.
+
+// The config directory layout used to plant credentials differs on Windows.
+#![cfg(not(windows))]
+
+mod fake_cloud;
+
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+use fake_cloud::{FakeCloud, FakeLink};
+
+/// An isolated home directory holding a saved login for a fake cloud.
+struct CliEnv {
+    home: TempDir,
+}
+
+impl CliEnv {
+    fn logged_in_to(cloud: &FakeCloud) -> Self {
+        let home = tempfile::tempdir().expect("could not create temporary home");
+        let config_dir = config_dir(home.path()).join("fermyon");
+        std::fs::create_dir_all(&config_dir).unwrap();
+        // An insecure login also pushes artifacts over plain HTTP, which is
+        // all the fake registry speaks.
+        let login = serde_json::json!({
+            "url": cloud.url(),
+            "danger_accept_invalid_certs": true,
+            "token": "e2e-token",
+        });
+        std::fs::write(config_dir.join("config.json"), login.to_string()).unwrap();
+        Self { home }
+    }
+
+    fn spin_cloud<I, S>(&self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let mut cmd = Command::cargo_bin("cloud-plugin").unwrap();
+        cmd.env("HOME", self.home.path())
+            .env("XDG_CONFIG_HOME", config_dir(self.home.path()))
+            .env_remove("FERMYON_DEPLOYMENT_ENVIRONMENT")
+            .env_remove("CLOUD_TOKEN")
+            .env_remove("CLOUD_URL")
+            .env_remove("CLOUD_PROFILE")
+            .env_remove("CLOUD_APP")
+            .args(args);
+        cmd
+    }
+}
+
+fn config_dir(home: &Path) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        home.join("Library").join("Application Support")
+    } else {
+        home.join(".config")
+    }
+}
+
+fn testdata(file: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join(file)
+}
+
+#[test]
+fn sqlite_create_list_rename_delete() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+
+    env.spin_cloud(["sqlite", "create", "inventory"])
+        .assert()
+        .success();
+    env.spin_cloud(["sqlite", "create", "inventory"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            r#"Database "inventory" already exists"#,
+        ));
+
+    env.spin_cloud(["sqlite", "list", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("inventory"));
+
+    env.spin_cloud(["sqlite", "rename", "inventory", "stock"])
+        .assert()
+        .success();
+    assert!(cloud.state().databases.contains_key("stock"));
+
+    env.spin_cloud(["sqlite", "delete", "stock", "--yes"])
+        .assert()
+        .success();
+    assert!(cloud.state().databases.is_empty());
+}
+
+#[test]
+fn sqlite_execute_sends_statement() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+    cloud.add_database("inventory");
+
+    env.spin_cloud([
+        "sqlite",
+        "execute",
+        "--database",
+        "inventory",
+        "CREATE TABLE items (id INTEGER)",
+    ])
+    .assert()
+    .success();
+
+    assert_eq!(
+        cloud.state().executed,
+        vec![(
+            "inventory".to_owned(),
+            "CREATE TABLE items (id INTEGER)".to_owned()
+        )]
+    );
+
+    env.spin_cloud(["sqlite", "execute", "--database", "missing", "SELECT 1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No database found"));
+}
+
+#[test]
+fn link_and_unlink_sqlite() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+    let app_id = cloud.add_app("shop");
+    cloud.add_database("inventory");
+
+    env.spin_cloud([
+        "link",
+        "sqlite",
+        "main",
+        "--app",
+        "shop",
+        "--database",
+        "inventory",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(
+        r#"Database "inventory" is now linked to app "shop" with the label "main""#,
+    ));
+    assert_eq!(
+        cloud.state().databases["inventory"],
+        vec![FakeLink {
+            app_id,
+            label: "main".to_owned()
+        }]
+    );
+
+    env.spin_cloud(["unlink", "sqlite", "main", "--app", "shop"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Database 'inventory' no longer linked to app shop",
+        ));
+    assert!(cloud.state().databases["inventory"].is_empty());
+
+    env.spin_cloud(["link", "sqlite", "main", "--app", "nope", "-d", "inventory"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"No app found with name "nope""#));
+}
+
+#[test]
+fn variables_set_list_delete() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+    cloud.add_app("shop");
+
+    env.spin_cloud(["variables", "set", "api_key=secret", "--app", "shop"])
+        .assert()
+        .success();
+    env.spin_cloud(["variables", "list", "--app", "shop"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("api_key"));
+
+    env.spin_cloud(["variables", "delete", "api_key", "--app", "shop"])
+        .assert()
+        .success();
+    assert!(cloud.state().apps[0].variables.is_empty());
+}
+
+#[test]
+fn deploy_rejects_unsupported_key_value_store() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+
+    env.spin_cloud(["deploy", "--from"])
+        .arg(testdata("invalid_kv_store_v2.toml"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid store "other""#));
+    assert!(cloud.state().apps.is_empty());
+}
+
+#[test]
+fn deploy_creates_app_and_pushes_artifacts() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+
+    // Artifacts are pushed to the registry at `registry.<cloud host>`, which
+    // does not resolve, so the fake cloud also serves as the plugin's proxy.
+    env.spin_cloud([
+        "deploy",
+        "--readiness-timeout",
+        "0",
+        "--no-build-info",
+        "--from",
+    ])
+    .arg(testdata("minimal_v2.toml"))
+    .env("HTTP_PROXY", cloud.url())
+    .env_remove("http_proxy")
+    .env_remove("NO_PROXY")
+    .env_remove("no_proxy")
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(
+        "Uploading minimal_v2 version 0.1.0 to Fermyon Cloud...",
+    ));
+
+    let state = cloud.state();
+    assert_eq!(state.apps.len(), 1);
+    assert_eq!(state.apps[0].name, "minimal_v2");
+    assert_eq!(
+        state.revisions,
+        vec![("oci://minimal_v2".to_owned(), "0.1.0".to_owned())]
+    );
+    assert!(state.manifests.contains_key("minimal_v2:0.1.0"));
+}
+
+#[test]
+fn link_and_unlink_report_json() {
+    let cloud = FakeCloud::start();
+    let env = CliEnv::logged_in_to(&cloud);
+    cloud.add_app("shop");
+    cloud.add_database("inventory");
+
+    let output = env
+        .spin_cloud([
+            "link",
+            "sqlite",
+            "main",
+            "--app",
+            "shop",
+            "--database",
+            "inventory",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["action"], "created");
+    assert_eq!(result["resource"], "inventory");
+    assert!(result["previous_resource"].is_null());
+
+    let output = env
+        .spin_cloud([
+            "unlink", "sqlite", "main", "--app", "shop", "--format", "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["action"], "removed");
+    assert!(result["resource"].is_null());
+    assert_eq!(result["previous_resource"], "inventory");
+}